@@ -16,11 +16,13 @@
 
 mod chain_type;
 
-use ccore::{MemPoolMinFees, MinerOptions, StratumConfig, TimeGapParams};
+use ccore::{MemPoolMinFees, MinerOptions, StratumConfig, TimeGapParams, ACTION_TYPES};
 use cidr::IpCidr;
 use ckey::PlatformAddress;
 use clap;
-use cnetwork::{FilterEntry, NetworkConfig, SocketAddr};
+use cnetwork::{BandwidthLimits, FilterEntry, NetworkConfig, SocketAddr};
+use ctypes::Tracker;
+use std::collections::HashMap;
 use std::fs;
 use std::str::{self, FromStr};
 use std::time::Duration;
@@ -74,6 +76,15 @@ impl Config {
                 None => unreachable!(),
             };
 
+        let excluded_actions = self.mining.excluded_actions.clone().unwrap_or_default();
+        if let Some(action) = excluded_actions.iter().find(|action| !ACTION_TYPES.contains(&action.as_str())) {
+            return Err(format!(
+                "{} isn't a valid value for excluded_actions. Possible values are {}",
+                action,
+                ACTION_TYPES.join(", ")
+            ))
+        }
+
         let mem_pool_min_fees = MemPoolMinFees::create_from_options(
             self.mining.min_pay_transaction_cost,
             self.mining.min_set_regular_key_transaction_cost,
@@ -97,8 +108,21 @@ impl Config {
                 0 => None,
                 mem_size => Some(mem_size * 1024 * 1024),
             },
+            mem_pool_signer_limit: match self.mining.mem_pool_signer_limit.unwrap() {
+                0 => None,
+                limit => Some(limit),
+            },
             mem_pool_fee_bump_shift: self.mining.mem_pool_fee_bump_shift.unwrap(),
             allow_create_shard: self.mining.allow_create_shard.unwrap_or(false),
+            priority_signers: self
+                .mining
+                .priority_signers
+                .as_ref()
+                .map(|signers| signers.iter().map(|signer| signer.into_address()).collect())
+                .unwrap_or_default(),
+            priority_trackers: self.mining.priority_trackers.clone().unwrap_or_default(),
+            excluded_actions,
+            reserved_stake_body_size: self.mining.reserved_stake_body_size.unwrap_or(0),
             new_work_notify: self.mining.notify_work.clone().unwrap(),
             force_sealing: self.mining.force_sealing.unwrap(),
             reseal_on_own_transaction,
@@ -195,6 +219,15 @@ impl Config {
             max_peers: self.network.max_peers.unwrap(),
             whitelist,
             blacklist,
+            bandwidth_limits: BandwidthLimits {
+                upload: self.network.upload_limit,
+                download: self.network.download_limit,
+                peer_upload: self.network.peer_upload_limit,
+                peer_download: self.network.peer_download_limit,
+                extension_upload: self.network.extension_upload_limits.clone().unwrap_or_default(),
+                extension_download: self.network.extension_download_limits.clone().unwrap_or_default(),
+            },
+            legacy_handshake: self.network.legacy_handshake.unwrap(),
         })
     }
 
@@ -234,14 +267,22 @@ pub struct Operating {
 pub struct Mining {
     pub author: Option<PlatformAddress>,
     pub engine_signer: Option<PlatformAddress>,
+    pub double_vote_reporter: Option<PlatformAddress>,
+    pub remote_signer: Option<String>,
+    pub remote_signer_secret_path: Option<String>,
     pub mem_pool_size: Option<usize>,
     pub mem_pool_mem_limit: Option<usize>,
+    pub mem_pool_signer_limit: Option<usize>,
     pub self_nomination_metadata: Option<String>,
     pub self_target_deposit: Option<u64>,
     pub self_nomination_enable: bool,
     pub self_nomination_interval: Option<u64>,
     pub mem_pool_fee_bump_shift: Option<usize>,
     pub allow_create_shard: Option<bool>,
+    pub priority_signers: Option<Vec<PlatformAddress>>,
+    pub priority_trackers: Option<Vec<Tracker>>,
+    pub excluded_actions: Option<Vec<String>>,
+    pub reserved_stake_body_size: Option<usize>,
     pub notify_work: Option<Vec<String>>,
     pub force_sealing: Option<bool>,
     pub reseal_on_txs: Option<String>,
@@ -249,6 +290,8 @@ pub struct Mining {
     pub reseal_max_period: Option<u64>,
     pub no_reseal_timer: Option<bool>,
     pub work_queue_size: Option<usize>,
+    pub cpu_mining: Option<bool>,
+    pub cpu_mining_threads: Option<usize>,
     pub allowed_past_gap: Option<u64>,
     pub allowed_future_gap: Option<u64>,
     pub min_pay_transaction_cost: Option<u64>,
@@ -284,6 +327,13 @@ pub struct Network {
     pub discovery_bucket_size: Option<u8>,
     pub blacklist_path: Option<String>,
     pub whitelist_path: Option<String>,
+    pub upload_limit: Option<usize>,
+    pub download_limit: Option<usize>,
+    pub peer_upload_limit: Option<usize>,
+    pub peer_download_limit: Option<usize>,
+    pub extension_upload_limits: Option<HashMap<String, usize>>,
+    pub extension_download_limits: Option<HashMap<String, usize>>,
+    pub legacy_handshake: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -415,6 +465,15 @@ impl Mining {
         if other.engine_signer.is_some() {
             self.engine_signer = other.engine_signer;
         }
+        if other.double_vote_reporter.is_some() {
+            self.double_vote_reporter = other.double_vote_reporter;
+        }
+        if other.remote_signer.is_some() {
+            self.remote_signer = other.remote_signer.clone();
+        }
+        if other.remote_signer_secret_path.is_some() {
+            self.remote_signer_secret_path = other.remote_signer_secret_path.clone();
+        }
         if other.self_nomination_metadata.is_some() {
             self.self_nomination_metadata = other.self_nomination_metadata.clone();
         }
@@ -430,9 +489,24 @@ impl Mining {
         if other.mem_pool_mem_limit.is_some() {
             self.mem_pool_mem_limit = other.mem_pool_mem_limit;
         }
+        if other.mem_pool_signer_limit.is_some() {
+            self.mem_pool_signer_limit = other.mem_pool_signer_limit;
+        }
         if other.allow_create_shard.is_some() {
             self.allow_create_shard = other.allow_create_shard;
         }
+        if other.priority_signers.is_some() {
+            self.priority_signers = other.priority_signers.clone();
+        }
+        if other.priority_trackers.is_some() {
+            self.priority_trackers = other.priority_trackers.clone();
+        }
+        if other.excluded_actions.is_some() {
+            self.excluded_actions = other.excluded_actions.clone();
+        }
+        if other.reserved_stake_body_size.is_some() {
+            self.reserved_stake_body_size = other.reserved_stake_body_size;
+        }
         if other.notify_work.is_some() {
             self.notify_work = other.notify_work.clone();
         }
@@ -454,6 +528,12 @@ impl Mining {
         if other.work_queue_size.is_some() {
             self.work_queue_size = other.work_queue_size;
         }
+        if other.cpu_mining.is_some() {
+            self.cpu_mining = other.cpu_mining;
+        }
+        if other.cpu_mining_threads.is_some() {
+            self.cpu_mining_threads = other.cpu_mining_threads;
+        }
         if other.min_pay_transaction_cost.is_some() {
             self.min_pay_transaction_cost = other.min_pay_transaction_cost;
         }
@@ -505,6 +585,15 @@ impl Mining {
         if let Some(engine_signer) = matches.value_of("engine-signer") {
             self.engine_signer = Some(engine_signer.parse().map_err(|_| "Invalid address format")?);
         }
+        if let Some(double_vote_reporter) = matches.value_of("double-vote-reporter") {
+            self.double_vote_reporter = Some(double_vote_reporter.parse().map_err(|_| "Invalid address format")?);
+        }
+        if let Some(remote_signer) = matches.value_of("remote-signer") {
+            self.remote_signer = Some(remote_signer.to_string());
+        }
+        if let Some(remote_signer_secret_path) = matches.value_of("remote-signer-secret-path") {
+            self.remote_signer_secret_path = Some(remote_signer_secret_path.to_string());
+        }
         if let Some(self_nomination_metadata) = matches.value_of("self-nomination-metadata") {
             self.self_nomination_metadata =
                 Some(self_nomination_metadata.parse().map_err(|_| "Invalid self nomination metadata format")?);
@@ -536,6 +625,10 @@ impl Mining {
         if let Some(mem_pool_size) = matches.value_of("mem-pool-size") {
             self.mem_pool_size = Some(mem_pool_size.parse().map_err(|_| "Invalid size")?);
         }
+        if let Some(mem_pool_signer_limit) = matches.value_of("mem-pool-signer-limit") {
+            self.mem_pool_signer_limit =
+                Some(mem_pool_signer_limit.parse().map_err(|_| "Invalid mem pool signer limit")?);
+        }
         if matches.is_present("allow-create-shard") {
             self.allow_create_shard = Some(true)
         }
@@ -560,6 +653,12 @@ impl Mining {
         if let Some(work_queue_size) = matches.value_of("work-queue-size") {
             self.work_queue_size = Some(work_queue_size.parse().map_err(|_| "Invalid size")?);
         }
+        if matches.is_present("cpu-mining") {
+            self.cpu_mining = Some(true);
+        }
+        if let Some(cpu_mining_threads) = matches.value_of("cpu-mining-threads") {
+            self.cpu_mining_threads = Some(cpu_mining_threads.parse().map_err(|_| "Invalid thread count")?);
+        }
         if let Some(allowed_past_gap) = matches.value_of("allowed-past-gap") {
             self.allowed_past_gap = Some(allowed_past_gap.parse().map_err(|_| "Invalid time gap")?);
         }
@@ -624,6 +723,27 @@ impl Network {
         if other.whitelist_path.is_some() {
             self.whitelist_path = other.whitelist_path.clone();
         }
+        if other.upload_limit.is_some() {
+            self.upload_limit = other.upload_limit;
+        }
+        if other.download_limit.is_some() {
+            self.download_limit = other.download_limit;
+        }
+        if other.peer_upload_limit.is_some() {
+            self.peer_upload_limit = other.peer_upload_limit;
+        }
+        if other.peer_download_limit.is_some() {
+            self.peer_download_limit = other.peer_download_limit;
+        }
+        if other.extension_upload_limits.is_some() {
+            self.extension_upload_limits = other.extension_upload_limits.clone();
+        }
+        if other.extension_download_limits.is_some() {
+            self.extension_download_limits = other.extension_download_limits.clone();
+        }
+        if other.legacy_handshake.is_some() {
+            self.legacy_handshake = other.legacy_handshake;
+        }
     }
 
     pub fn overwrite_with(&mut self, matches: &clap::ArgMatches) -> Result<(), String> {
@@ -658,6 +778,9 @@ impl Network {
         if matches.is_present("no-tx-relay") {
             self.transaction_relay = Some(false);
         }
+        if matches.is_present("no-legacy-handshake") {
+            self.legacy_handshake = Some(false);
+        }
 
         if matches.is_present("no-discovery") {
             self.discovery = Some(false);
@@ -679,6 +802,19 @@ impl Network {
             self.blacklist_path = Some(file_path.to_string());
         }
 
+        if let Some(limit) = matches.value_of("upload-limit") {
+            self.upload_limit = Some(limit.parse().map_err(|_| "Invalid upload-limit")?);
+        }
+        if let Some(limit) = matches.value_of("download-limit") {
+            self.download_limit = Some(limit.parse().map_err(|_| "Invalid download-limit")?);
+        }
+        if let Some(limit) = matches.value_of("peer-upload-limit") {
+            self.peer_upload_limit = Some(limit.parse().map_err(|_| "Invalid peer-upload-limit")?);
+        }
+        if let Some(limit) = matches.value_of("peer-download-limit") {
+            self.peer_download_limit = Some(limit.parse().map_err(|_| "Invalid peer-download-limit")?);
+        }
+
         Ok(())
     }
 }
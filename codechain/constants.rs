@@ -16,3 +16,6 @@
 
 pub const DEFAULT_KEYS_PATH: &str = "keys";
 pub const DEFAULT_DB_PATH: &str = "db";
+pub const DEFAULT_NODE_KEY_PATH: &str = "node.key";
+/// Unit: millisecond
+pub const DEFAULT_SELF_NOMINATION_INTERVAL: u64 = 10_000;
@@ -16,7 +16,7 @@
 
 use cidr::IpCidr;
 use ckey::Public;
-use cnetwork::{FilterEntry, NetworkControl, NetworkControlError, SocketAddr};
+use cnetwork::{AddressEntry, FilterEntry, NetworkControl, NetworkControlError, PeerReputation, SocketAddr};
 use std::collections::HashMap;
 use std::net::IpAddr;
 
@@ -109,4 +109,16 @@ impl NetworkControl for DummyNetworkService {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, NetworkControlError> {
         Err(NetworkControlError::Disabled)
     }
+
+    fn reputations(&self) -> Result<Vec<(IpAddr, PeerReputation)>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn address_book(&self) -> Result<Vec<(SocketAddr, AddressEntry)>, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
+
+    fn remove_from_address_book(&self, _addr: &SocketAddr) -> Result<bool, NetworkControlError> {
+        Err(NetworkControlError::Disabled)
+    }
 }
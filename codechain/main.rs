@@ -41,6 +41,7 @@ extern crate codechain_state as cstate;
 extern crate codechain_sync as csync;
 extern crate codechain_timer as ctimer;
 extern crate codechain_types as ctypes;
+extern crate codechain_vm as cvm;
 extern crate ctrlc;
 extern crate env_logger;
 extern crate fdlimit;
@@ -54,7 +55,6 @@ extern crate primitives;
 extern crate rpassword;
 extern crate toml;
 
-mod auto_self_nominate;
 mod config;
 mod constants;
 mod dummy_network_service;
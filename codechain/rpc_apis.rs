@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ccore::{AccountProvider, Client, Miner};
+use ccore::stake::SelfNominationManager;
+use ccore::{AccountProvider, Client, CpuMiner, Miner, Stratum};
 use clogger::SLOGGER;
 use cnetwork::{EventSender, NetworkControl};
 use crpc::{MetaIoHandler, Middleware, Params, Value};
@@ -27,21 +28,37 @@ pub struct ApiDependencies {
     pub network_control: Arc<dyn NetworkControl>,
     pub account_provider: Arc<AccountProvider>,
     pub block_sync: Option<EventSender<BlockSyncEvent>>,
+    pub self_nomination: Option<Arc<SelfNominationManager>>,
+    pub stratum: Option<Arc<Stratum>>,
+    pub cpu_miner: Option<Arc<CpuMiner>>,
 }
 
 impl ApiDependencies {
     pub fn extend_api(&self, enable_devel_api: bool, handler: &mut MetaIoHandler<(), impl Middleware<()>>) {
         use crpc::v1::*;
         handler.extend_with(ChainClient::new(Arc::clone(&self.client)).to_delegate());
-        handler.extend_with(MempoolClient::new(Arc::clone(&self.client)).to_delegate());
+        handler.extend_with(
+            MempoolClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), Arc::clone(&self.account_provider))
+                .to_delegate(),
+        );
         if enable_devel_api {
             handler.extend_with(
-                DevelClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.block_sync.clone())
-                    .to_delegate(),
+                DevelClient::new(
+                    Arc::clone(&self.client),
+                    Arc::clone(&self.miner),
+                    self.block_sync.clone(),
+                    self.cpu_miner.clone(),
+                )
+                .to_delegate(),
             );
         }
-        handler.extend_with(EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
-        handler.extend_with(MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner)).to_delegate());
+        handler.extend_with(
+            EngineClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.self_nomination.clone())
+                .to_delegate(),
+        );
+        handler.extend_with(
+            MinerClient::new(Arc::clone(&self.client), Arc::clone(&self.miner), self.stratum.clone()).to_delegate(),
+        );
         handler.extend_with(NetClient::new(Arc::clone(&self.network_control)).to_delegate());
         handler.extend_with(
             AccountClient::new(Arc::clone(&self.account_provider), Arc::clone(&self.client), Arc::clone(&self.miner))
@@ -14,20 +14,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::auto_self_nominate::AutoSelfNomination;
 use crate::config::{self, load_config};
-use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH};
+use crate::constants::{DEFAULT_DB_PATH, DEFAULT_KEYS_PATH, DEFAULT_NODE_KEY_PATH, DEFAULT_SELF_NOMINATION_INTERVAL};
 use crate::dummy_network_service::DummyNetworkService;
 use crate::json::PasswordFile;
 use crate::rpc::{rpc_http_start, rpc_ipc_start, rpc_ws_start};
 use crate::rpc_apis::ApiDependencies;
+use ccore::stake::{SelfNominationManager, SelfNominationParams};
 use ccore::{
-    AccountProvider, AccountProviderError, BlockId, ChainNotify, Client, ClientConfig, ClientService, ConsensusClient,
-    EngineClient, EngineInfo, EngineType, Miner, MinerService, PeerDb, Scheme, Stratum, StratumConfig, StratumError,
-    NUM_COLUMNS,
+    read_signer_secret, AccountProvider, AccountProviderError, BlockId, ChainNotify, Client, ClientConfig,
+    ClientService, ConsensusClient, CpuMiner, EngineClient, EngineInfo, EngineType, Miner, MinerService, PeerDb,
+    RemoteSigner, RemoteSignerEndpoint, Scheme, Stratum, StratumConfig, StratumError, NUM_COLUMNS,
 };
 use cdiscovery::{Config, Discovery};
-use ckey::{Address, NetworkId, PlatformAddress};
+use ckey::{Address, Generator, KeyPair, NetworkId, PlatformAddress, Private, Random};
 use ckeystore::accounts_dir::RootDiskDirectory;
 use ckeystore::KeyStore;
 use clap::ArgMatches;
@@ -41,9 +41,11 @@ use kvdb::KeyValueDB;
 use kvdb_rocksdb::{Database, DatabaseConfig};
 use parking_lot::{Condvar, Mutex};
 use std::fs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::Path;
 use std::sync::{Arc, Weak};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 fn network_start(
     network_id: NetworkId,
@@ -51,6 +53,7 @@ fn network_start(
     cfg: &NetworkConfig,
     routing_table: Arc<RoutingTable>,
     peer_db: Box<dyn ManagingPeerdb>,
+    node_key: KeyPair,
 ) -> Result<Arc<NetworkService>, String> {
     let addr = cfg.address.parse().map_err(|_| format!("Invalid NETWORK listen host given: {}", cfg.address))?;
     let sockaddress = SocketAddr::new(addr, cfg.port);
@@ -65,21 +68,34 @@ fn network_start(
         filters,
         routing_table,
         peer_db,
+        node_key,
+        &cfg.bandwidth_limits,
+        cfg.legacy_handshake,
     )
     .map_err(|e| format!("Network service error: {:?}", e))?;
 
     Ok(service)
 }
 
-fn self_nominate_start(c: Arc<dyn ConsensusClient>, matches: &ArgMatches, ap: Arc<AccountProvider>, address: Address) {
-    let auto_self_nominate = AutoSelfNomination::new(c, ap, address);
-    auto_self_nominate.send_self_nominate_transaction(matches);
+fn self_nomination_start(
+    config: &config::Mining,
+    client: Arc<dyn ConsensusClient>,
+    ap: Arc<AccountProvider>,
+    address: Address,
+) -> Arc<SelfNominationManager> {
+    let params = SelfNominationParams {
+        target_deposit: config.self_target_deposit.unwrap_or(0),
+        metadata: config.self_nomination_metadata.clone().unwrap_or_default().into_bytes(),
+        interval: Duration::from_millis(config.self_nomination_interval.unwrap_or(DEFAULT_SELF_NOMINATION_INTERVAL)),
+    };
+    SelfNominationManager::start(client, ap, address, params, config.self_nomination_enable)
 }
 
 fn discovery_start(
     service: &NetworkService,
     cfg: &config::Network,
     routing_table: Arc<RoutingTable>,
+    node_key: KeyPair,
 ) -> Result<(), String> {
     let config = Config {
         bucket_size: cfg.discovery_bucket_size.unwrap(),
@@ -91,10 +107,58 @@ fn discovery_start(
         Some(discovery_type) => return Err(format!("Unknown discovery {}", discovery_type)),
         None => return Ok(()),
     };
-    service.register_extension(move |api| Discovery::new(routing_table, config, api, use_kademlia));
+    let interface = cfg.interface.as_ref().unwrap();
+    let interface = interface.parse().map_err(|_| format!("Invalid NETWORK listen host given: {}", interface))?;
+    let addresses = if interface == IpAddr::V4(Ipv4Addr::UNSPECIFIED) {
+        vec![]
+    } else {
+        vec![SocketAddr::new(interface, cfg.port.unwrap())]
+    };
+    let mut extensions = vec!["discovery".to_string()];
+    if cfg.sync.unwrap() {
+        extensions.push("block-propagation".to_string());
+    }
+    if cfg.transaction_relay.unwrap() {
+        extensions.push("transaction-propagation".to_string());
+    }
+    service.register_extension(move |api| {
+        Discovery::new(routing_table, config, api, use_kademlia, node_key, addresses, extensions)
+    });
     Ok(())
 }
 
+/// Loads the key which identifies the node, or creates one if it doesn't exist.
+fn load_node_key(base_path: &str) -> Result<KeyPair, String> {
+    let path = Path::new(base_path).join(DEFAULT_NODE_KEY_PATH);
+    if path.exists() {
+        let hex =
+            fs::read_to_string(&path).map_err(|e| format!("Could not read node key at {}: {}", path.display(), e))?;
+        let private: Private =
+            hex.trim().parse().map_err(|e| format!("Invalid node key {}: {:?}", path.display(), e))?;
+        return KeyPair::from_private(private).map_err(|e| format!("Invalid node key {}: {:?}", path.display(), e))
+    }
+    let key_pair = Random.generate().map_err(|e| format!("Could not generate a node key: {:?}", e))?;
+    fs::create_dir_all(base_path).map_err(|e| format!("Could not create {}: {}", base_path, e))?;
+    write_node_key(&path, format!("{:x}", **key_pair.private()))
+        .map_err(|e| format!("Could not write node key at {}: {}", path.display(), e))?;
+    Ok(key_pair)
+}
+
+// The node key is readable only by the owner, like the key files of the key store.
+#[cfg(unix)]
+fn write_node_key(path: &Path, hex: String) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(hex.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_node_key(path: &Path, hex: String) -> io::Result<()> {
+    fs::write(path, hex)
+}
+
 fn client_start(
     client_config: &ClientConfig,
     timer_loop: &TimerLoop,
@@ -111,16 +175,17 @@ fn client_start(
     Ok(service)
 }
 
-fn stratum_start(cfg: &StratumConfig, miner: &Arc<Miner>, client: Arc<Client>) -> Result<(), String> {
+fn stratum_start(cfg: &StratumConfig, miner: &Arc<Miner>, client: Arc<Client>) -> Result<Arc<Stratum>, String> {
     match Stratum::start(cfg, Arc::clone(&miner), client) {
         // FIXME: Add specified condition like AddrInUse
         Err(StratumError::Service(_)) =>
             Err(format!("STRATUM address {} is already in use, make sure that another instance of a CodeChain node is not running or change the address using the --stratum-port option.", cfg.port)),
         Err(e) => Err(format!("STRATUM start error: {:?}", e)),
         Ok(stratum) => {
-            miner.add_work_listener(Box::new(stratum));
+            let stratum = Arc::new(stratum);
+            miner.add_work_listener(Box::new(Arc::clone(&stratum)));
             cinfo!(STRATUM, "Listening on {}", cfg.port);
-            Ok(())
+            Ok(stratum)
         }
     }
 }
@@ -131,9 +196,25 @@ fn new_miner(
     ap: Arc<AccountProvider>,
     db: Arc<dyn KeyValueDB>,
 ) -> Result<Arc<Miner>, String> {
-    let miner = Miner::new(config.miner_options()?, scheme, ap, db);
+    let miner = Miner::new(config.miner_options()?, scheme, Arc::clone(&ap), db);
 
     match miner.engine_type() {
+        EngineType::PBFT if config.mining.remote_signer.is_some() => {
+            let signer = connect_remote_signer(&config.mining)?;
+            if let Some(engine_signer) = &config.mining.engine_signer {
+                if engine_signer.into_address() != signer.address() {
+                    return Err(format!(
+                        "The remote signer holds the key of {}, not of the engine signer {}",
+                        signer.address(),
+                        engine_signer
+                    ))
+                }
+            }
+            miner.set_remote_signer(signer);
+        }
+        _ if config.mining.remote_signer.is_some() => {
+            return Err("Only PBFT type engine can sign with a remote signer.".to_string())
+        }
         EngineType::PoW => match &config.mining.author {
             Some(ref author) => {
                 miner.set_author((*author).into_address()).expect("set_author never fails when PoW is used")
@@ -162,9 +243,34 @@ fn new_miner(
             .expect("set_author never fails when Solo is used"),
     }
 
+    if let Some(reporter) = &config.mining.double_vote_reporter {
+        let address = reporter.into_address();
+        // Sign test message
+        match ap.get_unlocked_account(&address).and_then(|account| Ok(account.sign(&Default::default())?)) {
+            Err(AccountProviderError::NotUnlocked) => {
+                return Err(
+                    format!("The account {} is not unlocked. The key file should exist in the keys_path directory, and the account's password should exist in the password_path file.", reporter)
+                )
+            }
+            Err(e) => return Err(format!("{}", e)),
+            Ok(_) => scheme.engine.set_double_vote_reporter(ap, address),
+        }
+    }
+
     Ok(miner)
 }
 
+fn connect_remote_signer(mining: &config::Mining) -> Result<RemoteSigner, String> {
+    let endpoint: RemoteSignerEndpoint = mining.remote_signer.as_ref().expect("Checked by the caller").parse()?;
+    let secret_path = mining.remote_signer_secret_path.as_ref().ok_or_else(|| {
+        "The secret shared with the remote signer is required. Specify it using --remote-signer-secret-path option."
+            .to_string()
+    })?;
+    let secret = read_signer_secret(Path::new(secret_path))
+        .map_err(|e| format!("Cannot read the secret of the remote signer from {}: {}", secret_path, e))?;
+    RemoteSigner::connect(endpoint, secret).map_err(|e| format!("Cannot connect to the remote signer: {}", e))
+}
+
 fn wait_for_exit() {
     let exit = Arc::new((Mutex::new(()), Condvar::new()));
 
@@ -292,10 +398,12 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
             let network_id = c.network_id();
             let peer_db = PeerDb::new(c.get_kvdb());
             let routing_table = RoutingTable::new();
-            let service = network_start(network_id, timer_loop, &network_config, Arc::clone(&routing_table), peer_db)?;
+            let node_key = load_node_key(config.operating.base_path.as_ref().unwrap())?;
+            let service =
+                network_start(network_id, timer_loop, &network_config, Arc::clone(&routing_table), peer_db, node_key)?;
 
             if config.network.discovery.unwrap() {
-                discovery_start(&service, &config.network, routing_table)?;
+                discovery_start(&service, &config.network, routing_table, node_key)?;
             } else {
                 cwarn!(DISCOVERY, "Node runs without discovery extension");
             }
@@ -322,12 +430,42 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
             Arc::new(DummyNetworkService::new())
         }
     };
-    if config.mining.self_nomination_enable {
-        let c = client.client();
+    // The manager runs even if the self nomination is disabled, so that it can be enabled by RPC.
+    let self_nomination = {
         let address = miner.get_author_address();
-        let accountp = ap.clone();
-        self_nominate_start(c, matches, accountp, address);
-    }
+        if miner.engine_type() == EngineType::PBFT && !address.is_zero() {
+            Some(self_nomination_start(&config.mining, client.client(), Arc::clone(&ap), address))
+        } else {
+            if config.mining.self_nomination_enable {
+                return Err("Self nomination requires PBFT type engine and an engine signer.".to_string())
+            }
+            None
+        }
+    };
+
+    let stratum = if (!config.stratum.disable.unwrap()) && (miner.engine_type() == EngineType::PoW) {
+        Some(stratum_start(&config.stratum_config(), &miner, client.client())?)
+    } else {
+        None
+    };
+
+    let cpu_miner = if miner.engine_type() == EngineType::PoW {
+        let cpu_miner = Arc::new(CpuMiner::new(Arc::clone(&miner), client.client()));
+        miner.add_work_listener(Box::new(Arc::clone(&cpu_miner)));
+        if config.mining.cpu_mining.unwrap_or(false) {
+            let threads = config.mining.cpu_mining_threads.unwrap_or(1);
+            if threads == 0 {
+                return Err("CPU mining requires at least one thread.".to_string())
+            }
+            cpu_miner.start(threads);
+        }
+        Some(cpu_miner)
+    } else {
+        if config.mining.cpu_mining.unwrap_or(false) {
+            return Err("CPU mining requires PoW type engine.".to_string())
+        }
+        None
+    };
 
     let rpc_apis_deps = ApiDependencies {
         client: client.client(),
@@ -335,6 +473,9 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         network_control: Arc::clone(&network_service),
         account_provider: ap,
         block_sync: maybe_sync_sender,
+        self_nomination,
+        stratum,
+        cpu_miner,
     };
 
     let rpc_server = {
@@ -361,10 +502,6 @@ pub fn run_node(matches: &ArgMatches) -> Result<(), String> {
         }
     };
 
-    if (!config.stratum.disable.unwrap()) && (miner.engine_type() == EngineType::PoW) {
-        stratum_start(&config.stratum_config(), &miner, client.client())?
-    }
-
     let _snapshot_service = {
         if !config.snapshot.disable.unwrap() {
             // FIXME: Let's make it load snapshot period dynamically to support changing the period.
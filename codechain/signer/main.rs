@@ -0,0 +1,162 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A reference signing daemon for local testing.
+//! It keeps the private key in a plain file, so don't use it to protect a key holding real stakes.
+
+extern crate clap;
+extern crate codechain_core as ccore;
+extern crate codechain_key as ckey;
+#[macro_use]
+extern crate codechain_logger as clogger;
+#[macro_use]
+extern crate log;
+extern crate panic_hook;
+
+use ccore::{read_signer_secret, SignerDaemon};
+use ckey::{public_to_address, KeyPair, Private};
+use clap::{App, Arg};
+use clogger::LoggerConfig;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+fn main() -> Result<(), String> {
+    panic_hook::set();
+
+    let matches = App::new("codechain-signer")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("A signing daemon which signs consensus messages for a CodeChain node")
+        .arg(
+            Arg::with_name("listen")
+                .long("listen")
+                .value_name("ENDPOINT")
+                .help("Listen on unix:<path> or <host>:<port>.")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("private-key")
+                .long("private-key")
+                .value_name("PATH")
+                .help("Specify the file containing the private key in hex.")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("consensus-private-key")
+                .long("consensus-private-key")
+                .value_name("PATH")
+                .help("Specify the file containing the private key of a consensus key in hex. It can be repeated.")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("secret")
+                .long("secret")
+                .value_name("PATH")
+                .help("Specify the file containing the secret shared with the node.")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("state")
+                .long("state")
+                .value_name("PATH")
+                .help("Specify the file keeping the last signed vote. Without it, a restarted daemon may double sign.")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    clogger::init(&LoggerConfig::new(0), None).expect("Logger must be successfully initialized");
+
+    let key_pair = read_key_pair(matches.value_of("private-key").expect("It's a required argument"))?;
+
+    let secret_path = matches.value_of("secret").expect("It's a required argument");
+    let secret = read_signer_secret(Path::new(secret_path))
+        .map_err(|e| format!("Cannot read the secret from {}: {}", secret_path, e))?;
+
+    let state_path = matches.value_of("state").map(PathBuf::from);
+    let mut daemon =
+        SignerDaemon::new(key_pair, secret, state_path).map_err(|e| format!("Cannot load the state: {}", e))?;
+    for path in matches.values_of("consensus-private-key").into_iter().flatten() {
+        let consensus_key = read_key_pair(path)?;
+        cinfo!(ENGINE, "Holding the consensus key {}", public_to_address(consensus_key.public()));
+        daemon.add_consensus_key(consensus_key);
+    }
+    let daemon = Arc::new(daemon);
+
+    let listen = matches.value_of("listen").expect("It's a required argument");
+    cinfo!(ENGINE, "Signing as {} on {}", public_to_address(daemon.public()), listen);
+    if listen.starts_with("unix:") {
+        listen_unix(daemon, &listen["unix:".len()..])
+    } else {
+        let listener = TcpListener::bind(listen).map_err(|e| format!("Cannot listen on {}: {}", listen, e))?;
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => spawn(Arc::clone(&daemon), stream),
+                Err(e) => cwarn!(ENGINE, "Cannot accept a connection: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_key_pair(path: &str) -> Result<KeyPair, String> {
+    let private: Private = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read the private key from {}: {}", path, e))?
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid private key: {}", e))?;
+    KeyPair::from_private(private).map_err(|e| format!("Invalid private key: {}", e))
+}
+
+#[cfg(unix)]
+fn listen_unix(daemon: Arc<SignerDaemon>, path: &str) -> Result<(), String> {
+    if Path::new(path).exists() {
+        fs::remove_file(path).map_err(|e| format!("Cannot remove the stale socket {}: {}", path, e))?;
+    }
+    let listener = UnixListener::bind(path).map_err(|e| format!("Cannot listen on {}: {}", path, e))?;
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => spawn(Arc::clone(&daemon), stream),
+            Err(e) => cwarn!(ENGINE, "Cannot accept a connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn listen_unix(_daemon: Arc<SignerDaemon>, _path: &str) -> Result<(), String> {
+    Err("Unix sockets are not supported on this platform".to_string())
+}
+
+fn spawn<S: Read + Write + Send + 'static>(daemon: Arc<SignerDaemon>, mut stream: S) {
+    thread::Builder::new()
+        .name("signer".to_string())
+        .spawn(move || {
+            if let Err(e) = daemon.serve(&mut stream) {
+                cwarn!(ENGINE, "The connection is closed: {}", e);
+            }
+        })
+        .expect("Cannot spawn a thread");
+}
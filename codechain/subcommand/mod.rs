@@ -16,9 +16,11 @@
 
 mod account_command;
 mod convert_command;
+mod script_command;
 
 use self::account_command::run_account_command;
 use self::convert_command::run_convert_command;
+use self::script_command::run_script_command;
 use clap::ArgMatches;
 
 pub fn run_subcommand(matches: &ArgMatches) -> Result<(), String> {
@@ -26,6 +28,7 @@ pub fn run_subcommand(matches: &ArgMatches) -> Result<(), String> {
     match subcommand.name.as_str() {
         "account" => run_account_command(&subcommand.matches),
         "convert" => run_convert_command(&subcommand.matches),
+        "script" => run_script_command(&subcommand.matches),
         "commit-hash" => {
             println!("{}", env!("VERGEN_SHA"));
             Ok(())
@@ -0,0 +1,98 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::hex::{FromHex, ToHex};
+use clap::ArgMatches;
+use cvm::{assemble, disassemble, lint_lock_script, lock_script_hash};
+use primitives::remove_0x_prefix;
+use std::fs;
+use std::io::{stdin, Read};
+
+pub fn run_script_command(matches: &ArgMatches) -> Result<(), String> {
+    match matches.subcommand() {
+        ("asm", Some(matches)) => {
+            let source = read_input(matches.value_of("FILE"))?;
+            let script = assemble(&source).map_err(|e| e.to_string())?;
+            for diagnostic in lint_lock_script(&script) {
+                eprintln!("warning: {}", diagnostic);
+            }
+            println!("{}", script.to_hex());
+            Ok(())
+        }
+        ("disasm", Some(matches)) => {
+            let script = read_script(matches.value_of("SCRIPT"))?;
+            print!("{}", disassemble(&script).map_err(|e| e.to_string())?);
+            Ok(())
+        }
+        ("hash", Some(matches)) => {
+            let script = if matches.is_present("asm") {
+                let source = read_input(matches.value_of("SCRIPT"))?;
+                assemble(&source).map_err(|e| e.to_string())?
+            } else {
+                read_script(matches.value_of("SCRIPT"))?
+            };
+            println!("{:x}", lock_script_hash(&script));
+            Ok(())
+        }
+        _ => {
+            println!("{}", matches.usage());
+            Ok(())
+        }
+    }
+}
+
+/// Reads the whole file, or the standard input if the path is not given.
+fn read_input(path: Option<&str>) -> Result<String, String> {
+    match path {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e)),
+        None => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input).map_err(|e| e.to_string())?;
+            Ok(input)
+        }
+    }
+}
+
+fn read_script(script: Option<&str>) -> Result<Vec<u8>, String> {
+    let input = match script {
+        Some(script) => script.to_string(),
+        None => read_input(None)?,
+    };
+    parse_script(&input)
+}
+
+fn parse_script(input: &str) -> Result<Vec<u8>, String> {
+    remove_0x_prefix(input.trim()).from_hex().map_err(|e| format!("Error on reading script: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_script() {
+        assert_eq!(parse_script("0x3001"), Ok(vec![0x30, 0x01]));
+        assert_eq!(parse_script("3001\n"), Ok(vec![0x30, 0x01]));
+        assert!(parse_script("301").is_err());
+    }
+
+    #[test]
+    fn hash_of_assembled_script() {
+        let script = assemble("PUSH 1").unwrap();
+        assert_eq!(script, parse_script("0x3001").unwrap());
+        assert_eq!(format!("{:x}", lock_script_hash(&script)), "b042ad154a3359d276835c903587ebafefea22af");
+    }
+}
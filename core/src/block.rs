@@ -27,7 +27,7 @@ use ctypes::errors::HistoryError;
 use ctypes::header::{Header, Seal};
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockNumber, CommonParams, TxHash};
-use cvm::ChainTimeInfo;
+use cvm::{ChainTimeInfo, VMConfig};
 use merkle_trie::skewed_merkle_root;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
@@ -153,6 +153,7 @@ impl<'x> OpenBlock<'x> {
         &mut self,
         tx: SignedTransaction,
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> Result<(), Error> {
@@ -167,6 +168,7 @@ impl<'x> OpenBlock<'x> {
             &hash,
             &tx.signer_public(),
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
             self.block.header.timestamp(),
@@ -195,11 +197,12 @@ impl<'x> OpenBlock<'x> {
         &mut self,
         transactions: &[SignedTransaction],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> Result<(), Error> {
         for tx in transactions {
-            self.push_transaction(tx.clone(), client, parent_block_number, parent_block_timestamp)?;
+            self.push_transaction(tx.clone(), client, vm_config, parent_block_number, parent_block_timestamp)?;
         }
         Ok(())
     }
@@ -226,6 +229,10 @@ impl<'x> OpenBlock<'x> {
                 e
             })?;
         }
+        if let Err(e) = self.engine.machine().update_base_fee_multiplier(&mut self.block) {
+            warn!("Encountered error on updating the base fee multiplier: {}", e);
+            return Err(e)
+        }
         let state_root = self.block.state.commit().map_err(|e| {
             warn!("Encountered error on state commit: {}", e);
             e
@@ -479,7 +486,11 @@ pub fn enact<C: ChainTimeInfo + EngineInfo + FindActionHandler + TermInfo>(
     let mut b = OpenBlock::try_new(engine, db, parent, Address::default(), vec![])?;
 
     b.populate_from(header);
-    b.push_transactions(transactions, client, parent.number(), parent.timestamp())?;
+    let vm_config = client
+        .common_params(BlockId::Hash(*header.parent_hash()))
+        .map(|params| VMConfig::from(&params))
+        .ok_or_else(|| BlockError::UnknownParent(*header.parent_hash()))?;
+    b.push_transactions(transactions, client, vm_config, parent.number(), parent.timestamp())?;
 
     let term_common_params = client.term_common_params(BlockId::Hash(*header.parent_hash()));
     b.close_and_lock(parent, term_common_params.as_ref())
@@ -23,7 +23,7 @@ use super::{
 use crate::block::{ClosedBlock, IsBlock, OpenBlock, SealedBlock};
 use crate::blockchain::{BlockChain, BlockProvider, BodyProvider, HeaderProvider, InvoiceProvider, TransactionAddress};
 use crate::client::{ConsensusClient, TermInfo};
-use crate::consensus::{CodeChainEngine, EngineError};
+use crate::consensus::{CodeChainEngine, DoubleVoteEvidence, EngineError};
 use crate::encoded;
 use crate::error::{BlockImportError, Error, ImportError, SchemeError};
 use crate::miner::{Miner, MinerService};
@@ -34,19 +34,20 @@ use crate::types::{BlockId, BlockStatus, TransactionId, VerificationQueueInfo as
 use crate::MemPoolMinFees;
 use cdb::{new_journaldb, Algorithm, AsHashDB, DatabaseError};
 use cio::IoChannel;
-use ckey::{Address, NetworkId, PlatformAddress, Public};
+use ckey::{public_to_address, Address, NetworkId, PlatformAddress, Public};
 use cstate::{
     ActionHandler, AssetScheme, FindActionHandler, OwnedAsset, StateDB, StateResult, Text, TopLevelState, TopStateView,
 };
 use ctimer::{TimeoutHandler, TimerApi, TimerScheduleError, TimerToken};
 use ctypes::transaction::{AssetTransferInput, PartialHashing, ShardTransaction};
 use ctypes::{BlockHash, BlockNumber, CommonParams, Header, ShardId, Tracker, TxHash};
-use cvm::{decode, execute, ChainTimeInfo, ScriptResult, VMConfig};
+use cvm::{decode, execute_with_cost, ChainTimeInfo, RuntimeError as VMRuntimeError, ScriptResult, VMConfig};
 use kvdb::{DBTransaction, KeyValueDB};
 use merkle_trie::Result as TrieResult;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use primitives::{Bytes, H160, H256, U256};
 use rlp::Rlp;
+use std::cmp;
 use std::ops::Range;
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::sync::{Arc, Weak};
@@ -417,13 +418,17 @@ impl TextClient for Client {
 impl ExecuteClient for Client {
     fn execute_transaction(&self, transaction: &ShardTransaction, sender: &Address) -> StateResult<()> {
         let mut state = Client::state_at(&self, BlockId::Latest).expect("Latest state MUST exist");
+        let best_block_header = self.best_block_header();
+        let common_params =
+            self.common_params(best_block_header.hash().into()).expect("Common params of the best block MUST exist");
         state.apply_shard_transaction(
             transaction,
             sender,
             &[],
             self,
-            self.best_block_header().number(),
-            self.best_block_header().timestamp(),
+            VMConfig::from(&common_params),
+            best_block_header.number(),
+            best_block_header.timestamp(),
         )
     }
 
@@ -433,7 +438,11 @@ impl ExecuteClient for Client {
         inputs: &[AssetTransferInput],
         params: &[Vec<Bytes>],
         indices: &[usize],
-    ) -> Result<Vec<String>, DatabaseError> {
+    ) -> Result<Vec<(String, u64)>, DatabaseError> {
+        let best_block_header = self.best_block_header();
+        let common_params =
+            self.common_params(best_block_header.hash().into()).expect("Common params of the best block MUST exist");
+        let config = VMConfig::from(&common_params);
         let mut results = Vec::with_capacity(indices.len());
         for (i, index) in indices.iter().enumerate() {
             let input = inputs.get(*index);
@@ -444,27 +453,30 @@ impl ExecuteClient for Client {
                     let unlock_script = decode(&input.unlock_script);
                     match (lock_script, unlock_script) {
                         (Ok(lock_script), Ok(unlock_script)) => {
-                            match execute(
+                            let (result, cost) = execute_with_cost(
                                 &unlock_script,
                                 &param,
                                 &lock_script,
                                 tx,
-                                VMConfig::default(),
+                                config,
                                 &input,
                                 false,
                                 self,
-                                self.best_block_header().number(),
-                                self.best_block_header().timestamp(),
-                            ) {
-                                Ok(ScriptResult::Burnt) => "burnt".to_string(),
-                                Ok(ScriptResult::Unlocked) => "unlocked".to_string(),
-                                _ => "failed".to_string(),
-                            }
+                                best_block_header.number(),
+                                best_block_header.timestamp(),
+                            );
+                            let result = match result {
+                                Ok(ScriptResult::Burnt) => "burnt",
+                                Ok(ScriptResult::Unlocked) => "unlocked",
+                                Err(VMRuntimeError::OutOfCost) => "outOfCost",
+                                _ => "failed",
+                            };
+                            (result.to_string(), cost)
                         }
-                        _ => "invalid".to_string(),
+                        _ => ("invalid".to_string(), 0),
                     }
                 }
-                _ => "invalid".to_string(),
+                _ => ("invalid".to_string(), 0),
             };
             results.push(result);
         }
@@ -508,6 +520,16 @@ impl EngineInfo for Client {
         })
     }
 
+    fn base_fee_multiplier(&self, block_id: BlockId) -> Option<u64> {
+        self.state_info(block_id.into()).map(|state| {
+            state
+                .metadata()
+                .unwrap_or_else(|err| unreachable!("Unexpected failure. Maybe DB was corrupted: {:?}", err))
+                .unwrap()
+                .base_fee_multiplier()
+        })
+    }
+
     fn block_reward(&self, block_number: u64) -> u64 {
         self.engine().block_reward(block_number)
     }
@@ -533,6 +555,10 @@ impl EngineInfo for Client {
             addresses.into_iter().map(|address| PlatformAddress::new_v1(network_id, address)).collect()
         }))
     }
+
+    fn double_vote_evidences(&self) -> Vec<DoubleVoteEvidence> {
+        self.engine().double_vote_evidences()
+    }
 }
 
 impl EngineClient for Client {
@@ -746,6 +772,11 @@ impl BlockChainClient for Client {
         self.miner.status().transactions_in_pending_queue == 0
     }
 
+    fn pending_seq(&self, public: &Public) -> u64 {
+        let latest_seq = self.latest_seq(&public_to_address(public));
+        self.miner.next_seq(public).map_or(latest_seq, |seq| cmp::max(seq, latest_seq))
+    }
+
     fn block_number(&self, id: &BlockId) -> Option<BlockNumber> {
         self.block_number_ref(&id)
     }
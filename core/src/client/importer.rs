@@ -227,6 +227,7 @@ impl Importer {
         })?;
 
         let common_params = client.common_params(parent.hash().into()).unwrap();
+        let base_fee_multiplier = client.base_fee_multiplier(parent.hash().into()).unwrap();
 
         // Verify Block Family
         self.verifier
@@ -242,6 +243,7 @@ impl Importer {
                     client,
                 }),
                 &common_params,
+                base_fee_multiplier,
             )
             .map_err(|e| {
                 cwarn!(
@@ -29,7 +29,7 @@ pub use self::test_client::TestBlockChainClient;
 
 use crate::block::{ClosedBlock, OpenBlock, SealedBlock};
 use crate::blockchain_info::BlockChainInfo;
-use crate::consensus::EngineError;
+use crate::consensus::{DoubleVoteEvidence, EngineError};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
 use crate::miner::MemPoolMinFees;
@@ -87,10 +87,12 @@ pub trait EngineInfo: Send + Sync {
     fn network_id(&self) -> NetworkId;
     fn common_params(&self, block_id: BlockId) -> Option<CommonParams>;
     fn metadata_seq(&self, block_id: BlockId) -> Option<u64>;
+    fn base_fee_multiplier(&self, block_id: BlockId) -> Option<u64>;
     fn block_reward(&self, block_number: u64) -> u64;
     fn mining_reward(&self, block_number: u64) -> Option<u64>;
     fn recommended_confirmation(&self) -> u32;
     fn possible_authors(&self, block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError>;
+    fn double_vote_evidences(&self) -> Vec<DoubleVoteEvidence>;
 }
 
 /// Client facilities used by internally sealing Engines.
@@ -237,6 +239,9 @@ pub trait BlockChainClient: Sync + Send + AccountData + BlockChainTrait + Import
     /// Check there are transactions which are allowed into the next block.
     fn is_pending_queue_empty(&self) -> bool;
 
+    /// Get the seq of the next transaction of the signer, counting its transactions in the mem pool.
+    fn pending_seq(&self, public: &Public) -> u64;
+
     /// Look up the block number for the given block ID.
     fn block_number(&self, id: &BlockId) -> Option<BlockNumber>;
 
@@ -338,7 +343,7 @@ pub trait ExecuteClient: ChainTimeInfo {
         inputs: &[AssetTransferInput],
         params: &[Vec<Bytes>],
         indices: &[usize],
-    ) -> Result<Vec<String>, DatabaseError>;
+    ) -> Result<Vec<(String, u64)>, DatabaseError>;
 }
 
 pub trait StateInfo {
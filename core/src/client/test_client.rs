@@ -37,7 +37,7 @@ use crate::client::{
     ImportBlock, ImportResult, MiningBlockChainClient, StateInfo, StateOrBlock, TermInfo,
 };
 use crate::consensus::stake::{Validator, Validators};
-use crate::consensus::EngineError;
+use crate::consensus::{DoubleVoteEvidence, EngineError};
 use crate::db::{COL_STATE, NUM_COLUMNS};
 use crate::encoded;
 use crate::error::{BlockImportError, Error as GenericError};
@@ -51,7 +51,7 @@ use cstate::tests::helpers::empty_top_state;
 use cstate::{FindActionHandler, StateDB, TopLevelState};
 use ctimer::{TimeoutHandler, TimerToken};
 use ctypes::transaction::{Action, Transaction};
-use ctypes::{BlockHash, BlockNumber, CommonParams, Header as BlockHeader, Tracker, TxHash};
+use ctypes::{BlockHash, BlockNumber, CommonParams, Header as BlockHeader, Tracker, TxHash, BASE_FEE_MULTIPLIER_UNIT};
 use cvm::ChainTimeInfo;
 use kvdb::KeyValueDB;
 use kvdb_memorydb;
@@ -59,6 +59,7 @@ use merkle_trie::skewed_merkle_root;
 use parking_lot::RwLock;
 use primitives::{Bytes, H256, U256};
 use rlp::*;
+use std::cmp;
 use std::collections::HashMap;
 use std::mem;
 use std::ops::Range;
@@ -103,6 +104,14 @@ pub struct TestBlockChainClient {
     pub validator_keys: RwLock<HashMap<Public, Private>>,
     /// Fixed validators
     pub validators: Validators,
+    /// Parents of the blocks the engine requested to seal.
+    pub sealing_requests: RwLock<Vec<BlockId>>,
+    /// Blocks the engine requested to make the best block.
+    pub committed_requests: RwLock<Vec<BlockHash>>,
+    /// Double votes returned by `double_vote_evidences`.
+    pub double_vote_evidences: RwLock<Vec<DoubleVoteEvidence>>,
+    /// Base fee multiplier returned by `base_fee_multiplier`.
+    pub base_fee_multiplier: RwLock<u64>,
 }
 
 impl Default for TestBlockChainClient {
@@ -157,6 +166,10 @@ impl TestBlockChainClient {
             term_id: Some(1),
             validator_keys: RwLock::new(HashMap::new()),
             validators: Validators::from_vector_to_test(vec![]),
+            sealing_requests: RwLock::new(Vec::new()),
+            committed_requests: RwLock::new(Vec::new()),
+            double_vote_evidences: RwLock::new(Vec::new()),
+            base_fee_multiplier: RwLock::new(BASE_FEE_MULTIPLIER_UNIT),
         };
 
         // insert genesis hash.
@@ -170,6 +183,11 @@ impl TestBlockChainClient {
         self.balances.write().insert(address, balance);
     }
 
+    /// Set the base fee multiplier of every block.
+    pub fn set_base_fee_multiplier(&self, base_fee_multiplier: u64) {
+        *self.base_fee_multiplier.write() = base_fee_multiplier;
+    }
+
     /// Set seq of account `address` to `seq`.
     pub fn set_seq(&self, address: Address, seq: u64) {
         self.seqs.write().insert(address, seq);
@@ -180,6 +198,11 @@ impl TestBlockChainClient {
         self.storage.write().insert((address, position), value);
     }
 
+    /// Set the double votes returned by `double_vote_evidences`.
+    pub fn set_double_vote_evidences(&self, evidences: Vec<DoubleVoteEvidence>) {
+        *self.double_vote_evidences.write() = evidences;
+    }
+
     /// Set block queue size for testing
     pub fn set_queue_size(&self, size: usize) {
         self.queue_size.store(size, AtomicOrder::Relaxed);
@@ -260,6 +283,42 @@ impl TestBlockChainClient {
         self.blocks.write().insert(hash, rlp.out());
     }
 
+    /// Store a block without changing the best block.
+    pub fn insert_block(&self, bytes: Bytes) -> BlockHash {
+        let hash = Rlp::new(&bytes).val_at::<BlockHeader>(0).unwrap().hash();
+        self.blocks.write().insert(hash, bytes);
+        hash
+    }
+
+    /// Make a stored block the best block, and its ancestors canonical.
+    pub fn set_best_block(&self, hash: BlockHash) {
+        let blocks = self.blocks.read();
+        let mut numbers = self.numbers.write();
+        let header = Rlp::new(&blocks[&hash]).val_at::<BlockHeader>(0).unwrap();
+        let number = header.number() as usize;
+        numbers.retain(|n, _| *n <= number);
+        numbers.insert(number, hash);
+        let mut parent_hash = *header.parent_hash();
+        for n in (0..number).rev() {
+            if numbers.get(&n) == Some(&parent_hash) {
+                break
+            }
+            numbers.insert(n, parent_hash);
+            parent_hash = *Rlp::new(&blocks[&parent_hash]).val_at::<BlockHeader>(0).unwrap().parent_hash();
+        }
+        *self.last_hash.write() = hash;
+    }
+
+    /// Take the sealing requests made since the last call.
+    pub fn take_sealing_requests(&self) -> Vec<BlockId> {
+        mem::replace(&mut *self.sealing_requests.write(), Vec::new())
+    }
+
+    /// Take the requests to make a block the best block made since the last call.
+    pub fn take_committed_requests(&self) -> Vec<BlockHash> {
+        mem::replace(&mut *self.committed_requests.write(), Vec::new())
+    }
+
     /// TODO:
     pub fn block_hash_delta_minus(&mut self, delta: usize) -> BlockHash {
         let blocks_read = self.numbers.read();
@@ -569,6 +628,11 @@ impl BlockChainClient for TestBlockChainClient {
         self.miner.status().transactions_in_pending_queue == 0
     }
 
+    fn pending_seq(&self, public: &Public) -> u64 {
+        let latest_seq = self.latest_seq(&public_to_address(public));
+        self.miner.next_seq(public).map_or(latest_seq, |seq| cmp::max(seq, latest_seq))
+    }
+
     fn block_number(&self, _id: &BlockId) -> Option<BlockNumber> {
         unimplemented!()
     }
@@ -636,6 +700,7 @@ impl FindActionHandler for TestBlockChainClient {}
 
 impl super::EngineClient for TestBlockChainClient {
     fn update_sealing(&self, parent_block: BlockId, allow_empty_block: bool) {
+        self.sealing_requests.write().push(parent_block);
         self.miner.update_sealing(self, parent_block, allow_empty_block)
     }
 
@@ -649,7 +714,9 @@ impl super::EngineClient for TestBlockChainClient {
         U256::zero()
     }
 
-    fn update_best_as_committed(&self, _block_hash: BlockHash) {}
+    fn update_best_as_committed(&self, block_hash: BlockHash) {
+        self.committed_requests.write().push(block_hash);
+    }
 
     fn get_kvdb(&self) -> Arc<dyn KeyValueDB> {
         let db = kvdb_memorydb::create(NUM_COLUMNS.unwrap_or(0));
@@ -670,6 +737,10 @@ impl EngineInfo for TestBlockChainClient {
         unimplemented!()
     }
 
+    fn base_fee_multiplier(&self, _block_id: BlockId) -> Option<u64> {
+        Some(*self.base_fee_multiplier.read())
+    }
+
     fn block_reward(&self, _block_number: u64) -> u64 {
         unimplemented!()
     }
@@ -685,6 +756,10 @@ impl EngineInfo for TestBlockChainClient {
     fn possible_authors(&self, _block_number: Option<u64>) -> Result<Option<Vec<PlatformAddress>>, EngineError> {
         unimplemented!()
     }
+
+    fn double_vote_evidences(&self) -> Vec<DoubleVoteEvidence> {
+        self.double_vote_evidences.read().clone()
+    }
 }
 
 impl ConsensusClient for TestBlockChainClient {}
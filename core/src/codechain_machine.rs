@@ -23,7 +23,8 @@ use ckey::Address;
 use cstate::{StateError, TopState, TopStateView};
 use ctypes::errors::{HistoryError, SyntaxError};
 use ctypes::transaction::{Action, AssetTransferInput, Timelock};
-use ctypes::{CommonParams, Header};
+use ctypes::{apply_base_fee_multiplier, CommonParams, Header};
+use rlp::RlpStream;
 
 pub struct CodeChainMachine {
     params: CommonParams,
@@ -46,8 +47,9 @@ impl CodeChainMachine {
         &self,
         tx: &UnverifiedTransaction,
         common_params: &CommonParams,
+        base_fee_multiplier: u64,
     ) -> Result<(), Error> {
-        let min_cost = Self::min_cost(common_params, &tx.action);
+        let min_cost = Self::min_cost(common_params, base_fee_multiplier, &tx.action);
         if tx.fee < min_cost {
             return Err(SyntaxError::InsufficientFee {
                 minimal: min_cost,
@@ -171,8 +173,9 @@ impl CodeChainMachine {
         Ok(())
     }
 
-    pub fn min_cost(params: &CommonParams, action: &Action) -> u64 {
-        match action {
+    /// The minimum fee of the action scaled by the base fee multiplier in the per-mille unit.
+    pub fn min_cost(params: &CommonParams, base_fee_multiplier: u64, action: &Action) -> u64 {
+        let min_cost = match action {
             Action::MintAsset {
                 ..
             } => params.min_asset_mint_cost(),
@@ -215,7 +218,8 @@ impl CodeChainMachine {
             Action::Remove {
                 ..
             } => params.min_remove_transaction_cost(),
-        }
+        };
+        apply_base_fee_multiplier(min_cost, base_fee_multiplier)
     }
 
     pub fn balance(&self, live: &ExecutedBlock, address: &Address) -> Result<u64, Error> {
@@ -231,4 +235,23 @@ impl CodeChainMachine {
         live.state_mut().increase_term_id(last_term_finished_block_num)?;
         Ok(())
     }
+
+    /// Moves the base fee multiplier of the next block according to the body size of this block.
+    pub fn update_base_fee_multiplier(&self, live: &mut ExecutedBlock) -> Result<(), Error> {
+        let metadata = match live.state().metadata()? {
+            Some(metadata) => metadata,
+            None => return Ok(()),
+        };
+        let body_size = {
+            let mut body = RlpStream::new();
+            body.append_list(live.transactions());
+            body.out().len()
+        };
+        let params = metadata.params().unwrap_or(&self.params);
+        let multiplier = params.next_base_fee_multiplier(metadata.base_fee_multiplier(), body_size);
+        if multiplier != metadata.base_fee_multiplier() {
+            live.state_mut().update_base_fee_multiplier(multiplier)?;
+        }
+        Ok(())
+    }
 }
@@ -26,7 +26,7 @@ use ccrypto::blake256;
 use ckey::Address;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{CommonParams, Header};
-use primitives::U256;
+use primitives::{Bytes, H256, U256};
 use rlp::Rlp;
 use std::cmp::{max, min};
 
@@ -80,6 +80,24 @@ impl BlakePoW {
         };
         max(self.params.min_score, target)
     }
+
+    fn verify_seal_with_score(&self, header: &Header, score: &U256) -> Result<(), Error> {
+        let seal = Seal::parse_seal(header.seal())?;
+
+        let mut message = header.bare_hash().0;
+        message[0..8].copy_from_slice(&seal.nonce.to_le_bytes());
+
+        let target = self.score_to_target(score);
+        let hash = blake256(message);
+        if U256::from(hash) > target {
+            return Err(From::from(BlockError::PowOutOfBounds(OutOfBounds {
+                min: None,
+                max: Some(target),
+                found: U256::from(hash),
+            })))
+        }
+        Ok(())
+    }
 }
 
 impl ConsensusEngine for BlakePoW {
@@ -112,21 +130,11 @@ impl ConsensusEngine for BlakePoW {
     }
 
     fn verify_block_seal(&self, header: &Header) -> Result<(), Error> {
-        let seal = Seal::parse_seal(header.seal())?;
-
-        let mut message = header.bare_hash().0;
-        message[0..8].copy_from_slice(&seal.nonce.to_le_bytes());
+        self.verify_seal_with_score(header, header.score())
+    }
 
-        let target = self.score_to_target(header.score());
-        let hash = blake256(message);
-        if U256::from(hash) > target {
-            return Err(From::from(BlockError::PowOutOfBounds(OutOfBounds {
-                min: None,
-                max: Some(target),
-                found: U256::from(hash),
-            })))
-        }
-        Ok(())
+    fn verify_share_seal(&self, header: &Header, share_score: &U256) -> Result<(), Error> {
+        self.verify_seal_with_score(header, share_score)
     }
 
     fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
@@ -169,6 +177,16 @@ impl ConsensusEngine for BlakePoW {
         (U256::max_value() - *score) / *score
     }
 
+    fn try_pow_nonce(&self, pow_hash: &H256, target: &U256, nonce: u64) -> Option<Vec<Bytes>> {
+        let mut message = pow_hash.0;
+        message[0..8].copy_from_slice(&nonce.to_le_bytes());
+
+        if U256::from(blake256(message)) > *target {
+            return None
+        }
+        Some(vec![::rlp::encode(&nonce)])
+    }
+
     fn block_reward(&self, _block_number: u64) -> u64 {
         self.params.block_reward
     }
@@ -181,3 +199,30 @@ impl ConsensusEngine for BlakePoW {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::scheme::Scheme;
+
+    use super::*;
+
+    #[test]
+    fn try_pow_nonce_makes_valid_seal() {
+        let engine = Scheme::new_test_blake_pow().engine;
+        let mut header = Header::default();
+        header.set_score(U256::from(2));
+        let target = engine.score_to_target(header.score());
+
+        let seal = (0..).find_map(|nonce| engine.try_pow_nonce(&header.bare_hash(), &target, nonce)).unwrap();
+        header.set_seal(seal);
+        assert_eq!(Ok(()), engine.verify_block_seal(&header).map_err(|e| e.to_string()));
+    }
+
+    #[test]
+    fn try_pow_nonce_fails_with_zero_target() {
+        let engine = Scheme::new_test_blake_pow().engine;
+        let header = Header::default();
+
+        assert_eq!(None, engine.try_pow_nonce(&header.bare_hash(), &U256::zero(), 0));
+    }
+}
@@ -27,7 +27,7 @@ use ckey::Address;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{CommonParams, Header};
 use cuckoo::Cuckoo as CuckooVerifier;
-use primitives::U256;
+use primitives::{Bytes, H256, U256};
 use rlp::Rlp;
 use std::cmp::{max, min};
 
@@ -86,6 +86,28 @@ impl Cuckoo {
         };
         max(self.params.min_score, target)
     }
+
+    fn verify_seal_with_score(&self, header: &Header, score: &U256) -> Result<(), Error> {
+        let seal = Seal::parse_seal(header.seal())?;
+
+        let mut message = header.bare_hash().0;
+        message[0..8].copy_from_slice(&seal.nonce.to_le_bytes());
+
+        if !self.verifier.verify(&message, &seal.proof) {
+            return Err(From::from(BlockError::InvalidProofOfWork))
+        }
+
+        let target = self.score_to_target(score);
+        let hash = blake256(::rlp::encode_list(&seal.proof));
+        if U256::from(hash) > target {
+            return Err(From::from(BlockError::PowOutOfBounds(OutOfBounds {
+                min: None,
+                max: Some(target),
+                found: U256::from(hash),
+            })))
+        }
+        Ok(())
+    }
 }
 
 impl ConsensusEngine for Cuckoo {
@@ -118,25 +140,11 @@ impl ConsensusEngine for Cuckoo {
     }
 
     fn verify_block_seal(&self, header: &Header) -> Result<(), Error> {
-        let seal = Seal::parse_seal(header.seal())?;
-
-        let mut message = header.bare_hash().0;
-        message[0..8].copy_from_slice(&seal.nonce.to_le_bytes());
-
-        if !self.verifier.verify(&message, &seal.proof) {
-            return Err(From::from(BlockError::InvalidProofOfWork))
-        }
+        self.verify_seal_with_score(header, header.score())
+    }
 
-        let target = self.score_to_target(header.score());
-        let hash = blake256(::rlp::encode_list(&seal.proof));
-        if U256::from(hash) > target {
-            return Err(From::from(BlockError::PowOutOfBounds(OutOfBounds {
-                min: None,
-                max: Some(target),
-                found: U256::from(hash),
-            })))
-        }
-        Ok(())
+    fn verify_share_seal(&self, header: &Header, share_score: &U256) -> Result<(), Error> {
+        self.verify_seal_with_score(header, share_score)
     }
 
     fn verify_block_family(&self, header: &Header, parent: &Header) -> Result<(), Error> {
@@ -179,6 +187,17 @@ impl ConsensusEngine for Cuckoo {
         (U256::max_value() - *score) / *score
     }
 
+    fn try_pow_nonce(&self, pow_hash: &H256, target: &U256, nonce: u64) -> Option<Vec<Bytes>> {
+        let mut message = pow_hash.0;
+        message[0..8].copy_from_slice(&nonce.to_le_bytes());
+
+        let proof = self.verifier.solve(&message)?;
+        if U256::from(blake256(::rlp::encode_list(&proof))) > *target {
+            return None
+        }
+        Some(vec![::rlp::encode(&nonce), ::rlp::encode_list(&proof)])
+    }
+
     fn block_reward(&self, _block_number: u64) -> u64 {
         self.params.block_reward
     }
@@ -28,10 +28,12 @@ mod validator_set;
 pub use self::blake_pow::BlakePoW;
 pub use self::cuckoo::Cuckoo;
 pub use self::null_engine::NullEngine;
+pub use self::signer::{read_signer_secret, RemoteSigner, RemoteSignerEndpoint, RemoteSignerError, SignerDaemon};
 pub use self::simple_poa::SimplePoA;
 pub use self::solo::Solo;
 pub use self::tendermint::{
-    ConsensusMessage, Height, Step, Tendermint, TendermintParams, TimeGapParams, View, VoteOn, VoteStep,
+    ConsensusMessage, DoubleVoteEvidence, Height, Step, Tendermint, TendermintParams, TimeGapParams, View, VoteOn,
+    VoteStep,
 };
 pub use self::validator_set::validator_list::RoundRobinValidator;
 pub use self::validator_set::{DynamicValidator, ValidatorSet};
@@ -52,7 +54,7 @@ use ctypes::errors::SyntaxError;
 use ctypes::transaction::Action;
 use ctypes::util::unexpected::{Mismatch, OutOfBounds};
 use ctypes::{BlockHash, CommonParams, Header};
-use primitives::{Bytes, U256};
+use primitives::{Bytes, H256, U256};
 use std::fmt;
 use std::sync::{Arc, Weak};
 
@@ -195,6 +197,11 @@ pub trait ConsensusEngine: Sync + Send {
         Ok(())
     }
 
+    /// Verify the seal of a mining share, which only needs to meet `_share_score` instead of the block's score.
+    fn verify_share_seal(&self, header: &Header, _share_score: &U256) -> Result<(), Error> {
+        self.verify_block_seal(header)
+    }
+
     /// Phase 3 verification. Check block information against parent. Returns either a null `Ok` or a general error detailing the problem with import.
     /// The verification must be conducted only with the two headers' information because it does not guarantee whether the two corresponding bodies have been imported.
     fn verify_block_family(&self, _header: &Header, _parent: &Header) -> Result<(), Error> {
@@ -238,6 +245,18 @@ pub trait ConsensusEngine: Sync + Send {
     /// Register an account which signs consensus messages.
     fn set_signer(&self, _ap: Arc<AccountProvider>, _address: Address) {}
 
+    /// Register a signing daemon which signs consensus messages.
+    /// Only the engines signing nothing but consensus votes support it.
+    fn set_remote_signer(&self, _signer: RemoteSigner) {}
+
+    /// Register an account which signs the reports of double votes.
+    fn set_double_vote_reporter(&self, _ap: Arc<AccountProvider>, _address: Address) {}
+
+    /// The double votes found by this node.
+    fn double_vote_evidences(&self) -> Vec<DoubleVoteEvidence> {
+        Vec::new()
+    }
+
     fn register_network_extension_to_service(&self, _: &NetworkService) {}
 
     fn register_time_gap_config_to_worker(&self, _time_gap_params: TimeGapParams) {}
@@ -246,6 +265,12 @@ pub trait ConsensusEngine: Sync + Send {
         U256::zero()
     }
 
+    /// Try to make a seal of the work `_pow_hash` with `_nonce`.
+    /// Returns `None` if the engine doesn't use PoW or the seal doesn't meet `_target`.
+    fn try_pow_nonce(&self, _pow_hash: &H256, _target: &U256, _nonce: u64) -> Option<Vec<Bytes>> {
+        None
+    }
+
     fn block_reward(&self, block_number: u64) -> u64;
 
     fn block_fee(&self, transactions: Box<dyn Iterator<Item = UnverifiedTransaction>>) -> u64 {
@@ -361,6 +386,7 @@ pub trait CodeChainEngine: ConsensusEngine {
         &self,
         tx: &UnverifiedTransaction,
         common_params: &CommonParams,
+        base_fee_multiplier: u64,
     ) -> Result<(), Error> {
         if let Action::Custom {
             handler_id,
@@ -372,7 +398,7 @@ pub trait CodeChainEngine: ConsensusEngine {
                 .ok_or_else(|| SyntaxError::InvalidCustomAction(format!("{} is an invalid handler id", handler_id)))?;
             handler.verify(bytes, common_params)?;
         }
-        self.machine().verify_transaction_with_params(tx, common_params)
+        self.machine().verify_transaction_with_params(tx, common_params, base_fee_multiplier)
     }
 }
 
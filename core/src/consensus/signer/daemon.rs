@@ -0,0 +1,283 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::protocol::{open_request, read_frame, seal_response, write_frame, Hello, Request, Response, PROTOCOL_VERSION};
+use crate::consensus::tendermint::VoteRegressionChecker;
+use crate::consensus::{Step, VoteOn};
+use ckey::{sign, sign_schnorr, KeyPair, Public};
+use parking_lot::Mutex;
+use primitives::H256;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::iter::once;
+use std::path::PathBuf;
+
+/// A signing daemon holding the key of a validator, and the consensus keys that the validator registered.
+///
+/// It never signs a vote that regresses from the last signed one,
+/// and the last vote is persisted before the signature leaves the daemon,
+/// so restarting the daemon doesn't open a window for double signing.
+pub struct SignerDaemon {
+    key_pair: KeyPair,
+    consensus_keys: Vec<KeyPair>,
+    secret: H256,
+    checker: Mutex<VoteRegressionChecker>,
+    state_path: Option<PathBuf>,
+}
+
+impl SignerDaemon {
+    pub fn new(key_pair: KeyPair, secret: H256, state_path: Option<PathBuf>) -> io::Result<Self> {
+        let last_vote = match &state_path {
+            Some(path) if path.exists() => {
+                let bytes = fs::read(path)?;
+                let last_vote: VoteOn = rlp::decode(&bytes)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+                Some(last_vote)
+            }
+            _ => None,
+        };
+        if let Some(last_vote) = &last_vote {
+            cinfo!(ENGINE, "The last signed vote is {:?}", last_vote);
+        }
+        Ok(Self {
+            key_pair,
+            consensus_keys: Vec::new(),
+            secret,
+            checker: Mutex::new(VoteRegressionChecker::from_last_vote(last_vote)),
+            state_path,
+        })
+    }
+
+    pub fn public(&self) -> &Public {
+        self.key_pair.public()
+    }
+
+    /// Hold a key that the validator registered to sign consensus messages with.
+    pub fn add_consensus_key(&mut self, key_pair: KeyPair) {
+        self.consensus_keys.push(key_pair);
+    }
+
+    /// Serve a connection until the peer closes it or violates the protocol.
+    pub fn serve<S: Read + Write>(&self, stream: &mut S) -> io::Result<()> {
+        let nonce = H256::from(rand::random::<[u8; 32]>());
+        write_frame(stream, &rlp::encode(&Hello {
+            version: PROTOCOL_VERSION,
+            nonce,
+        }))?;
+
+        let mut last_seq = None;
+        loop {
+            let frame = match read_frame(stream) {
+                Ok(frame) => frame,
+                Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(err) => return Err(err),
+            };
+            let (seq, request) = open_request(&self.secret, &nonce, &frame)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))?;
+            if last_seq.map_or(false, |last_seq| seq <= last_seq) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "The sequence number is not increasing"))
+            }
+            last_seq = Some(seq);
+
+            let response = self.handle(request);
+            write_frame(stream, &seal_response(&self.secret, &nonce, seq, &response))?;
+        }
+    }
+
+    fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::PublicKey => Response::PublicKey(*self.key_pair.public()),
+            Request::SignVote(on) => self.sign_vote(&self.key_pair, on),
+            Request::SignVoteWithKey(public, on) => {
+                match once(&self.key_pair).chain(self.consensus_keys.iter()).find(|key| key.public() == &public) {
+                    Some(key_pair) => self.sign_vote(key_pair, on),
+                    None => Response::Refused(format!("The daemon doesn't hold the key {}", public)),
+                }
+            }
+            Request::SignMessage(hash) => match sign(self.key_pair.private(), &hash) {
+                Ok(signature) => Response::Ecdsa(signature),
+                Err(err) => Response::Refused(err.to_string()),
+            },
+        }
+    }
+
+    fn sign_vote(&self, key_pair: &KeyPair, on: VoteOn) -> Response {
+        if on.step.step == Step::Commit {
+            return Response::Refused("Votes on the commit step are not signed".to_string())
+        }
+
+        let mut checker = self.checker.lock();
+        let last_vote = checker.last_vote().cloned();
+        if !checker.check(&on) {
+            cwarn!(ENGINE, "Refused to sign {:?} since the last signed vote is {:?}", on, last_vote);
+            return Response::Refused("The vote regresses".to_string())
+        }
+        if last_vote.as_ref() != Some(&on) {
+            if let Err(err) = self.persist(&on) {
+                cerror!(ENGINE, "Cannot persist the last vote: {}", err);
+                return Response::Refused("Cannot persist the last vote".to_string())
+            }
+        }
+
+        match sign_schnorr(key_pair.private(), &on.hash()) {
+            Ok(signature) => Response::Schnorr(signature),
+            Err(err) => Response::Refused(err.to_string()),
+        }
+    }
+
+    fn persist(&self, on: &VoteOn) -> io::Result<()> {
+        if let Some(path) = &self.state_path {
+            let tmp = path.with_extension("tmp");
+            fs::write(&tmp, &rlp::encode(on))?;
+            fs::rename(&tmp, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::protocol::seal_request;
+    use super::*;
+    use crate::consensus::VoteStep;
+    use ckey::{verify_schnorr, Generator, Random};
+
+    fn daemon() -> SignerDaemon {
+        SignerDaemon::new(Random.generate().unwrap(), H256::random(), None).unwrap()
+    }
+
+    fn vote(height: u64, step: Step, block: u64) -> VoteOn {
+        VoteOn {
+            step: VoteStep::new(height, 0, step),
+            block_hash: Some(H256::from(block).into()),
+        }
+    }
+
+    #[test]
+    fn sign_increasing_votes() {
+        let daemon = daemon();
+        for on in &[vote(1, Step::Propose, 1), vote(1, Step::Prevote, 1), vote(1, Step::Precommit, 1)] {
+            match daemon.handle(Request::SignVote(on.clone())) {
+                Response::Schnorr(signature) => {
+                    assert!(verify_schnorr(daemon.public(), &signature, &on.hash()).unwrap())
+                }
+                response => panic!("Unexpected response {:?}", response),
+            }
+        }
+    }
+
+    #[test]
+    fn refuse_double_vote() {
+        let daemon = daemon();
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Prevote, 1))) {
+            Response::Schnorr(_) => true,
+            _ => false,
+        });
+        // Signing the same vote again is fine.
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Prevote, 1))) {
+            Response::Schnorr(_) => true,
+            _ => false,
+        });
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Prevote, 2))) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Propose, 1))) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Commit, 1))) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn sign_with_consensus_key() {
+        let mut daemon = daemon();
+        let consensus_key = Random.generate().unwrap();
+        daemon.add_consensus_key(consensus_key);
+
+        let on = vote(1, Step::Prevote, 1);
+        match daemon.handle(Request::SignVoteWithKey(*consensus_key.public(), on.clone())) {
+            Response::Schnorr(signature) => {
+                assert!(verify_schnorr(consensus_key.public(), &signature, &on.hash()).unwrap())
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        // The votes signed with the consensus key are checked against the same last vote.
+        assert!(match daemon.handle(Request::SignVote(vote(1, Step::Prevote, 2))) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+        assert!(match daemon.handle(Request::SignVoteWithKey(*Random.generate().unwrap().public(), on)) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn restore_last_vote() {
+        let path = std::env::temp_dir().join(format!("codechain-signer-{}", H256::random()));
+        let key_pair = Random.generate().unwrap();
+        {
+            let daemon = SignerDaemon::new(key_pair, H256::random(), Some(path.clone())).unwrap();
+            daemon.handle(Request::SignVote(vote(2, Step::Precommit, 1)));
+        }
+        let daemon = SignerDaemon::new(key_pair, H256::random(), Some(path.clone())).unwrap();
+        assert!(match daemon.handle(Request::SignVote(vote(2, Step::Prevote, 1))) {
+            Response::Refused(_) => true,
+            _ => false,
+        });
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn serve_requests() {
+        struct Pipe {
+            input: io::Cursor<Vec<u8>>,
+            output: Vec<u8>,
+        }
+        impl Read for Pipe {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.input.read(buf)
+            }
+        }
+        impl Write for Pipe {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.output.write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let daemon = daemon();
+        let mut pipe = Pipe {
+            input: io::Cursor::new(Vec::new()),
+            output: Vec::new(),
+        };
+        // A request sealed without the nonce of the connection is rejected.
+        write_frame(pipe.input.get_mut(), &seal_request(&daemon.secret, &H256::zero(), 1, &Request::PublicKey))
+            .unwrap();
+        assert!(daemon.serve(&mut pipe).is_err());
+
+        let mut output = io::Cursor::new(pipe.output);
+        let hello: Hello = rlp::decode(&read_frame(&mut output).unwrap()).unwrap();
+        assert_eq!(PROTOCOL_VERSION, hello.version);
+        assert!(read_frame(&mut output).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright 2018 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+mod daemon;
+mod protocol;
+mod remote;
+
+pub use self::daemon::SignerDaemon;
+pub use self::remote::{Endpoint as RemoteSignerEndpoint, Error as RemoteSignerError, RemoteSigner};
+
+use crate::account_provider::{AccountProvider, Error as AccountProviderError};
+use crate::consensus::VoteOn;
+use ccrypto::blake256;
+use ckey::{public_to_address, Address, Error as KeyError, Public, SchnorrSignature, Signature};
+use ckeystore::DecryptedAccount;
+use primitives::H256;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Read the secret shared with a signing daemon.
+/// Surrounding whitespaces in the file are ignored.
+pub fn read_signer_secret(path: &Path) -> io::Result<H256> {
+    let contents = fs::read_to_string(path)?;
+    let secret = contents.trim();
+    if secret.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "The secret is empty"))
+    }
+    Ok(blake256(secret))
+}
+
+#[derive(Debug)]
+pub enum Error {
+    AccountProvider(AccountProviderError),
+    Remote(RemoteSignerError),
+    /// The remote signer signs only consensus votes with Schnorr.
+    NotSupportedByRemote,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::AccountProvider(err) => err.fmt(f),
+            Error::Remote(err) => err.fmt(f),
+            Error::NotSupportedByRemote => f.write_str("The remote signer signs only consensus votes with Schnorr"),
+        }
+    }
+}
+
+impl From<AccountProviderError> for Error {
+    fn from(err: AccountProviderError) -> Self {
+        Error::AccountProvider(err)
+    }
+}
+
+impl From<KeyError> for Error {
+    fn from(err: KeyError) -> Self {
+        Error::AccountProvider(err.into())
+    }
+}
+
+impl From<RemoteSignerError> for Error {
+    fn from(err: RemoteSignerError) -> Self {
+        Error::Remote(err)
+    }
+}
+
+/// Everything that an Engine needs to sign messages.
+pub struct EngineSigner {
+    account_provider: Arc<AccountProvider>,
+    signer: Option<(Address, Public)>,
+    decrypted_account: Option<DecryptedAccount>,
+    /// The key signing consensus messages if the account registered another key.
+    /// The account is None if the remote signer holds the key.
+    consensus_key: Option<(Public, Option<DecryptedAccount>)>,
+    /// The signing daemon holding the key of the signer.
+    remote: Option<RemoteSigner>,
+}
+
+impl Default for EngineSigner {
+    fn default() -> Self {
+        EngineSigner {
+            account_provider: AccountProvider::transient_provider(),
+            signer: Default::default(),
+            decrypted_account: Default::default(),
+            consensus_key: Default::default(),
+            remote: Default::default(),
+        }
+    }
+}
+
+impl EngineSigner {
+    /// Set up the signer to sign with given address and password.
+    pub fn set(&mut self, ap: Arc<AccountProvider>, address: Address) {
+        let public = {
+            let account = ap.get_unlocked_account(&address).expect("The address must be registered in AccountProvider");
+            account.public().expect("Cannot get public from account")
+        };
+        self.account_provider = ap;
+        self.signer = Some((address, public));
+        self.decrypted_account = None;
+        self.consensus_key = None;
+        self.remote = None;
+        cinfo!(ENGINE, "Setting Engine signer to {}", address);
+    }
+
+    // TODO: remove decrypted_account after some timeout
+    pub fn set_to_keep_decrypted_account(&mut self, ap: Arc<AccountProvider>, address: Address) {
+        let account =
+            ap.get_unlocked_account(&address).expect("The address must be registered in AccountProvider").disclose();
+        let public = account.public().expect("Cannot get public from account");
+
+        self.account_provider = ap;
+        self.signer = Some((address, public));
+        self.decrypted_account = Some(account);
+        self.consensus_key = None;
+        self.remote = None;
+        cinfo!(ENGINE, "Setting Engine signer to {} (retaining)", address);
+    }
+
+    /// Set up the signer to sign with the key held by a signing daemon.
+    pub fn set_remote(&mut self, remote: RemoteSigner) {
+        let address = remote.address();
+        self.signer = Some((address, *remote.public()));
+        self.decrypted_account = None;
+        self.consensus_key = None;
+        self.remote = Some(remote);
+        cinfo!(ENGINE, "Setting Engine signer to {} (remote)", address);
+    }
+
+    /// Set the key signing consensus messages.
+    /// The account of the key should be unlocked unless it's the key of the signer.
+    /// With a remote signer, the daemon should hold the key instead.
+    pub fn set_consensus_key(&mut self, public: Public) -> Result<(), AccountProviderError> {
+        if self.public() == Some(&public) {
+            self.consensus_key = None;
+            cinfo!(ENGINE, "Signing consensus messages with the key of the engine signer");
+            return Ok(())
+        }
+        let address = public_to_address(&public);
+        if self.remote.is_some() {
+            self.consensus_key = Some((public, None));
+            cinfo!(ENGINE, "Signing consensus messages with the key of {} (remote)", address);
+            return Ok(())
+        }
+        let account = self.account_provider.get_unlocked_account(&address)?.disclose();
+        self.consensus_key = Some((public, Some(account)));
+        cinfo!(ENGINE, "Signing consensus messages with the key of {}", address);
+        Ok(())
+    }
+
+    /// Sign a consensus vote.
+    /// Unlike `sign`, it works with a remote signer, which checks whether the vote regresses.
+    pub fn sign_vote(&self, on: &VoteOn) -> Result<SchnorrSignature, Error> {
+        if let Some(remote) = &self.remote {
+            return match &self.consensus_key {
+                Some((public, _)) => Ok(remote.sign_vote_with_key(public, on)?),
+                None => Ok(remote.sign_vote(on)?),
+            }
+        }
+        self.sign(on.hash())
+    }
+
+    /// Sign a consensus message hash.
+    pub fn sign(&self, hash: H256) -> Result<SchnorrSignature, Error> {
+        if self.remote.is_some() {
+            return Err(Error::NotSupportedByRemote)
+        }
+        if let Some((_public, Some(account))) = &self.consensus_key {
+            return Ok(account.sign_schnorr(&hash)?)
+        }
+        let address = self.signer.map(|(address, _public)| address).unwrap_or_else(Default::default);
+        let result = match &self.decrypted_account {
+            Some(account) => account.sign_schnorr(&hash)?,
+            None => {
+                let account = self.account_provider.get_unlocked_account(&address)?;
+                account.sign_schnorr(&hash)?
+            }
+        };
+        Ok(result)
+    }
+
+    /// Sign a message hash with ECDSA.
+    pub fn sign_ecdsa(&self, hash: H256) -> Result<Signature, Error> {
+        if let Some(remote) = &self.remote {
+            return Ok(remote.sign_ecdsa(hash)?)
+        }
+        let address = self.signer.map(|(address, _public)| address).unwrap_or_else(Default::default);
+        let result = match &self.decrypted_account {
+            Some(account) => account.sign(&hash)?,
+            None => {
+                let account = self.account_provider.get_unlocked_account(&address)?;
+                account.sign(&hash)?
+            }
+        };
+        Ok(result)
+    }
+
+    /// Public Key of signer.
+    pub fn public(&self) -> Option<&Public> {
+        self.signer.as_ref().map(|(_address, public)| public)
+    }
+
+    /// Public Key signing consensus messages.
+    pub fn consensus_public(&self) -> Option<&Public> {
+        match &self.consensus_key {
+            Some((public, _account)) => Some(public),
+            None => self.public(),
+        }
+    }
+
+    /// Address of signer.
+    pub fn address(&self) -> Option<&Address> {
+        self.signer.as_ref().map(|(address, _)| address)
+    }
+
+    /// Check if the given address is the signing address.
+    pub fn is_address(&self, a: &Address) -> bool {
+        self.signer.map_or(false, |(address, _public)| *a == address)
+    }
+
+    /// Check if the signing address was set.
+    pub fn is_some(&self) -> bool {
+        self.signer.is_some()
+    }
+}
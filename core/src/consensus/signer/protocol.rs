@@ -0,0 +1,270 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The wire protocol between a node and a remote signing daemon.
+//!
+//! Every frame is a 4-byte big-endian length followed by an RLP payload.
+//! When a connection is accepted, the daemon sends a `Hello` frame carrying the protocol version and a random nonce.
+//! Each request and response is authenticated with a MAC keyed by the shared secret,
+//! which covers the nonce of the connection, the direction, the sequence number and the payload.
+//! The sequence number of requests must strictly increase in a connection,
+//! so a recorded request can't be replayed on the same or another connection.
+
+use crate::consensus::VoteOn;
+use ccrypto::blake256_with_key;
+use ckey::{Public, SchnorrSignature, Signature};
+use primitives::H256;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::io::{self, Read, Write};
+
+pub const PROTOCOL_VERSION: u8 = 1;
+/// Frames are small. Anything bigger than this is a protocol violation.
+pub const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+const REQUEST_DIRECTION: u8 = 0;
+const RESPONSE_DIRECTION: u8 = 1;
+
+#[derive(Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct Hello {
+    pub version: u8,
+    pub nonce: H256,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Request {
+    PublicKey,
+    /// Schnorr signature on a consensus vote. The daemon refuses it if the vote regresses.
+    SignVote(VoteOn),
+    /// ECDSA signature on a message hash.
+    SignMessage(H256),
+    /// Schnorr signature on a consensus vote with the consensus key of the validator.
+    /// The votes signed with any key are checked against the same last vote.
+    SignVoteWithKey(Public, VoteOn),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Response {
+    PublicKey(Public),
+    Schnorr(SchnorrSignature),
+    Ecdsa(Signature),
+    Refused(String),
+}
+
+const REQUEST_PUBLIC_KEY: u8 = 1;
+const REQUEST_SIGN_VOTE: u8 = 2;
+const REQUEST_SIGN_MESSAGE: u8 = 3;
+const REQUEST_SIGN_VOTE_WITH_KEY: u8 = 4;
+
+const RESPONSE_PUBLIC_KEY: u8 = 1;
+const RESPONSE_SCHNORR: u8 = 2;
+const RESPONSE_ECDSA: u8 = 3;
+const RESPONSE_REFUSED: u8 = 4;
+
+impl Encodable for Request {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Request::PublicKey => {
+                s.begin_list(1).append(&REQUEST_PUBLIC_KEY);
+            }
+            Request::SignVote(on) => {
+                s.begin_list(2).append(&REQUEST_SIGN_VOTE).append(on);
+            }
+            Request::SignMessage(hash) => {
+                s.begin_list(2).append(&REQUEST_SIGN_MESSAGE).append(hash);
+            }
+            Request::SignVoteWithKey(public, on) => {
+                s.begin_list(3).append(&REQUEST_SIGN_VOTE_WITH_KEY).append(public).append(on);
+            }
+        }
+    }
+}
+
+impl Decodable for Request {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let tag: u8 = rlp.val_at(0)?;
+        let (request, expected) = match tag {
+            REQUEST_PUBLIC_KEY => (Request::PublicKey, 1),
+            REQUEST_SIGN_VOTE => (Request::SignVote(rlp.val_at(1)?), 2),
+            REQUEST_SIGN_MESSAGE => (Request::SignMessage(rlp.val_at(1)?), 2),
+            REQUEST_SIGN_VOTE_WITH_KEY => (Request::SignVoteWithKey(rlp.val_at(1)?, rlp.val_at(2)?), 3),
+            _ => return Err(DecoderError::Custom("Unexpected request tag")),
+        };
+        let item_count = rlp.item_count()?;
+        if item_count != expected {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected,
+            })
+        }
+        Ok(request)
+    }
+}
+
+impl Encodable for Response {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        match self {
+            Response::PublicKey(public) => {
+                s.begin_list(2).append(&RESPONSE_PUBLIC_KEY).append(public);
+            }
+            Response::Schnorr(signature) => {
+                s.begin_list(2).append(&RESPONSE_SCHNORR).append(signature);
+            }
+            Response::Ecdsa(signature) => {
+                s.begin_list(2).append(&RESPONSE_ECDSA).append(signature);
+            }
+            Response::Refused(reason) => {
+                s.begin_list(2).append(&RESPONSE_REFUSED).append(reason);
+            }
+        }
+    }
+}
+
+impl Decodable for Response {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        let tag: u8 = rlp.val_at(0)?;
+        match tag {
+            RESPONSE_PUBLIC_KEY => Ok(Response::PublicKey(rlp.val_at(1)?)),
+            RESPONSE_SCHNORR => Ok(Response::Schnorr(rlp.val_at(1)?)),
+            RESPONSE_ECDSA => Ok(Response::Ecdsa(rlp.val_at(1)?)),
+            RESPONSE_REFUSED => Ok(Response::Refused(rlp.val_at(1)?)),
+            _ => Err(DecoderError::Custom("Unexpected response tag")),
+        }
+    }
+}
+
+fn mac(secret: &H256, nonce: &H256, direction: u8, seq: u64, payload: &[u8]) -> H256 {
+    let mut data = Vec::with_capacity(32 + 1 + 8 + payload.len());
+    data.extend_from_slice(nonce);
+    data.push(direction);
+    data.extend_from_slice(&seq.to_be_bytes());
+    data.extend_from_slice(payload);
+    blake256_with_key(&data, secret)
+}
+
+fn seal<T: Encodable>(secret: &H256, nonce: &H256, direction: u8, seq: u64, message: &T) -> Vec<u8> {
+    let payload = message.rlp_bytes();
+    let mac = mac(secret, nonce, direction, seq, &payload);
+    let mut s = RlpStream::new_list(3);
+    s.append(&seq).append_raw(&payload, 1).append(&mac);
+    s.out()
+}
+
+fn open<T: Decodable>(secret: &H256, nonce: &H256, direction: u8, frame: &[u8]) -> Result<(u64, T), DecoderError> {
+    let rlp = Rlp::new(frame);
+    let item_count = rlp.item_count()?;
+    if item_count != 3 {
+        return Err(DecoderError::RlpIncorrectListLen {
+            got: item_count,
+            expected: 3,
+        })
+    }
+    let seq: u64 = rlp.val_at(0)?;
+    let payload = rlp.at(1)?;
+    let received: H256 = rlp.val_at(2)?;
+    if mac(secret, nonce, direction, seq, payload.as_raw()) != received {
+        return Err(DecoderError::Custom("Invalid MAC"))
+    }
+    Ok((seq, payload.as_val()?))
+}
+
+pub fn seal_request(secret: &H256, nonce: &H256, seq: u64, request: &Request) -> Vec<u8> {
+    seal(secret, nonce, REQUEST_DIRECTION, seq, request)
+}
+
+pub fn open_request(secret: &H256, nonce: &H256, frame: &[u8]) -> Result<(u64, Request), DecoderError> {
+    open(secret, nonce, REQUEST_DIRECTION, frame)
+}
+
+pub fn seal_response(secret: &H256, nonce: &H256, seq: u64, response: &Response) -> Vec<u8> {
+    seal(secret, nonce, RESPONSE_DIRECTION, seq, response)
+}
+
+pub fn open_response(secret: &H256, nonce: &H256, frame: &[u8]) -> Result<(u64, Response), DecoderError> {
+    open(secret, nonce, RESPONSE_DIRECTION, frame)
+}
+
+pub fn write_frame<W: Write>(writer: &mut W, frame: &[u8]) -> io::Result<()> {
+    if frame.len() > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "The frame is too large"))
+    }
+    writer.write_all(&(frame.len() as u32).to_be_bytes())?;
+    writer.write_all(frame)?;
+    writer.flush()
+}
+
+pub fn read_frame<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut length = [0u8; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if length > MAX_FRAME_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "The frame is too large"))
+    }
+    let mut frame = vec![0u8; length];
+    reader.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::{Step, VoteStep};
+    use std::io::Cursor;
+
+    #[test]
+    fn request_round_trip() {
+        let secret = H256::random();
+        let nonce = H256::random();
+        let request = Request::SignVote(VoteOn {
+            step: VoteStep::new(3, 1, Step::Prevote),
+            block_hash: Some(H256::random().into()),
+        });
+
+        let frame = seal_request(&secret, &nonce, 7, &request);
+        let mut buffer = Cursor::new(Vec::new());
+        write_frame(&mut buffer, &frame).unwrap();
+        buffer.set_position(0);
+        let received = read_frame(&mut buffer).unwrap();
+
+        assert_eq!(Ok((7, request)), open_request(&secret, &nonce, &received));
+    }
+
+    #[test]
+    fn reject_wrong_secret_and_nonce() {
+        let secret = H256::random();
+        let nonce = H256::random();
+        let frame = seal_request(&secret, &nonce, 1, &Request::PublicKey);
+
+        assert!(open_request(&H256::random(), &nonce, &frame).is_err());
+        assert!(open_request(&secret, &H256::random(), &frame).is_err());
+    }
+
+    #[test]
+    fn reject_reflected_frame() {
+        let secret = H256::random();
+        let nonce = H256::random();
+        let frame = seal_response(&secret, &nonce, 1, &Response::Refused("test".to_string()));
+
+        assert!(open_request(&secret, &nonce, &frame).is_err());
+        assert!(open_response(&secret, &nonce, &frame).is_ok());
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::protocol::{open_response, read_frame, seal_request, write_frame, Hello, Request, Response, PROTOCOL_VERSION};
+use crate::consensus::VoteOn;
+use ckey::{public_to_address, verify, verify_schnorr, Address, Error as KeyError, Public, SchnorrSignature, Signature};
+use parking_lot::Mutex;
+use primitives::H256;
+use rlp::DecoderError;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The address of a signing daemon.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Endpoint {
+    #[cfg(unix)]
+    Unix(PathBuf),
+    Tcp(SocketAddr),
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    /// Parse `unix:<path>` or `<host>:<port>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        {
+            const UNIX_PREFIX: &str = "unix:";
+            if s.starts_with(UNIX_PREFIX) {
+                return Ok(Endpoint::Unix(PathBuf::from(&s[UNIX_PREFIX.len()..])))
+            }
+        }
+        s.to_socket_addrs()
+            .map_err(|err| format!("Invalid endpoint {}: {}", s, err))?
+            .next()
+            .map(Endpoint::Tcp)
+            .ok_or_else(|| format!("Cannot resolve {}", s))
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            Endpoint::Tcp(addr) => write!(f, "{}", addr),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The daemon violated the protocol, or doesn't share the secret.
+    Protocol(String),
+    Key(KeyError),
+    /// The daemon refused to sign.
+    Refused(String),
+    /// The daemon returned a signature which is not signed by its key.
+    InvalidSignature,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "Cannot communicate with the remote signer: {}", err),
+            Error::Protocol(msg) => write!(f, "The remote signer violated the protocol: {}", msg),
+            Error::Key(err) => err.fmt(f),
+            Error::Refused(reason) => write!(f, "The remote signer refused to sign: {}", reason),
+            Error::InvalidSignature => f.write_str("The remote signer returned an invalid signature"),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<DecoderError> for Error {
+    fn from(err: DecoderError) -> Self {
+        Error::Protocol(format!("{:?}", err))
+    }
+}
+
+impl From<KeyError> for Error {
+    fn from(err: KeyError) -> Self {
+        Error::Key(err)
+    }
+}
+
+enum Stream {
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+            Stream::Tcp(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+            Stream::Tcp(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+            Stream::Tcp(stream) => stream.flush(),
+        }
+    }
+}
+
+struct Connection {
+    stream: Stream,
+    nonce: H256,
+    seq: u64,
+}
+
+impl Connection {
+    fn open(endpoint: &Endpoint) -> Result<Self, Error> {
+        let mut stream = match endpoint {
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let stream = UnixStream::connect(path)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                Stream::Unix(stream)
+            }
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect_timeout(addr, TIMEOUT)?;
+                stream.set_read_timeout(Some(TIMEOUT))?;
+                stream.set_write_timeout(Some(TIMEOUT))?;
+                stream.set_nodelay(true)?;
+                Stream::Tcp(stream)
+            }
+        };
+        let hello: Hello = rlp::decode(&read_frame(&mut stream)?)?;
+        if hello.version != PROTOCOL_VERSION {
+            return Err(Error::Protocol(format!("Unsupported protocol version {}", hello.version)))
+        }
+        Ok(Self {
+            stream,
+            nonce: hello.nonce,
+            seq: 0,
+        })
+    }
+
+    fn request(&mut self, secret: &H256, request: &Request) -> Result<Response, Error> {
+        self.seq += 1;
+        write_frame(&mut self.stream, &seal_request(secret, &self.nonce, self.seq, request))?;
+        let (seq, response) = open_response(secret, &self.nonce, &read_frame(&mut self.stream)?)?;
+        if seq != self.seq {
+            return Err(Error::Protocol(format!("Expected the response of {} but got {}", self.seq, seq)))
+        }
+        Ok(response)
+    }
+}
+
+/// A client of a signing daemon, which holds the key of the engine signer.
+pub struct RemoteSigner {
+    endpoint: Endpoint,
+    secret: H256,
+    public: Public,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl RemoteSigner {
+    /// Connect to the daemon and fetch its public key.
+    pub fn connect(endpoint: Endpoint, secret: H256) -> Result<Self, Error> {
+        let mut connection = Connection::open(&endpoint)?;
+        let public = match connection.request(&secret, &Request::PublicKey)? {
+            Response::PublicKey(public) => public,
+            response => return Err(Error::Protocol(format!("Unexpected response {:?}", response))),
+        };
+        cinfo!(ENGINE, "Connected to the remote signer at {} for {}", endpoint, public_to_address(&public));
+        Ok(Self {
+            endpoint,
+            secret,
+            public,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+
+    pub fn public(&self) -> &Public {
+        &self.public
+    }
+
+    pub fn address(&self) -> Address {
+        public_to_address(&self.public)
+    }
+
+    /// Sign a consensus vote with Schnorr.
+    pub fn sign_vote(&self, on: &VoteOn) -> Result<SchnorrSignature, Error> {
+        self.request_vote_signature(Request::SignVote(on.clone()), &self.public, on)
+    }
+
+    /// Sign a consensus vote with Schnorr using a consensus key held by the daemon.
+    pub fn sign_vote_with_key(&self, public: &Public, on: &VoteOn) -> Result<SchnorrSignature, Error> {
+        self.request_vote_signature(Request::SignVoteWithKey(*public, on.clone()), public, on)
+    }
+
+    fn request_vote_signature(
+        &self,
+        request: Request,
+        public: &Public,
+        on: &VoteOn,
+    ) -> Result<SchnorrSignature, Error> {
+        match self.request(request)? {
+            Response::Schnorr(signature) => {
+                if !verify_schnorr(public, &signature, &on.hash())? {
+                    return Err(Error::InvalidSignature)
+                }
+                Ok(signature)
+            }
+            Response::Refused(reason) => Err(Error::Refused(reason)),
+            response => Err(Error::Protocol(format!("Unexpected response {:?}", response))),
+        }
+    }
+
+    /// Sign a message hash with ECDSA.
+    pub fn sign_ecdsa(&self, hash: H256) -> Result<Signature, Error> {
+        match self.request(Request::SignMessage(hash))? {
+            Response::Ecdsa(signature) => {
+                if !verify(&self.public, &signature, &hash)? {
+                    return Err(Error::InvalidSignature)
+                }
+                Ok(signature)
+            }
+            Response::Refused(reason) => Err(Error::Refused(reason)),
+            response => Err(Error::Protocol(format!("Unexpected response {:?}", response))),
+        }
+    }
+
+    /// Send the request, reconnecting once if the connection was broken.
+    /// Retrying is safe since the daemon signs the same vote again.
+    fn request(&self, request: Request) -> Result<Response, Error> {
+        let mut connection = self.connection.lock();
+        if let Some(established) = connection.as_mut() {
+            match established.request(&self.secret, &request) {
+                Ok(response) => return Ok(response),
+                Err(err) => cwarn!(ENGINE, "The connection to the remote signer is broken: {}", err),
+            }
+        }
+        *connection = None;
+
+        let mut established = Connection::open(&self.endpoint)?;
+        let response = established.request(&self.secret, &request)?;
+        *connection = Some(established);
+        Ok(response)
+    }
+}
@@ -87,13 +87,17 @@ impl ConsensusEngine for Solo {
         let parent_hash = *block.header().parent_hash();
         let parent = client.block_header(&parent_hash.into()).expect("Parent header must exist");
         let parent_common_params = client.common_params(parent_hash.into()).expect("CommonParams of parent must exist");
+        let parent_base_fee_multiplier =
+            client.base_fee_multiplier(parent_hash.into()).expect("Metadata of parent must exist");
         let author = *block.header().author();
         let (total_reward, total_min_fee) = {
             let transactions = block.transactions();
             let block_reward = self.block_reward(block.header().number());
             let total_min_fee: u64 = transactions.iter().map(|tx| tx.fee).sum();
-            let min_fee: u64 =
-                transactions.iter().map(|tx| CodeChainMachine::min_cost(&parent_common_params, &tx.action)).sum();
+            let min_fee: u64 = transactions
+                .iter()
+                .map(|tx| CodeChainMachine::min_cost(&parent_common_params, parent_base_fee_multiplier, &tx.action))
+                .sum();
             (block_reward + total_min_fee, min_fee)
         };
 
@@ -42,6 +42,8 @@ lazy_static! {
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Banned").into_key();
     pub static ref VALIDATORS_KEY: H256 =
         ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"Validators").into_key();
+    pub static ref CONSENSUS_KEYS_KEY: H256 =
+        ActionDataKeyBuilder::new(CUSTOM_ACTION_HANDLER_ID, 1).append(&"ConsensusKeys").into_key();
 }
 
 pub fn get_delegation_key(address: &Address) -> H256 {
@@ -699,6 +701,81 @@ impl Banned {
     }
 }
 
+/// The keys that accounts sign consensus messages with, instead of the keys of the accounts.
+#[derive(Default, Debug, PartialEq)]
+pub struct ConsensusKeys {
+    current: BTreeMap<Address, Public>,
+    /// The keys that take effect from the next term.
+    pending: BTreeMap<Address, Public>,
+}
+
+impl ConsensusKeys {
+    pub fn load_from_state(state: &TopLevelState) -> StateResult<Self> {
+        let key = *CONSENSUS_KEYS_KEY;
+        let action_data = state.action_data(&key)?;
+        let (current, pending) = decode_map_tuple(action_data.as_ref());
+
+        Ok(Self {
+            current,
+            pending,
+        })
+    }
+
+    pub fn save_to_state(&self, state: &mut TopLevelState) -> StateResult<()> {
+        let key = *CONSENSUS_KEYS_KEY;
+        if self.current.is_empty() && self.pending.is_empty() {
+            state.remove_action_data(&key);
+        } else {
+            let encoded = encode_map_tuple(&self.current, &self.pending);
+            state.update_action_data(&key, encoded)?;
+        }
+        Ok(())
+    }
+
+    /// The key that the owner of the pubkey signs consensus messages with.
+    pub fn consensus_key(&self, pubkey: &Public) -> Public {
+        self.current.get(&public_to_address(pubkey)).cloned().unwrap_or(*pubkey)
+    }
+
+    pub fn pending_key(&self, address: &Address) -> Option<&Public> {
+        self.pending.get(address)
+    }
+
+    /// Checks whether an account other than the given one registered the pubkey.
+    pub fn is_used_by_others(&self, address: &Address, pubkey: &Public) -> bool {
+        self.current.iter().chain(self.pending.iter()).any(|(owner, key)| owner != address && key == pubkey)
+    }
+
+    pub fn register(&mut self, address: Address, pubkey: Public) {
+        self.pending.insert(address, pubkey);
+    }
+
+    /// Drops the keys of the account, which can't be a validator anymore.
+    pub fn remove(&mut self, address: &Address) {
+        self.current.remove(address);
+        self.pending.remove(address);
+    }
+
+    /// Drops the keys of the accounts that are no longer candidates.
+    /// They should register their keys again after nominating themselves.
+    pub fn retain_candidates(&mut self, candidates: &Candidates) {
+        let is_candidate = |(address, _): &(Address, Public)| candidates.get_candidate(address).is_some();
+        self.current = mem::replace(&mut self.current, BTreeMap::new()).into_iter().filter(is_candidate).collect();
+        self.pending = mem::replace(&mut self.pending, BTreeMap::new()).into_iter().filter(is_candidate).collect();
+    }
+
+    pub fn apply_pending(&mut self) {
+        for (address, pubkey) in mem::replace(&mut self.pending, BTreeMap::new()) {
+            // Registering the account's own key restores it.
+            if public_to_address(&pubkey) == address {
+                self.current.remove(&address);
+            } else {
+                self.current.insert(address, pubkey);
+            }
+        }
+    }
+}
+
 fn decode_set<V>(data: Option<&ActionData>) -> BTreeSet<V>
 where
     V: Ord + Decodable, {
@@ -1718,6 +1795,57 @@ mod tests {
         assert!(!banned.is_banned(&innocent));
     }
 
+    #[test]
+    fn consensus_keys_take_effect_after_apply() {
+        let mut state = helpers::get_temp_state();
+        let owner = Public::random();
+        let address = public_to_address(&owner);
+        let new_key = Public::random();
+
+        let mut keys = ConsensusKeys::load_from_state(&state).unwrap();
+        keys.register(address, new_key);
+        keys.save_to_state(&mut state).unwrap();
+
+        let mut keys = ConsensusKeys::load_from_state(&state).unwrap();
+        assert_eq!(Some(&new_key), keys.pending_key(&address));
+        assert_eq!(owner, keys.consensus_key(&owner));
+        assert!(keys.is_used_by_others(&Address::random(), &new_key));
+        assert!(!keys.is_used_by_others(&address, &new_key));
+
+        keys.apply_pending();
+        assert_eq!(None, keys.pending_key(&address));
+        assert_eq!(new_key, keys.consensus_key(&owner));
+
+        keys.register(address, owner);
+        keys.apply_pending();
+        assert_eq!(owner, keys.consensus_key(&owner));
+        keys.save_to_state(&mut state).unwrap();
+        let result = state.action_data(&*CONSENSUS_KEYS_KEY).unwrap();
+        assert_eq!(result, None, "Should clean the state if no account uses another key");
+    }
+
+    #[test]
+    fn consensus_keys_of_non_candidates_are_dropped() {
+        let candidate = Public::random();
+        let quitter = Public::random();
+        let mut candidates = Candidates(Vec::new());
+        candidates.add_deposit(&candidate, 10, 0, b"".to_vec());
+
+        let mut keys = ConsensusKeys::default();
+        keys.register(public_to_address(&candidate), Public::random());
+        keys.register(public_to_address(&quitter), Public::random());
+        keys.apply_pending();
+        keys.register(public_to_address(&quitter), Public::random());
+
+        keys.retain_candidates(&candidates);
+        assert_ne!(candidate, keys.consensus_key(&candidate));
+        assert_eq!(quitter, keys.consensus_key(&quitter));
+        assert_eq!(None, keys.pending_key(&public_to_address(&quitter)));
+
+        keys.remove(&public_to_address(&candidate));
+        assert_eq!(ConsensusKeys::default(), keys);
+    }
+
     #[test]
     fn latest_deposit_higher_priority() {
         let mut state = helpers::get_temp_state();
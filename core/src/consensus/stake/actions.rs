@@ -17,13 +17,26 @@
 use crate::client::ConsensusClient;
 use crate::consensus::{ConsensusMessage, ValidatorSet};
 use ccrypto::Blake;
-use ckey::{recover, Address, Signature};
+use ckey::{recover, Address, NetworkId, Public, Signature};
 use ctypes::errors::SyntaxError;
 use ctypes::CommonParams;
 use primitives::{Bytes, H256};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::sync::Arc;
 
+/// Separates the proof of a consensus key from the signatures on other messages.
+const CHANGE_CONSENSUS_KEY_DOMAIN: &str = "ChangeConsensusKey";
+
+/// The era of the common params from which the consensus keys can be registered.
+pub const CONSENSUS_KEY_ERA: u64 = 1;
+
+/// The message that the new consensus key signs to prove that the account owns it.
+pub fn change_consensus_key_message(network_id: NetworkId, account: &Address, pubkey: &Public) -> H256 {
+    let mut s = RlpStream::new_list(4);
+    s.append(&CHANGE_CONSENSUS_KEY_DOMAIN).append(&network_id).append(account).append(pubkey);
+    H256::blake(s.out())
+}
+
 #[derive(Clone, Copy)]
 #[repr(u8)]
 enum ActionTag {
@@ -33,6 +46,7 @@ enum ActionTag {
     SelfNominate = 4,
     ReportDoubleVote = 5,
     Redelegate = 6,
+    ChangeConsensusKey = 7,
     ChangeParams = 0xFF,
 }
 
@@ -52,6 +66,7 @@ impl Decodable for ActionTag {
             4 => Ok(ActionTag::SelfNominate),
             5 => Ok(ActionTag::ReportDoubleVote),
             6 => Ok(ActionTag::Redelegate),
+            7 => Ok(ActionTag::ChangeConsensusKey),
             0xFF => Ok(ActionTag::ChangeParams),
             _ => Err(DecoderError::Custom("Unexpected ActionTag Value")),
         }
@@ -81,6 +96,13 @@ pub enum Action {
         deposit: u64,
         metadata: Bytes,
     },
+    /// Registers the key that signs consensus messages from the next term.
+    /// The signature on `change_consensus_key_message` is the proof that the sender owns the key.
+    ChangeConsensusKey {
+        network_id: NetworkId,
+        pubkey: Public,
+        signature: Signature,
+    },
     ChangeParams {
         metadata_seq: u64,
         params: Box<CommonParams>,
@@ -113,6 +135,23 @@ impl Action {
             Action::Redelegate {
                 ..
             } => {}
+            Action::ChangeConsensusKey {
+                network_id,
+                ..
+            } => {
+                if current_params.era() < CONSENSUS_KEY_ERA {
+                    return Err(SyntaxError::InvalidCustomAction(format!(
+                        "The consensus key is available from era {}",
+                        CONSENSUS_KEY_ERA
+                    )))
+                }
+                if *network_id != current_params.network_id() {
+                    return Err(SyntaxError::InvalidCustomAction(format!(
+                        "The proof of the consensus key is signed for the network {}",
+                        network_id
+                    )))
+                }
+            }
             Action::SelfNominate {
                 metadata,
                 ..
@@ -238,6 +277,17 @@ impl Encodable for Action {
             } => {
                 s.begin_list(3).append(&ActionTag::SelfNominate).append(deposit).append(metadata);
             }
+            Action::ChangeConsensusKey {
+                network_id,
+                pubkey,
+                signature,
+            } => {
+                s.begin_list(4)
+                    .append(&ActionTag::ChangeConsensusKey)
+                    .append(network_id)
+                    .append(pubkey)
+                    .append(signature);
+            }
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -334,6 +384,20 @@ impl Decodable for Action {
                     metadata: rlp.val_at(2)?,
                 })
             }
+            ActionTag::ChangeConsensusKey => {
+                let item_count = rlp.item_count()?;
+                if item_count != 4 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 4,
+                        got: item_count,
+                    })
+                }
+                Ok(Action::ChangeConsensusKey {
+                    network_id: rlp.val_at(1)?,
+                    pubkey: rlp.val_at(2)?,
+                    signature: rlp.val_at(3)?,
+                })
+            }
             ActionTag::ChangeParams => {
                 let item_count = rlp.item_count()?;
                 if item_count < 4 {
@@ -404,6 +468,15 @@ mod tests {
         });
     }
 
+    #[test]
+    fn rlp_of_change_consensus_key() {
+        rlp_encode_and_decode_test!(Action::ChangeConsensusKey {
+            network_id: "tc".into(),
+            pubkey: Public::random(),
+            signature: Signature::random(),
+        });
+    }
+
     struct ConsensusMessageInfo {
         pub height: u64,
         pub view: u64,
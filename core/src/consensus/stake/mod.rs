@@ -17,12 +17,16 @@
 mod action_data;
 mod actions;
 mod distribute;
+mod self_nomination;
 
-use crate::client::ConsensusClient;
+use crate::client::{ConsensusClient, EngineInfo};
+use crate::codechain_machine::CodeChainMachine;
+use crate::types::BlockId;
 use ccrypto::Blake;
-use ckey::{public_to_address, recover, Address, Public, Signature};
+use ckey::{public_to_address, recover, Address, NetworkId, Public, Signature};
 use cstate::{ActionHandler, StateResult, TopLevelState, TopState, TopStateView};
 use ctypes::errors::{RuntimeError, SyntaxError};
+use ctypes::transaction::Action as TransactionAction;
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{CommonParams, Header};
 use parking_lot::RwLock;
@@ -32,10 +36,11 @@ use std::collections::btree_map::BTreeMap;
 use std::collections::HashMap;
 use std::sync::{Arc, Weak};
 
-pub use self::action_data::{Banned, Candidates, Jail, Validator, Validators};
+pub use self::action_data::{Banned, Candidates, ConsensusKeys, Jail, Validator, Validators};
 use self::action_data::{Delegation, IntermediateRewards, ReleaseResult, StakeAccount, Stakeholders};
-pub use self::actions::Action;
+pub use self::actions::{change_consensus_key_message, Action, CONSENSUS_KEY_ERA};
 pub use self::distribute::fee_distribute;
+pub use self::self_nomination::{NextAction, SelfNominationManager, SelfNominationParams, SelfNominationStatus};
 use super::ValidatorSet;
 
 pub const CUSTOM_ACTION_HANDLER_ID: u64 = 2;
@@ -126,6 +131,11 @@ impl ActionHandler for Stake {
                 };
                 self_nominate(state, fee_payer, sender_public, deposit, current_term, nomination_ends_at, metadata)
             }
+            Action::ChangeConsensusKey {
+                network_id,
+                pubkey,
+                signature,
+            } => change_consensus_key(state, fee_payer, sender_public, network_id, pubkey, &signature),
             Action::ChangeParams {
                 metadata_seq,
                 params,
@@ -140,9 +150,13 @@ impl ActionHandler for Stake {
                 let client = self.client.read().as_ref().and_then(Weak::upgrade).expect("Client must be initialized");
                 let parent_hash =
                     client.block_header(&(message1.height() - 1).into()).expect("Parent header verified").hash();
-                let malicious_user_public = validator_set.get(&parent_hash, message1.signer_index());
+                // The validator may sign with a consensus key, so find the account by the address.
+                let malicious_user = *validator_set
+                    .addresses(&parent_hash)
+                    .get(message1.signer_index())
+                    .expect("The signer index is verified");
 
-                ban(state, sender_public, public_to_address(&malicious_user_public))
+                ban(state, sender_public, malicious_user)
             }
         }
     }
@@ -275,6 +289,13 @@ fn self_nominate(
         return Err(RuntimeError::FailedToHandleCustomAction("Account is blacklisted".to_string()).into())
     }
 
+    if ConsensusKeys::load_from_state(state)?.is_used_by_others(fee_payer, sender_public) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The key is the consensus key of another account".to_string(),
+        )
+        .into())
+    }
+
     let mut jail = Jail::load_from_state(&state)?;
     let total_deposit = match jail.try_release(fee_payer, current_term) {
         ReleaseResult::InCustody => {
@@ -305,6 +326,68 @@ fn self_nominate(
     Ok(())
 }
 
+fn change_consensus_key(
+    state: &mut TopLevelState,
+    fee_payer: &Address,
+    sender_public: &Public,
+    network_id: NetworkId,
+    pubkey: Public,
+    signature: &Signature,
+) -> StateResult<()> {
+    if public_to_address(sender_public) != *fee_payer {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "Cannot change the consensus key with regular key".to_string(),
+        )
+        .into())
+    }
+
+    let candidates = Candidates::load_from_state(state)?;
+    if candidates.get_candidate(fee_payer).is_none() {
+        return Err(RuntimeError::FailedToHandleCustomAction("Account is not a candidate".to_string()).into())
+    }
+
+    let message = change_consensus_key_message(network_id, fee_payer, &pubkey);
+    if recover(signature, &message).ok() != Some(pubkey) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The signature is not signed with the consensus key".to_string(),
+        )
+        .into())
+    }
+
+    let key_owner = public_to_address(&pubkey);
+    if key_owner != *fee_payer {
+        let jail = Jail::load_from_state(state)?;
+        let banned = Banned::load_from_state(state)?;
+        if candidates.get_candidate(&key_owner).is_some()
+            || jail.get_prisoner(&key_owner).is_some()
+            || banned.is_banned(&key_owner)
+        {
+            return Err(RuntimeError::FailedToHandleCustomAction("The key belongs to another candidate".to_string()).into())
+        }
+    }
+
+    let mut keys = ConsensusKeys::load_from_state(state)?;
+    if keys.is_used_by_others(fee_payer, &pubkey) {
+        return Err(RuntimeError::FailedToHandleCustomAction(
+            "The key is the consensus key of another account".to_string(),
+        )
+        .into())
+    }
+    keys.register(*fee_payer, pubkey);
+    keys.save_to_state(state)?;
+
+    ctrace!(ENGINE, "ChangeConsensusKey. account: {}, consensus key: {}", fee_payer, key_owner);
+    Ok(())
+}
+
+/// Returns the minimum fee that the mem pool requires for the stake transaction at the latest block.
+/// It is scaled by the base fee multiplier.
+pub fn min_fee_at_latest<C: EngineInfo + ?Sized>(client: &C, action: &TransactionAction) -> Option<u64> {
+    let common_params = client.common_params(BlockId::Latest)?;
+    let base_fee_multiplier = client.base_fee_multiplier(BlockId::Latest)?;
+    Some(CodeChainMachine::min_cost(&common_params, base_fee_multiplier, action))
+}
+
 pub fn get_stakes(state: &TopLevelState) -> StateResult<HashMap<Address, u64>> {
     let stakeholders = Stakeholders::load_from_state(state)?;
     let mut result = HashMap::new();
@@ -320,6 +403,10 @@ pub fn get_validators(state: &TopLevelState) -> StateResult<Validators> {
     Validators::load_from_state(state)
 }
 
+pub fn get_consensus_keys(state: &TopLevelState) -> StateResult<ConsensusKeys> {
+    ConsensusKeys::load_from_state(state)
+}
+
 pub fn add_intermediate_rewards(state: &mut TopLevelState, address: Address, reward: u64) -> StateResult<()> {
     let mut rewards = IntermediateRewards::load_from_state(state)?;
     rewards.add_quantity(address, reward);
@@ -413,6 +500,12 @@ pub fn on_term_close(
 
     jail(state, inactive_validators, custody_until, kick_at)?;
 
+    let candidates = Candidates::load_from_state(state)?;
+    let mut consensus_keys = ConsensusKeys::load_from_state(state)?;
+    consensus_keys.apply_pending();
+    consensus_keys.retain_candidates(&candidates);
+    consensus_keys.save_to_state(state)?;
+
     let validators = Validators::elect(state)?;
     validators.save_to_state(state)?;
 
@@ -496,6 +589,10 @@ pub fn ban(state: &mut TopLevelState, informant: &Public, criminal: Address) ->
     banned.add(criminal);
     validators.remove(&criminal);
 
+    let mut consensus_keys = ConsensusKeys::load_from_state(state)?;
+    consensus_keys.remove(&criminal);
+    consensus_keys.save_to_state(state)?;
+
     jailed.save_to_state(state)?;
     banned.save_to_state(state)?;
     candidates.save_to_state(state)?;
@@ -540,9 +637,13 @@ mod tests {
     use super::action_data::get_account_key;
     use super::*;
 
+    use crate::client::TestBlockChainClient;
     use crate::consensus::stake::action_data::{get_delegation_key, Candidate, Prisoner};
+    use crate::transaction::UnverifiedTransaction;
+    use ckey::{sign, Generator, KeyPair, Random};
     use cstate::tests::helpers;
     use cstate::TopStateView;
+    use ctypes::transaction::Transaction;
     use rlp::Encodable;
 
     fn metadata_for_election() -> TopLevelState {
@@ -554,6 +655,38 @@ mod tests {
         state
     }
 
+    #[test]
+    fn stake_transactions_pay_the_scaled_minimum_fee() {
+        let client = TestBlockChainClient::new();
+        client.set_base_fee_multiplier(1500);
+        let params = client.common_params(BlockId::Latest).unwrap();
+        let action = TransactionAction::Custom {
+            handler_id: CUSTOM_ACTION_HANDLER_ID,
+            bytes: Action::SelfNominate {
+                deposit: 100,
+                metadata: Bytes::new(),
+            }
+            .rlp_bytes(),
+        };
+
+        let fee = min_fee_at_latest(&client, &action).unwrap();
+        assert_eq!(params.min_custom_transaction_cost() * 3 / 2, fee);
+
+        let machine = client.scheme.engine.machine();
+        let transaction_with_fee = |fee| {
+            let tx = Transaction {
+                seq: 0,
+                fee,
+                network_id: params.network_id(),
+                action: action.clone(),
+            };
+            UnverifiedTransaction::new(tx, Signature::random())
+        };
+        assert!(machine.verify_transaction_with_params(&transaction_with_fee(fee), &params, 1500).is_ok());
+        let unscaled = transaction_with_fee(params.min_custom_transaction_cost());
+        assert!(machine.verify_transaction_with_params(&unscaled, &params, 1500).is_err());
+    }
+
     #[test]
     fn genesis_stakes() {
         let address1 = Address::random();
@@ -1757,4 +1890,146 @@ mod tests {
     fn pseudo_term_to_block_num_calculator(term_id: u64) -> u64 {
         term_id * 10 + 1
     }
+
+    fn change_consensus_key_action(account: &Address, consensus_key: &KeyPair) -> Action {
+        let network_id = "tc".into();
+        let message = change_consensus_key_message(network_id, account, consensus_key.public());
+        Action::ChangeConsensusKey {
+            network_id,
+            pubkey: *consensus_key.public(),
+            signature: sign(consensus_key.private(), &message).unwrap(),
+        }
+    }
+
+    #[test]
+    fn consensus_key_is_changed_from_the_next_term() {
+        let key_pair = Random.generate().unwrap();
+        let address = key_pair.address();
+        let consensus_key = Random.generate().unwrap();
+
+        let mut state = metadata_for_election();
+        increase_term_id_until(&mut state, 29);
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &address, key_pair.public(), 200, 0, 30, b"".to_vec()).unwrap();
+
+        let action = change_consensus_key_action(&address, &consensus_key);
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &address, key_pair.public()));
+
+        let keys = ConsensusKeys::load_from_state(&state).unwrap();
+        assert_eq!(Some(consensus_key.public()), keys.pending_key(&address));
+        assert_eq!(*key_pair.public(), keys.consensus_key(key_pair.public()));
+
+        let result = on_term_close(&mut state, pseudo_term_to_block_num_calculator(29), &[]);
+        assert_eq!(result, Ok(()));
+
+        let keys = ConsensusKeys::load_from_state(&state).unwrap();
+        assert_eq!(*consensus_key.public(), keys.consensus_key(key_pair.public()));
+        let candidates = Candidates::load_from_state(&state).unwrap();
+        assert_eq!(
+            Some(200),
+            candidates.get_candidate(&address).map(|candidate| candidate.deposit),
+            "The deposit is kept after changing the consensus key"
+        );
+    }
+
+    #[test]
+    fn cannot_change_consensus_key_without_the_key() {
+        let key_pair = Random.generate().unwrap();
+        let address = key_pair.address();
+        let consensus_key = Random.generate().unwrap();
+        let other_key = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &address, key_pair.public(), 200, 0, 30, b"".to_vec()).unwrap();
+
+        let network_id = "tc".into();
+        let message = change_consensus_key_message(network_id, &address, consensus_key.public());
+        let action = Action::ChangeConsensusKey {
+            network_id,
+            pubkey: *consensus_key.public(),
+            signature: sign(other_key.private(), &message).unwrap(),
+        };
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &address, key_pair.public());
+        assert!(result.is_err(), "The signature must be signed with the consensus key");
+    }
+
+    #[test]
+    fn proof_of_consensus_key_is_bound_to_the_network() {
+        let key_pair = Random.generate().unwrap();
+        let address = key_pair.address();
+        let consensus_key = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&address, 1000).unwrap();
+
+        let stake = Stake::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &address, key_pair.public(), 200, 0, 30, b"".to_vec()).unwrap();
+
+        let signature =
+            sign(consensus_key.private(), &change_consensus_key_message("tc".into(), &address, consensus_key.public()))
+                .unwrap();
+        let action = Action::ChangeConsensusKey {
+            network_id: "tw".into(),
+            pubkey: *consensus_key.public(),
+            signature,
+        };
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &address, key_pair.public());
+        assert!(result.is_err(), "The proof signed for another network must be rejected");
+
+        let mut params = CommonParams::default_for_test();
+        params.set_era_for_test(CONSENSUS_KEY_ERA);
+        assert_eq!(NetworkId::from("tc"), params.network_id());
+        assert!(stake.verify(&action.rlp_bytes(), &params).is_err());
+    }
+
+    #[test]
+    fn consensus_key_is_rejected_before_the_era() {
+        let key_pair = Random.generate().unwrap();
+        let consensus_key = Random.generate().unwrap();
+        let action = change_consensus_key_action(&key_pair.address(), &consensus_key);
+        let stake = Stake::new(HashMap::new());
+
+        let mut params = CommonParams::default_for_test();
+        params.set_era_for_test(CONSENSUS_KEY_ERA - 1);
+        assert!(stake.verify(&action.rlp_bytes(), &params).is_err());
+        params.set_era_for_test(CONSENSUS_KEY_ERA);
+        assert!(stake.verify(&action.rlp_bytes(), &params).is_ok());
+    }
+
+    #[test]
+    fn cannot_use_consensus_key_of_others() {
+        let key_pair1 = Random.generate().unwrap();
+        let address1 = key_pair1.address();
+        let key_pair2 = Random.generate().unwrap();
+        let address2 = key_pair2.address();
+        let consensus_key = Random.generate().unwrap();
+
+        let mut state = helpers::get_temp_state();
+        state.add_balance(&address1, 1000).unwrap();
+        state.add_balance(&address2, 1000).unwrap();
+
+        let stake = Stake::new(HashMap::new());
+        stake.init(&mut state).unwrap();
+        self_nominate(&mut state, &address1, key_pair1.public(), 200, 0, 30, b"".to_vec()).unwrap();
+        self_nominate(&mut state, &address2, key_pair2.public(), 200, 0, 30, b"".to_vec()).unwrap();
+
+        let action = change_consensus_key_action(&address1, &consensus_key);
+        assert_eq!(Ok(()), stake.execute(&action.rlp_bytes(), &mut state, &address1, key_pair1.public()));
+
+        let action = change_consensus_key_action(&address2, &consensus_key);
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &address2, key_pair2.public());
+        assert!(result.is_err(), "A consensus key cannot be shared");
+
+        let action = change_consensus_key_action(&address2, &key_pair1);
+        let result = stake.execute(&action.rlp_bytes(), &mut state, &address2, key_pair2.public());
+        assert!(result.is_err(), "The key of another candidate cannot be a consensus key");
+    }
 }
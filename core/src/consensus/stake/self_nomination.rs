@@ -0,0 +1,460 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::action_data::{Banned, Candidate, Candidates, Jail, Prisoner};
+use super::{min_fee_at_latest, Action, CUSTOM_ACTION_HANDLER_ID};
+use crate::account_provider::AccountProvider;
+use crate::client::ConsensusClient;
+use crate::transaction::{SignedTransaction, UnverifiedTransaction};
+use crate::types::{BlockId, TransactionId};
+use ckey::Address;
+use cstate::TopStateView;
+use ctypes::transaction::{Action as TransactionAction, Transaction};
+use ctypes::TxHash;
+use parking_lot::{Condvar, Mutex};
+use primitives::Bytes;
+use rlp::Encodable;
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Renew the nomination if it ends within this number of terms.
+const RENEWAL_MARGIN_IN_TERMS: u64 = 3;
+/// Give up waiting for a transaction which is not mined after this number of checks.
+const MAX_CHECKS_FOR_PENDING_TRANSACTION: u32 = 10;
+/// The interval doubles on every consecutive failure up to 2^MAX_BACKOFF_SHIFT times.
+const MAX_BACKOFF_SHIFT: u32 = 6;
+
+pub struct SelfNominationParams {
+    /// The deposit to keep. `min_deposit` of the common params is used if it's smaller.
+    pub target_deposit: u64,
+    pub metadata: Bytes,
+    pub interval: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NextAction {
+    /// The nomination lasts long enough, and the deposit reaches the target.
+    Nothing,
+    /// Send a `SelfNominate` with the deposit.
+    Nominate {
+        deposit: u64,
+    },
+    /// Wait until the transaction sent before is mined.
+    WaitForTransaction(TxHash),
+    /// Self nomination is impossible for now.
+    Blocked(String),
+    Paused,
+}
+
+#[derive(Clone, Debug)]
+pub struct SelfNominationStatus {
+    pub enabled: bool,
+    pub address: Address,
+    pub deposit: u64,
+    pub nomination_ends_at: Option<u64>,
+    pub target_deposit: u64,
+    pub next_action: NextAction,
+    /// Unix timestamp in seconds
+    pub next_check_at: u64,
+    pub last_transaction: Option<TxHash>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+}
+
+#[derive(Clone)]
+struct PendingTransaction {
+    hash: TxHash,
+    seq: u64,
+    checks: u32,
+}
+
+#[derive(Clone)]
+struct Inner {
+    status: SelfNominationStatus,
+    pending: Option<PendingTransaction>,
+}
+
+/// Keeps the engine signer nominated as a candidate.
+///
+/// It renews the nomination before it expires and tops up the deposit only when it's below the target.
+/// A failure doubles the interval of the next check until a check succeeds.
+pub struct SelfNominationManager {
+    client: Arc<dyn ConsensusClient>,
+    account_provider: Arc<AccountProvider>,
+    address: Address,
+    params: SelfNominationParams,
+    inner: Mutex<Inner>,
+    wakeup: Condvar,
+}
+
+impl SelfNominationManager {
+    pub fn start(
+        client: Arc<dyn ConsensusClient>,
+        account_provider: Arc<AccountProvider>,
+        address: Address,
+        params: SelfNominationParams,
+        enabled: bool,
+    ) -> Arc<Self> {
+        let manager = Arc::new(Self {
+            client,
+            account_provider,
+            address,
+            inner: Mutex::new(Inner {
+                status: SelfNominationStatus {
+                    enabled,
+                    address,
+                    deposit: 0,
+                    nomination_ends_at: None,
+                    target_deposit: params.target_deposit,
+                    next_action: if enabled {
+                        NextAction::Nothing
+                    } else {
+                        NextAction::Paused
+                    },
+                    next_check_at: 0,
+                    last_transaction: None,
+                    last_error: None,
+                    consecutive_failures: 0,
+                },
+                pending: None,
+            }),
+            params,
+            wakeup: Condvar::new(),
+        });
+
+        let weak = Arc::downgrade(&manager);
+        thread::Builder::new()
+            .name("self nomination".to_string())
+            .spawn(move || run(weak))
+            .expect("Cannot spawn the self nomination thread");
+        manager
+    }
+
+    pub fn status(&self) -> SelfNominationStatus {
+        self.inner.lock().status.clone()
+    }
+
+    /// Enable or pause the self nomination. Enabling it checks the nomination immediately.
+    pub fn set_enabled(&self, enabled: bool) {
+        let mut inner = self.inner.lock();
+        inner.status.enabled = enabled;
+        if enabled {
+            inner.status.consecutive_failures = 0;
+        } else {
+            inner.status.next_action = NextAction::Paused;
+        }
+        cinfo!(ENGINE, "Self nomination is {}", if enabled { "enabled" } else { "paused" });
+        self.wakeup.notify_all();
+    }
+
+    /// Check the nomination, and returns the time to wait until the next check.
+    /// The check works on a copy, so the lock isn't held while reading the chain and queuing the transaction.
+    fn tick(&self) -> Duration {
+        let mut checked = {
+            let inner = self.inner.lock();
+            if !inner.status.enabled {
+                return self.params.interval
+            }
+            inner.clone()
+        };
+        match self.check(&mut checked) {
+            Ok(()) => {}
+            Err(err) => {
+                cwarn!(ENGINE, "Self nomination failed: {}", err);
+                checked.status.last_error = Some(err);
+                checked.status.consecutive_failures += 1;
+            }
+        }
+        let shift = checked.status.consecutive_failures.min(MAX_BACKOFF_SHIFT);
+
+        let mut inner = self.inner.lock();
+        // It may be paused during the check. The transaction sent by the check is still tracked.
+        if !inner.status.enabled {
+            checked.status.enabled = false;
+            checked.status.next_action = NextAction::Paused;
+        }
+        *inner = checked;
+        self.params.interval * (1 << shift)
+    }
+
+    fn check(&self, inner: &mut Inner) -> Result<(), String> {
+        if self.check_pending_transaction(inner)? {
+            return Ok(())
+        }
+
+        let state = self.client.state_at(BlockId::Latest).ok_or("Cannot read the latest state")?;
+        let current_term = self.client.current_term_id(BlockId::Latest).ok_or("Cannot read the current term")?;
+        let common_params = self.client.common_params(BlockId::Latest).ok_or("Cannot read the common params")?;
+        let target_deposit = self.params.target_deposit.max(common_params.min_deposit());
+
+        let banned = Banned::load_from_state(&state).map_err(|err| err.to_string())?.is_banned(&self.address);
+        let jail = Jail::load_from_state(&state).map_err(|err| err.to_string())?;
+        let candidates = Candidates::load_from_state(&state).map_err(|err| err.to_string())?;
+        let (next_action, deposit, nomination_ends_at) = plan(
+            current_term,
+            target_deposit,
+            banned,
+            jail.get_prisoner(&self.address),
+            candidates.get_candidate(&self.address),
+        );
+        inner.status.deposit = deposit;
+        inner.status.nomination_ends_at = nomination_ends_at;
+        inner.status.target_deposit = target_deposit;
+        inner.status.next_action = next_action.clone();
+
+        let deposit = match next_action {
+            NextAction::Nominate {
+                deposit,
+            } => deposit,
+            _ => {
+                inner.status.consecutive_failures = 0;
+                return Ok(())
+            }
+        };
+
+        let action = self.action(deposit);
+        let fee = min_fee_at_latest(&*self.client, &action).ok_or("Cannot read the base fee multiplier")?;
+        let balance = state.balance(&self.address).map_err(|err| err.to_string())?;
+        if balance < deposit + fee {
+            return Err(format!("Insufficient balance. {} is required but the balance is {}", deposit + fee, balance))
+        }
+
+        let (hash, seq) = self.send(action, fee)?;
+        cinfo!(ENGINE, "Sent self nomination {} with deposit {}", hash, deposit);
+        inner.pending = Some(PendingTransaction {
+            hash,
+            seq,
+            checks: 0,
+        });
+        inner.status.last_transaction = Some(hash);
+        inner.status.next_action = NextAction::WaitForTransaction(hash);
+        Ok(())
+    }
+
+    /// Returns true if it should keep waiting for the transaction sent before.
+    fn check_pending_transaction(&self, inner: &mut Inner) -> Result<bool, String> {
+        let pending = match &mut inner.pending {
+            Some(pending) => pending,
+            None => return Ok(false),
+        };
+        let hash = pending.hash;
+        if let Some(hint) = self.client.error_hint(&hash) {
+            inner.pending = None;
+            return Err(format!("Self nomination {} failed: {}", hash, hint))
+        }
+        if self.client.transaction_block(&TransactionId::Hash(hash)).is_some() {
+            inner.pending = None;
+            inner.status.consecutive_failures = 0;
+            inner.status.last_error = None;
+            return Ok(false)
+        }
+        if self.client.latest_seq(&self.address) > pending.seq {
+            inner.pending = None;
+            return Err(format!("Self nomination {} was replaced by another transaction", hash))
+        }
+        pending.checks += 1;
+        if pending.checks > MAX_CHECKS_FOR_PENDING_TRANSACTION {
+            inner.pending = None;
+            return Err(format!("Self nomination {} is not mined", hash))
+        }
+        inner.status.next_action = NextAction::WaitForTransaction(hash);
+        Ok(true)
+    }
+
+    /// Sends the self nomination after the transactions of the signer in the mem pool.
+    /// Returns the hash and the seq of the transaction.
+    fn action(&self, deposit: u64) -> TransactionAction {
+        let action = Action::SelfNominate {
+            deposit,
+            metadata: self.params.metadata.clone(),
+        };
+        TransactionAction::Custom {
+            handler_id: CUSTOM_ACTION_HANDLER_ID,
+            bytes: action.rlp_bytes(),
+        }
+    }
+
+    fn send(&self, action: TransactionAction, fee: u64) -> Result<(TxHash, u64), String> {
+        let account = self
+            .account_provider
+            .get_unlocked_account(&self.address)
+            .map_err(|err| format!("Cannot sign the self nomination: {}", err))?;
+        let public = account.public().map_err(|err| format!("Cannot sign the self nomination: {}", err))?;
+        let seq = self.client.pending_seq(&public);
+
+        let tx = Transaction {
+            seq,
+            fee,
+            network_id: self.client.network_id(),
+            action,
+        };
+        let signature =
+            account.sign(&tx.hash()).map_err(|err| format!("Cannot sign the self nomination: {}", err))?;
+        let unverified = UnverifiedTransaction::new(tx, signature);
+        let signed = SignedTransaction::try_new(unverified).expect("secret is valid so it's recoverable");
+        let hash = signed.hash();
+        self.client
+            .queue_own_transaction(signed)
+            .map_err(|err| format!("Cannot queue the self nomination: {}", err))?;
+        Ok((hash, seq))
+    }
+}
+
+fn run(weak: Weak<SelfNominationManager>) {
+    while let Some(manager) = weak.upgrade() {
+        let interval = manager.tick();
+        let mut inner = manager.inner.lock();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        inner.status.next_check_at = now + interval.as_secs();
+        manager.wakeup.wait_for(&mut inner, interval);
+    }
+}
+
+/// Decide the next action from the stake state of the account.
+/// Returns the action with the current deposit and the term when the nomination ends.
+fn plan(
+    current_term: u64,
+    target_deposit: u64,
+    banned: bool,
+    prisoner: Option<&Prisoner>,
+    candidate: Option<&Candidate>,
+) -> (NextAction, u64, Option<u64>) {
+    if banned {
+        return (NextAction::Blocked("The account is banned".to_string()), 0, None)
+    }
+    if let Some(prisoner) = prisoner {
+        if prisoner.custody_until >= current_term {
+            let reason = format!("The account is in custody until term {}", prisoner.custody_until);
+            return (NextAction::Blocked(reason), prisoner.deposit, None)
+        }
+        // The deposit in jail is returned to the candidate when it nominates itself.
+        let deposit = prisoner.deposit;
+        return (
+            NextAction::Nominate {
+                deposit: target_deposit.saturating_sub(deposit),
+            },
+            deposit,
+            None,
+        )
+    }
+
+    let (deposit, nomination_ends_at) = match candidate {
+        Some(candidate) => (candidate.deposit, Some(candidate.nomination_ends_at)),
+        None => (0, None),
+    };
+    let needs_renewal = nomination_ends_at.map_or(true, |ends_at| ends_at < current_term + RENEWAL_MARGIN_IN_TERMS);
+    let top_up = target_deposit.saturating_sub(deposit);
+    if needs_renewal || top_up > 0 {
+        (
+            NextAction::Nominate {
+                deposit: top_up,
+            },
+            deposit,
+            nomination_ends_at,
+        )
+    } else {
+        (NextAction::Nothing, deposit, nomination_ends_at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ckey::Public;
+
+    fn candidate(deposit: u64, nomination_ends_at: u64) -> Candidate {
+        Candidate {
+            pubkey: Public::random(),
+            deposit,
+            nomination_ends_at,
+            metadata: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn nominate_new_candidate_with_target_deposit() {
+        assert_eq!(
+            (
+                NextAction::Nominate {
+                    deposit: 100
+                },
+                0,
+                None
+            ),
+            plan(10, 100, false, None, None)
+        );
+    }
+
+    #[test]
+    fn do_nothing_until_renewal() {
+        let candidate = candidate(100, 20);
+        assert_eq!((NextAction::Nothing, 100, Some(20)), plan(10, 100, false, None, Some(&candidate)));
+        assert_eq!(
+            (
+                NextAction::Nominate {
+                    deposit: 0
+                },
+                100,
+                Some(20)
+            ),
+            plan(18, 100, false, None, Some(&candidate))
+        );
+    }
+
+    #[test]
+    fn top_up_only_the_shortage() {
+        let candidate = candidate(70, 20);
+        assert_eq!(
+            (
+                NextAction::Nominate {
+                    deposit: 30
+                },
+                70,
+                Some(20)
+            ),
+            plan(10, 100, false, None, Some(&candidate))
+        );
+    }
+
+    #[test]
+    fn blocked_while_banned_or_in_custody() {
+        let prisoner = Prisoner {
+            address: Address::random(),
+            deposit: 50,
+            custody_until: 12,
+            released_at: 15,
+        };
+        assert!(match plan(10, 100, true, None, None).0 {
+            NextAction::Blocked(_) => true,
+            _ => false,
+        });
+        assert!(match plan(12, 100, false, Some(&prisoner), None).0 {
+            NextAction::Blocked(_) => true,
+            _ => false,
+        });
+        assert_eq!(
+            (
+                NextAction::Nominate {
+                    deposit: 50
+                },
+                50,
+                None
+            ),
+            plan(13, 100, false, Some(&prisoner), None)
+        );
+    }
+}
@@ -16,6 +16,7 @@
 
 use super::super::stake;
 use super::super::{ConsensusEngine, EngineError, Seal};
+use super::evidence::DoubleVoteEvidence;
 use super::network::TendermintExtension;
 pub use super::params::{TendermintParams, TimeoutParams};
 use super::types::TendermintSealView;
@@ -26,7 +27,7 @@ use crate::block::*;
 use crate::client::{Client, ConsensusClient};
 use crate::codechain_machine::CodeChainMachine;
 use crate::consensus::tendermint::params::TimeGapParams;
-use crate::consensus::{EngineType, ValidatorSet};
+use crate::consensus::{EngineType, RemoteSigner, ValidatorSet};
 use crate::encoded;
 use crate::error::Error;
 use crate::views::HeaderView;
@@ -144,6 +145,8 @@ impl ConsensusEngine for Tendermint {
         let parent_hash = *block.header().parent_hash();
         let parent = client.block_header(&parent_hash.into()).expect("Parent header must exist").decode();
         let parent_common_params = client.common_params(parent_hash.into()).expect("CommonParams of parent must exist");
+        let parent_base_fee_multiplier =
+            client.base_fee_multiplier(parent_hash.into()).expect("Metadata of parent must exist");
         let author = *block.header().author();
         let block_number = block.header().number();
 
@@ -151,8 +154,10 @@ impl ConsensusEngine for Tendermint {
             let transactions = block.transactions();
             let block_reward = self.block_reward(block_number);
             let total_min_fee: u64 = transactions.iter().map(|tx| tx.fee).sum();
-            let min_fee =
-                transactions.iter().map(|tx| CodeChainMachine::min_cost(&parent_common_params, &tx.action)).sum();
+            let min_fee = transactions
+                .iter()
+                .map(|tx| CodeChainMachine::min_cost(&parent_common_params, parent_base_fee_multiplier, &tx.action))
+                .sum();
             (block_reward + total_min_fee, min_fee)
         };
         assert!(total_reward >= total_min_fee, "{} >= {}", total_reward, total_min_fee);
@@ -255,6 +260,26 @@ impl ConsensusEngine for Tendermint {
             .unwrap();
     }
 
+    fn set_remote_signer(&self, signer: RemoteSigner) {
+        self.has_signer.store(true, AtomicOrdering::SeqCst);
+        self.inner.send(worker::Event::SetRemoteSigner(Box::new(signer))).unwrap();
+    }
+
+    fn set_double_vote_reporter(&self, ap: Arc<AccountProvider>, address: Address) {
+        self.inner
+            .send(worker::Event::SetDoubleVoteReporter {
+                ap,
+                address,
+            })
+            .unwrap();
+    }
+
+    fn double_vote_evidences(&self) -> Vec<DoubleVoteEvidence> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.inner.send(worker::Event::DoubleVoteEvidences(result)).unwrap();
+        receiver.recv().unwrap()
+    }
+
     fn register_network_extension_to_service(&self, service: &NetworkService) {
         let timeouts = self.timeouts;
 
@@ -0,0 +1,263 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::types::Height;
+use super::vote_collector::DoubleVote;
+use super::{ConsensusMessage, VoteStep};
+use crate::db;
+use ctypes::TxHash;
+use kvdb::{DBTransaction, KeyValueDB};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::collections::BTreeMap;
+
+const DOUBLE_VOTE_REPORTS_KEY: &[u8] = b"tendermint-double-vote-reports";
+
+/// Reports on the double votes older than this number of heights are forgotten.
+/// The worker doesn't collect the votes of such heights, so it cannot find them again.
+const DOUBLE_VOTE_REPORT_LIFETIME: Height = 1000;
+
+/// A double vote found by this node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DoubleVoteEvidence {
+    pub message1: ConsensusMessage,
+    pub message2: ConsensusMessage,
+    /// The transaction reporting the double vote. None if it is not submitted yet.
+    pub report: Option<TxHash>,
+}
+
+struct Report {
+    double_vote: DoubleVote,
+    transaction: Option<TxHash>,
+    /// The signer is already banned or jailed, so it is not reported.
+    punished: bool,
+}
+
+impl Report {
+    fn is_pending(&self) -> bool {
+        self.transaction.is_none() && !self.punished
+    }
+}
+
+impl Encodable for Report {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3).append(&self.double_vote).append(&self.transaction).append(&self.punished);
+    }
+}
+
+impl Decodable for Report {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        Ok(Report {
+            double_vote: rlp.val_at(0)?,
+            transaction: rlp.val_at(1)?,
+            punished: rlp.val_at(2)?,
+        })
+    }
+}
+
+/// Double votes found by the worker, and the transactions reporting them.
+///
+/// A signer is reported once for each step even if it signed more than two messages in the step.
+#[derive(Default)]
+pub struct DoubleVoteReports {
+    reports: BTreeMap<(VoteStep, usize), Report>,
+}
+
+impl DoubleVoteReports {
+    /// Returns false if the double vote of the signer in the step is already known.
+    pub fn insert(&mut self, double_vote: DoubleVote) -> bool {
+        let key = (*double_vote.round(), double_vote.author_index());
+        if self.reports.contains_key(&key) {
+            return false
+        }
+        self.reports.insert(key, Report {
+            double_vote,
+            transaction: None,
+            punished: false,
+        });
+        true
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.reports.values().any(Report::is_pending)
+    }
+
+    pub fn pending(&self) -> Vec<DoubleVote> {
+        self.reports.values().filter(|report| report.is_pending()).map(|r| r.double_vote.clone()).collect()
+    }
+
+    pub fn set_submitted(&mut self, double_vote: &DoubleVote, transaction: TxHash) {
+        self.report_mut(double_vote).transaction = Some(transaction);
+    }
+
+    /// Marks the double vote of a signer who is already banned or jailed. It is not reported.
+    pub fn set_punished(&mut self, double_vote: &DoubleVote) {
+        self.report_mut(double_vote).punished = true;
+    }
+
+    fn report_mut(&mut self, double_vote: &DoubleVote) -> &mut Report {
+        let key = (*double_vote.round(), double_vote.author_index());
+        self.reports.get_mut(&key).expect("Only the inserted double votes are updated")
+    }
+
+    /// Forgets the reports on the double votes older than `DOUBLE_VOTE_REPORT_LIFETIME` heights.
+    /// Returns true if any report is removed.
+    pub fn prune(&mut self, current_height: Height) -> bool {
+        let oldest = current_height.saturating_sub(DOUBLE_VOTE_REPORT_LIFETIME);
+        let old_len = self.reports.len();
+        self.reports = self.reports.split_off(&(
+            VoteStep {
+                height: oldest,
+                ..Default::default()
+            },
+            0,
+        ));
+        self.reports.len() != old_len
+    }
+
+    pub fn evidences(&self) -> Vec<DoubleVoteEvidence> {
+        self.reports
+            .values()
+            .map(|report| {
+                let (message1, message2) = report.double_vote.messages();
+                DoubleVoteEvidence {
+                    message1: message1.clone(),
+                    message2: message2.clone(),
+                    report: report.transaction,
+                }
+            })
+            .collect()
+    }
+
+    pub fn save(&self, db: &dyn KeyValueDB) {
+        let mut s = RlpStream::new_list(self.reports.len());
+        for report in self.reports.values() {
+            s.append(report);
+        }
+        let mut batch = DBTransaction::new();
+        batch.put(db::COL_EXTRA, DOUBLE_VOTE_REPORTS_KEY, &s.drain());
+        db.write(batch).expect("Low level database error. Some issue with disk?");
+    }
+
+    pub fn load(db: &dyn KeyValueDB) -> Self {
+        let value = db
+            .get(db::COL_EXTRA, DOUBLE_VOTE_REPORTS_KEY)
+            .expect("Low level database error. Some issue with disk?");
+        let reports: Vec<Report> = match value {
+            Some(bytes) => rlp::decode_list(&bytes),
+            None => return Default::default(),
+        };
+        DoubleVoteReports {
+            reports: reports
+                .into_iter()
+                .map(|report| ((*report.double_vote.round(), report.double_vote.author_index()), report))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vote_collector::VoteCollector;
+    use super::super::{Step, VoteOn};
+    use super::*;
+    use ckey::SchnorrSignature;
+    use primitives::H256;
+
+    fn double_vote(height: u64, signer_index: usize) -> DoubleVote {
+        let message = |block_hash: H256| ConsensusMessage {
+            signature: SchnorrSignature::random(),
+            signer_index,
+            on: VoteOn {
+                step: VoteStep::new(height, 0, Step::Prevote),
+                block_hash: Some(block_hash.into()),
+            },
+        };
+        let mut collector = VoteCollector::default();
+        collector.collect(message(H256::from(1))).unwrap();
+        collector.collect(message(H256::from(2))).unwrap_err()
+    }
+
+    #[test]
+    fn double_votes_are_reported_once() {
+        let mut reports = DoubleVoteReports::default();
+        assert!(reports.insert(double_vote(1, 0)));
+        assert!(!reports.insert(double_vote(1, 0)));
+        assert!(reports.insert(double_vote(1, 1)));
+        assert!(reports.insert(double_vote(2, 0)));
+
+        assert_eq!(3, reports.pending().len());
+        assert_eq!(3, reports.evidences().len());
+    }
+
+    #[test]
+    fn submitted_reports_are_not_pending() {
+        let mut reports = DoubleVoteReports::default();
+        let first = double_vote(1, 0);
+        let second = double_vote(1, 1);
+        let third = double_vote(1, 2);
+        reports.insert(first.clone());
+        reports.insert(second.clone());
+        reports.insert(third);
+
+        reports.set_submitted(&first, H256::from(3).into());
+        reports.set_punished(&second);
+        assert!(reports.has_pending());
+        assert_eq!(1, reports.pending().len());
+
+        let evidences = reports.evidences();
+        assert_eq!(Some(H256::from(3).into()), evidences[0].report);
+        assert_eq!(None, evidences[1].report);
+        assert_eq!(None, evidences[2].report);
+    }
+
+    #[test]
+    fn old_reports_are_pruned() {
+        let mut reports = DoubleVoteReports::default();
+        reports.insert(double_vote(1, 0));
+        reports.insert(double_vote(2, 0));
+        reports.insert(double_vote(3, 0));
+
+        assert!(!reports.prune(DOUBLE_VOTE_REPORT_LIFETIME + 1));
+        assert!(reports.prune(DOUBLE_VOTE_REPORT_LIFETIME + 2));
+        let heights: Vec<_> = reports.evidences().iter().map(|evidence| evidence.message1.height()).collect();
+        assert_eq!(vec![2, 3], heights);
+    }
+
+    #[test]
+    fn reports_are_restored() {
+        let db = kvdb_memorydb::create(db::NUM_COLUMNS.unwrap());
+        let mut reports = DoubleVoteReports::default();
+        let first = double_vote(1, 0);
+        let second = double_vote(1, 1);
+        reports.insert(first.clone());
+        reports.insert(second.clone());
+        reports.set_submitted(&first, H256::from(3).into());
+        reports.set_punished(&second);
+        reports.save(&db);
+
+        let restored = DoubleVoteReports::load(&db);
+        assert!(!restored.has_pending());
+        assert_eq!(reports.evidences(), restored.evidences());
+        assert!(!DoubleVoteReports::load(&kvdb_memorydb::create(db::NUM_COLUMNS.unwrap())).has_pending());
+    }
+}
@@ -17,18 +17,23 @@
 mod backup;
 mod chain_notify;
 mod engine;
+mod evidence;
 mod message;
 mod network;
 mod params;
+#[cfg(test)]
+mod simulation;
 pub mod types;
 pub mod vote_collector;
 mod vote_regression_checker;
 mod worker;
 
 use self::chain_notify::TendermintChainNotify;
+pub use self::evidence::DoubleVoteEvidence;
 pub use self::message::{ConsensusMessage, VoteOn, VoteStep};
 pub use self::params::{TendermintParams, TimeGapParams, TimeoutParams};
 pub use self::types::{Height, Step, View};
+pub(crate) use self::vote_regression_checker::VoteRegressionChecker;
 pub use super::{stake, ValidatorSet};
 use crate::client::ConsensusClient;
 use crate::codechain_machine::CodeChainMachine;
@@ -25,7 +25,7 @@ use super::{
 };
 use crate::consensus::EngineError;
 use ckey::SchnorrSignature;
-use cnetwork::{Api, NetworkExtension, NodeId};
+use cnetwork::{Api, MessagePriority, NetworkExtension, NodeId, PeerBehavior};
 use crossbeam_channel as crossbeam;
 use ctimer::TimerToken;
 use ctypes::BlockHash;
@@ -230,6 +230,11 @@ impl NetworkExtension<Event> for TendermintExtension {
         &VERSIONS
     }
 
+    // Consensus messages must not wait behind the block sync and the transaction relay.
+    fn priority() -> MessagePriority {
+        MessagePriority::High
+    }
+
     fn on_node_added(&mut self, token: &NodeId, _version: u64) {
         self.peers.insert(*token, PeerState::new());
     }
@@ -263,6 +268,16 @@ impl NetworkExtension<Event> for TendermintExtension {
                                 current_height
                             );
                         }
+                        Err(e @ EngineError::MessageWithInvalidSignature {
+                            ..
+                        }) => {
+                            cinfo!(ENGINE, "Failed to handle message {:?}", e);
+                            self.api.report(token, PeerBehavior::InvalidSignature);
+                        }
+                        Err(e @ EngineError::MalformedMessage(_)) => {
+                            cinfo!(ENGINE, "Failed to handle message {:?}", e);
+                            self.api.report(token, PeerBehavior::UndecodableMessage);
+                        }
                         Err(e) => {
                             cinfo!(ENGINE, "Failed to handle message {:?}", e);
                         }
@@ -395,7 +410,10 @@ impl NetworkExtension<Event> for TendermintExtension {
                     }
                 }
             }
-            _ => cinfo!(ENGINE, "Invalid message from peer {}", token),
+            _ => {
+                cinfo!(ENGINE, "Invalid message from peer {}", token);
+                self.api.report(token, PeerBehavior::UndecodableMessage);
+            }
         }
     }
 
@@ -0,0 +1,96 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+/// Virtual time in milliseconds since the beginning of a simulation.
+pub type Millis = u64;
+
+/// A discrete event scheduler driven by virtual time.
+///
+/// Events scheduled for the same instant are popped in the order they were scheduled,
+/// so a simulation run is fully determined by its seed.
+pub struct VirtualClock<E> {
+    now: Millis,
+    next_sequence: u64,
+    queue: BinaryHeap<Reverse<(Millis, u64)>>,
+    events: HashMap<u64, E>,
+}
+
+impl<E> VirtualClock<E> {
+    pub fn new() -> Self {
+        VirtualClock {
+            now: 0,
+            next_sequence: 0,
+            queue: BinaryHeap::new(),
+            events: HashMap::new(),
+        }
+    }
+
+    pub fn now(&self) -> Millis {
+        self.now
+    }
+
+    /// Schedules the event `delay` milliseconds after the current virtual time.
+    pub fn schedule(&mut self, delay: Millis, event: E) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.queue.push(Reverse((self.now + delay, sequence)));
+        self.events.insert(sequence, event);
+    }
+
+    /// The time of the next event, if any.
+    pub fn next_time(&self) -> Option<Millis> {
+        self.queue.peek().map(|Reverse((at, _))| *at)
+    }
+
+    /// Pops the next event and advances the virtual time to it.
+    pub fn pop(&mut self) -> Option<(Millis, E)> {
+        let Reverse((at, sequence)) = self.queue.pop()?;
+        debug_assert!(at >= self.now, "Virtual time must not go backward");
+        self.now = at;
+        let event = self.events.remove(&sequence).expect("Every queued sequence has an event");
+        Some((at, event))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_ordered_by_time_then_by_schedule_order() {
+        let mut clock = VirtualClock::new();
+        clock.schedule(10, "c");
+        clock.schedule(5, "a");
+        clock.schedule(5, "b");
+
+        assert_eq!(Some((5, "a")), clock.pop());
+        assert_eq!(Some((5, "b")), clock.pop());
+        assert_eq!(5, clock.now());
+        clock.schedule(1, "d");
+        assert_eq!(Some((6, "d")), clock.pop());
+        assert_eq!(Some((10, "c")), clock.pop());
+        assert_eq!(None, clock.pop());
+        assert!(clock.is_empty());
+    }
+}
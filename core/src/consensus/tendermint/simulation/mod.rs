@@ -0,0 +1,523 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A deterministic in-process simulation of Tendermint validators.
+//!
+//! Every validator runs the real Tendermint worker over a simulated network and a virtual clock.
+//! The simulation waits until a worker handles each input before it moves on,
+//! so a scenario is reproduced exactly by its seed.
+//! The network can drop, delay, duplicate and partition messages,
+//! and validators can be configured to behave in byzantine ways.
+//! The safety invariant is checked after every event,
+//! and `run_until_height` checks the liveness of the correct validators.
+
+mod clock;
+mod network;
+mod node;
+
+pub use self::clock::{Millis, VirtualClock};
+pub use self::network::{Fault, FaultRule, Link, NetworkStats, NodeIndex, SimNetwork};
+pub use self::node::{Behaviour, Output, SimMessage, SimNode};
+
+use super::params::TimeoutParams;
+use super::types::Height;
+use ckey::Generator;
+use ctypes::BlockHash;
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+use std::collections::BTreeMap;
+
+pub struct SimulationConfig {
+    pub validators: usize,
+    pub seed: u64,
+    /// The latency of every link before faults are applied.
+    pub latency: Millis,
+    /// The interval at which each validator broadcasts its step state.
+    pub gossip_interval: Millis,
+    pub timeouts: TimeoutParams,
+    pub behaviours: Vec<(NodeIndex, Behaviour)>,
+    pub faults: Vec<FaultRule>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        SimulationConfig {
+            validators: 4,
+            seed: 0,
+            latency: 50,
+            gossip_interval: 1_000,
+            timeouts: TimeoutParams::default(),
+            behaviours: Vec::new(),
+            faults: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    /// Two correct validators committed different blocks at the same height.
+    ConflictingCommits {
+        height: Height,
+        first: (NodeIndex, BlockHash),
+        second: (NodeIndex, BlockHash),
+    },
+    /// Correct validators did not commit the target height before the deadline.
+    NoProgress {
+        target: Height,
+        deadline: Millis,
+        best_heights: Vec<Height>,
+    },
+}
+
+enum Event {
+    Deliver {
+        from: NodeIndex,
+        to: NodeIndex,
+        message: SimMessage,
+    },
+    Timeout {
+        node: NodeIndex,
+        nonce: u64,
+    },
+    Gossip(NodeIndex),
+}
+
+pub struct Simulation {
+    clock: VirtualClock<Event>,
+    network: SimNetwork,
+    rng: XorShiftRng,
+    nodes: Vec<SimNode>,
+    gossip_interval: Millis,
+    /// The first commit of each height made by a correct validator.
+    decided: BTreeMap<Height, (NodeIndex, BlockHash)>,
+    started: bool,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> Self {
+        assert!(config.validators > 0);
+        let mut seed = [0u8; 16];
+        seed[..8].copy_from_slice(&config.seed.to_le_bytes());
+        // XorShift does not accept the all-zero seed.
+        seed[8] = 1;
+        let mut rng = XorShiftRng::from_seed(seed);
+
+        let key_pairs: Vec<_> = (0..config.validators)
+            .map(|_| match rng.generate() {
+                Ok(key_pair) => key_pair,
+                Err(never) => match never {},
+            })
+            .collect();
+        let validators: Vec<_> = key_pairs.iter().map(|key_pair| *key_pair.public()).collect();
+        let nodes = key_pairs
+            .into_iter()
+            .enumerate()
+            .map(|(index, key_pair)| {
+                let behaviour = config
+                    .behaviours
+                    .iter()
+                    .find(|(node, _)| *node == index)
+                    .map_or(Behaviour::Honest, |(_, behaviour)| *behaviour);
+                SimNode::new(index, key_pair, validators.clone(), behaviour, config.timeouts)
+            })
+            .collect();
+
+        let mut network = SimNetwork::new(config.latency);
+        for rule in config.faults {
+            network.add_rule(rule);
+        }
+
+        Simulation {
+            clock: VirtualClock::new(),
+            network,
+            rng,
+            nodes,
+            gossip_interval: config.gossip_interval,
+            decided: Default::default(),
+            started: false,
+        }
+    }
+
+    pub fn now(&self) -> Millis {
+        self.clock.now()
+    }
+
+    pub fn node(&self, index: NodeIndex) -> &SimNode {
+        &self.nodes[index]
+    }
+
+    pub fn nodes(&self) -> &[SimNode] {
+        &self.nodes
+    }
+
+    pub fn network_stats(&self) -> NetworkStats {
+        self.network.stats()
+    }
+
+    fn correct_nodes(&self) -> impl Iterator<Item = &SimNode> {
+        self.nodes.iter().filter(|node| node.behaviour().is_correct())
+    }
+
+    /// The heights committed by the correct validators, in the order of their indices.
+    pub fn best_heights(&self) -> Vec<Height> {
+        self.correct_nodes().map(SimNode::best_height).collect()
+    }
+
+    fn start(&mut self) {
+        self.started = true;
+        for index in 0..self.nodes.len() {
+            if self.nodes[index].behaviour() == Behaviour::Silent {
+                continue
+            }
+            self.nodes[index].start();
+            self.flush(index);
+            self.clock.schedule(self.gossip_interval, Event::Gossip(index));
+        }
+    }
+
+    /// Processes the next event and checks the safety invariant.
+    /// Returns false if there is no more event.
+    pub fn step(&mut self) -> Result<bool, Violation> {
+        if !self.started {
+            self.start();
+        }
+        let (_, event) = match self.clock.pop() {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+        let index = match event {
+            Event::Deliver {
+                from,
+                to,
+                message,
+            } => {
+                if self.nodes[to].behaviour() == Behaviour::Silent {
+                    return Ok(true)
+                }
+                self.nodes[to].on_message(from, message);
+                to
+            }
+            Event::Timeout {
+                node,
+                nonce,
+            } => {
+                self.nodes[node].on_timeout(nonce);
+                node
+            }
+            Event::Gossip(node) => {
+                self.nodes[node].on_gossip();
+                self.clock.schedule(self.gossip_interval, Event::Gossip(node));
+                node
+            }
+        };
+        self.flush(index);
+        self.check_safety(index)?;
+        Ok(true)
+    }
+
+    /// Runs the simulation until `done` returns true or the virtual time passes the deadline.
+    /// Returns whether `done` is satisfied.
+    pub fn run_until<F>(&mut self, deadline: Millis, done: F) -> Result<bool, Violation>
+    where
+        F: Fn(&Simulation) -> bool, {
+        if !self.started {
+            self.start();
+        }
+        while !done(self) {
+            match self.clock.next_time() {
+                Some(next) if next <= deadline => {
+                    self.step()?;
+                }
+                _ => return Ok(false),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Runs the simulation until every correct validator commits the target height.
+    pub fn run_until_height(&mut self, target: Height, deadline: Millis) -> Result<Millis, Violation> {
+        let done = |simulation: &Simulation| simulation.best_heights().into_iter().all(|height| height >= target);
+        if self.run_until(deadline, done)? {
+            Ok(self.now())
+        } else {
+            Err(Violation::NoProgress {
+                target,
+                deadline,
+                best_heights: self.best_heights(),
+            })
+        }
+    }
+
+    /// Runs the simulation until the virtual time reaches `until`.
+    pub fn run_for(&mut self, until: Millis) -> Result<(), Violation> {
+        self.run_until(until, |_| false).map(|_| ())
+    }
+
+    fn flush(&mut self, index: NodeIndex) {
+        let now = self.clock.now();
+        for output in self.nodes[index].take_outputs() {
+            match output {
+                Output::Broadcast(message) => {
+                    for to in (0..self.nodes.len()).filter(|to| *to != index) {
+                        self.deliver(now, index, to, message.clone());
+                    }
+                }
+                Output::Send(to, message) => self.deliver(now, index, to, message),
+                Output::SetTimer {
+                    delay,
+                    nonce,
+                } => self.clock.schedule(delay, Event::Timeout {
+                    node: index,
+                    nonce,
+                }),
+            }
+        }
+    }
+
+    fn deliver(&mut self, now: Millis, from: NodeIndex, to: NodeIndex, message: SimMessage) {
+        for delay in self.network.route(&mut self.rng, now, from, to) {
+            self.clock.schedule(delay, Event::Deliver {
+                from,
+                to,
+                message: message.clone(),
+            });
+        }
+    }
+
+    fn check_safety(&mut self, index: NodeIndex) -> Result<(), Violation> {
+        let node = &self.nodes[index];
+        if !node.behaviour().is_correct() {
+            return Ok(())
+        }
+        for (height, block_hash) in node.committed_blocks() {
+            let (first_node, first_hash) = *self.decided.entry(height).or_insert((index, block_hash));
+            if first_hash != block_hash {
+                return Err(Violation::ConflictingCommits {
+                    height,
+                    first: (first_node, first_hash),
+                    second: (index, block_hash),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulation(config: SimulationConfig) -> Simulation {
+        Simulation::new(config)
+    }
+
+    fn assert_agreement(simulation: &Simulation) {
+        let correct: Vec<_> = simulation.correct_nodes().collect();
+        for node in &correct {
+            for (height, block_hash) in node.committed_blocks() {
+                for other in &correct {
+                    if let Some(other_hash) = other.committed_block(height) {
+                        assert_eq!(block_hash, other_hash, "Conflicting commits at {}", height);
+                    }
+                }
+            }
+        }
+    }
+
+    fn detected_double_voters(simulation: &Simulation) -> Vec<NodeIndex> {
+        let mut authors: Vec<_> = simulation
+            .correct_nodes()
+            .flat_map(|node| node.double_votes().into_iter().map(|evidence| evidence.message1.signer_index()))
+            .collect();
+        authors.sort();
+        authors.dedup();
+        authors
+    }
+
+    #[test]
+    fn correct_validators_commit_blocks() {
+        let mut simulation = simulation(SimulationConfig::default());
+        simulation.run_until_height(5, 60_000).unwrap();
+        assert_agreement(&simulation);
+        assert_eq!(0, simulation.network_stats().dropped);
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_same_run() {
+        let config = || SimulationConfig {
+            validators: 5,
+            seed: 42,
+            faults: vec![
+                FaultRule::new(Fault::Drop {
+                    probability: 0.3,
+                }),
+                FaultRule::new(Fault::Delay {
+                    min: 0,
+                    max: 500,
+                }),
+            ],
+            ..Default::default()
+        };
+        let mut first = simulation(config());
+        let mut second = simulation(config());
+        let first_reached = first.run_until_height(3, 120_000).unwrap();
+        let second_reached = second.run_until_height(3, 120_000).unwrap();
+
+        assert_eq!(first_reached, second_reached);
+        assert_eq!(first.network_stats(), second.network_stats());
+        for (a, b) in first.nodes().iter().zip(second.nodes()) {
+            assert_eq!(a.committed_blocks(), b.committed_blocks());
+        }
+    }
+
+    #[test]
+    fn lossy_duplicating_network_is_safe_and_live() {
+        for seed in 0..5 {
+            let mut simulation = simulation(SimulationConfig {
+                validators: 7,
+                seed,
+                faults: vec![
+                    FaultRule::new(Fault::Drop {
+                        probability: 0.2,
+                    }),
+                    FaultRule::new(Fault::Duplicate {
+                        probability: 0.3,
+                    }),
+                    FaultRule::new(Fault::Delay {
+                        min: 0,
+                        max: 800,
+                    }),
+                ],
+                ..Default::default()
+            });
+            simulation.run_until_height(3, 300_000).unwrap();
+            assert_agreement(&simulation);
+        }
+    }
+
+    #[test]
+    fn no_commit_without_quorum_and_recovery_after_healing() {
+        let mut simulation = simulation(SimulationConfig {
+            faults: vec![FaultRule::new(Fault::Partition {
+                groups: vec![vec![0, 1], vec![2, 3]],
+            })
+            .during(0, 20_000)],
+            ..Default::default()
+        });
+        simulation.run_for(19_000).unwrap();
+        assert_eq!(vec![0, 0, 0, 0], simulation.best_heights());
+
+        simulation.run_until_height(3, 120_000).unwrap();
+        assert_agreement(&simulation);
+    }
+
+    #[test]
+    fn isolated_validator_catches_up() {
+        let mut simulation = simulation(SimulationConfig {
+            faults: vec![FaultRule::new(Fault::Partition {
+                groups: vec![vec![0, 1, 2]],
+            })
+            .during(0, 30_000)],
+            ..Default::default()
+        });
+        let done = |simulation: &Simulation| simulation.node(0).best_height() >= 3;
+        assert!(simulation.run_until(29_000, done).unwrap());
+        assert_eq!(0, simulation.node(3).best_height());
+
+        let target = simulation.node(0).best_height() + 1;
+        simulation.run_until_height(target, 120_000).unwrap();
+        assert_agreement(&simulation);
+    }
+
+    #[test]
+    fn silent_validator_does_not_stop_the_chain() {
+        let mut simulation = simulation(SimulationConfig {
+            behaviours: vec![(2, Behaviour::Silent)],
+            ..Default::default()
+        });
+        simulation.run_until_height(5, 120_000).unwrap();
+        assert_agreement(&simulation);
+    }
+
+    #[test]
+    fn two_silent_validators_stop_the_chain() {
+        let mut simulation = simulation(SimulationConfig {
+            behaviours: vec![(2, Behaviour::Silent), (3, Behaviour::Silent)],
+            ..Default::default()
+        });
+        match simulation.run_until_height(1, 60_000) {
+            Err(Violation::NoProgress {
+                best_heights,
+                ..
+            }) => assert_eq!(vec![0, 0], best_heights),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn double_voter_is_detected_and_tolerated() {
+        for seed in 0..3 {
+            let mut simulation = simulation(SimulationConfig {
+                seed,
+                behaviours: vec![(3, Behaviour::DoubleVoter)],
+                ..Default::default()
+            });
+            simulation.run_until_height(4, 120_000).unwrap();
+            assert_agreement(&simulation);
+            assert_eq!(vec![3], detected_double_voters(&simulation));
+        }
+    }
+
+    #[test]
+    fn equivocating_proposer_is_detected_and_tolerated() {
+        // The proposer of the first height is the validator 1.
+        let mut simulation = simulation(SimulationConfig {
+            behaviours: vec![(1, Behaviour::EquivocatingProposer)],
+            ..Default::default()
+        });
+        simulation.run_until_height(4, 120_000).unwrap();
+        assert_agreement(&simulation);
+        assert_eq!(vec![1], detected_double_voters(&simulation));
+    }
+
+    #[test]
+    fn byzantine_validators_under_faulty_network() {
+        for seed in 0..3 {
+            let mut simulation = simulation(SimulationConfig {
+                validators: 7,
+                seed,
+                behaviours: vec![(1, Behaviour::EquivocatingProposer), (4, Behaviour::DoubleVoter)],
+                faults: vec![
+                    FaultRule::new(Fault::Drop {
+                        probability: 0.1,
+                    })
+                    .during(0, 40_000),
+                    FaultRule::new(Fault::Delay {
+                        min: 0,
+                        max: 400,
+                    }),
+                    FaultRule::new(Fault::Partition {
+                        groups: vec![vec![0, 1, 2, 3], vec![4, 5, 6]],
+                    })
+                    .during(5_000, 15_000),
+                ],
+                ..Default::default()
+            });
+            simulation.run_until_height(4, 300_000).unwrap();
+            assert_agreement(&simulation);
+        }
+    }
+}
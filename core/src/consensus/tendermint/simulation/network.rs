@@ -0,0 +1,262 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::clock::Millis;
+use rand::Rng;
+use rand_xorshift::XorShiftRng;
+
+pub type NodeIndex = usize;
+
+/// Selects the links a fault applies to.
+#[derive(Clone, Debug)]
+pub enum Link {
+    All,
+    From(Vec<NodeIndex>),
+    To(Vec<NodeIndex>),
+    Between(NodeIndex, NodeIndex),
+}
+
+impl Link {
+    fn matches(&self, from: NodeIndex, to: NodeIndex) -> bool {
+        match self {
+            Link::All => true,
+            Link::From(senders) => senders.contains(&from),
+            Link::To(receivers) => receivers.contains(&to),
+            Link::Between(a, b) => (from == *a && to == *b) || (from == *b && to == *a),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Fault {
+    /// Drops a message with the given probability.
+    Drop {
+        probability: f64,
+    },
+    /// Adds a uniformly distributed extra latency.
+    Delay {
+        min: Millis,
+        max: Millis,
+    },
+    /// Delivers a message twice with the given probability.
+    Duplicate {
+        probability: f64,
+    },
+    /// Drops every message between nodes in different groups.
+    /// Nodes that are not in any group are isolated.
+    Partition {
+        groups: Vec<Vec<NodeIndex>>,
+    },
+}
+
+/// A fault that is active on some links during `[from, until)`.
+#[derive(Clone, Debug)]
+pub struct FaultRule {
+    pub fault: Fault,
+    pub link: Link,
+    pub from: Millis,
+    pub until: Option<Millis>,
+}
+
+impl FaultRule {
+    pub fn new(fault: Fault) -> Self {
+        FaultRule {
+            fault,
+            link: Link::All,
+            from: 0,
+            until: None,
+        }
+    }
+
+    pub fn on(mut self, link: Link) -> Self {
+        self.link = link;
+        self
+    }
+
+    pub fn during(mut self, from: Millis, until: Millis) -> Self {
+        assert!(from <= until);
+        self.from = from;
+        self.until = Some(until);
+        self
+    }
+
+    fn is_active(&self, now: Millis, from: NodeIndex, to: NodeIndex) -> bool {
+        self.from <= now && self.until.map_or(true, |until| now < until) && self.link.matches(from, to)
+    }
+}
+
+/// Statistics of the simulated network.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub sent: usize,
+    pub dropped: usize,
+    pub duplicated: usize,
+}
+
+/// A simulated network that decides when, and how many times, each message is delivered.
+pub struct SimNetwork {
+    latency: Millis,
+    rules: Vec<FaultRule>,
+    stats: NetworkStats,
+}
+
+impl SimNetwork {
+    pub fn new(latency: Millis) -> Self {
+        SimNetwork {
+            latency,
+            rules: Vec::new(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: FaultRule) {
+        self.rules.push(rule);
+    }
+
+    /// The time after which no fault is active.
+    /// Returns None if there is a permanent fault.
+    pub fn global_stabilization_time(&self) -> Option<Millis> {
+        self.rules.iter().try_fold(0, |gst, rule| rule.until.map(|until| gst.max(until)))
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.stats
+    }
+
+    /// Returns the delays after which a message is delivered.
+    /// An empty result means the message is lost.
+    pub fn route(&mut self, rng: &mut XorShiftRng, now: Millis, from: NodeIndex, to: NodeIndex) -> Vec<Millis> {
+        self.stats.sent += 1;
+        let mut delay = self.latency;
+        let mut copies = 1;
+        for rule in self.rules.iter().filter(|rule| rule.is_active(now, from, to)) {
+            match &rule.fault {
+                Fault::Drop {
+                    probability,
+                } => {
+                    if rng.gen::<f64>() < *probability {
+                        copies = 0;
+                    }
+                }
+                Fault::Delay {
+                    min,
+                    max,
+                } => {
+                    delay += if min < max {
+                        rng.gen_range(*min, *max)
+                    } else {
+                        *min
+                    };
+                }
+                Fault::Duplicate {
+                    probability,
+                } => {
+                    if rng.gen::<f64>() < *probability {
+                        copies += 1;
+                    }
+                }
+                Fault::Partition {
+                    groups,
+                } => {
+                    let same_group = groups.iter().any(|group| group.contains(&from) && group.contains(&to));
+                    if !same_group {
+                        copies = 0;
+                    }
+                }
+            }
+        }
+        if copies == 0 {
+            self.stats.dropped += 1;
+            return Vec::new()
+        }
+        self.stats.duplicated += copies - 1;
+        // A duplicated copy takes another trip, so it arrives a little later.
+        (0..copies).map(|copy| delay + copy as Millis * self.latency).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn partition_drops_messages_across_groups_only_while_active() {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let mut network = SimNetwork::new(10);
+        network.add_rule(
+            FaultRule::new(Fault::Partition {
+                groups: vec![vec![0, 1], vec![2, 3]],
+            })
+            .during(100, 200),
+        );
+
+        assert_eq!(vec![10], network.route(&mut rng, 0, 0, 2));
+        assert_eq!(Vec::<Millis>::new(), network.route(&mut rng, 150, 0, 2));
+        assert_eq!(vec![10], network.route(&mut rng, 150, 0, 1));
+        assert_eq!(vec![10], network.route(&mut rng, 200, 3, 1));
+        assert_eq!(Some(200), network.global_stabilization_time());
+        assert_eq!(1, network.stats().dropped);
+    }
+
+    #[test]
+    fn duplicate_and_delay_are_applied_to_matching_links() {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let mut network = SimNetwork::new(10);
+        network.add_rule(
+            FaultRule::new(Fault::Duplicate {
+                probability: 1.0,
+            })
+            .on(Link::From(vec![1])),
+        );
+        network.add_rule(
+            FaultRule::new(Fault::Delay {
+                min: 50,
+                max: 50,
+            })
+            .on(Link::Between(1, 2)),
+        );
+
+        assert_eq!(vec![10, 20], network.route(&mut rng, 0, 1, 0));
+        assert_eq!(vec![60, 70], network.route(&mut rng, 0, 1, 2));
+        assert_eq!(vec![60], network.route(&mut rng, 0, 2, 1));
+        assert_eq!(vec![10], network.route(&mut rng, 0, 0, 2));
+        assert_eq!(None, network.global_stabilization_time());
+    }
+
+    #[test]
+    fn drop_is_applied_to_matching_receivers() {
+        let mut rng = XorShiftRng::from_seed([7; 16]);
+        let mut network = SimNetwork::new(10);
+        network.add_rule(
+            FaultRule::new(Fault::Drop {
+                probability: 1.0,
+            })
+            .on(Link::To(vec![0])),
+        );
+
+        assert_eq!(Vec::<Millis>::new(), network.route(&mut rng, 0, 1, 0));
+        assert_eq!(vec![10], network.route(&mut rng, 0, 0, 1));
+        assert_eq!(
+            NetworkStats {
+                sent: 2,
+                dropped: 1,
+                duplicated: 0,
+            },
+            network.stats()
+        );
+    }
+}
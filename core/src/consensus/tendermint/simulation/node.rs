@@ -0,0 +1,686 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::super::message::{ConsensusMessage, TendermintMessage, VoteOn, VoteStep};
+use super::super::network::Event as ExtensionEvent;
+use super::super::params::{TimeGapParams, TimeoutParams};
+use super::super::types::{PeerState, Step, View};
+use super::super::worker;
+use super::super::{
+    DoubleVoteEvidence, ENGINE_TIMEOUT_BROADCAST_STEP_STATE, ENGINE_TIMEOUT_EMPTY_PROPOSAL,
+    ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
+};
+use super::clock::Millis;
+use super::network::NodeIndex;
+use crate::account_provider::AccountProvider;
+use crate::block::{IsBlock, OpenBlock, SealedBlock};
+use crate::client::{BlockChainTrait, ConsensusClient, TestBlockChainClient};
+use crate::consensus::validator_set::DynamicValidator;
+use crate::scheme::Scheme;
+use crate::tests::helpers::get_temp_state_db;
+use crate::BlockId;
+use ccrypto::blake256;
+use ckey::{public_to_address, sign_schnorr, Address, KeyPair, Public, SchnorrSignature};
+use cnetwork::NodeId;
+use crossbeam_channel as crossbeam;
+use ctimer::TimerToken;
+use ctypes::{BlockHash, BlockNumber, Header};
+use primitives::Bytes;
+use rlp::{Encodable, Rlp};
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The timestamp of the genesis block shared by every node.
+const GENESIS_TIMESTAMP: u64 = 1_600_000_000;
+const WORKER_STOPPED: &str = "The Tendermint worker of a simulated node stopped";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behaviour {
+    Honest,
+    /// Never sends nor receives a message, as if it crashed before the simulation started.
+    Silent,
+    /// Signs a conflicting vote for every vote it broadcasts,
+    /// and sends each of them to a different half of the peers.
+    DoubleVoter,
+    /// Proposes two different blocks when it is the proposer,
+    /// and sends each of them to a different half of the peers.
+    EquivocatingProposer,
+}
+
+impl Behaviour {
+    pub fn is_correct(self) -> bool {
+        self == Behaviour::Honest
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SimMessage {
+    /// An RLP encoded `TendermintMessage`, as the Tendermint extension sends it.
+    Tendermint(Bytes),
+    /// Requests the canonical blocks from the given number, as the block sync extension does.
+    RequestBlocks(BlockNumber),
+    Blocks(Vec<Bytes>),
+}
+
+pub enum Output {
+    Broadcast(SimMessage),
+    Send(NodeIndex, SimMessage),
+    SetTimer {
+        delay: Millis,
+        nonce: u64,
+    },
+}
+
+/// A validator running the Tendermint worker.
+///
+/// The node spawns the real worker and drives it only through its channels.
+/// It plays the parts the worker expects from the rest of a node:
+/// the Tendermint extension relays the messages and sets the timers,
+/// the miner seals the blocks, and the client imports them.
+/// The node waits until the worker handles each input, so a run depends only on its inputs.
+pub struct SimNode {
+    key_pair: KeyPair,
+    behaviour: Behaviour,
+    timeouts: TimeoutParams,
+    client: Arc<TestBlockChainClient>,
+    worker: Option<JoinHandle<()>>,
+    inner: crossbeam::Sender<worker::Event>,
+    quit: crossbeam::Sender<()>,
+    extension_events: crossbeam::Receiver<ExtensionEvent>,
+    peers: BTreeMap<NodeIndex, PeerState>,
+    /// The nonce of the pending timer of each token.
+    timers: HashMap<TimerToken, u64>,
+    timer_nonce: u64,
+    /// Whether the blocks are requested since the last gossip.
+    blocks_requested: bool,
+    outputs: Vec<Output>,
+}
+
+impl SimNode {
+    pub fn new(
+        index: NodeIndex,
+        key_pair: KeyPair,
+        validators: Vec<Public>,
+        behaviour: Behaviour,
+        timeouts: TimeoutParams,
+    ) -> Self {
+        debug_assert_eq!(key_pair.public(), &validators[index]);
+        let mut scheme = Scheme::new_test();
+        // The author of the genesis block must be a validator to choose the first proposer.
+        scheme.author = public_to_address(&validators[0]);
+        scheme.timestamp = GENESIS_TIMESTAMP;
+        let mut client = TestBlockChainClient::new_with_scheme(scheme);
+        // The validators given to the worker are used in the first term.
+        client.term_id = Some(0);
+        let client = Arc::new(client);
+
+        let (join, time_gap_params, extension_initializer, inner, quit) =
+            worker::spawn(Arc::new(DynamicValidator::new(validators.clone())));
+        let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.");
+        time_gap_params
+            .send(TimeGapParams {
+                allowed_past_gap: since_epoch,
+                allowed_future_gap: Duration::from_secs(60),
+            })
+            .expect(WORKER_STOPPED);
+        let (extension, extension_events) = crossbeam::unbounded();
+        let consensus_client = Arc::clone(&client) as Arc<dyn ConsensusClient>;
+        extension_initializer.send((extension, Arc::downgrade(&consensus_client))).expect(WORKER_STOPPED);
+
+        let ap = AccountProvider::transient_provider();
+        let password = index.to_string();
+        let address = ap.insert_account(*key_pair.private(), &password.as_str().into()).expect("The key is valid");
+        ap.unlock_account_permanently(address, password.as_str().into()).expect("The account is inserted");
+        inner
+            .send(worker::Event::SetSigner {
+                ap,
+                address,
+            })
+            .expect(WORKER_STOPPED);
+
+        let peers = (0..validators.len()).filter(|peer| *peer != index).map(|peer| (peer, PeerState::new())).collect();
+        let node = SimNode {
+            key_pair,
+            behaviour,
+            timeouts,
+            client,
+            worker: Some(join),
+            inner,
+            quit,
+            extension_events,
+            peers,
+            timers: Default::default(),
+            timer_nonce: 0,
+            blocks_requested: false,
+            outputs: Vec::new(),
+        };
+        node.call(worker::Event::Restore);
+        node
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        self.behaviour
+    }
+
+    pub fn best_height(&self) -> BlockNumber {
+        self.best_header().number()
+    }
+
+    pub fn committed_block(&self, height: BlockNumber) -> Option<BlockHash> {
+        self.client.block_header(&BlockId::Number(height)).map(|header| header.hash())
+    }
+
+    /// The canonical blocks except the genesis block.
+    pub fn committed_blocks(&self) -> Vec<(BlockNumber, BlockHash)> {
+        (1..=self.best_height()).filter_map(|height| self.committed_block(height).map(|hash| (height, hash))).collect()
+    }
+
+    /// The double votes found by the worker.
+    pub fn double_votes(&self) -> Vec<DoubleVoteEvidence> {
+        let (result, receiver) = crossbeam::bounded(1);
+        self.send(worker::Event::DoubleVoteEvidences(result));
+        receiver.recv().expect(WORKER_STOPPED)
+    }
+
+    pub fn take_outputs(&mut self) -> Vec<Output> {
+        mem::replace(&mut self.outputs, Vec::new())
+    }
+
+    /// Sets the first timer of the worker, as the Tendermint extension does when it's registered.
+    pub fn start(&mut self) {
+        self.set_timer(ENGINE_TIMEOUT_TOKEN_NONCE_BASE, self.timeouts.initial());
+        self.process();
+    }
+
+    pub fn on_timeout(&mut self, nonce: u64) {
+        let token = match self.timers.iter().find(|(_, timer_nonce)| **timer_nonce == nonce) {
+            Some((token, _)) => *token,
+            // The timer is cleared or set again.
+            None => return,
+        };
+        self.timers.remove(&token);
+        self.send(worker::Event::OnTimeout(token));
+        self.process();
+    }
+
+    pub fn on_gossip(&mut self) {
+        self.blocks_requested = false;
+        self.send(worker::Event::OnTimeout(ENGINE_TIMEOUT_BROADCAST_STEP_STATE));
+        self.process();
+    }
+
+    pub fn on_message(&mut self, from: NodeIndex, message: SimMessage) {
+        match message {
+            SimMessage::Tendermint(bytes) => self.on_tendermint_message(from, &bytes),
+            SimMessage::RequestBlocks(from_number) => {
+                let blocks: Vec<_> = (from_number..=self.best_height())
+                    .filter_map(|number| self.client.block(&BlockId::Number(number)))
+                    .map(|block| block.into_inner())
+                    .collect();
+                if !blocks.is_empty() {
+                    self.outputs.push(Output::Send(from, SimMessage::Blocks(blocks)));
+                }
+            }
+            SimMessage::Blocks(blocks) => {
+                for block in blocks {
+                    self.import(block);
+                }
+            }
+        }
+        self.process();
+    }
+
+    /// Handles a message as the Tendermint extension does.
+    fn on_tendermint_message(&mut self, from: NodeIndex, data: &[u8]) {
+        let token = node_id(from);
+        let message = match Rlp::new(data).as_val::<TendermintMessage>() {
+            Ok(message) => message,
+            Err(_) => return,
+        };
+        match message {
+            TendermintMessage::ConsensusMessage(messages) => {
+                self.call(|result| worker::Event::HandleMessages {
+                    messages,
+                    result,
+                });
+            }
+            TendermintMessage::ProposalBlock {
+                signature,
+                view,
+                message,
+            } => {
+                let accepted = self.call(|result| worker::Event::ProposalBlock {
+                    signature,
+                    view,
+                    message: message.clone(),
+                    result,
+                });
+                if let Some(Some(_client)) = accepted.first() {
+                    self.import(message);
+                }
+            }
+            TendermintMessage::StepState {
+                vote_step,
+                proposal,
+                lock_view,
+                known_votes,
+            } => {
+                if let Some(peer) = self.peers.get_mut(&from) {
+                    peer.vote_step = vote_step;
+                    peer.proposal = proposal;
+                    peer.messages = known_votes;
+                }
+                // The worker ignores the peers more than two heights ahead, so they need the block sync.
+                let next = self.best_height() + 1;
+                if next + 2 < vote_step.height && !self.blocks_requested {
+                    self.blocks_requested = true;
+                    self.outputs.push(Output::Send(from, SimMessage::RequestBlocks(next)));
+                }
+                let replies = self.call(|result| worker::Event::StepState {
+                    token,
+                    vote_step,
+                    proposal,
+                    lock_view,
+                    known_votes: Box::new(known_votes),
+                    result,
+                });
+                self.send_all(from, replies);
+            }
+            TendermintMessage::RequestProposal {
+                height,
+                view,
+            } => {
+                let replies = self.call(|result| worker::Event::RequestProposal {
+                    token,
+                    height,
+                    view,
+                    result,
+                });
+                self.send_all(from, replies);
+            }
+            TendermintMessage::RequestMessage {
+                vote_step,
+                requested_votes,
+            } => {
+                let votes = self.call(|result| worker::Event::GetAllVotesAndAuthors {
+                    vote_step,
+                    requested: requested_votes,
+                    result,
+                });
+                if !votes.is_empty() {
+                    let votes = votes.iter().map(Encodable::rlp_bytes).collect();
+                    self.send_all(from, vec![TendermintMessage::ConsensusMessage(votes).rlp_bytes()]);
+                }
+            }
+            TendermintMessage::RequestCommit {
+                height,
+            } => {
+                let replies = self.call(|result| worker::Event::RequestCommit {
+                    height,
+                    result,
+                });
+                self.send_all(from, replies);
+            }
+            TendermintMessage::Commit {
+                block,
+                votes,
+            } => {
+                let accepted = self.call(|result| worker::Event::GetCommit {
+                    block: block.clone(),
+                    votes,
+                    result,
+                });
+                if let Some(Some(_client)) = accepted.first() {
+                    self.import(block);
+                }
+            }
+        }
+    }
+
+    fn send(&self, event: worker::Event) {
+        self.inner.send(event).expect(WORKER_STOPPED);
+    }
+
+    /// Sends an event and collects its results after the worker handles it.
+    fn call<T, F>(&self, event: F) -> Vec<T>
+    where
+        F: FnOnce(crossbeam::Sender<T>) -> worker::Event, {
+        let (result, receiver) = crossbeam::unbounded();
+        self.send(event(result));
+        self.wait_for_worker();
+        receiver.try_iter().collect()
+    }
+
+    /// The worker handles the events in order,
+    /// so it has handled all the previous events when it answers.
+    fn wait_for_worker(&self) {
+        self.double_votes();
+    }
+
+    /// Serves the requests of the worker until it has nothing more to do.
+    fn process(&mut self) {
+        loop {
+            self.wait_for_worker();
+            let events: Vec<_> = self.extension_events.try_iter().collect();
+            let sealing_requests = self.client.take_sealing_requests();
+            let committed_requests = self.client.take_committed_requests();
+            if events.is_empty() && sealing_requests.is_empty() && committed_requests.is_empty() {
+                return
+            }
+            for event in events {
+                self.on_extension_event(event);
+            }
+            for parent in sealing_requests {
+                self.seal(parent);
+            }
+            for block_hash in committed_requests {
+                self.commit(block_hash);
+            }
+        }
+    }
+
+    /// Handles an event from the worker as the Tendermint extension does.
+    /// Unlike the extension, it sends the messages to every peer instead of random peers.
+    fn on_extension_event(&mut self, event: ExtensionEvent) {
+        match event {
+            ExtensionEvent::BroadcastMessage {
+                message,
+            } => {
+                if self.behaviour == Behaviour::DoubleVoter {
+                    self.broadcast_double_vote(message);
+                } else {
+                    self.broadcast(TendermintMessage::ConsensusMessage(vec![message]).rlp_bytes());
+                }
+            }
+            ExtensionEvent::BroadcastState {
+                vote_step,
+                proposal,
+                lock_view,
+                votes,
+            } => {
+                let message = TendermintMessage::StepState {
+                    vote_step,
+                    proposal,
+                    lock_view,
+                    known_votes: votes,
+                };
+                self.broadcast(message.rlp_bytes());
+            }
+            ExtensionEvent::RequestMessagesToAll {
+                vote_step,
+                requested_votes,
+            } => {
+                let message = TendermintMessage::RequestMessage {
+                    vote_step,
+                    requested_votes,
+                }
+                .rlp_bytes();
+                let peers: Vec<_> = self
+                    .peers
+                    .iter()
+                    .filter(|(_, peer)| vote_step <= peer.vote_step && !peer.messages.is_empty())
+                    .map(|(index, _)| *index)
+                    .collect();
+                for peer in peers {
+                    self.send_all(peer, vec![message.clone()]);
+                }
+            }
+            ExtensionEvent::RequestProposalToAny {
+                height,
+                view,
+            } => {
+                let message = TendermintMessage::RequestProposal {
+                    height,
+                    view,
+                }
+                .rlp_bytes();
+                let peers: Vec<_> = self
+                    .peers
+                    .iter()
+                    .filter(|(_, peer)| {
+                        let peer_step = &peer.vote_step;
+                        let is_future_height_and_view = peer_step.height > height
+                            || (peer_step.height == height && peer_step.view > view);
+                        let is_same_height_and_view = peer_step.height == height && peer_step.view == view;
+                        is_future_height_and_view || (is_same_height_and_view && peer.proposal.is_some())
+                    })
+                    .map(|(index, _)| *index)
+                    .collect();
+                for peer in peers {
+                    self.send_all(peer, vec![message.clone()]);
+                }
+            }
+            ExtensionEvent::SetTimerStep {
+                step,
+                view,
+                expired_token_nonce,
+            } => {
+                self.timers.remove(&ENGINE_TIMEOUT_EMPTY_PROPOSAL);
+                self.timers.remove(&expired_token_nonce);
+                self.set_timer(expired_token_nonce + 1, self.timeouts.timeout(step, view));
+            }
+            ExtensionEvent::SetTimerEmptyProposal {
+                view,
+            } => {
+                self.set_timer(ENGINE_TIMEOUT_EMPTY_PROPOSAL, self.timeouts.timeout(Step::Propose, view) / 2);
+            }
+            ExtensionEvent::BroadcastProposalBlock {
+                signature,
+                view,
+                message,
+            } => {
+                if self.behaviour == Behaviour::EquivocatingProposer {
+                    self.broadcast_equivocating_proposal(signature, view, message);
+                } else {
+                    let message = TendermintMessage::ProposalBlock {
+                        signature,
+                        view,
+                        message,
+                    };
+                    self.broadcast(message.rlp_bytes());
+                }
+            }
+        }
+    }
+
+    fn broadcast(&mut self, message: Bytes) {
+        self.outputs.push(Output::Broadcast(SimMessage::Tendermint(message)));
+    }
+
+    fn send_all(&mut self, to: NodeIndex, messages: Vec<Bytes>) {
+        for message in messages {
+            self.outputs.push(Output::Send(to, SimMessage::Tendermint(message)));
+        }
+    }
+
+    /// Sends the first message to the even peers and the second one to the odd peers.
+    /// The first peer gets both of them, so that the misbehaviour is always observable.
+    fn split_broadcast(&mut self, first: Bytes, second: Bytes) {
+        let peers: Vec<_> = self.peers.keys().cloned().collect();
+        for (order, peer) in peers.into_iter().enumerate() {
+            if order == 0 || peer % 2 == 0 {
+                self.send_all(peer, vec![first.clone()]);
+            }
+            if order == 0 || peer % 2 == 1 {
+                self.send_all(peer, vec![second.clone()]);
+            }
+        }
+    }
+
+    fn broadcast_double_vote(&mut self, message: Bytes) {
+        let vote: ConsensusMessage = Rlp::new(&message).as_val().expect("The worker broadcasts valid votes");
+        let on = VoteOn {
+            step: vote.on.step,
+            block_hash: match vote.on.block_hash {
+                Some(_) => None,
+                None => Some(blake256(&message).into()),
+            },
+        };
+        let conflicting = ConsensusMessage {
+            signature: self.sign(&on),
+            signer_index: vote.signer_index,
+            on,
+        };
+        self.split_broadcast(
+            TendermintMessage::ConsensusMessage(vec![message]).rlp_bytes(),
+            TendermintMessage::ConsensusMessage(vec![conflicting.rlp_bytes()]).rlp_bytes(),
+        );
+    }
+
+    fn broadcast_equivocating_proposal(&mut self, signature: SchnorrSignature, view: View, message: Bytes) {
+        let header: Header = Rlp::new(&message).val_at(0).expect("The worker broadcasts valid proposals");
+        let parent = self.header(*header.parent_hash()).expect("The parent of a proposal is imported");
+        let conflicting = self.build_block(&parent, header.seal().to_vec(), b"equivocation".to_vec());
+        let conflicting_signature = self.sign(&VoteOn {
+            step: VoteStep::new(header.number(), view, Step::Propose),
+            block_hash: Some(conflicting.header().hash()),
+        });
+        let first = TendermintMessage::ProposalBlock {
+            signature,
+            view,
+            message,
+        };
+        let second = TendermintMessage::ProposalBlock {
+            signature: conflicting_signature,
+            view,
+            message: conflicting.rlp_bytes(),
+        };
+        self.split_broadcast(first.rlp_bytes(), second.rlp_bytes());
+    }
+
+    fn sign(&self, on: &VoteOn) -> SchnorrSignature {
+        sign_schnorr(self.key_pair.private(), &on.hash()).expect("The key pair is valid")
+    }
+
+    fn set_timer(&mut self, token: TimerToken, delay: Duration) {
+        self.timer_nonce += 1;
+        self.timers.insert(token, self.timer_nonce);
+        self.outputs.push(Output::SetTimer {
+            delay: delay.as_millis() as Millis,
+            nonce: self.timer_nonce,
+        });
+    }
+
+    /// Seals a block as the miner does when the worker requests it.
+    fn seal(&mut self, parent: BlockId) {
+        let parent = match self.client.block_header(&parent) {
+            Some(parent) => parent.decode(),
+            None => return,
+        };
+        let seals = self.call(|result| worker::Event::GenerateSeal {
+            block_number: parent.number() + 1,
+            parent_hash: parent.hash(),
+            result,
+        });
+        let seal = match seals.first().and_then(|seal| seal.seal_fields()) {
+            Some(seal) => seal,
+            // The height has changed after the request.
+            None => return,
+        };
+        let block = self.build_block(&parent, seal, Bytes::new());
+        let bytes = block.rlp_bytes();
+        self.send(worker::Event::ProposalGenerated(Box::new(block)));
+        self.import(bytes);
+    }
+
+    /// Builds an empty block.
+    /// The null engine gives no reward, so every block has the state of the genesis block.
+    fn build_block(&self, parent: &Header, seal: Vec<Bytes>, extra_data: Bytes) -> SealedBlock {
+        let engine = &*self.client.scheme.engine;
+        let db = self.client.scheme.ensure_genesis_state(get_temp_state_db()).expect("The genesis state is valid");
+        let mut open_block = OpenBlock::try_new(engine, db, parent, self.address(), extra_data)
+            .expect("The parent has the genesis state");
+        open_block.set_timestamp(parent.timestamp() + 1);
+        let locked_block = open_block.close_and_lock(parent, None).expect("An empty block can be closed");
+        match locked_block.try_seal(engine, seal) {
+            Ok(sealed_block) => sealed_block,
+            Err((err, _)) => panic!("The null engine accepts any seal: {}", err),
+        }
+    }
+
+    /// Imports a block as the client does.
+    /// The seal of a block has the precommits of its parent, so the parent becomes the best block.
+    fn import(&mut self, bytes: Bytes) {
+        let header: Header = match Rlp::new(&bytes).val_at(0) {
+            Ok(header) => header,
+            Err(_) => return,
+        };
+        if self.header(header.hash()).is_some() {
+            return
+        }
+        let parent = match self.header(*header.parent_hash()) {
+            Some(parent) => parent,
+            None => return,
+        };
+        let block_hash = self.client.insert_block(bytes);
+        let best_hash = self.best_header().hash();
+        let mut enacted = Vec::new();
+        if parent.hash() != best_hash && *parent.parent_hash() == best_hash {
+            self.client.set_best_block(parent.hash());
+            enacted.push(parent.hash());
+        }
+        self.send(worker::Event::NewBlocks {
+            imported: vec![block_hash],
+            enacted,
+        });
+    }
+
+    /// Makes a committed block the best block as the client does when the worker requests it.
+    fn commit(&mut self, block_hash: BlockHash) {
+        let header = match self.header(block_hash) {
+            Some(header) => header,
+            None => return,
+        };
+        if *header.parent_hash() != self.best_header().hash() {
+            return
+        }
+        self.client.set_best_block(block_hash);
+        self.send(worker::Event::NewBlocks {
+            imported: Vec::new(),
+            enacted: vec![block_hash],
+        });
+    }
+
+    fn header(&self, block_hash: BlockHash) -> Option<Header> {
+        self.client.block_header(&BlockId::Hash(block_hash)).map(|header| header.decode())
+    }
+
+    fn best_header(&self) -> Header {
+        self.client.best_block_header().decode()
+    }
+
+    fn address(&self) -> Address {
+        self.key_pair.address()
+    }
+}
+
+impl Drop for SimNode {
+    fn drop(&mut self) {
+        // The worker may have stopped already.
+        let _ = self.quit.send(());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn node_id(index: NodeIndex) -> NodeId {
+    NodeId::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 3485 + index as u16)
+}
@@ -19,7 +19,7 @@ use super::{ConsensusMessage, VoteStep};
 use crate::consensus::BitSet;
 use ckey::SchnorrSignature;
 use ctypes::BlockHash;
-use rlp::{Encodable, RlpStream};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::collections::{BTreeMap, HashMap};
 use std::iter::Iterator;
 
@@ -36,7 +36,7 @@ struct StepCollector {
     messages: Vec<ConsensusMessage>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DoubleVote {
     author_index: usize,
     vote_one: ConsensusMessage,
@@ -44,6 +44,18 @@ pub struct DoubleVote {
 }
 
 impl DoubleVote {
+    pub fn author_index(&self) -> usize {
+        self.author_index
+    }
+
+    pub fn round(&self) -> &VoteStep {
+        self.vote_one.round()
+    }
+
+    pub fn messages(&self) -> (&ConsensusMessage, &ConsensusMessage) {
+        (&self.vote_one, &self.vote_two)
+    }
+
     pub fn to_action(&self) -> Action {
         Action::ReportDoubleVote {
             message1: Box::new(self.vote_one.clone()),
@@ -58,6 +70,28 @@ impl Encodable for DoubleVote {
     }
 }
 
+impl Decodable for DoubleVote {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        let vote_one: ConsensusMessage = rlp.val_at(0)?;
+        let vote_two: ConsensusMessage = rlp.val_at(1)?;
+        if vote_one.signer_index() != vote_two.signer_index() || vote_one.round() != vote_two.round() {
+            return Err(DecoderError::Custom("The messages of a double vote must share the signer and the step"))
+        }
+        Ok(DoubleVote {
+            author_index: vote_one.signer_index(),
+            vote_one,
+            vote_two,
+        })
+    }
+}
+
 impl StepCollector {
     /// Some(true): a message is new
     /// Some(false): a message is duplicated
@@ -12,6 +12,17 @@ impl VoteRegressionChecker {
         }
     }
 
+    /// Restore the checker from the last vote, which was persisted somewhere.
+    pub fn from_last_vote(last_vote: Option<VoteOn>) -> VoteRegressionChecker {
+        VoteRegressionChecker {
+            last_vote,
+        }
+    }
+
+    pub fn last_vote(&self) -> Option<&VoteOn> {
+        self.last_vote.as_ref()
+    }
+
     pub fn check(&mut self, vote_on: &VoteOn) -> bool {
         assert!(
             match vote_on.step.step {
@@ -16,10 +16,11 @@
 
 use super::super::BitSet;
 use super::backup::{backup, restore, BackupView};
+use super::evidence::{DoubleVoteEvidence, DoubleVoteReports};
 use super::message::*;
 use super::network;
 use super::params::TimeGapParams;
-use super::stake::CUSTOM_ACTION_HANDLER_ID;
+use super::stake::{min_fee_at_latest, Banned, Jail, CUSTOM_ACTION_HANDLER_ID};
 use super::types::{Height, Proposal, Step, TendermintSealView, TendermintState, TwoThirdsMajority, View};
 use super::vote_collector::{DoubleVote, VoteCollector};
 use super::vote_regression_checker::VoteRegressionChecker;
@@ -29,7 +30,7 @@ use super::{
 use crate::account_provider::AccountProvider;
 use crate::block::*;
 use crate::client::ConsensusClient;
-use crate::consensus::signer::EngineSigner;
+use crate::consensus::signer::{EngineSigner, RemoteSigner};
 use crate::consensus::validator_set::{DynamicValidator, ValidatorSet};
 use crate::consensus::{EngineError, Seal};
 use crate::encoded;
@@ -95,6 +96,9 @@ struct Worker {
     time_gap_params: TimeGapParams,
     timeout_token_nonce: usize,
     vote_regression_checker: VoteRegressionChecker,
+    /// Used to sign the reports of double votes. The engine signer is used if it's not set.
+    double_vote_reporter: EngineSigner,
+    double_vote_reports: DoubleVoteReports,
 }
 
 pub enum Event {
@@ -134,6 +138,12 @@ pub enum Event {
         ap: Arc<AccountProvider>,
         address: Address,
     },
+    SetRemoteSigner(Box<RemoteSigner>),
+    SetDoubleVoteReporter {
+        ap: Arc<AccountProvider>,
+        address: Address,
+    },
+    DoubleVoteEvidences(crossbeam::Sender<Vec<DoubleVoteEvidence>>),
     Restore(crossbeam::Sender<()>),
     ProposalBlock {
         signature: SchnorrSignature,
@@ -196,6 +206,8 @@ impl Worker {
             time_gap_params,
             timeout_token_nonce: ENGINE_TIMEOUT_TOKEN_NONCE_BASE,
             vote_regression_checker: VoteRegressionChecker::new(),
+            double_vote_reporter: Default::default(),
+            double_vote_reports: Default::default(),
         }
     }
 
@@ -306,6 +318,18 @@ impl Worker {
                             }) => {
                                 inner.set_signer(ap, address);
                             }
+                            Ok(Event::SetRemoteSigner(signer)) => {
+                                inner.signer.set_remote(*signer);
+                            }
+                            Ok(Event::SetDoubleVoteReporter {
+                                ap,
+                                address,
+                            }) => {
+                                inner.set_double_vote_reporter(ap, address);
+                            }
+                            Ok(Event::DoubleVoteEvidences(result)) => {
+                                result.send(inner.double_vote_reports.evidences()).unwrap();
+                            }
                             Ok(Event::Restore(result)) => {
                                 inner.restore();
                                 result.send(()).unwrap();
@@ -1037,6 +1061,7 @@ impl Worker {
 
     fn restore(&mut self) {
         let client = self.client();
+        self.double_vote_reports = DoubleVoteReports::load(client.get_kvdb().as_ref());
         let backup = restore(client.get_kvdb().as_ref());
         if let Some(backup) = backup {
             let backup_step = match backup.step {
@@ -1444,39 +1469,102 @@ impl Worker {
         Ok(())
     }
 
-    fn report_double_vote(&self, double: &DoubleVote) {
-        let network_id = self.client().network_id();
-        let seq = match self.signer.address() {
-            Some(address) => self.client().latest_seq(address),
+    fn report_double_vote(&mut self, double: &DoubleVote) {
+        if !self.double_vote_reports.insert(double.clone()) {
+            cdebug!(ENGINE, "The double vote is already reported: {:?}", double);
+            return
+        }
+        self.submit_double_vote_reports();
+    }
+
+    /// Returns true if the signer of the double vote is already banned or jailed.
+    /// Such a signer is not reported again.
+    fn is_punished(&self, double: &DoubleVote) -> Result<bool, String> {
+        let client = self.client();
+        let parent_height = double.round().height - 1;
+        let parent_hash =
+            client.block_header(&parent_height.into()).ok_or("The parent of the double vote is not imported")?.hash();
+        let criminal =
+            *self.validators.addresses(&parent_hash).get(double.author_index()).ok_or("Invalid signer index")?;
+        let state = client.state_at(BlockId::Latest).ok_or("Cannot read the latest state")?;
+        let banned = Banned::load_from_state(&state).map_err(|err| err.to_string())?;
+        let jail = Jail::load_from_state(&state).map_err(|err| err.to_string())?;
+        Ok(banned.is_banned(&criminal) || jail.get_prisoner(&criminal).is_some())
+    }
+
+    /// Submits the pending reports, and saves the reports so that they are not reported again after restarting.
+    fn submit_double_vote_reports(&mut self) {
+        self.queue_double_vote_reports();
+        self.double_vote_reports.save(self.client().get_kvdb().as_ref());
+    }
+
+    fn queue_double_vote_reports(&mut self) {
+        let reporter = if self.double_vote_reporter.is_some() {
+            &self.double_vote_reporter
+        } else {
+            &self.signer
+        };
+        let public = match reporter.public() {
+            Some(public) => *public,
             None => {
-                cerror!(ENGINE, "Found double vote, but signer was not assigned yet");
+                cerror!(ENGINE, "Found double vote, but reporter was not assigned yet");
                 return
             }
         };
-
-        let tx = Transaction {
-            seq,
-            fee: 0,
-            network_id,
-            action: Action::Custom {
+        let client = self.client();
+        let network_id = client.network_id();
+
+        for double in self.double_vote_reports.pending() {
+            match self.is_punished(&double) {
+                Ok(true) => {
+                    cinfo!(ENGINE, "The signer of the double vote is already punished: {:?}", double);
+                    self.double_vote_reports.set_punished(&double);
+                    continue
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    cwarn!(ENGINE, "Cannot check whether the double voter is punished: {}", e);
+                    continue
+                }
+            }
+            let seq = client.pending_seq(&public);
+            let action = Action::Custom {
                 handler_id: CUSTOM_ACTION_HANDLER_ID,
                 bytes: double.to_action().rlp_bytes(),
-            },
-        };
-        let signature = match self.signer.sign_ecdsa(*tx.hash()) {
-            Ok(signature) => signature,
-            Err(e) => {
-                cerror!(ENGINE, "Found double vote, but could not sign the message: {}", e);
-                return
-            }
-        };
-        let unverified = UnverifiedTransaction::new(tx, signature);
-        let signed = SignedTransaction::try_new(unverified).expect("secret is valid so it's recoverable");
+            };
+            let fee = min_fee_at_latest(&*client, &action).expect("Metadata of the latest block always exists");
+            let tx = Transaction {
+                seq,
+                fee,
+                network_id,
+                action,
+            };
+            let signature = match reporter.sign_ecdsa(*tx.hash()) {
+                Ok(signature) => signature,
+                Err(e) => {
+                    cerror!(ENGINE, "Found double vote, but could not sign the message: {}", e);
+                    return
+                }
+            };
+            let unverified = UnverifiedTransaction::new(tx, signature);
+            let signed = SignedTransaction::try_new(unverified).expect("secret is valid so it's recoverable");
+            let hash = signed.hash();
 
-        match self.client().queue_own_transaction(signed) {
-            Ok(_) => {}
-            Err(e) => {
-                cerror!(ENGINE, "Failed to queue double vote transaction: {}", e);
+            match client.queue_own_transaction(signed) {
+                Ok(_) => {
+                    cinfo!(
+                        ENGINE,
+                        "Reported the double vote of {} in {:?} by {}",
+                        double.author_index(),
+                        double.round(),
+                        hash
+                    );
+                    self.double_vote_reports.set_submitted(&double, hash);
+                }
+                Err(e) => {
+                    cerror!(ENGINE, "Failed to queue double vote transaction: {}", e);
+                    return
+                }
             }
         }
     }
@@ -1528,6 +1616,13 @@ impl Worker {
         self.signer.set_to_keep_decrypted_account(ap, address);
     }
 
+    fn set_double_vote_reporter(&mut self, ap: Arc<AccountProvider>, address: Address) {
+        self.double_vote_reporter.set(ap, address);
+        if self.double_vote_reports.has_pending() {
+            self.submit_double_vote_reports();
+        }
+    }
+
     fn vote_on_block_hash(&mut self, block_hash: Option<BlockHash>) -> Result<Option<ConsensusMessage>, Error> {
         let signer_index = if let Some(signer_index) = self.signer_index() {
             signer_index
@@ -1535,6 +1630,7 @@ impl Worker {
             ctrace!(ENGINE, "No message, since there is no engine signer.");
             return Ok(None)
         };
+        self.update_consensus_key(signer_index);
 
         let on = VoteOn {
             step: VoteStep::new(self.height, self.view, self.step.to_step()),
@@ -1542,7 +1638,7 @@ impl Worker {
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        let signature = self.signer.sign_vote(&on)?;
 
         let vote = ConsensusMessage {
             signature,
@@ -1562,6 +1658,7 @@ impl Worker {
         let parent_hash = header.parent_hash();
         let prev_proposer_idx = self.block_proposer_idx(*parent_hash).expect("Prev block must exists");
         let signer_index = self.validators.proposer_index(*parent_hash, prev_proposer_idx, self.view as usize);
+        self.update_consensus_key(signer_index);
 
         let on = VoteOn {
             step: VoteStep::new(self.height, self.view, Step::Propose),
@@ -1569,7 +1666,7 @@ impl Worker {
         };
         assert!(self.vote_regression_checker.check(&on), "Vote should not regress");
 
-        let signature = self.signer.sign(on.hash())?;
+        let signature = self.signer.sign_vote(&on)?;
 
         let vote = ConsensusMessage {
             signature,
@@ -1607,7 +1704,18 @@ impl Worker {
     fn signer_index(&self) -> Option<usize> {
         let parent = self.prev_block_hash();
         // FIXME: More effecient way to find index
-        self.signer.public().and_then(|public| self.validators.get_index(&parent, public))
+        self.signer.address().and_then(|address| self.validators.get_index_by_address(&parent, address))
+    }
+
+    /// Switch to the consensus key registered for the current height.
+    fn update_consensus_key(&mut self, signer_index: usize) {
+        let public = self.validators.get(&self.prev_block_hash(), signer_index);
+        if self.signer.consensus_public() == Some(&public) {
+            return
+        }
+        if let Err(e) = self.signer.set_consensus_key(public) {
+            cerror!(ENGINE, "Cannot sign with the consensus key {}: {}", public_to_address(&public), e);
+        }
     }
 
     fn new_blocks(&mut self, imported: Vec<BlockHash>, enacted: Vec<BlockHash>) {
@@ -1619,6 +1727,13 @@ impl Worker {
             }
         };
 
+        if self.double_vote_reports.prune(self.height) {
+            self.double_vote_reports.save(c.get_kvdb().as_ref());
+        }
+        if self.double_vote_reports.has_pending() {
+            self.submit_double_vote_reports();
+        }
+
         if self.step.is_commit() && (imported.len() + enacted.len() == 1) {
             let (_, committed_block_hash) = self.step.committed().expect("Commit state always has block_hash");
             if imported.first() == Some(&committed_block_hash) {
@@ -17,7 +17,7 @@
 use super::{RoundRobinValidator, ValidatorSet};
 use crate::client::ConsensusClient;
 use crate::consensus::bit_set::BitSet;
-use crate::consensus::stake::{get_validators, Validator};
+use crate::consensus::stake::{get_consensus_keys, get_validators, Validator};
 use crate::consensus::EngineError;
 use ckey::{public_to_address, Address, Public};
 use ctypes::util::unexpected::OutOfBounds;
@@ -40,6 +40,11 @@ impl DynamicValidator {
     }
 
     fn validators(&self, parent: BlockHash) -> Option<Vec<Validator>> {
+        self.validators_with_consensus_keys(parent).map(|(validators, _)| validators)
+    }
+
+    /// Returns the validators with the keys they sign consensus messages with.
+    fn validators_with_consensus_keys(&self, parent: BlockHash) -> Option<(Vec<Validator>, Vec<Public>)> {
         let client: Arc<dyn ConsensusClient> =
             self.client.read().as_ref().and_then(Weak::upgrade).expect("Client is not initialized");
         let block_id = parent.into();
@@ -58,7 +63,9 @@ impl DynamicValidator {
         } else {
             let mut validators: Vec<_> = validators.into();
             validators.reverse();
-            Some(validators)
+            let consensus_keys = get_consensus_keys(&state).unwrap();
+            let keys = validators.iter().map(|val| consensus_keys.consensus_key(val.pubkey())).collect();
+            Some((validators, keys))
         }
     }
 
@@ -66,6 +73,10 @@ impl DynamicValidator {
         self.validators(parent).map(|validators| validators.into_iter().map(|val| *val.pubkey()).collect())
     }
 
+    fn validators_consensus_pubkey(&self, parent: BlockHash) -> Option<Vec<Public>> {
+        self.validators_with_consensus_keys(parent).map(|(_, keys)| keys)
+    }
+
     pub fn proposer_index(&self, parent: BlockHash, prev_proposer_index: usize, proposed_view: usize) -> usize {
         if let Some(validators) = self.validators(parent) {
             let num_validators = validators.len();
@@ -79,7 +90,7 @@ impl DynamicValidator {
 
 impl ValidatorSet for DynamicValidator {
     fn contains(&self, parent: &BlockHash, public: &Public) -> bool {
-        if let Some(validators) = self.validators_pubkey(*parent) {
+        if let Some(validators) = self.validators_consensus_pubkey(*parent) {
             validators.into_iter().any(|pubkey| pubkey == *public)
         } else {
             self.initial_list.contains(parent, public)
@@ -95,7 +106,7 @@ impl ValidatorSet for DynamicValidator {
     }
 
     fn get(&self, parent: &BlockHash, index: usize) -> Public {
-        if let Some(validators) = self.validators_pubkey(*parent) {
+        if let Some(validators) = self.validators_consensus_pubkey(*parent) {
             let n_validators = validators.len();
             *validators.get(index % n_validators).unwrap()
         } else {
@@ -104,7 +115,7 @@ impl ValidatorSet for DynamicValidator {
     }
 
     fn get_index(&self, parent: &BlockHash, public: &Public) -> Option<usize> {
-        if let Some(validators) = self.validators_pubkey(*parent) {
+        if let Some(validators) = self.validators_consensus_pubkey(*parent) {
             validators.into_iter().enumerate().find(|(_index, pubkey)| pubkey == public).map(|(index, _)| index)
         } else {
             self.initial_list.get_index(parent, public)
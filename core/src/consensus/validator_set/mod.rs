@@ -28,6 +28,10 @@ pub mod validator_list;
 pub use self::dynamic_validator::DynamicValidator;
 
 /// A validator set.
+///
+/// A validator is identified by its account, but it may sign consensus messages with another key.
+/// The methods taking or returning a public key use the consensus keys,
+/// and the methods taking or returning an address use the addresses of the accounts.
 pub trait ValidatorSet: Send + Sync {
     /// Checks if a given public key is a validator,
     /// using underlying, default call mechanism.
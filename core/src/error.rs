@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::account_provider::Error as AccountProviderError;
+use crate::consensus::signer::Error as SignerError;
 use crate::consensus::EngineError;
 use cdb::DatabaseError;
 use cio::IoError;
@@ -175,11 +176,15 @@ pub enum Error {
     Key(KeyError),
     /// PoW hash is invalid or out of date.
     PowHashInvalid,
+    /// PoW hash belongs to a work that is no longer being sealed.
+    PowHashStale,
     /// The value of the nonce or mishash is invalid.
     PowInvalid,
     Scheme(SchemeError),
     /// Account Provider error.
     AccountProvider(AccountProviderError),
+    /// Engine signer error.
+    Signer(SignerError),
     Trie(TrieError),
     Runtime(RuntimeError),
     History(HistoryError),
@@ -199,9 +204,11 @@ impl fmt::Display for Error {
             Error::Engine(err) => err.fmt(f),
             Error::Key(err) => err.fmt(f),
             Error::PowHashInvalid => f.write_str("Invalid or out of date PoW hash."),
+            Error::PowHashStale => f.write_str("Stale PoW hash."),
             Error::PowInvalid => f.write_str("Invalid nonce or mishash"),
             Error::Scheme(err) => err.fmt(f),
             Error::AccountProvider(err) => err.fmt(f),
+            Error::Signer(err) => err.fmt(f),
             Error::Trie(err) => err.fmt(f),
             Error::Runtime(err) => err.fmt(f),
             Error::History(err) => err.fmt(f),
@@ -271,6 +278,12 @@ impl From<AccountProviderError> for Error {
     }
 }
 
+impl From<SignerError> for Error {
+    fn from(err: SignerError) -> Error {
+        Error::Signer(err)
+    }
+}
+
 impl From<TrieError> for Error {
     fn from(err: TrieError) -> Self {
         Error::Trie(err)
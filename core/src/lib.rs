@@ -87,10 +87,16 @@ pub use crate::client::{
     TermInfo, TestBlockChainClient, TextClient,
 };
 pub use crate::consensus::stake;
-pub use crate::consensus::{EngineType, TimeGapParams};
+pub use crate::consensus::{
+    read_signer_secret, DoubleVoteEvidence, EngineType, RemoteSigner, RemoteSignerEndpoint, RemoteSignerError,
+    SignerDaemon, TimeGapParams,
+};
 pub use crate::db::{COL_STATE, NUM_COLUMNS};
 pub use crate::error::{BlockImportError, Error, ImportError};
-pub use crate::miner::{MemPoolMinFees, Miner, MinerOptions, MinerService, Stratum, StratumConfig, StratumError};
+pub use crate::miner::{
+    BlockTemplatePolicy, CpuMiner, CpuMiningStatus, MemPoolMinFees, Miner, MinerOptions, MinerService, Stratum,
+    StratumConfig, StratumError, StratumWorkerStats, TemplateContext, TemplateDecision, ACTION_TYPES,
+};
 pub use crate::peer_db::PeerDb;
 pub use crate::scheme::Scheme;
 pub use crate::service::ClientService;
@@ -0,0 +1,385 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::mem_pool::spent_trackers;
+use crate::consensus::stake::CUSTOM_ACTION_HANDLER_ID as STAKE_ACTION_HANDLER_ID;
+use crate::transaction::SignedTransaction;
+use ckey::{public_to_address, Address};
+use ctypes::transaction::Action;
+use ctypes::{BlockNumber, Tracker};
+use std::collections::HashSet;
+
+/// The names of the action types, which are the same as the ones used by the RPCs.
+pub const ACTION_TYPES: &[&str] = &[
+    "mintAsset",
+    "transferAsset",
+    "changeAssetScheme",
+    "increaseAssetSupply",
+    "unwrapCCC",
+    "pay",
+    "setRegularKey",
+    "createShard",
+    "setShardOwners",
+    "setShardUsers",
+    "wrapCCC",
+    "store",
+    "remove",
+    "custom",
+];
+
+pub fn action_type(action: &Action) -> &'static str {
+    match action {
+        Action::MintAsset {
+            ..
+        } => "mintAsset",
+        Action::TransferAsset {
+            ..
+        } => "transferAsset",
+        Action::ChangeAssetScheme {
+            ..
+        } => "changeAssetScheme",
+        Action::IncreaseAssetSupply {
+            ..
+        } => "increaseAssetSupply",
+        Action::UnwrapCCC {
+            ..
+        } => "unwrapCCC",
+        Action::Pay {
+            ..
+        } => "pay",
+        Action::SetRegularKey {
+            ..
+        } => "setRegularKey",
+        Action::CreateShard {
+            ..
+        } => "createShard",
+        Action::SetShardOwners {
+            ..
+        } => "setShardOwners",
+        Action::SetShardUsers {
+            ..
+        } => "setShardUsers",
+        Action::WrapCCC {
+            ..
+        } => "wrapCCC",
+        Action::Store {
+            ..
+        } => "store",
+        Action::Remove {
+            ..
+        } => "remove",
+        Action::Custom {
+            ..
+        } => "custom",
+    }
+}
+
+/// The block being filled by the miner.
+pub struct TemplateContext {
+    pub block_number: BlockNumber,
+    /// The size of the transactions already put into the block.
+    pub body_size: usize,
+    pub max_body_size: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TemplateDecision {
+    /// The transaction goes into the block if no other policy skips it.
+    Include,
+    /// The transaction is left in the mem pool, and so are the following transactions of the signer.
+    Skip,
+}
+
+/// Decides which pending transactions go into a block template and in which order.
+pub trait BlockTemplatePolicy: Send + Sync {
+    /// The name used in the logs.
+    fn name(&self) -> &'static str;
+
+    /// Reorders the candidates fetched from the mem pool.
+    /// The candidates are not cut by the body size yet, so a transaction at the end of the mem pool can be moved
+    /// into the block. The transactions of a signer must keep their relative order.
+    fn prioritize(&self, _candidates: &mut Vec<SignedTransaction>) {}
+
+    /// Called for each candidate right before the miner pushes it into the block.
+    fn decide(&self, _tx: &SignedTransaction, _tx_size: usize, _context: &TemplateContext) -> TemplateDecision {
+        TemplateDecision::Include
+    }
+}
+
+/// Puts the transactions of the given signers before the others.
+pub struct PrioritizeSigners {
+    signers: HashSet<Address>,
+}
+
+impl PrioritizeSigners {
+    pub fn new(signers: impl IntoIterator<Item = Address>) -> Self {
+        Self {
+            signers: signers.into_iter().collect(),
+        }
+    }
+}
+
+impl BlockTemplatePolicy for PrioritizeSigners {
+    fn name(&self) -> &'static str {
+        "PrioritizeSigners"
+    }
+
+    fn prioritize(&self, candidates: &mut Vec<SignedTransaction>) {
+        let (mut prioritized, others): (Vec<_>, Vec<_>) = candidates
+            .drain(..)
+            .partition(|tx| self.signers.contains(&public_to_address(&tx.signer_public())));
+        prioritized.extend(others);
+        *candidates = prioritized;
+    }
+}
+
+/// Puts the transactions which create or spend the outputs of the given trackers before the others.
+/// The preceding transactions of the same signer are moved with them.
+pub struct PrioritizeTrackers {
+    trackers: HashSet<Tracker>,
+}
+
+impl PrioritizeTrackers {
+    pub fn new(trackers: impl IntoIterator<Item = Tracker>) -> Self {
+        Self {
+            trackers: trackers.into_iter().collect(),
+        }
+    }
+
+    fn matches(&self, tx: &SignedTransaction) -> bool {
+        tx.tracker().map_or(false, |tracker| self.trackers.contains(&tracker))
+            || spent_trackers(&tx.action).iter().any(|tracker| self.trackers.contains(tracker))
+    }
+}
+
+impl BlockTemplatePolicy for PrioritizeTrackers {
+    fn name(&self) -> &'static str {
+        "PrioritizeTrackers"
+    }
+
+    fn prioritize(&self, candidates: &mut Vec<SignedTransaction>) {
+        // A transaction cannot go before the preceding transactions of its signer.
+        let mut prioritized_signers = HashSet::new();
+        let mut is_prioritized: Vec<bool> = candidates
+            .iter()
+            .rev()
+            .map(|tx| {
+                let signer = tx.signer_public();
+                if self.matches(tx) {
+                    prioritized_signers.insert(signer);
+                }
+                prioritized_signers.contains(&signer)
+            })
+            .collect();
+        is_prioritized.reverse();
+
+        let (prioritized, others): (Vec<_>, Vec<_>) =
+            candidates.drain(..).zip(is_prioritized).partition(|(_, is_prioritized)| *is_prioritized);
+        *candidates = prioritized.into_iter().chain(others).map(|(tx, _)| tx).collect();
+    }
+}
+
+/// Leaves the transactions of the given action types out of blocks.
+pub struct ExcludeActions {
+    actions: HashSet<String>,
+}
+
+impl ExcludeActions {
+    pub fn new(actions: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            actions: actions.into_iter().collect(),
+        }
+    }
+}
+
+impl BlockTemplatePolicy for ExcludeActions {
+    fn name(&self) -> &'static str {
+        "ExcludeActions"
+    }
+
+    fn decide(&self, tx: &SignedTransaction, _tx_size: usize, _context: &TemplateContext) -> TemplateDecision {
+        if self.actions.contains(action_type(&tx.action)) {
+            TemplateDecision::Skip
+        } else {
+            TemplateDecision::Include
+        }
+    }
+}
+
+/// Keeps the last `reserved` bytes of the block body for stake transactions.
+pub struct ReserveStakeSpace {
+    reserved: usize,
+}
+
+impl ReserveStakeSpace {
+    pub fn new(reserved: usize) -> Self {
+        Self {
+            reserved,
+        }
+    }
+}
+
+impl BlockTemplatePolicy for ReserveStakeSpace {
+    fn name(&self) -> &'static str {
+        "ReserveStakeSpace"
+    }
+
+    fn decide(&self, tx: &SignedTransaction, tx_size: usize, context: &TemplateContext) -> TemplateDecision {
+        if let Action::Custom {
+            handler_id: STAKE_ACTION_HANDLER_ID,
+            ..
+        } = &tx.action
+        {
+            return TemplateDecision::Include
+        }
+        if context.body_size + tx_size > context.max_body_size.saturating_sub(self.reserved) {
+            TemplateDecision::Skip
+        } else {
+            TemplateDecision::Include
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, KeyPair, Random};
+    use ctypes::transaction::{AssetOutPoint, AssetTransferInput, Transaction};
+    use primitives::H256;
+
+    use super::*;
+
+    fn create_signed(seq: u64, action: Action, keypair: &KeyPair) -> SignedTransaction {
+        let tx = Transaction {
+            seq,
+            fee: 100,
+            network_id: "tc".into(),
+            action,
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private())
+    }
+
+    fn pay() -> Action {
+        Action::Pay {
+            receiver: 1u64.into(),
+            quantity: 100_000,
+        }
+    }
+
+    fn context(body_size: usize) -> TemplateContext {
+        TemplateContext {
+            block_number: 1,
+            body_size,
+            max_body_size: 1000,
+        }
+    }
+
+    #[test]
+    fn every_action_type_is_listed() {
+        assert!(ACTION_TYPES.contains(&action_type(&pay())));
+        assert_eq!(ACTION_TYPES.len(), ACTION_TYPES.iter().collect::<HashSet<_>>().len());
+    }
+
+    #[test]
+    fn prioritized_signers_keep_the_order_of_their_transactions() {
+        let keypair1 = Random.generate().unwrap();
+        let keypair2 = Random.generate().unwrap();
+        let tx1 = create_signed(0, pay(), &keypair1);
+        let tx2 = create_signed(0, pay(), &keypair2);
+        let tx3 = create_signed(1, pay(), &keypair1);
+        let tx4 = create_signed(1, pay(), &keypair2);
+        let mut candidates = vec![tx1.clone(), tx2.clone(), tx3.clone(), tx4.clone()];
+
+        let policy = PrioritizeSigners::new(vec![public_to_address(keypair2.public())]);
+        policy.prioritize(&mut candidates);
+        assert_eq!(vec![tx2, tx4, tx1, tx3], candidates);
+    }
+
+    fn transfer(spent: Tracker) -> Action {
+        Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: vec![],
+            inputs: vec![AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker: spent,
+                    index: 0,
+                    asset_type: Default::default(),
+                    shard_id: 0,
+                    quantity: 10,
+                },
+                timelock: None,
+                lock_script: vec![],
+                unlock_script: vec![],
+            }],
+            outputs: vec![],
+            metadata: "".into(),
+            approvals: vec![],
+            expiration: None,
+        }
+    }
+
+    #[test]
+    fn prioritized_trackers_bring_the_preceding_transactions_of_the_signer() {
+        let keypair1 = Random.generate().unwrap();
+        let keypair2 = Random.generate().unwrap();
+        let tracker: Tracker = H256::random().into();
+        let tx1 = create_signed(0, pay(), &keypair1);
+        let tx2 = create_signed(0, pay(), &keypair2);
+        let tx3 = create_signed(1, transfer(tracker), &keypair2);
+        let tx4 = create_signed(2, pay(), &keypair2);
+        let tx5 = create_signed(1, transfer(H256::random().into()), &keypair1);
+        let mut candidates = vec![tx1.clone(), tx2.clone(), tx3.clone(), tx4.clone(), tx5.clone()];
+
+        let policy = PrioritizeTrackers::new(vec![tracker]);
+        policy.prioritize(&mut candidates);
+        assert_eq!(vec![tx2, tx3, tx1, tx4, tx5], candidates);
+    }
+
+    #[test]
+    fn excluded_actions_are_skipped() {
+        let keypair = Random.generate().unwrap();
+        let policy = ExcludeActions::new(vec!["store".to_string()]);
+        let store = create_signed(
+            0,
+            Action::Store {
+                content: "text".to_string(),
+                certifier: Default::default(),
+                signature: Default::default(),
+            },
+            &keypair,
+        );
+        assert_eq!(TemplateDecision::Skip, policy.decide(&store, 100, &context(0)));
+        assert_eq!(TemplateDecision::Include, policy.decide(&create_signed(0, pay(), &keypair), 100, &context(0)));
+    }
+
+    #[test]
+    fn reserved_space_is_only_for_stake_transactions() {
+        let keypair = Random.generate().unwrap();
+        let policy = ReserveStakeSpace::new(300);
+        let tx = create_signed(0, pay(), &keypair);
+        assert_eq!(TemplateDecision::Include, policy.decide(&tx, 100, &context(600)));
+        assert_eq!(TemplateDecision::Skip, policy.decide(&tx, 100, &context(601)));
+
+        let stake = create_signed(
+            0,
+            Action::Custom {
+                handler_id: STAKE_ACTION_HANDLER_ID,
+                bytes: vec![],
+            },
+            &keypair,
+        );
+        assert_eq!(TemplateDecision::Include, policy.decide(&stake, 100, &context(601)));
+    }
+}
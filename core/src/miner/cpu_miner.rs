@@ -0,0 +1,219 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Built-in CPU miner for PoW development chains
+
+use crate::block::IsBlock;
+use crate::client::{Client, EngineClient};
+use crate::miner::work_notify::NotifyWork;
+use crate::miner::{Miner, MinerService};
+use parking_lot::{Condvar, Mutex};
+use primitives::{H256, U256};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Work {
+    pow_hash: H256,
+    target: U256,
+    generation: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CpuMiningStatus {
+    pub running: bool,
+    pub threads: usize,
+    /// The number of nonces tried since the node started.
+    pub hashes: usize,
+    /// The number of blocks sealed since the node started.
+    pub blocks: usize,
+}
+
+struct Inner {
+    miner: Arc<Miner>,
+    client: Arc<Client>,
+    work: Mutex<Option<Work>>,
+    work_changed: Condvar,
+    /// The generation of the latest work, which lets the threads abandon the old work without locking.
+    generation: AtomicUsize,
+    stopped: AtomicBool,
+    hashes: AtomicUsize,
+    blocks: AtomicUsize,
+}
+
+/// Searches nonces for the PoW engines on multiple threads and submits the found seals to the miner.
+pub struct CpuMiner {
+    inner: Arc<Inner>,
+    handles: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl CpuMiner {
+    pub fn new(miner: Arc<Miner>, client: Arc<Client>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                miner,
+                client,
+                work: Mutex::new(None),
+                work_changed: Condvar::new(),
+                generation: AtomicUsize::new(0),
+                stopped: AtomicBool::new(true),
+                hashes: AtomicUsize::new(0),
+                blocks: AtomicUsize::new(0),
+            }),
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Start mining on `threads` threads. Mining threads that are already running are restarted.
+    pub fn start(&self, threads: usize) {
+        assert_ne!(0, threads, "CPU miner needs at least one thread");
+        self.stop();
+
+        {
+            let mut handles = self.handles.lock();
+            self.inner.stopped.store(false, Ordering::SeqCst);
+            for index in 0..threads {
+                let inner = Arc::clone(&self.inner);
+                let handle = thread::Builder::new()
+                    .name(format!("cpu_miner.{}", index))
+                    .spawn(move || inner.run(index as u64, threads as u64))
+                    .expect("Failed to spawn a CPU mining thread");
+                handles.push(handle);
+            }
+        }
+        cinfo!(MINER, "CPU mining started with {} threads", threads);
+
+        self.inner.request_work();
+    }
+
+    /// Stop mining and wait for the threads to finish.
+    pub fn stop(&self) {
+        let mut handles = self.handles.lock();
+        if handles.is_empty() {
+            return
+        }
+        {
+            let _work = self.inner.work.lock();
+            self.inner.stopped.store(true, Ordering::SeqCst);
+            self.inner.work_changed.notify_all();
+        }
+        for handle in handles.drain(..) {
+            if handle.join().is_err() {
+                cwarn!(MINER, "A CPU mining thread panicked");
+            }
+        }
+        cinfo!(MINER, "CPU mining stopped");
+    }
+
+    pub fn status(&self) -> CpuMiningStatus {
+        let threads = self.handles.lock().len();
+        CpuMiningStatus {
+            running: threads != 0,
+            threads,
+            hashes: self.inner.hashes.load(Ordering::Relaxed),
+            blocks: self.inner.blocks.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Drop for CpuMiner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl NotifyWork for CpuMiner {
+    fn notify(&self, pow_hash: H256, target: U256) {
+        ctrace!(MINER, "CPU miner received work {}", pow_hash);
+        self.inner.set_work(pow_hash, target);
+    }
+}
+
+impl Inner {
+    fn set_work(&self, pow_hash: H256, target: U256) {
+        let mut work = self.work.lock();
+        if work.map_or(false, |work| work.pow_hash == pow_hash) {
+            return
+        }
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        *work = Some(Work {
+            pow_hash,
+            target,
+            generation,
+        });
+        self.work_changed.notify_all();
+    }
+
+    /// Ask the miner for the current work, which also keeps the miner sealing.
+    /// It must not be called while holding the work lock since the miner notifies the new work.
+    fn request_work(&self) {
+        let work = self.miner.map_sealing_work(&*self.client, |b| (b.hash(), *b.header().score()));
+        if let Some((pow_hash, score)) = work {
+            self.set_work(pow_hash, self.client.score_to_target(&score));
+        }
+    }
+
+    /// Wait for a work newer than `last_generation`. Returns `None` if the miner is stopped.
+    fn wait_work(&self, last_generation: usize) -> Option<Work> {
+        let mut work = self.work.lock();
+        loop {
+            if self.stopped.load(Ordering::SeqCst) {
+                return None
+            }
+            let current = *work;
+            match current {
+                Some(current) if current.generation != last_generation => return Some(current),
+                _ => self.work_changed.wait(&mut work),
+            }
+        }
+    }
+
+    fn is_outdated(&self, work: &Work) -> bool {
+        self.stopped.load(Ordering::Relaxed) || self.generation.load(Ordering::Relaxed) != work.generation
+    }
+
+    fn run(&self, index: u64, threads: u64) {
+        let engine = self.client.engine();
+        let mut last_generation = 0;
+        while let Some(work) = self.wait_work(last_generation) {
+            last_generation = work.generation;
+
+            let mut nonce = index;
+            let seal = loop {
+                if self.is_outdated(&work) {
+                    break None
+                }
+                self.hashes.fetch_add(1, Ordering::Relaxed);
+                if let Some(seal) = engine.try_pow_nonce(&work.pow_hash, &work.target, nonce) {
+                    break Some(seal)
+                }
+                nonce = nonce.wrapping_add(threads);
+            };
+
+            if let Some(seal) = seal {
+                ctrace!(MINER, "CPU miner found a seal of {} with nonce {}", work.pow_hash, nonce);
+                match self.miner.submit_seal(&*self.client, work.pow_hash.into(), seal) {
+                    Ok(()) => {
+                        self.blocks.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(e) => cdebug!(MINER, "The seal found by the CPU miner is rejected: {}", e),
+                }
+                self.request_work();
+            }
+        }
+    }
+}
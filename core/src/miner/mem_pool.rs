@@ -26,7 +26,9 @@ use crate::transaction::{PendingSignedTransactions, SignedTransaction};
 use crate::{BlockId, Error as CoreError};
 use ckey::{public_to_address, Public};
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
-use ctypes::{BlockNumber, TxHash};
+use ctypes::transaction::Action;
+use ctypes::{BlockNumber, Tracker, TxHash};
+use cvm::{verify_script, Diagnostic, VMConfig};
 use kvdb::{DBTransaction, KeyValueDB};
 use rlp;
 use std::cmp::max;
@@ -94,8 +96,14 @@ pub struct MemPool {
     queue_count_limit: usize,
     /// The memory limit of each queue
     queue_memory_limit: usize,
+    /// The count(number) limit of each queue for a single signer
+    signer_count_limit: usize,
     /// All transactions managed by pool indexed by hash
     by_hash: HashMap<TxHash, MemPoolItem>,
+    /// The transactions creating shard outputs indexed by the tracker of the outputs
+    by_created_tracker: HashMap<Tracker, TxHash>,
+    /// The transactions spending shard outputs indexed by the tracker of the spent outputs
+    by_spent_tracker: HashMap<Tracker, HashSet<TxHash>>,
     /// Current seq of each public key (fee payer)
     first_seqs: HashMap<Public, u64>,
     /// Next seq of transaction in current (to quickly check next expected transaction)
@@ -110,6 +118,8 @@ pub struct MemPool {
     next_transaction_id: u64,
     /// Arc of KeyValueDB in which the backup information is stored.
     db: Arc<dyn KeyValueDB>,
+    /// The VM configuration of the next block, used to verify the scripts of new transactions
+    vm_config: VMConfig,
 }
 
 impl MemPool {
@@ -117,6 +127,7 @@ impl MemPool {
     pub fn with_limits(
         limit: usize,
         memory_limit: usize,
+        signer_limit: usize,
         fee_bump_shift: usize,
         db: Arc<dyn KeyValueDB>,
         minimum_fees: MemPoolMinFees,
@@ -130,7 +141,10 @@ impl MemPool {
             by_signer_public: Table::new(),
             queue_count_limit: limit,
             queue_memory_limit: memory_limit,
+            signer_count_limit: signer_limit,
             by_hash: HashMap::new(),
+            by_created_tracker: HashMap::new(),
+            by_spent_tracker: HashMap::new(),
             first_seqs: HashMap::new(),
             next_seqs: HashMap::new(),
             is_local_account: HashSet::new(),
@@ -138,6 +152,7 @@ impl MemPool {
             last_timestamp: 0,
             next_transaction_id: 0,
             db,
+            vm_config: VMConfig::default(),
         }
     }
 
@@ -146,26 +161,104 @@ impl MemPool {
         self.queue_count_limit = limit;
     }
 
-    /// Enforce the limit to the current/future queue
-    fn enforce_limit(&mut self, batch: &mut DBTransaction) {
+    /// Inserts the item to `by_hash` and the tracker indexes.
+    fn insert_item(&mut self, hash: TxHash, item: MemPoolItem) {
+        if let Some(tracker) = item.created_tracker {
+            self.by_created_tracker.insert(tracker, hash);
+        }
+        for tracker in spent_trackers(&item.tx.action) {
+            self.by_spent_tracker.entry(tracker).or_default().insert(hash);
+        }
+        self.by_hash.insert(hash, item);
+    }
+
+    /// Removes the item from `by_hash` and the tracker indexes.
+    fn remove_item(&mut self, hash: &TxHash) -> Option<MemPoolItem> {
+        let item = self.by_hash.remove(hash)?;
+        if let Some(tracker) = item.created_tracker {
+            // A replacement may create the same outputs, and then the index points to it.
+            if self.by_created_tracker.get(&tracker) == Some(hash) {
+                self.by_created_tracker.remove(&tracker);
+            }
+        }
+        for tracker in spent_trackers(&item.tx.action) {
+            if let Some(spenders) = self.by_spent_tracker.get_mut(&tracker) {
+                spenders.remove(hash);
+                if spenders.is_empty() {
+                    self.by_spent_tracker.remove(&tracker);
+                }
+            }
+        }
+        Some(item)
+    }
+
+    /// Set the VM configuration of the next block, which the scripts of new transactions are verified with.
+    pub fn set_vm_config(&mut self, vm_config: VMConfig) {
+        self.vm_config = vm_config;
+    }
+
+    /// Enforce the limits to the current/future queue.
+    /// Returns the dropped transactions with the limit that each of them hit.
+    /// The trackers of the shard outputs created by the dropped transactions are added to `lost_trackers`.
+    fn enforce_limit(
+        &mut self,
+        batch: &mut DBTransaction,
+        lost_trackers: &mut HashSet<Tracker>,
+    ) -> HashMap<TxHash, HistoryError> {
         // Get transaction orders to drop from each queue (current/future)
         fn get_orders_to_drop(
             set: &BTreeSet<TransactionOrder>,
             limit: usize,
             memory_limit: usize,
-        ) -> Vec<TransactionOrder> {
+        ) -> Vec<(TransactionOrder, HistoryError)> {
             let mut count = 0;
             let mut mem_usage = 0;
             set.iter()
-                .filter(|order| {
+                .filter_map(|order| {
                     count += 1;
                     mem_usage += order.mem_usage;
-                    !order.origin.is_local_or_retracted() && (mem_usage > memory_limit || count > limit)
+                    if order.origin.is_local_or_retracted() {
+                        None
+                    } else if count > limit {
+                        Some((*order, HistoryError::LimitReached))
+                    } else if mem_usage > memory_limit {
+                        Some((*order, HistoryError::MemoryLimitReached))
+                    } else {
+                        None
+                    }
                 })
-                .cloned()
                 .collect()
         }
 
+        let mut dropped = HashMap::new();
+
+        // The transactions of a signer are ordered by their seqs, so the ones with the highest seqs are dropped.
+        let signer_limit = self.signer_count_limit;
+        let mut to_drop_by_signer = Vec::new();
+        for signer_public in self.by_signer_public.keys() {
+            let row = self.by_signer_public.row(signer_public).expect("The key is from `by_signer_public`");
+            for tag in &[QueueTag::Current, QueueTag::Future] {
+                let mut orders: Vec<_> = row
+                    .values()
+                    .filter(|order_with_tag| {
+                        order_with_tag.tag == *tag && !order_with_tag.order.origin.is_local_or_retracted()
+                    })
+                    .map(|order_with_tag| order_with_tag.order)
+                    .collect();
+                if orders.len() <= signer_limit {
+                    continue
+                }
+                orders.sort_unstable();
+                to_drop_by_signer.extend(orders.into_iter().skip(signer_limit));
+            }
+        }
+        for order in to_drop_by_signer {
+            lost_trackers.extend(self.drop_order(&order, batch));
+            dropped.insert(order.hash, HistoryError::SignerLimitReached {
+                limit: signer_limit as u64,
+            });
+        }
+
         let to_drop_current =
             if self.current.mem_usage > self.queue_memory_limit || self.current.count > self.queue_count_limit {
                 get_orders_to_drop(&self.current.queue, self.queue_count_limit, self.queue_memory_limit)
@@ -180,26 +273,50 @@ impl MemPool {
                 vec![]
             };
 
-        for (order, is_current) in
-            to_drop_current.iter().map(|order| (order, true)).chain(to_drop_future.iter().map(|order| (order, false)))
-        {
-            let hash = order.hash;
-            let item = self.by_hash.remove(&hash).expect("`by_hash` and `current/future` should be synced");
-            backup::remove_item(batch, &hash);
-            let signer_public = item.signer_public();
-            let seq = item.seq();
-            self.by_signer_public
-                .remove(&signer_public, &seq)
-                .expect("`by_hash` and `by_signer_public` should be synced");
-            if self.by_signer_public.clear_if_empty(&signer_public) {
-                self.is_local_account.remove(&signer_public);
-            }
-            if is_current {
+        for (order, error) in to_drop_current.into_iter().chain(to_drop_future) {
+            lost_trackers.extend(self.drop_order(&order, batch));
+            dropped.insert(order.hash, error);
+        }
+        dropped
+    }
+
+    /// Drops the transaction from the pool.
+    /// If it is in the current queue, the following transactions of the signer are moved to the future queue
+    /// because they cannot be executed until the dropped seq is filled again.
+    /// Returns the tracker of the shard outputs created by the dropped transaction.
+    fn drop_order(&mut self, order: &TransactionOrder, batch: &mut DBTransaction) -> Option<Tracker> {
+        let hash = order.hash;
+        let item = self.remove_item(&hash).expect("`by_hash` and `current/future` should be synced");
+        backup::remove_item(batch, &hash);
+        let signer_public = item.signer_public();
+        let seq = item.seq();
+        let order_with_tag = self
+            .by_signer_public
+            .remove(&signer_public, &seq)
+            .expect("`by_hash` and `by_signer_public` should be synced");
+        match order_with_tag.tag {
+            QueueTag::Current => {
                 self.current.remove(order);
-            } else {
+                let first_seq = *self.first_seqs.get(&signer_public).unwrap_or(&0);
+                let next_seq = self.next_seqs.get(&signer_public).cloned().unwrap_or(first_seq);
+                if seq < next_seq && self.by_signer_public.has_row(&signer_public) {
+                    self.move_queue(signer_public, seq + 1, next_seq, QueueTag::Future);
+                }
+                if seq <= first_seq {
+                    self.next_seqs.remove(&signer_public);
+                } else if seq < next_seq {
+                    self.next_seqs.insert(signer_public, seq);
+                }
+            }
+            QueueTag::Future => {
                 self.future.remove(order);
             }
+            QueueTag::New => unreachable!(),
+        }
+        if !self.by_signer_public.has_row(&signer_public) {
+            self.is_local_account.remove(&signer_public);
         }
+        item.created_tracker
     }
 
     /// Returns current limit of transactions in the pool.
@@ -207,16 +324,6 @@ impl MemPool {
         self.queue_count_limit
     }
 
-    /// Get one more than the lowest fee in the pool iff the pool is
-    /// full, otherwise 0.
-    pub fn effective_minimum_fee(&self) -> u64 {
-        if self.current.len() >= self.queue_count_limit {
-            self.current.minimum_fee()
-        } else {
-            0
-        }
-    }
-
     /// Returns current status for this pool
     pub fn status(&self) -> MemPoolStatus {
         MemPoolStatus {
@@ -242,6 +349,7 @@ impl MemPool {
         let mut insert_results = Vec::new();
         let mut to_insert: HashMap<Public, Vec<u64>> = HashMap::new();
         let mut new_local_accounts = HashSet::new();
+        let mut lost_trackers = HashSet::new();
         let mut batch = backup::backup_batch_with_capacity(inputs.len());
 
         for input in inputs {
@@ -274,13 +382,15 @@ impl MemPool {
             let order_with_tag = TransactionOrderWithTag::new(order, QueueTag::New);
 
             backup::backup_item(&mut batch, *hash, &item);
-            self.by_hash.insert(hash, item);
+            self.insert_item(hash, item);
 
             if let Some(old_order_with_tag) = self.by_signer_public.insert(signer_public, seq, order_with_tag) {
                 let old_order = old_order_with_tag.order;
                 let tag = old_order_with_tag.tag;
 
-                self.by_hash.remove(&old_order.hash);
+                if let Some(old_item) = self.remove_item(&old_order.hash) {
+                    lost_trackers.extend(old_item.created_tracker);
+                }
                 backup::remove_item(&mut batch, &old_order.hash);
 
                 match tag {
@@ -295,7 +405,7 @@ impl MemPool {
             }
 
             to_insert.entry(signer_public).or_default().push(seq);
-            insert_results.push(Ok((signer_public, seq)));
+            insert_results.push(Ok((signer_public, seq, hash)));
         }
 
         let keys = self.by_signer_public.keys().map(Clone::clone).collect::<Vec<_>>();
@@ -351,7 +461,7 @@ impl MemPool {
             }
         }
 
-        self.enforce_limit(&mut batch);
+        let dropped = self.enforce_limit(&mut batch, &mut lost_trackers);
 
         self.last_block_number = inserted_block_number;
         self.last_timestamp = inserted_timestamp;
@@ -361,16 +471,27 @@ impl MemPool {
         assert_eq!(self.by_signer_public.len(), self.by_hash.len());
 
         self.db.write(batch).expect("Low level database error. Some issue with disk?");
+
+        // A replaced transaction may create the same shard outputs with a higher fee.
+        lost_trackers.retain(|tracker| !self.by_created_tracker.contains_key(tracker));
+        let orphans = self.dependents(lost_trackers);
+        if !orphans.is_empty() {
+            cdebug!(MEM_POOL, "Removing {} transactions whose parents are dropped", orphans.len());
+            let fetch_seq = |public: &Public| fetch_account(public).seq;
+            self.remove(&orphans, &fetch_seq, inserted_block_number, inserted_timestamp);
+        }
+
         insert_results
             .into_iter()
             .map(|v| match v {
-                Ok((signer_public, seq)) => match self.by_signer_public.get(&signer_public, &seq) {
+                Ok((signer_public, seq, hash)) => match self.by_signer_public.get(&signer_public, &seq) {
                     Some(order_with_tag) => match order_with_tag.tag {
                         QueueTag::Current => Ok(TransactionImportResult::Current),
                         QueueTag::Future => Ok(TransactionImportResult::Future),
                         QueueTag::New => unreachable!(),
                     },
-                    None => Err(HistoryError::LimitReached.into()),
+                    None if orphans.contains(&hash) => Err(HistoryError::ParentDropped.into()),
+                    None => Err(dropped.get(&hash).cloned().unwrap_or(HistoryError::LimitReached).into()),
                 },
                 Err(e) => Err(e),
             })
@@ -456,7 +577,7 @@ impl MemPool {
             let order = TransactionOrder::for_transaction(&item, client_account.seq);
             let order_with_tag = TransactionOrderWithTag::new(order, QueueTag::New);
 
-            self.by_hash.insert((*hash).into(), item.clone());
+            self.insert_item((*hash).into(), item.clone());
 
             self.by_signer_public.insert(signer_public, seq, order_with_tag);
             if item.origin == TxOrigin::Local {
@@ -504,6 +625,8 @@ impl MemPool {
     /// so transactions left in pool are processed according to client seq.
     ///
     /// If gap is introduced marks subsequent transactions as future
+    ///
+    /// The transactions spending the shard outputs of the removed ones are removed too.
     pub fn remove<F>(
         &mut self,
         transaction_hashes: &[TxHash],
@@ -514,9 +637,21 @@ impl MemPool {
         F: Fn(&Public) -> u64, {
         ctrace!(MEM_POOL, "remove() called, time: {}, timestamp: {}", current_block_number, current_timestamp);
         let mut removed: HashMap<_, _> = HashMap::new();
+        let lost_trackers = transaction_hashes
+            .iter()
+            .filter_map(|hash| self.by_hash.get(hash))
+            .filter_map(|item| item.created_tracker)
+            .collect();
+        let mut transaction_hashes = transaction_hashes.to_vec();
+        for dependent in self.dependents(lost_trackers) {
+            if !transaction_hashes.contains(&dependent) {
+                cdebug!(MEM_POOL, "Removing {:?} since its parent is removed", dependent);
+                transaction_hashes.push(dependent);
+            }
+        }
         let mut batch = backup::backup_batch_with_capacity(transaction_hashes.len());
 
-        for hash in transaction_hashes {
+        for hash in &transaction_hashes {
             if let Some(item) = self.by_hash.get(hash).map(Clone::clone) {
                 let signer_public = item.signer_public();
                 let seq = item.seq();
@@ -533,7 +668,7 @@ impl MemPool {
                     QueueTag::New => unreachable!(),
                 }
 
-                self.by_hash.remove(hash);
+                self.remove_item(hash);
                 backup::remove_item(&mut batch, hash);
                 self.by_signer_public.remove(&signer_public, &seq);
                 if current_seq <= seq {
@@ -735,6 +870,7 @@ impl MemPool {
             .expect("This function should be called after checking from `self.by_signer_public.keys()`");
 
         let seqs = row.keys().map(Clone::clone).collect::<Vec<_>>();
+        let mut outdated = Vec::new();
 
         for seq in seqs {
             let order_with_tag = *row.get(&seq).expect("Must exist");
@@ -749,8 +885,7 @@ impl MemPool {
             row.remove(&seq);
 
             if seq < current_seq {
-                self.by_hash.remove(&old_order.hash);
-                backup::remove_item(batch, &old_order.hash);
+                outdated.push(old_order.hash);
             } else {
                 let new_order = old_order.update_height(seq, current_seq);
                 let new_order = if to_local {
@@ -769,11 +904,16 @@ impl MemPool {
                 }
             }
         }
+
+        for hash in outdated {
+            self.remove_item(&hash);
+            backup::remove_item(batch, &hash);
+        }
     }
 
     /// Verify signed transaction with its content.
     /// This function can return errors: InsufficientFee, InsufficientBalance,
-    /// TransactionAlreadyImported, Old, TooCheapToReplace
+    /// TransactionAlreadyImported, Old, TooCheapToReplace, InvalidScript
     fn verify_transaction(
         &self,
         tx: &SignedTransaction,
@@ -797,23 +937,6 @@ impl MemPool {
             .into())
         }
 
-        let full_pools_lowest = self.effective_minimum_fee();
-        if origin != TxOrigin::Local && tx.fee < full_pools_lowest {
-            ctrace!(
-                MEM_POOL,
-                "Dropping transaction below lowest fee in a full pool: {:?} (gp: {} < {})",
-                tx.hash(),
-                tx.fee,
-                full_pools_lowest
-            );
-
-            return Err(SyntaxError::InsufficientFee {
-                minimal: full_pools_lowest,
-                got: tx.fee,
-            }
-            .into())
-        }
-
         if client_account.balance < tx.fee {
             ctrace!(
                 MEM_POOL,
@@ -849,7 +972,7 @@ impl MemPool {
             {
                 let old_fee = order.fee;
                 let new_fee = tx.fee;
-                let min_required_fee = old_fee + (old_fee >> self.fee_bump_shift);
+                let min_required_fee = self.min_fee_to_replace(old_fee);
 
                 if new_fee < min_required_fee {
                     ctrace!(
@@ -865,6 +988,11 @@ impl MemPool {
             }
         }
 
+        if let Err(err) = verify_scripts(&tx.action, self.vm_config) {
+            ctrace!(MEM_POOL, "Dropping transaction with invalid scripts: {:?} ({})", tx.hash(), err);
+            return Err(err.into())
+        }
+
         Ok(())
     }
 
@@ -904,7 +1032,7 @@ impl MemPool {
             })
             .collect();
 
-        let transactions = pending_items.iter().map(|t| t.tx.clone()).collect();
+        let transactions = self.sort_by_dependencies(pending_items.iter().map(|t| t.tx.clone()).collect());
         let last_timestamp = pending_items.into_iter().map(|t| t.inserted_timestamp).max();
 
         PendingSignedTransactions {
@@ -951,6 +1079,12 @@ impl MemPool {
                 .count()
     }
 
+    /// The seq the next transaction of the signer should have to be appended to the current queue.
+    /// None if the signer has no transaction in the current queue.
+    pub fn next_seq(&self, public: &Public) -> Option<u64> {
+        self.next_seqs.get(public).cloned()
+    }
+
     /// Return all future transactions along with current transactions.
     pub fn get_future_pending_transactions(
         &self,
@@ -1048,6 +1182,96 @@ impl MemPool {
         self.by_hash.get(&tx_hash).map(|found_item| found_item.origin.is_local())
     }
 
+    /// Returns the transaction in the pool with the given hash.
+    pub fn transaction(&self, tx_hash: &TxHash) -> Option<SignedTransaction> {
+        self.by_hash.get(tx_hash).map(|found_item| found_item.tx.clone())
+    }
+
+    /// Returns the minimum fee of a transaction to replace the one that pays `old_fee`.
+    pub fn min_fee_to_replace(&self, old_fee: u64) -> u64 {
+        old_fee + (old_fee >> self.fee_bump_shift)
+    }
+
+    /// Returns the trackers of the shard outputs created by the transactions in the pool.
+    pub fn pending_outputs(&self) -> HashMap<Tracker, TxHash> {
+        self.by_created_tracker.clone()
+    }
+
+    /// Returns the transactions in the pool spending the given shard outputs, directly or through other transactions.
+    fn dependents(&self, trackers: HashSet<Tracker>) -> Vec<TxHash> {
+        let mut found = HashSet::new();
+        let mut dependents = Vec::new();
+        let mut trackers: Vec<_> = trackers.into_iter().collect();
+        while let Some(tracker) = trackers.pop() {
+            for hash in self.by_spent_tracker.get(&tracker).into_iter().flatten() {
+                if !found.insert(*hash) {
+                    continue
+                }
+                dependents.push(*hash);
+                trackers.extend(self.by_hash.get(hash).and_then(|item| item.created_tracker));
+            }
+        }
+        dependents
+    }
+
+    /// Reorders the given transactions so that the ones spending the shard outputs of other pending transactions come
+    /// after them. The relative order of the others and the seq order of each signer are kept.
+    /// The transactions whose pending parents are not in the given list are dropped.
+    pub fn sort_by_dependencies(&self, transactions: Vec<SignedTransaction>) -> Vec<SignedTransaction> {
+        let parents_of = |tx: &SignedTransaction| -> HashSet<TxHash> {
+            spent_trackers(&tx.action)
+                .iter()
+                .filter_map(|tracker| self.by_created_tracker.get(tracker).cloned())
+                .collect()
+        };
+        if transactions.iter().all(|tx| parents_of(tx).is_empty()) {
+            return transactions
+        }
+
+        let total = transactions.len();
+        let mut included = HashSet::new();
+        let mut sorted = Vec::with_capacity(total);
+        // A transaction waits for its pending parents and for the previous transaction of its signer.
+        let mut waiting_for: HashMap<TxHash, Vec<usize>> = HashMap::new();
+        let mut remaining = vec![0; total];
+        let mut last_of_signer = HashMap::new();
+        let mut transactions: Vec<_> = transactions.into_iter().map(Some).collect();
+        for index in 0..total {
+            let (hash, signer, mut awaited) = {
+                let tx = transactions[index].as_ref().expect("Not included yet");
+                (tx.hash(), tx.signer_public(), parents_of(tx))
+            };
+            awaited.extend(last_of_signer.insert(signer, hash));
+            awaited.retain(|awaited| !included.contains(awaited));
+            remaining[index] = awaited.len();
+            for awaited in awaited {
+                waiting_for.entry(awaited).or_default().push(index);
+            }
+            if remaining[index] != 0 {
+                continue
+            }
+
+            let mut ready = vec![index];
+            while let Some(index) = ready.pop() {
+                let tx = transactions[index].take().expect("A transaction is included once");
+                let hash = tx.hash();
+                included.insert(hash);
+                sorted.push(tx);
+                for waiter in waiting_for.remove(&hash).unwrap_or_default().into_iter().rev() {
+                    remaining[waiter] -= 1;
+                    if remaining[waiter] == 0 {
+                        ready.push(waiter);
+                    }
+                }
+            }
+        }
+        if sorted.len() < total {
+            let postponed = total - sorted.len();
+            cdebug!(MEM_POOL, "{} transactions are postponed since their parents are not included", postponed);
+        }
+        sorted
+    }
+
     /// Checks the given timelock with the current time/timestamp.
     fn should_wait_timelock(timelock: &TxTimelock, best_block_number: BlockNumber, best_block_timestamp: u64) -> bool {
         if let Some(block_number) = timelock.block {
@@ -1064,6 +1288,72 @@ impl MemPool {
     }
 }
 
+/// Runs the static verifier on the scripts of the inputs and the burns.
+/// Only the scripts that fail on every path are rejected, and the diagnostics of the first of them are returned
+/// in the error. The worst cases of the other paths are logged as lints.
+fn verify_scripts(action: &Action, vm_config: VMConfig) -> Result<(), SyntaxError> {
+    if let Action::TransferAsset {
+        burns,
+        inputs,
+        ..
+    } = action
+    {
+        for (kind, index, input) in burns
+            .iter()
+            .enumerate()
+            .map(|(index, burn)| ("burn", index, burn))
+            .chain(inputs.iter().enumerate().map(|(index, input)| ("input", index, input)))
+        {
+            if let Err(diagnostics) = verify_script(&input.unlock_script, &input.lock_script, vm_config) {
+                let (fatal, lints): (Vec<_>, Vec<_>) = diagnostics.into_iter().partition(Diagnostic::is_fatal);
+                for lint in lints {
+                    ctrace!(MEM_POOL, "{} {}: {}", kind, index, lint);
+                }
+                if !fatal.is_empty() {
+                    let diagnostics: Vec<_> = fatal.iter().map(ToString::to_string).collect();
+                    return Err(SyntaxError::InvalidScript(format!("{} {}: {}", kind, index, diagnostics.join(", "))))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the trackers of the transactions whose shard outputs are spent by the given action.
+pub fn spent_trackers(action: &Action) -> Vec<Tracker> {
+    match action {
+        Action::TransferAsset {
+            burns,
+            inputs,
+            ..
+        } => burns.iter().chain(inputs.iter()).map(|input| input.prev_out.tracker).collect(),
+        Action::UnwrapCCC {
+            burn,
+            ..
+        } => vec![burn.prev_out.tracker],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the tracker of the shard outputs created by the given transaction.
+pub fn created_tracker(tx: &SignedTransaction) -> Option<Tracker> {
+    match tx.action {
+        Action::MintAsset {
+            ..
+        }
+        | Action::TransferAsset {
+            ..
+        }
+        | Action::IncreaseAssetSupply {
+            ..
+        }
+        | Action::WrapCCC {
+            ..
+        } => tx.tracker(),
+        _ => None,
+    }
+}
+
 
 #[cfg(test)]
 pub mod test {
@@ -1071,7 +1361,7 @@ pub mod test {
 
     use crate::client::{AccountData, TestBlockChainClient};
     use ckey::{Generator, KeyPair, Random};
-    use ctypes::transaction::{Action, AssetMintOutput, Transaction};
+    use ctypes::transaction::{Action, AssetMintOutput, AssetOutPoint, AssetTransferInput, Transaction};
     use primitives::H160;
 
     use super::*;
@@ -1408,13 +1698,8 @@ pub mod test {
     fn fee_per_byte_order_simple() {
         let order1 = create_transaction_order(1_000_000_000, 100);
         let order2 = create_transaction_order(1_500_000_000, 300);
-        assert!(
-            order1.fee_per_byte > order2.fee_per_byte,
-            "{} must be larger than {}",
-            order1.fee_per_byte,
-            order2.fee_per_byte
-        );
-        assert_eq!(Ordering::Greater, order1.cmp(&order2));
+        assert!(order1.fee * (order2.mem_usage as u64) > order2.fee * (order1.mem_usage as u64));
+        assert_eq!(Ordering::Less, order1.cmp(&order2));
     }
 
     #[test]
@@ -1435,11 +1720,11 @@ pub mod test {
         let prev_orders = orders.clone();
         orders.sort_unstable();
         let sorted_orders = orders;
-        assert_eq!(prev_orders[1], sorted_orders[0]);
-        assert_eq!(prev_orders[4], sorted_orders[1]);
+        assert_eq!(prev_orders[3], sorted_orders[0]);
+        assert_eq!(prev_orders[0], sorted_orders[1]);
         assert_eq!(prev_orders[2], sorted_orders[2]);
-        assert_eq!(prev_orders[0], sorted_orders[3]);
-        assert_eq!(prev_orders[3], sorted_orders[4]);
+        assert_eq!(prev_orders[4], sorted_orders[3]);
+        assert_eq!(prev_orders[1], sorted_orders[4]);
     }
 
     #[test]
@@ -1516,7 +1801,8 @@ pub mod test {
         test_client.set_balance(default_addr, u64::max_value());
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db.clone(), Default::default());
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db.clone(), Default::default());
 
         let fetch_account = fetch_account_creator(&test_client, BlockId::Latest);
         let no_timelock = TxTimelock {
@@ -1557,7 +1843,8 @@ pub mod test {
         inputs.push(create_mempool_input_with_pay(7u64, keypair, no_timelock));
         mem_pool.add(inputs, inserted_block_number, inserted_timestamp, &fetch_account);
 
-        let mut mem_pool_recovered = MemPool::with_limits(8192, usize::max_value(), 3, db, Default::default());
+        let mut mem_pool_recovered =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
         mem_pool_recovered.recover_from_db(&test_client);
 
         assert_eq!(mem_pool_recovered.first_seqs, mem_pool.first_seqs);
@@ -1676,7 +1963,7 @@ pub mod test {
         );
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, fees);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, fees);
         let keypair = Random.generate().unwrap();
         let address = public_to_address(keypair.public());
 
@@ -1731,7 +2018,7 @@ pub mod test {
         );
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, fees);
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, fees);
         let keypair = Random.generate().unwrap();
         let address = public_to_address(keypair.public());
 
@@ -1768,13 +2055,140 @@ pub mod test {
         assert_eq!(Vec::<SignedTransaction>::default(), mem_pool.future_transactions());
     }
 
+    #[test]
+    fn external_transactions_over_the_signer_limit_are_rejected() {
+        let test_client = TestBlockChainClient::new();
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 2, 3, db, Default::default());
+        let keypair = Random.generate().unwrap();
+        let address = public_to_address(keypair.public());
+
+        test_client.set_balance(address, 1_000_000_000_000);
+
+        let signer_limit_reached = Err(Error::History(HistoryError::SignerLimitReached {
+            limit: 2,
+        }));
+
+        let txs = vec![create_signed_pay(0, keypair), create_signed_pay(1, keypair), create_signed_pay(2, keypair)];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(
+            vec![
+                Ok(TransactionImportResult::Current),
+                Ok(TransactionImportResult::Current),
+                signer_limit_reached.clone()
+            ],
+            result
+        );
+
+        let txs = vec![create_signed_pay(4, keypair), create_signed_pay(5, keypair), create_signed_pay(6, keypair)];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(
+            vec![Ok(TransactionImportResult::Future), Ok(TransactionImportResult::Future), signer_limit_reached],
+            result
+        );
+
+        assert_eq!(
+            vec![create_signed_pay(0, keypair), create_signed_pay(1, keypair)],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+        assert_eq!(2, mem_pool.future_transactions().len());
+    }
+
+    #[test]
+    fn transactions_paying_less_per_byte_are_dropped_if_the_pool_is_full() {
+        let test_client = TestBlockChainClient::new();
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(2, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let keypairs: Vec<_> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        for keypair in &keypairs {
+            test_client.set_balance(public_to_address(keypair.public()), 1_000_000_000_000);
+        }
+
+        let txs =
+            vec![create_signed_pay_with_fee(0, 100, keypairs[0]), create_signed_pay_with_fee(0, 300, keypairs[1])];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(vec![Ok(TransactionImportResult::Current), Ok(TransactionImportResult::Current)], result);
+
+        let txs = vec![create_signed_pay_with_fee(0, 200, keypairs[2])];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        assert_eq!(
+            vec![create_signed_pay_with_fee(0, 300, keypairs[1]), create_signed_pay_with_fee(0, 200, keypairs[2]),],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+
+        let txs = vec![create_signed_pay_with_fee(0, 150, keypairs[0])];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(vec![Err(Error::History(HistoryError::LimitReached))], result);
+    }
+
+    #[test]
+    fn following_transactions_are_moved_to_future_if_the_pool_drops_their_parent() {
+        let test_client = TestBlockChainClient::new();
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool = MemPool::with_limits(2, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let keypairs: Vec<_> = (0..2).map(|_| Random.generate().unwrap()).collect();
+        for keypair in &keypairs {
+            test_client.set_balance(public_to_address(keypair.public()), 1_000_000_000_000);
+        }
+
+        let txs =
+            vec![create_signed_pay_with_fee(0, 100, keypairs[0]), create_signed_pay_with_fee(0, 300, keypairs[1])];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::External);
+        assert_eq!(vec![Ok(TransactionImportResult::Current), Ok(TransactionImportResult::Current)], result);
+
+        // The transactions of the retracted blocks are never dropped, so the external parent of it is dropped.
+        let txs = vec![create_signed_pay_with_fee(1, 100, keypairs[0])];
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, txs, TxOrigin::RetractedBlock);
+        assert_eq!(vec![Ok(TransactionImportResult::Future)], result);
+        assert_eq!(
+            vec![create_signed_pay_with_fee(0, 300, keypairs[1])],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+        assert_eq!(vec![create_signed_pay_with_fee(1, 100, keypairs[0])], mem_pool.future_transactions());
+    }
+
+    #[test]
+    fn min_fee_to_replace_satisfies_the_fee_bump_shift() {
+        let test_client = TestBlockChainClient::new();
+
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let keypair = Random.generate().unwrap();
+        test_client.set_balance(public_to_address(keypair.public()), 1_000_000_000_000);
+
+        let old = create_signed_pay_with_fee(0, 100, keypair);
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, vec![old.clone()], TxOrigin::External);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        assert_eq!(Some(old.clone()), mem_pool.transaction(&old.hash()));
+
+        let min_fee = mem_pool.min_fee_to_replace(old.fee);
+        assert_eq!(112, min_fee);
+
+        let too_cheap = create_signed_pay_with_fee(0, min_fee - 1, keypair);
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, vec![too_cheap], TxOrigin::External);
+        assert_eq!(vec![Err(Error::History(HistoryError::TooCheapToReplace))], result);
+
+        let new = create_signed_pay_with_fee(0, min_fee, keypair);
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, vec![new.clone()], TxOrigin::External);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        assert_eq!(None, mem_pool.transaction(&old.hash()));
+        let new_hash = new.hash();
+        assert_eq!(Some(new), mem_pool.transaction(&new_hash));
+    }
+
     #[test]
     fn transactions_are_moved_to_future_queue_if_the_preceding_one_removed() {
         //setup test_client
         let test_client = TestBlockChainClient::new();
 
         let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db, Default::default());
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
 
         let fetch_account = fetch_account_creator(&test_client, BlockId::Latest);
         let keypair = Random.generate().unwrap();
@@ -1827,4 +2241,203 @@ pub mod test {
 
         assert_eq!(vec![create_signed_pay(2, keypair),], mem_pool.future_transactions());
     }
+
+    #[test]
+    fn scripts_of_asset_transfer_are_verified() {
+        let input = |lock_script: Vec<u8>, unlock_script: Vec<u8>| AssetTransferInput {
+            prev_out: AssetOutPoint {
+                tracker: Default::default(),
+                index: 0,
+                asset_type: H160::default(),
+                shard_id: 0,
+                quantity: 1,
+            },
+            timelock: None,
+            lock_script,
+            unlock_script,
+        };
+        let transfer = |inputs: Vec<AssetTransferInput>| Action::TransferAsset {
+            network_id: "tc".into(),
+            burns: vec![],
+            inputs,
+            outputs: vec![],
+            metadata: "".into(),
+            approvals: vec![],
+            expiration: None,
+        };
+
+        // PUSH 1, SUCCESS
+        let valid = input(vec![0x30, 0x01, 0x02], vec![]);
+        assert_eq!(verify_scripts(&transfer(vec![valid.clone()]), VMConfig::default()), Ok(()));
+
+        // The lock script is committed, so the jump beyond the end is not checked.
+        // JMP 3
+        let committed = input(vec![0x20, 0x03], vec![]);
+        assert_eq!(verify_scripts(&transfer(vec![committed]), VMConfig::default()), Ok(()));
+
+        // PUSH 1, DUP
+        let invalid = input(vec![0x02], vec![0x30, 0x01, 0x33]);
+        assert_eq!(
+            verify_scripts(&transfer(vec![valid, invalid]), VMConfig::default()),
+            Err(SyntaxError::InvalidScript(
+                "input 1: 0002 of the unlock script: only PUSH and PUSHB are allowed".to_string()
+            ))
+        );
+
+        // PUSH 1, PUSH 2, ADD, SUCCESS
+        let arithmetic = input(vec![0x30, 0x01, 0x30, 0x02, 0x40, 0x02], vec![]);
+        let genesis_era = VMConfig {
+            era: 0,
+            ..VMConfig::default()
+        };
+        assert_eq!(
+            verify_scripts(&transfer(vec![arithmetic.clone()]), genesis_era),
+            Err(SyntaxError::InvalidScript("input 0: 0004: the instruction is available from era 1".to_string()))
+        );
+        // The worst case of a path doesn't reject the transaction.
+        let low_cost = VMConfig {
+            max_cost: 3,
+            era: 1,
+            ..VMConfig::default()
+        };
+        assert_eq!(verify_scripts(&transfer(vec![arithmetic]), low_cost), Ok(()));
+    }
+
+    fn create_signed_transfer(
+        seq: u64,
+        fee: u64,
+        parent: Option<Tracker>,
+        metadata: &str,
+        keypair: KeyPair,
+    ) -> SignedTransaction {
+        let inputs = parent
+            .map(|tracker| AssetTransferInput {
+                prev_out: AssetOutPoint {
+                    tracker,
+                    index: 0,
+                    asset_type: H160::default(),
+                    shard_id: 0,
+                    quantity: 1,
+                },
+                timelock: None,
+                lock_script: vec![0x30, 0x01, 0x02],
+                unlock_script: vec![],
+            })
+            .into_iter()
+            .collect();
+        let tx = Transaction {
+            seq,
+            fee,
+            network_id: "tc".into(),
+            action: Action::TransferAsset {
+                network_id: "tc".into(),
+                burns: vec![],
+                inputs,
+                outputs: vec![],
+                metadata: metadata.to_string(),
+                approvals: vec![],
+                expiration: None,
+            },
+        };
+        SignedTransaction::new_with_sign(tx, keypair.private())
+    }
+
+    fn create_chained_transfers(
+        test_client: &TestBlockChainClient,
+        mem_pool: &mut MemPool,
+    ) -> (KeyPair, SignedTransaction, SignedTransaction) {
+        let parent_keypair = Random.generate().unwrap();
+        let child_keypair = Random.generate().unwrap();
+        test_client.set_balance(public_to_address(parent_keypair.public()), 1_000_000_000_000);
+        test_client.set_balance(public_to_address(child_keypair.public()), 1_000_000_000_000);
+
+        let parent = create_signed_transfer(0, 100, None, "parent", parent_keypair);
+        let child = create_signed_transfer(0, 10_000, created_tracker(&parent), "child", child_keypair);
+        let result = abbreviated_mempool_add(test_client, mem_pool, vec![child.clone()], TxOrigin::Local);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        let result = abbreviated_mempool_add(test_client, mem_pool, vec![parent.clone()], TxOrigin::Local);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        (parent_keypair, parent, child)
+    }
+
+    #[test]
+    fn child_transfers_come_after_their_parents() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let (_, parent, child) = create_chained_transfers(&test_client, &mut mem_pool);
+
+        assert_eq!(
+            vec![parent.clone(), child.clone()],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+        // The child is postponed if its parent is not selected.
+        assert_eq!(Vec::<SignedTransaction>::new(), mem_pool.sort_by_dependencies(vec![child]));
+        assert_eq!(vec![parent.clone()], mem_pool.sort_by_dependencies(vec![parent.clone()]));
+        assert_eq!(vec![parent.clone(), child.clone()], mem_pool.sort_by_dependencies(vec![child, parent]));
+    }
+
+    #[test]
+    fn tracker_indexes_follow_the_pool() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let (_, parent, child) = create_chained_transfers(&test_client, &mut mem_pool);
+
+        let tracker = created_tracker(&parent).unwrap();
+        assert_eq!(Some(&parent.hash()), mem_pool.by_created_tracker.get(&tracker));
+        assert_eq!(vec![child.hash()], mem_pool.dependents(vec![tracker].into_iter().collect()));
+
+        let fetch_seq = |_: &Public| -> u64 { 0 };
+        mem_pool.remove(&[parent.hash()], &fetch_seq, 1, 100);
+
+        assert!(mem_pool.by_created_tracker.is_empty());
+        assert!(mem_pool.by_spent_tracker.is_empty());
+    }
+
+    #[test]
+    fn child_transfers_are_removed_with_their_parents() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let (_, parent, child) = create_chained_transfers(&test_client, &mut mem_pool);
+
+        let fetch_seq = |_: &Public| -> u64 { 0 };
+        mem_pool.remove(&[parent.hash()], &fetch_seq, 1, 100);
+
+        assert_eq!(None, mem_pool.transaction(&child.hash()));
+        assert_eq!(
+            Vec::<SignedTransaction>::new(),
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+    }
+
+    #[test]
+    fn child_transfers_are_dropped_if_their_parents_are_replaced() {
+        let test_client = TestBlockChainClient::new();
+        let db = Arc::new(kvdb_memorydb::create(crate::db::NUM_COLUMNS.unwrap_or(0)));
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db, Default::default());
+        let (parent_keypair, parent, child) = create_chained_transfers(&test_client, &mut mem_pool);
+
+        // The replacement creating the same outputs keeps the child.
+        let replacement = create_signed_transfer(0, 1_000, None, "parent", parent_keypair);
+        assert_eq!(created_tracker(&parent), created_tracker(&replacement));
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, vec![replacement], TxOrigin::Local);
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        assert_eq!(Some(child.clone()), mem_pool.transaction(&child.hash()));
+
+        let replacement = create_signed_transfer(0, 10_000, None, "replacement", parent_keypair);
+        let result = abbreviated_mempool_add(&test_client, &mut mem_pool, vec![replacement.clone()], TxOrigin::Local);
+
+        assert_eq!(vec![Ok(TransactionImportResult::Current)], result);
+        assert_eq!(None, mem_pool.transaction(&child.hash()));
+        assert_eq!(
+            vec![replacement],
+            mem_pool.top_transactions(std::usize::MAX, None, 0..std::u64::MAX).transactions
+        );
+    }
 }
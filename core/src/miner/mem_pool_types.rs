@@ -14,10 +14,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::mem_pool::created_tracker;
 use crate::transaction::SignedTransaction;
 use ckey::Public;
 use ctypes::transaction::Action;
-use ctypes::{BlockNumber, TxHash};
+use ctypes::{BlockNumber, Tracker, TxHash};
 use rlp;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use std::cmp::Ordering;
@@ -113,8 +114,6 @@ pub struct TransactionOrder {
     pub seq_height: u64,
     /// Fee of the transaction.
     pub fee: u64,
-    /// Fee per bytes(rlp serialized) of the transaction
-    pub fee_per_byte: u64,
     /// Memory usage of this transaction.
     /// Currently using the RLP byte length of the transaction as the mem usage.
     pub mem_usage: usize,
@@ -137,7 +136,6 @@ impl TransactionOrder {
             seq_height: item.seq() - seq_seq,
             fee,
             mem_usage: rlp_bytes_len,
-            fee_per_byte: fee / rlp_bytes_len as u64,
             hash: item.hash(),
             insertion_id: item.insertion_id,
             origin: item.origin,
@@ -145,6 +143,14 @@ impl TransactionOrder {
         }
     }
 
+    /// Compares the fee per byte(rlp serialized) without the rounding error.
+    /// The transaction paying more per byte is `Less`, so it comes first.
+    fn cmp_fee_per_byte(&self, b: &TransactionOrder) -> Ordering {
+        let lhs = u128::from(self.fee) * b.mem_usage as u128;
+        let rhs = u128::from(b.fee) * self.mem_usage as u128;
+        rhs.cmp(&lhs)
+    }
+
     pub fn update_height(mut self, seq: u64, base_seq: u64) -> Self {
         self.seq_height = seq - base_seq;
         self
@@ -168,6 +174,10 @@ impl PartialOrd for TransactionOrder {
     }
 }
 
+/// The order of the transactions in a queue, from the one mined first to the one evicted first.
+///
+/// The transactions of the same origin are ordered by their seq heights before their fees, so the fee per byte
+/// only orders the transactions of the same seq height.
 impl Ord for TransactionOrder {
     fn cmp(&self, b: &TransactionOrder) -> Ordering {
         // Local transactions should always have priority
@@ -180,8 +190,10 @@ impl Ord for TransactionOrder {
             return self.seq_height.cmp(&b.seq_height)
         }
 
-        if self.fee_per_byte != b.fee_per_byte {
-            return self.fee_per_byte.cmp(&b.fee_per_byte)
+        // Prefer the transaction paying more per byte
+        let fee_per_byte = self.cmp_fee_per_byte(b);
+        if fee_per_byte != Ordering::Equal {
+            return fee_per_byte
         }
 
         // Then compare fee
@@ -200,7 +212,7 @@ impl Ord for TransactionOrder {
 }
 
 /// Transaction item in the mem pool.
-#[derive(Clone, Eq, PartialEq, Debug, RlpEncodable, RlpDecodable)]
+#[derive(Clone, Eq, PartialEq, Debug)]
 pub struct MemPoolItem {
     /// Transaction.
     pub tx: SignedTransaction,
@@ -214,6 +226,8 @@ pub struct MemPoolItem {
     pub insertion_id: u64,
     /// A timelock.
     pub timelock: TxTimelock,
+    /// The tracker of the shard outputs created by the transaction. It is not backed up.
+    pub created_tracker: Option<Tracker>,
 }
 
 impl MemPoolItem {
@@ -225,6 +239,7 @@ impl MemPoolItem {
         insertion_id: u64,
         timelock: TxTimelock,
     ) -> Self {
+        let created_tracker = created_tracker(&tx);
         MemPoolItem {
             tx,
             origin,
@@ -232,6 +247,7 @@ impl MemPoolItem {
             inserted_timestamp,
             insertion_id,
             timelock,
+            created_tracker,
         }
     }
 
@@ -272,6 +288,38 @@ impl MemPoolItem {
     }
 }
 
+impl Encodable for MemPoolItem {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(6)
+            .append(&self.tx)
+            .append(&self.origin)
+            .append(&self.inserted_block_number)
+            .append(&self.inserted_timestamp)
+            .append(&self.insertion_id)
+            .append(&self.timelock);
+    }
+}
+
+impl Decodable for MemPoolItem {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 6 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                expected: 6,
+                got: item_count,
+            })
+        }
+        Ok(MemPoolItem::new(
+            rlp.val_at(0)?,
+            rlp.val_at(1)?,
+            rlp.val_at(2)?,
+            rlp.val_at(3)?,
+            rlp.val_at(4)?,
+            rlp.val_at(5)?,
+        ))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum QueueTag {
     Current,
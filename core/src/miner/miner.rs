@@ -14,10 +14,15 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::mem_pool::{Error as MemPoolError, MemPool};
+use super::block_template::{
+    BlockTemplatePolicy, ExcludeActions, PrioritizeSigners, PrioritizeTrackers, ReserveStakeSpace, TemplateContext,
+    TemplateDecision,
+};
+use super::mem_pool::{created_tracker, spent_trackers, Error as MemPoolError, MemPool};
 pub use super::mem_pool_types::MemPoolMinFees;
 use super::mem_pool_types::{MemPoolInput, TxOrigin, TxTimelock};
 use super::sealing_queue::SealingQueue;
+use super::stratum::share_score;
 use super::work_notify::{NotifyWork, WorkPoster};
 use super::{fetch_account_creator, MinerService, MinerStatus, TransactionImportResult};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
@@ -26,7 +31,7 @@ use crate::client::{
     AccountData, BlockChainTrait, BlockProducer, Client, EngineInfo, ImportBlock, MiningBlockChainClient, TermInfo,
 };
 use crate::codechain_machine::CodeChainMachine;
-use crate::consensus::{CodeChainEngine, EngineType};
+use crate::consensus::{CodeChainEngine, EngineType, RemoteSigner};
 use crate::error::Error;
 use crate::scheme::Scheme;
 use crate::transaction::{PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
@@ -35,13 +40,14 @@ use ckey::{public_to_address, Address, Password, PlatformAddress, Public};
 use cstate::{FindActionHandler, TopLevelState};
 use ctypes::errors::{HistoryError, RuntimeError};
 use ctypes::transaction::{Action, IncompleteTransaction, Timelock};
-use ctypes::{BlockHash, BlockNumber, Header, TxHash};
-use cvm::ChainTimeInfo;
+use ctypes::{BlockHash, BlockNumber, Header, Tracker, TxHash};
+use cvm::{ChainTimeInfo, VMConfig};
 use kvdb::KeyValueDB;
 use parking_lot::{Mutex, RwLock};
 use primitives::{Bytes, H256, U256};
+use rlp::Encodable;
 use std::borrow::Borrow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::iter::once;
 use std::ops::Range;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -69,12 +75,22 @@ pub struct MinerOptions {
     pub mem_pool_size: usize,
     /// Maximum memory usage of transactions in the queue (current / future).
     pub mem_pool_memory_limit: Option<usize>,
+    /// Maximum number of external transactions of a signer in each queue (current / future).
+    pub mem_pool_signer_limit: Option<usize>,
     /// A value which is used to check whether a new transaciton can replace a transaction in the memory pool with the same signer and seq.
     /// If the fee of the new transaction is `new_fee` and the fee of the transaction in the memory pool is `old_fee`,
     /// then `new_fee > old_fee + old_fee >> mem_pool_fee_bump_shift` should be satisfied to replace.
     /// Local transactions ignore this option.
     pub mem_pool_fee_bump_shift: usize,
     pub allow_create_shard: bool,
+    /// Transactions signed by these accounts are put into blocks before the others.
+    pub priority_signers: Vec<Address>,
+    /// Transactions which create or spend the outputs of these trackers are put into blocks before the others.
+    pub priority_trackers: Vec<Tracker>,
+    /// Action types that are left out of blocks.
+    pub excluded_actions: Vec<String>,
+    /// Size of the block body that only stake transactions can use.
+    pub reserved_stake_body_size: usize,
     /// How many historical work packages can we store before running out?
     pub work_queue_size: usize,
     /// Minimum fees configured by the machine.
@@ -93,8 +109,13 @@ impl Default for MinerOptions {
             no_reseal_timer: false,
             mem_pool_size: 8192,
             mem_pool_memory_limit: Some(2 * 1024 * 1024),
+            mem_pool_signer_limit: Some(256),
             mem_pool_fee_bump_shift: 3,
             allow_create_shard: false,
+            priority_signers: vec![],
+            priority_trackers: vec![],
+            excluded_actions: vec![],
+            reserved_stake_body_size: 0,
             work_queue_size: 20,
             mem_pool_min_fees: Default::default(),
         }
@@ -110,6 +131,8 @@ pub struct AuthoringParams {
 struct SealingWork {
     queue: SealingQueue,
     enabled: bool,
+    /// The seals already accepted as shares, by the hash of the work they were submitted for.
+    submitted_shares: HashMap<BlockHash, HashSet<Vec<Bytes>>>,
 }
 
 pub struct Miner {
@@ -126,6 +149,7 @@ pub struct Miner {
 
     accounts: Arc<AccountProvider>,
     notifiers: Notifiers,
+    template_policies: RwLock<Vec<Box<dyn BlockTemplatePolicy>>>,
     malicious_users: Users,
     immune_users: Users,
 }
@@ -263,6 +287,11 @@ impl Miner {
         self.notifiers.push(notifier);
     }
 
+    /// Push a policy that is consulted after the configured ones while filling blocks
+    pub fn add_block_template_policy(&self, policy: Box<dyn BlockTemplatePolicy>) {
+        self.template_policies.write().push(policy);
+    }
+
     pub fn new(
         options: MinerOptions,
         scheme: &Scheme,
@@ -283,9 +312,11 @@ impl Miner {
         db: Arc<dyn KeyValueDB>,
     ) -> Self {
         let mem_limit = options.mem_pool_memory_limit.unwrap_or_else(usize::max_value);
+        let signer_limit = options.mem_pool_signer_limit.unwrap_or_else(usize::max_value);
         let mem_pool = Arc::new(RwLock::new(MemPool::with_limits(
             options.mem_pool_size,
             mem_limit,
+            signer_limit,
             options.mem_pool_fee_bump_shift,
             db,
             options.mem_pool_min_fees,
@@ -297,6 +328,20 @@ impl Miner {
             vec![Box::new(WorkPoster::new(&options.new_work_notify))]
         };
 
+        let mut template_policies: Vec<Box<dyn BlockTemplatePolicy>> = Vec::new();
+        if !options.priority_signers.is_empty() {
+            template_policies.push(Box::new(PrioritizeSigners::new(options.priority_signers.clone())));
+        }
+        if !options.priority_trackers.is_empty() {
+            template_policies.push(Box::new(PrioritizeTrackers::new(options.priority_trackers.clone())));
+        }
+        if !options.excluded_actions.is_empty() {
+            template_policies.push(Box::new(ExcludeActions::new(options.excluded_actions.clone())));
+        }
+        if options.reserved_stake_body_size != 0 {
+            template_policies.push(Box::new(ReserveStakeSpace::new(options.reserved_stake_body_size)));
+        }
+
         Self {
             mem_pool,
             next_allowed_reseal: NextAllowedReseal::new(Instant::now()),
@@ -306,12 +351,14 @@ impl Miner {
             sealing_work: Mutex::new(SealingWork {
                 queue: SealingQueue::new(options.work_queue_size),
                 enabled: options.force_sealing || scheme.engine.seals_internally().is_some(),
+                submitted_shares: HashMap::new(),
             }),
             engine: scheme.engine.clone(),
             options,
             sealing_enabled: AtomicBool::new(true),
             accounts,
             notifiers: Notifiers::new(notifiers),
+            template_policies: RwLock::new(template_policies),
             malicious_users: Users::new(),
             immune_users: Users::new(),
         }
@@ -423,7 +470,8 @@ impl Miner {
                     .map_err(From::from)
                     .and_then(|_| {
                         let common_params = client.common_params(best_header.hash().into()).unwrap();
-                        self.engine.verify_transaction_with_params(&tx, &common_params)
+                        let base_fee_multiplier = client.base_fee_multiplier(best_header.hash().into()).unwrap();
+                        self.engine.verify_transaction_with_params(&tx, &common_params, base_fee_multiplier)
                     })
                     .and_then(|_| CodeChainMachine::verify_transaction_seal(tx, &fake_header))
                     .map_err(|e| {
@@ -459,6 +507,9 @@ impl Miner {
 
         let block_id = BlockId::Hash(best_header.hash());
         let fetch_account = fetch_account_creator(client, block_id);
+        if let Some(common_params) = client.common_params(block_id) {
+            mem_pool.set_vm_config(VMConfig::from(&common_params));
+        }
 
         let insertion_results = mem_pool.add(to_insert, current_block_number, current_timestamp, &fetch_account);
 
@@ -586,7 +637,8 @@ impl Miner {
         parent_block_id: BlockId,
         chain: &C,
     ) -> Result<Option<(ClosedBlock, Option<H256>)>, Error> {
-        let (transactions, mut open_block, original_work_hash, block_number) = {
+        let template_policies = self.template_policies.read();
+        let (mut transactions, mut open_block, original_work_hash, block_number, max_body_size) = {
             let sealing_work = self.sealing_work.lock();
 
             let last_work_hash = sealing_work.queue.peek_last_ref().map(|pb| *pb.block().header().hash());
@@ -602,19 +654,38 @@ impl Miner {
             let max_body_size = chain.common_params(parent_hash.into()).unwrap().max_body_size();
             const DEFAULT_RANGE: Range<u64> = 0..::std::u64::MAX;
 
+            // The policies can move a transaction from the end of the mem pool into the block,
+            // so the candidates are cut by the body size while they are pushed.
+            let size_limit = if template_policies.is_empty() {
+                max_body_size
+            } else {
+                usize::max_value()
+            };
+
             // NOTE: This lock should be acquired after `prepare_open_block` to prevent deadlock
             let mem_pool = self.mem_pool.read();
             let transactions = mem_pool
-                .top_transactions(max_body_size, Some(open_block.header().timestamp()), DEFAULT_RANGE)
+                .top_transactions(size_limit, Some(open_block.header().timestamp()), DEFAULT_RANGE)
                 .transactions;
 
-            (transactions, open_block, last_work_hash, block_number)
+            (transactions, open_block, last_work_hash, block_number, max_body_size)
+        };
+        for policy in template_policies.iter() {
+            policy.prioritize(&mut transactions);
+        }
+        // Policies may move a child transfer in front of its parent.
+        let (transactions, pending_outputs) = {
+            let mem_pool = self.mem_pool.read();
+            (mem_pool.sort_by_dependencies(transactions), mem_pool.pending_outputs())
         };
 
         let parent_header = {
             let parent_hash = open_block.header().parent_hash();
             chain.block_header(&BlockId::Hash(*parent_hash)).expect("Parent header MUST exist")
         };
+        let parent_common_params = chain.common_params(parent_header.hash().into()).unwrap();
+        let base_fee_multiplier = chain.base_fee_multiplier(parent_header.hash().into()).unwrap();
+        let vm_config = VMConfig::from(&parent_common_params);
         if self.engine_type().is_seal_first() {
             match self.engine.seals_internally() {
                 Some(false) => panic!("If a signer is not prepared, prepare_block should not be called"),
@@ -634,6 +705,9 @@ impl Miner {
         let mut tx_count: usize = 0;
         let tx_total = transactions.len();
         let mut invalid_tx_users = HashSet::new();
+        let mut body_size = 0;
+        let mut skipped_by_policy: HashMap<&'static str, usize> = HashMap::new();
+        let mut pushed_outputs = HashSet::new();
 
         for tx in transactions {
             let signer_public = tx.signer_public();
@@ -646,18 +720,58 @@ impl Miner {
                 // The previous transaction has failed
                 continue
             }
+            let parent_missing = spent_trackers(&tx.action)
+                .iter()
+                .any(|tracker| pending_outputs.contains_key(tracker) && !pushed_outputs.contains(tracker));
+            if parent_missing {
+                // The transaction stays in the mem pool until its parent is included.
+                invalid_tx_users.insert(signer_public);
+                continue
+            }
             if !self.is_allowed_transaction(&tx.action) {
                 invalid_tx_users.insert(signer_public);
                 invalid_transactions.push(tx.hash());
                 continue
             }
+            if tx.fee < CodeChainMachine::min_cost(&parent_common_params, base_fee_multiplier, &tx.action) {
+                // The transaction stays in the mem pool until the base fee goes down.
+                invalid_tx_users.insert(signer_public);
+                continue
+            }
+            let tx_size = tx.rlp_bytes().len();
+            if body_size + tx_size >= max_body_size {
+                // The transaction stays in the mem pool until there is enough space.
+                invalid_tx_users.insert(signer_public);
+                continue
+            }
+            let context = TemplateContext {
+                block_number,
+                body_size,
+                max_body_size,
+            };
+            let skipped_by =
+                template_policies.iter().find(|policy| policy.decide(&tx, tx_size, &context) == TemplateDecision::Skip);
+            if let Some(policy) = skipped_by {
+                ctrace!(MINER, "{} skipped transaction {:?} in block #{}", policy.name(), tx.hash(), block_number);
+                *skipped_by_policy.entry(policy.name()).or_default() += 1;
+                // The following transactions of the signer cannot be included without this one.
+                invalid_tx_users.insert(signer_public);
+                continue
+            }
 
             let hash = tx.hash();
+            let created = created_tracker(&tx);
             let start = Instant::now();
             // Check whether transaction type is allowed for sender
             let result =
                 self.engine.machine().verify_transaction(&tx, open_block.header(), chain, true).and_then(|_| {
-                    open_block.push_transaction(tx, chain, parent_header.number(), parent_header.timestamp())
+                    open_block.push_transaction(
+                        tx,
+                        chain,
+                        vm_config,
+                        parent_header.number(),
+                        parent_header.timestamp(),
+                    )
                 });
 
             match result {
@@ -693,10 +807,15 @@ impl Miner {
                     let took = start.elapsed();
                     ctrace!(MINER, "Adding transaction {:?} took {:?}", hash, took);
                     tx_count += 1;
+                    body_size += tx_size;
+                    pushed_outputs.extend(created);
                 } // imported ok
             }
         }
         cdebug!(MINER, "Pushed {}/{} transactions", tx_count, tx_total);
+        for (name, skipped) in &skipped_by_policy {
+            cinfo!(MINER, "{} skipped {} transactions in block #{}", name, skipped, block_number);
+        }
 
         let (parent_header, parent_hash) = {
             let parent_hash = *open_block.header().parent_hash();
@@ -805,6 +924,68 @@ impl Miner {
         }
         true
     }
+
+    /// Returns the score of the work being sealed with the given PoW hash.
+    pub fn work_score(&self, pow_hash: &H256) -> Option<U256> {
+        self.sealing_work.lock().queue.find_used(pow_hash).map(|b| *b.header().score())
+    }
+
+    /// Submit a share whose seal only needs to meet the score divided by `share_divisor`.
+    /// Returns whether the share also sealed the block.
+    pub fn submit_share<C: ImportBlock>(
+        &self,
+        chain: &C,
+        block_hash: BlockHash,
+        seal: Vec<Bytes>,
+        share_divisor: u64,
+    ) -> Result<bool, Error> {
+        let mut header = {
+            let sealing_work = self.sealing_work.lock();
+            let queue = &sealing_work.queue;
+            match queue.find_used(&block_hash) {
+                Some(b) => {
+                    let current_parent = queue.peek_last_ref().map(|last| *last.header().parent_hash());
+                    if current_parent != Some(*b.header().parent_hash()) {
+                        ctrace!(MINER, "Submitted share rejected: The parent of {} is out of date.", block_hash);
+                        return Err(Error::PowHashStale)
+                    }
+                    b.header().clone()
+                }
+                None if queue.is_retired(&block_hash) => {
+                    ctrace!(MINER, "Submitted share rejected: {} is no longer being sealed.", block_hash);
+                    return Err(Error::PowHashStale)
+                }
+                None => {
+                    cwarn!(MINER, "Submitted share rejected: Block unknown.");
+                    return Err(Error::PowHashInvalid)
+                }
+            }
+        };
+        header.set_seal(seal.clone());
+
+        let share_score = share_score(header.score(), share_divisor);
+        if let Err(e) = self.engine.verify_share_seal(&header, &share_score) {
+            cdebug!(MINER, "Submitted share rejected: {}", e);
+            return Err(Error::PowInvalid)
+        }
+        {
+            let mut sealing_work = self.sealing_work.lock();
+            let SealingWork {
+                queue,
+                submitted_shares,
+                ..
+            } = &mut *sealing_work;
+            submitted_shares.retain(|hash, _| queue.find_used(hash).is_some());
+            if !submitted_shares.entry(block_hash).or_default().insert(seal.clone()) {
+                ctrace!(MINER, "Submitted share rejected: The same seal was already submitted for {}.", block_hash);
+                return Err(Error::PowHashStale)
+            }
+        }
+        if self.engine.verify_block_seal(&header).is_err() {
+            return Ok(false)
+        }
+        self.submit_seal(chain, block_hash, seal).map(|()| true)
+    }
 }
 
 const SEALING_TIMEOUT_IN_BLOCKS: u64 = 5;
@@ -843,6 +1024,21 @@ impl MinerService for Miner {
         Ok(())
     }
 
+    fn set_remote_signer(&self, signer: RemoteSigner) {
+        let address = signer.address();
+        self.params.apply(|params| params.author = address);
+
+        if self.engine_type().need_signer_key() && self.engine.seals_internally().is_some() {
+            ctrace!(MINER, "Set author to {:?} (remote)", address);
+            // Limit the scope of the locks.
+            {
+                let mut sealing_work = self.sealing_work.lock();
+                sealing_work.enabled = true;
+            }
+            self.engine.set_remote_signer(signer);
+        }
+    }
+
     fn get_author_address(&self) -> Address {
         self.params.get().author
     }
@@ -1184,6 +1380,76 @@ impl MinerService for Miner {
         Ok((hash, seq))
     }
 
+    fn replace_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
+        &self,
+        client: &C,
+        account_provider: &AccountProvider,
+        hash: &TxHash,
+        tx: IncompleteTransaction,
+        passphrase: Option<Password>,
+    ) -> Result<Option<SignedTransaction>, Error> {
+        let (pending, min_fee_to_replace) = {
+            let mem_pool = self.mem_pool.read();
+            match mem_pool.transaction(hash) {
+                Some(pending) => {
+                    let min_fee_to_replace = mem_pool.min_fee_to_replace(pending.fee);
+                    (pending, min_fee_to_replace)
+                }
+                None => return Ok(None),
+            }
+        };
+        let common_params = client.common_params(BlockId::Latest).expect("Common params of the best block must exist");
+        let base_fee_multiplier =
+            client.base_fee_multiplier(BlockId::Latest).expect("Metadata of the best block must exist");
+        let fee = *[
+            tx.fee,
+            min_fee_to_replace,
+            CodeChainMachine::min_cost(&common_params, base_fee_multiplier, &tx.action),
+            self.options.mem_pool_min_fees.min_cost(&tx.action),
+        ]
+        .iter()
+        .max()
+        .expect("The array is not empty");
+        let tx = IncompleteTransaction {
+            fee,
+            ..tx
+        }
+        .complete(pending.seq);
+        let tx_hash = tx.hash();
+        let signer_address = public_to_address(&pending.signer_public());
+        let sig = account_provider.get_account(&signer_address, passphrase.as_ref())?.sign(&tx_hash)?;
+        let unverified = UnverifiedTransaction::new(tx, sig);
+        let signed = SignedTransaction::try_new(unverified)?;
+        self.import_own_transaction(client, signed.clone())?;
+        cinfo!(OWN_TX, "{:?} is replaced with {:?}", hash, signed.hash());
+
+        Ok(Some(signed))
+    }
+
+    fn cancel_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
+        &self,
+        client: &C,
+        account_provider: &AccountProvider,
+        hash: &TxHash,
+        passphrase: Option<Password>,
+    ) -> Result<Option<SignedTransaction>, Error> {
+        let pending = match self.mem_pool.read().transaction(hash) {
+            Some(pending) => pending,
+            None => return Ok(None),
+        };
+        let signer_address = public_to_address(&pending.signer_public());
+        let receiver = client.latest_regular_key_owner(&signer_address).unwrap_or(signer_address);
+        let tx = IncompleteTransaction {
+            fee: 0,
+            network_id: pending.network_id,
+            action: Action::Pay {
+                receiver,
+                quantity: 0,
+            },
+        };
+        self.replace_transaction(client, account_provider, hash, tx, passphrase)
+    }
+
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions {
         // FIXME: Update the body size when the common params are updated
         let max_body_size = self.engine.machine().genesis_common_params().max_body_size();
@@ -1207,6 +1473,10 @@ impl MinerService for Miner {
         self.mem_pool.read().future_transactions()
     }
 
+    fn next_seq(&self, public: &Public) -> Option<u64> {
+        self.mem_pool.read().next_seq(public)
+    }
+
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C) {
         cdebug!(MINER, "Start sealing");
         self.sealing_enabled.store(true, Ordering::Relaxed);
@@ -1279,7 +1549,8 @@ pub mod test {
         let scheme = Scheme::new_test();
         let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
 
-        let mut mem_pool = MemPool::with_limits(8192, usize::max_value(), 3, db.clone(), Default::default());
+        let mut mem_pool =
+            MemPool::with_limits(8192, usize::max_value(), usize::max_value(), 3, db.clone(), Default::default());
         let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
 
         let private: Private = H256::random().into();
@@ -1313,6 +1584,23 @@ pub mod test {
         miner.add_transactions_to_pool(client.as_ref(), transactions, TxOrigin::Local, &mut mem_pool);
     }
 
+    #[test]
+    fn the_same_share_is_rejected_when_submitted_twice() {
+        let db = Arc::new(kvdb_memorydb::create(NUM_COLUMNS.unwrap()));
+        let scheme = Scheme::new_test_blake_pow();
+        let miner = Arc::new(Miner::with_scheme_for_test(&scheme, db.clone()));
+        let client = generate_test_client(db, Arc::clone(&miner), &scheme).unwrap();
+
+        let pow_hash = miner.map_sealing_work(client.as_ref(), |block| block.block().header().hash()).unwrap();
+        let seal = vec![::rlp::encode(&0u64)];
+
+        assert!(miner.submit_share(client.as_ref(), pow_hash, seal.clone(), u64::max_value()).is_ok());
+        match miner.submit_share(client.as_ref(), pow_hash, seal, u64::max_value()) {
+            Err(Error::PowHashStale) => {}
+            result => panic!("The duplicated share must be rejected as stale: {:?}", result),
+        }
+    }
+
     fn generate_test_client(db: Arc<dyn KeyValueDB>, miner: Arc<Miner>, scheme: &Scheme) -> Result<Arc<Client>, Error> {
         let timer_loop = TimerLoop::new(2);
 
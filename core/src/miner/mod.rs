@@ -15,6 +15,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod backup;
+mod block_template;
+mod cpu_miner;
 mod mem_pool;
 mod mem_pool_types;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
@@ -23,16 +25,20 @@ mod sealing_queue;
 mod stratum;
 mod work_notify;
 
+pub use self::block_template::{BlockTemplatePolicy, TemplateContext, TemplateDecision, ACTION_TYPES};
+pub use self::cpu_miner::{CpuMiner, CpuMiningStatus};
 use self::mem_pool_types::AccountDetails;
 pub use self::mem_pool_types::MemPoolMinFees;
 pub use self::miner::{AuthoringParams, Miner, MinerOptions};
-pub use self::stratum::{Config as StratumConfig, Error as StratumError, Stratum};
+pub use self::stratum::{
+    Config as StratumConfig, Error as StratumError, Stratum, WorkerStats as StratumWorkerStats,
+};
 use crate::account_provider::{AccountProvider, Error as AccountProviderError};
 use crate::block::ClosedBlock;
 use crate::client::{
     AccountData, BlockChainTrait, BlockProducer, EngineInfo, ImportBlock, MiningBlockChainClient, TermInfo,
 };
-use crate::consensus::EngineType;
+use crate::consensus::{EngineType, RemoteSigner};
 use crate::error::Error;
 use crate::transaction::{PendingSignedTransactions, SignedTransaction, UnverifiedTransaction};
 use crate::BlockId;
@@ -58,6 +64,9 @@ pub trait MinerService: Send + Sync {
     /// Set the author that we will seal blocks as.
     fn set_author(&self, author: Address) -> Result<(), AccountProviderError>;
 
+    /// Seal blocks as the account of the signing daemon.
+    fn set_remote_signer(&self, signer: RemoteSigner);
+
     ///Get the address that sealed the block.
     fn get_author_address(&self) -> Address;
 
@@ -140,6 +149,28 @@ pub trait MinerService: Send + Sync {
         seq: Option<u64>,
     ) -> Result<(TxHash, u64), Error>;
 
+    /// Replaces the transaction in the mem pool with the given one, signed by the same account with the same seq.
+    /// The fee is raised to the minimum fee required to replace it.
+    /// Returns the new transaction, or None if there's no such transaction in the mem pool.
+    fn replace_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        account_provider: &AccountProvider,
+        hash: &TxHash,
+        tx: IncompleteTransaction,
+        passphrase: Option<Password>,
+    ) -> Result<Option<SignedTransaction>, Error>;
+
+    /// Cancels the transaction in the mem pool by replacing it with a Pay of zero quantity to its signer.
+    /// Returns the new transaction, or None if there's no such transaction in the mem pool.
+    fn cancel_transaction<C: MiningBlockChainClient + AccountData + EngineInfo + TermInfo>(
+        &self,
+        chain: &C,
+        account_provider: &AccountProvider,
+        hash: &TxHash,
+        passphrase: Option<Password>,
+    ) -> Result<Option<SignedTransaction>, Error>;
+
     /// Get a list of all pending transactions in the mem pool.
     fn ready_transactions(&self, range: Range<u64>) -> PendingSignedTransactions;
 
@@ -155,6 +186,9 @@ pub trait MinerService: Send + Sync {
     /// Get a list of all future transactions.
     fn future_transactions(&self) -> Vec<SignedTransaction>;
 
+    /// Get the seq that follows the current transactions of the signer in the mem pool.
+    fn next_seq(&self, public: &Public) -> Option<u64>;
+
     /// Start sealing.
     fn start_sealing<C: MiningBlockChainClient + EngineInfo + TermInfo>(&self, client: &C);
 
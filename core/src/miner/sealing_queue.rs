@@ -15,12 +15,16 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::block::ClosedBlock;
+use primitives::H256;
+use std::collections::VecDeque;
 
 pub struct SealingQueue {
     /// Not yet being sealed by a miner, but if one asks for work, we'd prefer they do this.
     pending: Option<ClosedBlock>,
     /// Currently being sealed by miners.
     in_use: Vec<ClosedBlock>,
+    /// Hashes of the items that were in use but dropped or taken, at most `max_size`.
+    retired: VecDeque<H256>,
     /// The maximum allowable number of items in_use.
     max_size: usize,
 }
@@ -30,6 +34,7 @@ impl SealingQueue {
         Self {
             pending: None,
             in_use: Vec::new(),
+            retired: VecDeque::new(),
             max_size,
         }
     }
@@ -51,7 +56,8 @@ impl SealingQueue {
         if let Some(x) = self.pending.take() {
             self.in_use.push(x);
             if self.in_use.len() > self.max_size {
-                self.in_use.remove(0);
+                let retired = self.in_use.remove(0);
+                self.retire(retired.hash());
             }
         }
         self.in_use.last()
@@ -60,13 +66,36 @@ impl SealingQueue {
     /// Clears everything; the queue is entirely reset.
     pub fn reset(&mut self) {
         self.pending = None;
-        self.in_use.clear();
+        for retired in ::std::mem::replace(&mut self.in_use, Vec::new()) {
+            self.retire(retired.hash());
+        }
     }
 
     pub fn take_used_if<P>(&mut self, predicate: P) -> Option<ClosedBlock>
     where
         P: Fn(&ClosedBlock) -> bool, {
-        self.in_use.iter().position(|r| predicate(r)).map(|i| self.in_use.remove(i))
+        let taken = self.in_use.iter().position(|r| predicate(r)).map(|i| self.in_use.remove(i));
+        if let Some(taken) = &taken {
+            self.retire(taken.hash());
+        }
+        taken
+    }
+
+    /// Return a reference to the item in use with the given hash.
+    pub fn find_used(&self, hash: &H256) -> Option<&ClosedBlock> {
+        self.in_use.iter().find(|b| b.hash() == *hash)
+    }
+
+    /// Whether the item was in use but is no longer in the queue.
+    pub fn is_retired(&self, hash: &H256) -> bool {
+        self.retired.contains(hash)
+    }
+
+    fn retire(&mut self, hash: H256) {
+        self.retired.push_back(hash);
+        if self.retired.len() > self.max_size {
+            self.retired.pop_front();
+        }
     }
 }
 
@@ -204,4 +233,27 @@ mod tests {
 
         assert!(q.take_used_if(|b| b.hash() == h).is_none());
     }
+
+    #[test]
+    fn remember_retired_items() {
+        let mut q = SealingQueue::new(1);
+        let b1 = create_closed_block(Address::from(1));
+        let b2 = create_closed_block(Address::from(2));
+        let h1 = b1.hash();
+        let h2 = b2.hash();
+
+        q.push(b1);
+        q.use_last_ref();
+        assert!(q.find_used(&h1).is_some());
+        assert!(!q.is_retired(&h1));
+
+        q.push(b2);
+        q.use_last_ref();
+        assert!(q.find_used(&h1).is_none());
+        assert!(q.is_retired(&h1));
+
+        assert!(q.take_used_if(|b| b.hash() == h2).is_some());
+        assert!(q.is_retired(&h2));
+        assert!(!q.is_retired(&h1), "Only the last max_size items are remembered");
+    }
 }
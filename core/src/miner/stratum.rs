@@ -16,12 +16,14 @@
 
 //! Client-side stratum job dispatcher and mining notifier handler
 
-use crate::client::Client;
+use crate::client::{Client, EngineClient};
 use crate::error::Error as MinerError;
 use crate::miner::work_notify::NotifyWork;
 use crate::miner::{Miner, MinerService};
+pub use cstratum::WorkerStats;
 use cstratum::{Error as StratumServiceError, JobDispatcher, PushWorkHandler, Stratum as StratumService};
 use primitives::{Bytes, H256, U256};
+use std::cmp::max;
 use std::net::{AddrParseError, SocketAddr};
 use std::sync::Arc;
 
@@ -66,6 +68,39 @@ impl JobDispatcher for StratumJobDispatcher {
             }
         }
     }
+
+    fn job_with_id(&self, job_id: &str, pow_hash: H256, share_divisor: u64) -> Option<String> {
+        let score = self.miner.work_score(&pow_hash)?;
+        let target = self.client.score_to_target(&share_score(&score, share_divisor));
+        Some(format!(r#"["{}","0x{:x}","0x{:x}"]"#, job_id, pow_hash, target))
+    }
+
+    fn submit_share(&self, payload: (H256, Vec<Bytes>), share_divisor: u64) -> Result<bool, StratumServiceError> {
+        let (pow_hash, seal) = payload;
+
+        ctrace!(
+            STRATUM,
+            "submit_share: Decoded: pow_hash={}, seal={:?}, share_divisor={}",
+            pow_hash,
+            seal,
+            share_divisor
+        );
+
+        if !self.miner.can_produce_work_package() {
+            cwarn!(STRATUM, "Cannot get work package - engine seals internally.");
+            return Err(StratumServiceError::InternalError)
+        }
+
+        self.miner.submit_share(&*self.client, pow_hash.into(), seal, share_divisor).map_err(|e| {
+            cdebug!(STRATUM, "submit_share error: {:?}", e);
+            StratumServiceError::from(e)
+        })
+    }
+}
+
+/// The score a share must meet when it is `share_divisor` times easier than the block.
+pub(crate) fn share_score(score: &U256, share_divisor: u64) -> U256 {
+    max(U256::one(), *score / U256::from(max(share_divisor, 1)))
 }
 
 impl StratumJobDispatcher {
@@ -76,15 +111,9 @@ impl StratumJobDispatcher {
             miner,
         }
     }
-
-    /// Serializes payload for stratum service
-    fn payload(&self, pow_hash: H256, target: U256) -> String {
-        format!(r#"["0x{:x}","0x{:x}"]"#, pow_hash, target)
-    }
 }
 /// Wrapper for dedicated stratum service
 pub struct Stratum {
-    dispatcher: Arc<StratumJobDispatcher>,
     service: StratumService,
 }
 
@@ -101,6 +130,7 @@ impl From<MinerError> for StratumServiceError {
     fn from(err: MinerError) -> Self {
         match err {
             MinerError::PowHashInvalid => StratumServiceError::PowHashInvalid,
+            MinerError::PowHashStale => StratumServiceError::StaleShare,
             MinerError::PowInvalid => StratumServiceError::PowInvalid,
             _ => StratumServiceError::InternalError,
         }
@@ -120,12 +150,10 @@ impl From<AddrParseError> for Error {
 }
 
 impl NotifyWork for Stratum {
-    fn notify(&self, pow_hash: H256, target: U256) {
+    fn notify(&self, pow_hash: H256, _target: U256) {
         ctrace!(STRATUM, "Notify work");
 
-        self.service
-            .push_work_all(self.dispatcher.payload(pow_hash, target))
-            .unwrap_or_else(|e| cwarn!(STRATUM, "Error while pushing work: {:?}", e));
+        self.service.push_job(pow_hash).unwrap_or_else(|e| cwarn!(STRATUM, "Error while pushing work: {:?}", e));
     }
 }
 
@@ -137,13 +165,17 @@ impl Stratum {
         let dispatcher = Arc::new(StratumJobDispatcher::new(miner, client));
         let stratum_svc = StratumService::start(
             &SocketAddr::new(config.listen_addr.parse::<IpAddr>()?, config.port),
-            dispatcher.clone(),
+            dispatcher,
             config.secret,
         )?;
 
         Ok(Stratum {
-            dispatcher,
             service: stratum_svc,
         })
     }
+
+    /// Share statistics of the workers that have been authorized
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        self.service.worker_stats()
+    }
 }
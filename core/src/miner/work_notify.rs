@@ -24,6 +24,7 @@ use self::hyper::{Next, Url};
 use parking_lot::Mutex;
 use primitives::{H256, U256};
 use std::io::Write;
+use std::sync::Arc;
 
 /// Trait for notifying about new mining work
 pub trait NotifyWork: Send + Sync {
@@ -31,6 +32,12 @@ pub trait NotifyWork: Send + Sync {
     fn notify(&self, pow_hash: H256, target: U256);
 }
 
+impl<T: NotifyWork + ?Sized> NotifyWork for Arc<T> {
+    fn notify(&self, pow_hash: H256, target: U256) {
+        (**self).notify(pow_hash, target)
+    }
+}
+
 /// POSTs info about new work to given urls.
 pub struct WorkPoster {
     urls: Vec<Url>,
@@ -14,35 +14,40 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use crate::db::COL_EXTRA;
-use cnetwork::{ManagingPeerdb, SocketAddr};
+use cnetwork::{AddressEntry, ManagingPeerdb, PeerReputation, SocketAddr};
 use kvdb::{DBTransaction, KeyValueDB};
 use parking_lot::Mutex;
-use rlp::RlpStream;
+use rlp::{Decodable, DecoderError, Rlp, RlpStream};
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct PeerDb {
     db: Arc<dyn KeyValueDB>,
-    peers_and_count: Mutex<(HashMap<SocketAddr, u64>, usize)>,
+    peers_and_count: Mutex<(HashMap<SocketAddr, AddressEntry>, usize)>,
+    reputations_and_count: Mutex<(HashMap<IpAddr, PeerReputation>, usize)>,
 }
 
 impl PeerDb {
     pub fn new(database: Arc<dyn KeyValueDB>) -> Box<Self> {
+        let peers = load_entries(&*database, PEER_DB_KEY);
+        let reputations = load_entries::<PeerReputation>(&*database, PEER_REPUTATIONS_KEY)
+            .into_iter()
+            .map(|(address, reputation)| (address.ip(), reputation))
+            .collect();
         Box::new(Self {
             db: database,
-            peers_and_count: Default::default(),
+            peers_and_count: Mutex::new((peers, 0)),
+            reputations_and_count: Mutex::new((reputations, 0)),
         })
     }
 }
 
 impl ManagingPeerdb for PeerDb {
-    fn insert(&self, key: SocketAddr) {
+    fn insert(&self, key: SocketAddr, entry: AddressEntry) {
         let (peers, count) = &mut *self.peers_and_count.lock();
-        peers.entry(key).or_insert_with(|| {
-            *count += 1;
-            SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()
-        });
+        peers.insert(key, entry);
+        *count += 1;
 
         if let Some(batch) = get_db_transaction_if_enough_hit(peers, count) {
             self.db.write(batch).expect("The DB must alive");
@@ -59,20 +64,53 @@ impl ManagingPeerdb for PeerDb {
             self.db.write(batch).expect("The DB must alive");
         }
     }
+
+    fn addresses(&self) -> Vec<(SocketAddr, AddressEntry)> {
+        let (peers, _) = &*self.peers_and_count.lock();
+        peers.iter().map(|(address, entry)| (*address, *entry)).collect()
+    }
+
+    fn reputations(&self) -> Vec<(IpAddr, PeerReputation)> {
+        let (reputations, _) = &*self.reputations_and_count.lock();
+        reputations.iter().map(|(ip, reputation)| (*ip, *reputation)).collect()
+    }
+
+    fn update_reputation(&self, key: IpAddr, reputation: PeerReputation) {
+        let (reputations, count) = &mut *self.reputations_and_count.lock();
+        let old = reputations.insert(key, reputation);
+        *count += 1;
+
+        // Bans should survive the restart, so they are written immediately.
+        let ban_changed = old.and_then(|old| old.banned_until) != reputation.banned_until;
+        if ban_changed {
+            *count = 0;
+            self.db.write(get_reputations_db_transaction(reputations)).expect("The DB must alive");
+        } else if let Some(batch) = get_reputations_db_transaction_if_enough_hit(reputations, count) {
+            self.db.write(batch).expect("The DB must alive");
+        }
+    }
 }
 
-// XXX: It may not be needed. Generally, in the p2p networks, the old node lives longer.
-// Exaggeratedly, the new node does not affect the stability of the network. In other words, it
-// doesn't matter even we don't restore the fresh updated nodes.
+// Flushes the updates that are not written yet, so the node reconnects to the known peers after it restarts.
 impl Drop for PeerDb {
     fn drop(&mut self) {
         let (peers, _) = &*self.peers_and_count.lock();
         let batch = get_db_transaction(peers);
         self.db.write(batch).expect("The DB must alive");
+
+        let (reputations, _) = &*self.reputations_and_count.lock();
+        let batch = get_reputations_db_transaction(reputations);
+        self.db.write(batch).expect("The DB must alive");
     }
 }
 
-fn get_db_transaction_if_enough_hit(peers: &HashMap<SocketAddr, u64>, count: &mut usize) -> Option<DBTransaction> {
+const PEER_DB_KEY: &[u8] = b"address-book";
+const PEER_REPUTATIONS_KEY: &[u8] = b"peer-reputations";
+
+fn get_db_transaction_if_enough_hit(
+    peers: &HashMap<SocketAddr, AddressEntry>,
+    count: &mut usize,
+) -> Option<DBTransaction> {
     const UPDATE_AT: usize = 10;
     if *count < UPDATE_AT {
         return None
@@ -83,17 +121,57 @@ fn get_db_transaction_if_enough_hit(peers: &HashMap<SocketAddr, u64>, count: &mu
     Some(get_db_transaction(peers))
 }
 
-fn get_db_transaction(peers: &HashMap<SocketAddr, u64>) -> DBTransaction {
+fn get_db_transaction(peers: &HashMap<SocketAddr, AddressEntry>) -> DBTransaction {
     let mut s = RlpStream::new_list(peers.len());
-    for (address, time) in peers {
-        s.begin_list(2).append(address).append(time);
+    for (address, entry) in peers {
+        s.begin_list(2).append(address).append(entry);
     }
     let encoded = s.drain();
 
     let mut batch = DBTransaction::new();
 
     const COLUMN_TO_WRITE: Option<u32> = COL_EXTRA;
-    const PEER_DB_KEY: &[u8] = b"peer-list";
     batch.put(COLUMN_TO_WRITE, PEER_DB_KEY, &encoded);
     batch
 }
+
+fn get_reputations_db_transaction_if_enough_hit(
+    reputations: &HashMap<IpAddr, PeerReputation>,
+    count: &mut usize,
+) -> Option<DBTransaction> {
+    const UPDATE_AT: usize = 100;
+    if *count < UPDATE_AT {
+        return None
+    }
+
+    *count = 0;
+
+    Some(get_reputations_db_transaction(reputations))
+}
+
+fn get_reputations_db_transaction(reputations: &HashMap<IpAddr, PeerReputation>) -> DBTransaction {
+    let mut s = RlpStream::new_list(reputations.len());
+    for (ip, reputation) in reputations {
+        // The reputations are kept by IP, so they are stored as the addresses of port 0.
+        s.begin_list(2).append(&SocketAddr::new(*ip, 0)).append(reputation);
+    }
+    let encoded = s.drain();
+
+    let mut batch = DBTransaction::new();
+    batch.put(COL_EXTRA, PEER_REPUTATIONS_KEY, &encoded);
+    batch
+}
+
+fn load_entries<T: Decodable>(db: &dyn KeyValueDB, key: &[u8]) -> HashMap<SocketAddr, T> {
+    let encoded = match db.get(COL_EXTRA, key).expect("Low level database error") {
+        Some(encoded) => encoded,
+        None => return Default::default(),
+    };
+    let rlp = Rlp::new(&encoded);
+    let decoded: Result<HashMap<_, _>, DecoderError> =
+        rlp.iter().map(|item| Ok((item.val_at(0)?, item.val_at(1)?))).collect();
+    decoded.unwrap_or_else(|err| {
+        cwarn!(NETWORK, "Cannot decode {}: {:?}", String::from_utf8_lossy(key), err);
+        Default::default()
+    })
+}
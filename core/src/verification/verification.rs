@@ -68,6 +68,7 @@ pub fn verify_block_with_params(
     bytes: &[u8],
     engine: &dyn CodeChainEngine,
     common_params: &CommonParams,
+    base_fee_multiplier: u64,
 ) -> Result<(), Error> {
     verify_header_with_params(&header, common_params)?;
 
@@ -77,7 +78,7 @@ pub fn verify_block_with_params(
     }
 
     for t in body_rlp.iter().map(|rlp| rlp.as_val().expect("verify_block_basic already checked it")) {
-        engine.verify_transaction_with_params(&t, common_params)?;
+        engine.verify_transaction_with_params(&t, common_params, base_fee_multiplier)?;
     }
     Ok(())
 }
@@ -200,8 +201,9 @@ pub fn verify_block_family<C: BlockChainTrait>(
     engine: &dyn CodeChainEngine,
     do_full: Option<FullFamilyParams<C>>,
     common_params: &CommonParams,
+    base_fee_multiplier: u64,
 ) -> Result<(), Error> {
-    verify_block_with_params(header, block, engine, common_params)?;
+    verify_block_with_params(header, block, engine, common_params, base_fee_multiplier)?;
 
     // TODO: verify timestamp
     verify_parent(&header, &parent)?;
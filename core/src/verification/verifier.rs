@@ -46,8 +46,9 @@ impl<C: BlockChainTrait> Verifier<C> {
         engine: &dyn CodeChainEngine,
         do_full: Option<verification::FullFamilyParams<C>>,
         common_params: &CommonParams,
+        base_fee_multiplier: u64,
     ) -> Result<(), Error> {
-        verification::verify_block_family(block, header, parent, engine, do_full, common_params)
+        verification::verify_block_family(block, header, parent, engine, do_full, common_params, base_fee_multiplier)
     }
 
     /// Do a final verification check for an enacted header vs its expected counterpart.
@@ -15,28 +15,62 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::message::Message;
-use super::node_id::{address_to_hash, KademliaId};
+use super::node_id::{address_to_hash, log2_distance, KademliaId};
+use super::node_record::{NodeRecord, MAX_ADDRESSES};
 use super::Config;
-use cnetwork::{Api, IntoSocketAddr, NetworkExtension, NodeId, RoutingTable};
+use ckey::{KeyPair, Public};
+use cnetwork::{Api, IntoSocketAddr, NetworkExtension, NodeId, PeerBehavior, RoutingTable, SocketAddr};
 use ctimer::TimerToken;
 use never_type::Never;
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use rlp::{Decodable, Encodable, Rlp};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// The version which exchanges signed node records.
+const RECORD_VERSION: u64 = 1;
+/// The maximum number of node records kept in memory.
+const MAX_RECORDS: usize = 1024;
+/// The maximum number of node records accepted from a response.
+const MAX_RECORDS_PER_RESPONSE: usize = 16;
+/// The number of distinct peers that should observe the same address before it is advertised.
+const MIN_OBSERVERS: usize = 3;
+
+struct KnownRecord {
+    record: NodeRecord,
+    /// The log2 distance from the local node in the Kademlia key space.
+    distance: usize,
+    last_seen: Instant,
+}
 
 pub struct Extension {
     config: Config,
     routing_table: Arc<RoutingTable>,
     api: Box<dyn Api>,
-    nodes: HashSet<NodeId>, // FIXME: Find the optimized data structure for it
+    nodes: HashMap<NodeId, u64>, // FIXME: Find the optimized data structure for it
     use_kademlia: bool,
+    key_pair: KeyPair,
+    /// The addresses given by the configuration, which are always advertised.
+    listen_addresses: Vec<SocketAddr>,
+    /// The address of this node observed by each peer.
+    observed_addresses: HashMap<NodeId, SocketAddr>,
+    local_record: NodeRecord,
+    records: HashMap<Public, KnownRecord>,
 }
 
 impl Extension {
-    pub fn new(routing_table: Arc<RoutingTable>, config: Config, api: Box<dyn Api>, use_kademlia: bool) -> Self {
+    pub fn new(
+        routing_table: Arc<RoutingTable>,
+        config: Config,
+        api: Box<dyn Api>,
+        use_kademlia: bool,
+        key_pair: KeyPair,
+        addresses: Vec<SocketAddr>,
+        extensions: Vec<String>,
+    ) -> Self {
         if use_kademlia {
             cinfo!(DISCOVERY, "Discovery starts with kademlia option");
         } else {
@@ -44,13 +78,155 @@ impl Extension {
         }
         api.set_timer(REFRESH_TOKEN, Duration::from_millis(u64::from(config.t_refresh)))
             .expect("Refresh must be registered");
+        let local_record = NodeRecord::new(&key_pair, 0, addresses.clone(), extensions);
+        cinfo!(DISCOVERY, "Node public key is {:?}", key_pair.public());
         Self {
             config,
             routing_table,
             api,
             nodes: Default::default(),
             use_kademlia,
+            key_pair,
+            listen_addresses: addresses,
+            observed_addresses: Default::default(),
+            local_record,
+            records: Default::default(),
+        }
+    }
+
+    fn request(&self, version: u64) -> Message {
+        if version >= RECORD_VERSION {
+            Message::RecordRequest {
+                len: self.config.bucket_size,
+                record: self.local_record.clone(),
+            }
+        } else {
+            Message::Request(self.config.bucket_size)
+        }
+    }
+
+    /// Stores the record if it is newer than the known one and adds its addresses to the routing table.
+    fn insert_record(&mut self, record: NodeRecord) {
+        if record.public() == self.key_pair.public() {
+            return
+        }
+        let now = Instant::now();
+        if let Some(known) = self.records.get_mut(record.public()) {
+            if known.record.seq() > record.seq() {
+                return
+            }
+            known.last_seen = now;
+            if known.record.seq() == record.seq() {
+                return
+            }
+        } else if self.records.len() >= MAX_RECORDS {
+            self.evict_record();
+        }
+        self.routing_table.touch_addresses(record.addresses().iter().cloned());
+        let distance = log2_distance(&record.key_hash(), &self.local_record.key_hash());
+        self.records.insert(*record.public(), KnownRecord {
+            record,
+            distance,
+            last_seen: now,
+        });
+    }
+
+    /// Evicts the least recently seen record in the most crowded distance bucket,
+    /// so that a flood of records cannot push out the nodes close to this node.
+    fn evict_record(&mut self) {
+        let mut bucket_sizes: HashMap<usize, usize> = HashMap::new();
+        for known in self.records.values() {
+            *bucket_sizes.entry(known.distance).or_default() += 1;
+        }
+        let crowded = match bucket_sizes.into_iter().max_by_key(|(distance, size)| (*size, *distance)) {
+            Some((distance, _)) => distance,
+            None => return,
+        };
+        let evicted = self
+            .records
+            .iter()
+            .filter(|(_, known)| known.distance == crowded)
+            .min_by_key(|(_, known)| known.last_seen)
+            .map(|(public, _)| *public);
+        if let Some(public) = evicted {
+            self.records.remove(&public);
+        }
+    }
+
+    /// Records the address of this node observed by a peer.
+    /// The address is advertised with the configured ones after enough peers observe it,
+    /// and the local record is re-signed with a new sequence number only if its addresses change.
+    fn update_local_address(&mut self, node: &NodeId, observed: SocketAddr) {
+        if observed.ip().is_unspecified() || !observed.is_global() {
+            return
+        }
+        self.observed_addresses.insert(*node, observed);
+        if self.local_record.addresses().contains(&observed) {
+            return
+        }
+        let observers = self
+            .observed_addresses
+            .iter()
+            .filter(|(_, address)| **address == observed)
+            .map(|(node, _)| node.into_addr().ip())
+            .collect::<HashSet<IpAddr>>();
+        if observers.len() < MIN_OBSERVERS {
+            return
+        }
+
+        let mut addresses = self.listen_addresses.clone();
+        addresses.push(observed);
+        addresses.extend(
+            self.local_record.addresses().iter().filter(|address| !self.listen_addresses.contains(address)).cloned(),
+        );
+        addresses.truncate(MAX_ADDRESSES);
+        if addresses.as_slice() == self.local_record.addresses() {
+            return
+        }
+        let seq = self.local_record.seq() + 1;
+        let extensions = self.local_record.extensions().to_vec();
+        self.local_record = NodeRecord::new(&self.key_pair, seq, addresses, extensions);
+        cdebug!(DISCOVERY, "Local node record is updated to {:?}", self.local_record);
+    }
+
+    fn addresses_for(&self, node: &NodeId, len: u8) -> Vec<SocketAddr> {
+        let len = ::std::cmp::min(self.config.bucket_size, len) as usize;
+        if self.use_kademlia {
+            let datum = address_to_hash(&node.into_addr());
+            let mut addresses = self
+                .routing_table
+                .reachable_addresses(&node.into_addr())
+                .into_iter()
+                .map(|address| KademliaId::new(address, &datum))
+                .collect::<Vec<_>>();
+
+            addresses.sort_unstable();
+
+            addresses.into_iter().map(From::from).take(len).collect()
+        } else {
+            let mut addresses = self.routing_table.reachable_addresses(&node.into_addr());
+            addresses.shuffle(&mut thread_rng());
+            addresses.into_iter().take(len).collect()
+        }
+    }
+
+    fn records_for(&self, node: &NodeId, requester: &NodeRecord, len: u8) -> Vec<NodeRecord> {
+        let len = ::std::cmp::min(self.config.bucket_size, len) as usize;
+        let from = node.into_addr();
+        let mut records = self
+            .records
+            .values()
+            .map(|known| &known.record)
+            .filter(|record| record.public() != requester.public())
+            .filter(|record| record.addresses().iter().any(|address| from.is_reachable(address)))
+            .collect::<Vec<_>>();
+        if self.use_kademlia {
+            let datum = requester.key_hash();
+            records.sort_unstable_by_key(|record| log2_distance(&record.key_hash(), &datum));
+        } else {
+            records.shuffle(&mut thread_rng());
         }
+        records.into_iter().take(len).cloned().collect()
     }
 }
 
@@ -66,17 +242,18 @@ impl NetworkExtension<Never> for Extension {
     }
 
     fn versions() -> &'static [u64] {
-        const VERSIONS: &[u64] = &[0];
+        const VERSIONS: &[u64] = &[0, RECORD_VERSION];
         &VERSIONS
     }
 
-    fn on_node_added(&mut self, node: &NodeId, _version: u64) {
-        self.nodes.insert(*node);
-        self.api.send(&node, Arc::new(Message::Request(self.config.bucket_size).rlp_bytes()));
+    fn on_node_added(&mut self, node: &NodeId, version: u64) {
+        self.nodes.insert(*node, version);
+        self.api.send(&node, Arc::new(self.request(version).rlp_bytes()));
     }
 
     fn on_node_removed(&mut self, node: &NodeId) {
         self.nodes.remove(node);
+        self.observed_addresses.remove(node);
     }
 
     fn on_message(&mut self, node: &NodeId, message: &[u8]) {
@@ -84,47 +261,58 @@ impl NetworkExtension<Never> for Extension {
             Ok(message) => message,
             Err(err) => {
                 cwarn!(DISCOVERY, "Invalid message from {} : {:?}", node, err);
+                self.api.report(node, PeerBehavior::UndecodableMessage);
                 return
             }
         };
         match message {
             Message::Request(len) => {
-                let addresses = if self.use_kademlia {
-                    let datum = address_to_hash(&node.into_addr());
-                    let mut addresses = self
-                        .routing_table
-                        .reachable_addresses(&node.into_addr())
-                        .into_iter()
-                        .map(|address| KademliaId::new(address, &datum))
-                        .collect::<Vec<_>>();
-
-                    addresses.sort_unstable();
-
-                    addresses
-                        .into_iter()
-                        .map(From::from)
-                        .take(::std::cmp::min(self.config.bucket_size, len) as usize)
-                        .collect()
-                } else {
-                    let mut addresses = self.routing_table.reachable_addresses(&node.into_addr());
-                    addresses.shuffle(&mut thread_rng());
-                    addresses.into_iter().take(::std::cmp::min(self.config.bucket_size, len) as usize).collect()
-                };
-                let response = Arc::new(Message::Response(addresses).rlp_bytes());
+                let response = Arc::new(Message::Response(self.addresses_for(node, len)).rlp_bytes());
                 self.api.send(&node, response);
             }
             Message::Response(addresses) => {
                 self.routing_table.touch_addresses(addresses);
             }
+            Message::RecordRequest {
+                len,
+                record,
+            } => {
+                if !record.verify() {
+                    cwarn!(DISCOVERY, "Invalid node record from {}", node);
+                    self.api.report(node, PeerBehavior::InvalidSignature);
+                    return
+                }
+                let records = self.records_for(node, &record, len);
+                self.insert_record(record);
+                let response = Message::RecordResponse {
+                    observed: node.into_addr(),
+                    records,
+                };
+                self.api.send(&node, Arc::new(response.rlp_bytes()));
+            }
+            Message::RecordResponse {
+                observed,
+                records,
+            } => {
+                self.update_local_address(node, observed);
+                let len = ::std::cmp::min(self.config.bucket_size as usize, MAX_RECORDS_PER_RESPONSE);
+                for record in records.into_iter().take(len) {
+                    if !record.verify() {
+                        cwarn!(DISCOVERY, "Invalid node record from {}", node);
+                        self.api.report(node, PeerBehavior::InvalidSignature);
+                        return
+                    }
+                    self.insert_record(record);
+                }
+            }
         }
     }
 
     fn on_timeout(&mut self, timer: TimerToken) {
         match timer {
             REFRESH_TOKEN => {
-                let request = Arc::new(Message::Request(self.config.bucket_size).rlp_bytes());
-                for node in &self.nodes {
-                    self.api.send(node, Arc::clone(&request));
+                for (node, version) in &self.nodes {
+                    self.api.send(node, Arc::new(self.request(*version).rlp_bytes()));
                 }
             }
             _ => unreachable!(),
@@ -36,6 +36,7 @@ mod config;
 mod extension;
 mod message;
 mod node_id;
+mod node_record;
 
 const K: u8 = 16;
 const T_REFRESH: u32 = 60_000;
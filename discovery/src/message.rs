@@ -14,13 +14,28 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::node_record::NodeRecord;
 use cnetwork::SocketAddr;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
+const MESSAGE_ID_RECORD_REQUEST: u8 = 0x01;
+const MESSAGE_ID_RECORD_RESPONSE: u8 = 0x02;
+
+/// `Request` and `Response` are the messages of the version 0 protocol.
+/// The version 1 protocol exchanges signed node records instead of bare addresses.
 #[derive(Debug, PartialEq)]
 pub enum Message {
     Request(u8),
     Response(Vec<SocketAddr>),
+    RecordRequest {
+        len: u8,
+        record: NodeRecord,
+    },
+    RecordResponse {
+        /// The address of the requester as seen by the responder
+        observed: SocketAddr,
+        records: Vec<NodeRecord>,
+    },
 }
 
 impl Encodable for Message {
@@ -32,6 +47,18 @@ impl Encodable for Message {
             Message::Response(addresses) => {
                 s.append_list(addresses);
             }
+            Message::RecordRequest {
+                len,
+                record,
+            } => {
+                s.begin_list(3).append(&MESSAGE_ID_RECORD_REQUEST).append(len).append(record);
+            }
+            Message::RecordResponse {
+                observed,
+                records,
+            } => {
+                s.begin_list(3).append(&MESSAGE_ID_RECORD_RESPONSE).append(observed).append_list(records);
+            }
         }
     }
 }
@@ -39,15 +66,36 @@ impl Encodable for Message {
 impl Decodable for Message {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         if rlp.is_int() {
-            Ok(Message::Request(rlp.as_val()?))
-        } else {
-            Ok(Message::Response(rlp.as_list()?))
+            return Ok(Message::Request(rlp.as_val()?))
+        }
+        // The items of a version 0 response are addresses, which are encoded as lists.
+        if rlp.is_empty() || !rlp.at(0)?.is_int() {
+            return Ok(Message::Response(rlp.as_list()?))
+        }
+        let item_count = rlp.item_count()?;
+        if item_count != 3 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 3,
+            })
+        }
+        match rlp.val_at(0)? {
+            MESSAGE_ID_RECORD_REQUEST => Ok(Message::RecordRequest {
+                len: rlp.val_at(1)?,
+                record: rlp.val_at(2)?,
+            }),
+            MESSAGE_ID_RECORD_RESPONSE => Ok(Message::RecordResponse {
+                observed: rlp.val_at(1)?,
+                records: rlp.list_at(2)?,
+            }),
+            _ => Err(DecoderError::Custom("Unexpected message id")),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use ckey::{Generator, Random};
     use rlp::rlp_encode_and_decode_test;
 
     use super::*;
@@ -89,4 +137,33 @@ mod tests {
             SocketAddr::v4(127, 0, 0, 1, 3481),
         ]));
     }
+
+    fn create_record(port: u16) -> NodeRecord {
+        let key_pair = Random.generate().unwrap();
+        NodeRecord::new(&key_pair, 0, vec![SocketAddr::v4(127, 0, 0, 1, port)], vec!["discovery".to_string()])
+    }
+
+    #[test]
+    fn encode_and_decode_record_request() {
+        rlp_encode_and_decode_test!(Message::RecordRequest {
+            len: 3,
+            record: create_record(3480),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_empty_record_response() {
+        rlp_encode_and_decode_test!(Message::RecordResponse {
+            observed: SocketAddr::v4(127, 0, 0, 1, 3480),
+            records: vec![],
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_two_record_response() {
+        rlp_encode_and_decode_test!(Message::RecordResponse {
+            observed: SocketAddr::v4(127, 0, 0, 1, 3480),
+            records: vec![create_record(3481), create_record(3482)],
+        });
+    }
 }
@@ -15,6 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use ccrypto::Blake;
+use ckey::Public;
 use cnetwork::{IntoSocketAddr, NodeId, SocketAddr};
 use primitives::H128;
 use std::net::IpAddr;
@@ -28,7 +29,7 @@ pub struct KademliaId {
 impl KademliaId {
     pub fn new(address: SocketAddr, datum: &H128) -> Self {
         Self {
-            distance: log2_distance(&address, datum),
+            distance: log2_distance(&address_to_hash(&address), datum),
             node_id: address.into(),
         }
     }
@@ -63,10 +64,12 @@ pub fn address_to_hash(addr: &SocketAddr) -> H128 {
     }
 }
 
-fn log2_distance(addr: &SocketAddr, datum: &H128) -> usize {
-    let hash = address_to_hash(addr);
+pub fn key_to_hash(public: &Public) -> H128 {
+    H128::blake(public)
+}
 
-    let distance = hash ^ *datum;
+pub fn log2_distance(hash: &H128, datum: &H128) -> usize {
+    let distance = *hash ^ *datum;
     const B: usize = 16 * 8;
     const BYTES_SIZE: usize = B / 8;
     debug_assert_eq!(B % 8, 0);
@@ -0,0 +1,171 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::node_id::key_to_hash;
+use ccrypto::blake256;
+use ckey::{sign_schnorr, verify_schnorr, KeyPair, Public, SchnorrSignature};
+use cnetwork::SocketAddr;
+use primitives::{H128, H256};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// The maximum number of addresses a record can advertise.
+pub const MAX_ADDRESSES: usize = 4;
+/// The maximum number of extension names a record can advertise.
+pub const MAX_EXTENSIONS: usize = 16;
+
+/// A self-certified description of a node.
+///
+/// The record is signed with the node key, so a peer cannot advertise addresses on behalf of another node.
+/// A record with a higher sequence number replaces the older one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeRecord {
+    public: Public,
+    seq: u64,
+    addresses: Vec<SocketAddr>,
+    extensions: Vec<String>,
+    signature: SchnorrSignature,
+}
+
+impl NodeRecord {
+    pub fn new(key_pair: &KeyPair, seq: u64, mut addresses: Vec<SocketAddr>, mut extensions: Vec<String>) -> Self {
+        addresses.truncate(MAX_ADDRESSES);
+        extensions.truncate(MAX_EXTENSIONS);
+        let hash = unsigned_hash(key_pair.public(), seq, &addresses, &extensions);
+        let signature = sign_schnorr(key_pair.private(), &hash).expect("The node key is valid");
+        Self {
+            public: *key_pair.public(),
+            seq,
+            addresses,
+            extensions,
+            signature,
+        }
+    }
+
+    pub fn public(&self) -> &Public {
+        &self.public
+    }
+
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    pub fn addresses(&self) -> &[SocketAddr] {
+        &self.addresses
+    }
+
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+
+    /// The hash used as the position of the node in the Kademlia key space.
+    pub fn key_hash(&self) -> H128 {
+        key_to_hash(&self.public)
+    }
+
+    pub fn verify(&self) -> bool {
+        if self.addresses.len() > MAX_ADDRESSES || self.extensions.len() > MAX_EXTENSIONS {
+            return false
+        }
+        let hash = unsigned_hash(&self.public, self.seq, &self.addresses, &self.extensions);
+        verify_schnorr(&self.public, &self.signature, &hash).unwrap_or(false)
+    }
+}
+
+fn unsigned_hash(public: &Public, seq: u64, addresses: &[SocketAddr], extensions: &[String]) -> H256 {
+    let mut s = RlpStream::new_list(4);
+    s.append(public).append(&seq).append_list(addresses).append_list::<String, String>(extensions);
+    blake256(s.out())
+}
+
+impl Encodable for NodeRecord {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&self.public)
+            .append(&self.seq)
+            .append_list(&self.addresses)
+            .append_list::<String, String>(&self.extensions)
+            .append(&self.signature);
+    }
+}
+
+impl Decodable for NodeRecord {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 5 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 5,
+            })
+        }
+        Ok(Self {
+            public: rlp.val_at(0)?,
+            seq: rlp.val_at(1)?,
+            addresses: rlp.list_at(2)?,
+            extensions: rlp.list_at(3)?,
+            signature: rlp.val_at(4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{Generator, Random};
+    use rlp::rlp_encode_and_decode_test;
+
+    use super::*;
+
+    fn create_record(seq: u64) -> NodeRecord {
+        let key_pair = Random.generate().unwrap();
+        NodeRecord::new(&key_pair, seq, vec![SocketAddr::v4(127, 0, 0, 1, 3485)], vec!["discovery".to_string()])
+    }
+
+    #[test]
+    fn signed_record_is_valid() {
+        assert!(create_record(0).verify());
+    }
+
+    #[test]
+    fn tampered_record_is_invalid() {
+        let mut record = create_record(1);
+        record.seq = 2;
+        assert!(!record.verify());
+
+        let mut record = create_record(1);
+        record.addresses.push(SocketAddr::v4(127, 0, 0, 1, 3486));
+        assert!(!record.verify());
+    }
+
+    #[test]
+    fn record_signed_by_another_key_is_invalid() {
+        let mut record = create_record(0);
+        record.public = *Random.generate().unwrap().public();
+        assert!(!record.verify());
+    }
+
+    #[test]
+    fn addresses_are_truncated() {
+        let key_pair = Random.generate().unwrap();
+        let addresses = (0..10).map(|i| SocketAddr::v4(127, 0, 0, 1, 3485 + i)).collect();
+        let record = NodeRecord::new(&key_pair, 0, addresses, vec![]);
+        assert_eq!(MAX_ADDRESSES, record.addresses().len());
+        assert!(record.verify());
+    }
+
+    #[test]
+    fn encode_and_decode_record() {
+        rlp_encode_and_decode_test!(create_record(3));
+    }
+}
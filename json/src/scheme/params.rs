@@ -69,6 +69,12 @@ pub struct Params {
     /// A monotonically increasing number to denote the consensus version.
     /// It is increased when we fork.
     pub era: Option<Uint>,
+
+    /// Maximum cost of running the scripts of an input.
+    pub max_script_cost: Option<Uint>,
+
+    /// The base fee multiplier moves at most 1/`base_fee_change_denominator` per block. Zero disables the fee market.
+    pub base_fee_change_denominator: Option<Uint>,
 }
 
 #[cfg(test)]
@@ -227,12 +227,50 @@ pub fn recover_schnorr(signature: &SchnorrSignature, message: &Message) -> Resul
     Ok(public)
 }
 
+/// Sums up the public keys. A signature made with the sum of the private keys is valid for the result.
+///
+/// The key owners must prove the possession of their private keys to each other before using the sum.
+/// Otherwise, a participant can choose a public key that cancels out the keys of the others.
+pub fn aggregate_schnorr_publics(publics: &[Public]) -> Result<Public, Error> {
+    let context = &SECP256K1;
+    let to_secp_public = |public: &Public| {
+        let mut pdata = [4u8; 65];
+        pdata[1..65].copy_from_slice(&**public);
+        key::PublicKey::from_slice(context, &pdata)
+    };
+
+    let (first, rest) = publics.split_first().ok_or(Error::InvalidPublic)?;
+    let mut aggregated = to_secp_public(first)?;
+    for public in rest {
+        aggregated.add_assign(context, &to_secp_public(public)?)?;
+    }
+    let serialized = aggregated.serialize_vec(context, false);
+
+    let mut public = Public::default();
+    public.copy_from_slice(&serialized[1..65]);
+    Ok(public)
+}
+
+/// Sums up the private keys. It is the private key of `aggregate_schnorr_publics`.
+pub fn aggregate_schnorr_privates(privates: &[Private]) -> Result<Private, Error> {
+    let context = &SECP256K1;
+    let (first, rest) = privates.split_first().ok_or(Error::InvalidPrivate)?;
+    let mut aggregated = key::SecretKey::from_slice(context, &first[..])?;
+    for private in rest {
+        aggregated.add_assign(context, &key::SecretKey::from_slice(context, &private[..])?)?;
+    }
+    Ok(Private::from(aggregated))
+}
+
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
-    use super::{recover_schnorr, sign_schnorr, verify_schnorr, verify_schnorr_address, SchnorrSignature};
-    use crate::{Generator, Message, Random};
+    use super::{
+        aggregate_schnorr_privates, aggregate_schnorr_publics, recover_schnorr, sign_schnorr, verify_schnorr,
+        verify_schnorr_address, SchnorrSignature,
+    };
+    use crate::{Generator, KeyPair, Message, Random};
 
     #[test]
     fn signature_to_and_from_str() {
@@ -267,4 +305,29 @@ mod tests {
         let signature = sign_schnorr(keypair.private(), &message).unwrap();
         assert!(verify_schnorr_address(&keypair.address(), &signature, &message).unwrap());
     }
+
+    #[test]
+    fn sign_and_verify_with_aggregated_keys() {
+        let keypairs: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+        let publics: Vec<_> = keypairs.iter().map(|keypair| *keypair.public()).collect();
+        let privates: Vec<_> = keypairs.iter().map(|keypair| *keypair.private()).collect();
+
+        let aggregated_public = aggregate_schnorr_publics(&publics).unwrap();
+        let aggregated_private = aggregate_schnorr_privates(&privates).unwrap();
+        assert_eq!(&aggregated_public, KeyPair::from_private(aggregated_private).unwrap().public());
+
+        let message = Message::random();
+        let signature = sign_schnorr(&aggregated_private, &message).unwrap();
+        assert!(verify_schnorr(&aggregated_public, &signature, &message).unwrap());
+        assert!(!verify_schnorr(&publics[0], &signature, &message).unwrap());
+
+        let signature = sign_schnorr(keypairs[0].private(), &message).unwrap();
+        assert!(!verify_schnorr(&aggregated_public, &signature, &message).unwrap());
+    }
+
+    #[test]
+    fn aggregate_nothing() {
+        assert!(aggregate_schnorr_publics(&[]).is_err());
+        assert!(aggregate_schnorr_privates(&[]).is_err());
+    }
 }
@@ -0,0 +1,280 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::SocketAddr;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::cmp::{min, Reverse};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// The first delay after a failure, in seconds. It doubles on each consecutive failure.
+pub const BASE_BACKOFF: u64 = 30;
+/// The maximum delay between the attempts, in seconds.
+pub const MAX_BACKOFF: u64 = 60 * 60;
+/// The address that never succeeded is forgotten after this number of consecutive failures.
+pub const MAX_FAILURES_OF_UNKNOWN: u32 = 4;
+/// The address that succeeded once is forgotten after this number of consecutive failures.
+pub const MAX_FAILURES_OF_KNOWN: u32 = 16;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AddressEntry {
+    /// The UNIX timestamp when the peer was connected or tried last.
+    pub last_seen: u64,
+    /// The UNIX timestamp when the connection to the peer was established last.
+    pub last_success: Option<u64>,
+    /// The number of the consecutive failures.
+    pub failures: u32,
+    /// The UNIX timestamp before which the peer is not tried.
+    pub next_attempt: u64,
+}
+
+impl AddressEntry {
+    fn is_ready(&self, now: u64) -> bool {
+        self.next_attempt <= now
+    }
+}
+
+impl Encodable for AddressEntry {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4)
+            .append(&self.last_seen)
+            .append(&self.last_success.unwrap_or(0))
+            .append(&self.failures)
+            .append(&self.next_attempt);
+    }
+}
+
+impl Decodable for AddressEntry {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 4 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 4,
+            })
+        }
+        let last_success: u64 = rlp.val_at(1)?;
+        Ok(Self {
+            last_seen: rlp.val_at(0)?,
+            last_success: if last_success == 0 {
+                None
+            } else {
+                Some(last_success)
+            },
+            failures: rlp.val_at(2)?,
+            next_attempt: rlp.val_at(3)?,
+        })
+    }
+}
+
+/// The peers that the node has tried to connect to.
+#[derive(Default)]
+pub struct AddressBook {
+    entries: HashMap<SocketAddr, AddressEntry>,
+}
+
+impl AddressBook {
+    pub fn new(entries: Vec<(SocketAddr, AddressEntry)>) -> Self {
+        Self {
+            entries: entries.into_iter().collect(),
+        }
+    }
+
+    pub fn get(&self, addr: &SocketAddr) -> Option<&AddressEntry> {
+        self.entries.get(addr)
+    }
+
+    pub fn addresses(&self) -> Vec<SocketAddr> {
+        self.entries.keys().cloned().collect()
+    }
+
+    pub fn to_vec(&self) -> Vec<(SocketAddr, AddressEntry)> {
+        let mut entries: Vec<_> = self.entries.iter().map(|(addr, entry)| (*addr, *entry)).collect();
+        entries.sort_unstable_by_key(|(addr, _)| *addr);
+        entries
+    }
+
+    pub fn remove(&mut self, addr: &SocketAddr) -> bool {
+        self.entries.remove(addr).is_some()
+    }
+
+    /// Records that the peer was disconnected.
+    pub fn seen(&mut self, addr: SocketAddr, now: u64) -> Option<AddressEntry> {
+        let entry = self.entries.entry(addr).or_default();
+        entry.last_seen = now;
+        Some(*entry)
+    }
+
+    /// Records that the connection to the peer is established.
+    pub fn succeeded(&mut self, addr: SocketAddr, now: u64) -> Option<AddressEntry> {
+        let entry = self.entries.entry(addr).or_default();
+        entry.last_seen = now;
+        entry.last_success = Some(now);
+        entry.failures = 0;
+        entry.next_attempt = 0;
+        Some(*entry)
+    }
+
+    /// Records that the connection to the peer failed and postpones the next attempt.
+    /// Returns None if the peer is removed because it failed too many times.
+    pub fn failed(&mut self, addr: SocketAddr, now: u64) -> Option<AddressEntry> {
+        let entry = self.entries.entry(addr).or_default();
+        entry.last_seen = now;
+        entry.failures += 1;
+        let max_failures = if entry.last_success.is_some() {
+            MAX_FAILURES_OF_KNOWN
+        } else {
+            MAX_FAILURES_OF_UNKNOWN
+        };
+        if entry.failures >= max_failures {
+            self.entries.remove(&addr);
+            return None
+        }
+        let shift = min(entry.failures - 1, 16);
+        entry.next_attempt = now + min(BASE_BACKOFF << shift, MAX_BACKOFF);
+        Some(*entry)
+    }
+
+    /// Selects at most `count` addresses among the candidates that are not waiting for the backoff.
+    /// The addresses are picked from the different buckets in turn, and the known-good ones come first in each bucket.
+    pub fn select<R: Rng>(&self, candidates: Vec<SocketAddr>, count: usize, now: u64, rng: &mut R) -> Vec<SocketAddr> {
+        let mut buckets: HashMap<Vec<u8>, Vec<SocketAddr>> = HashMap::new();
+        for addr in candidates {
+            if self.entries.get(&addr).map_or(true, |entry| entry.is_ready(now)) {
+                buckets.entry(bucket(&addr.ip())).or_default().push(addr);
+            }
+        }
+        let priority = |addr: &SocketAddr| {
+            let entry = self.entries.get(addr);
+            let last_success = entry.and_then(|entry| entry.last_success).unwrap_or(0);
+            let failures = entry.map_or(0, |entry| entry.failures);
+            (Reverse(last_success), failures)
+        };
+        let mut buckets: Vec<_> = buckets.into_iter().map(|(_, addresses)| addresses).collect();
+        for addresses in buckets.iter_mut() {
+            addresses.shuffle(rng);
+            addresses.sort_by_key(&priority);
+            addresses.reverse();
+        }
+        buckets.shuffle(rng);
+        buckets.sort_by_key(|addresses| addresses.last().map(&priority));
+
+        let mut selected = Vec::with_capacity(count);
+        while selected.len() < count && !buckets.is_empty() {
+            for addresses in buckets.iter_mut() {
+                if selected.len() == count {
+                    break
+                }
+                selected.extend(addresses.pop());
+            }
+            buckets.retain(|addresses| !addresses.is_empty());
+        }
+        selected
+    }
+}
+
+/// The /16 network for IPv4 and the /32 network for IPv6.
+fn bucket(ip: &IpAddr) -> Vec<u8> {
+    match ip {
+        IpAddr::V4(ip) => ip.octets()[..2].to_vec(),
+        IpAddr::V6(ip) => ip.octets()[..4].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn failures_back_off_exponentially() {
+        let mut book = AddressBook::default();
+        let addr = SocketAddr::v4(1, 2, 3, 4, 3485);
+        book.succeeded(addr, 0);
+
+        assert_eq!(100 + BASE_BACKOFF, book.failed(addr, 100).unwrap().next_attempt);
+        assert_eq!(200 + BASE_BACKOFF * 2, book.failed(addr, 200).unwrap().next_attempt);
+        assert_eq!(300 + BASE_BACKOFF * 4, book.failed(addr, 300).unwrap().next_attempt);
+        for _ in 3..10 {
+            book.failed(addr, 400);
+        }
+        assert_eq!(400 + MAX_BACKOFF, book.get(&addr).unwrap().next_attempt);
+
+        let entry = book.succeeded(addr, 500).unwrap();
+        assert_eq!(0, entry.failures);
+        assert_eq!(Some(500), entry.last_success);
+        assert_eq!(0, entry.next_attempt);
+    }
+
+    #[test]
+    fn unknown_peers_are_forgotten_earlier() {
+        let mut book = AddressBook::default();
+        let unknown = SocketAddr::v4(1, 2, 3, 4, 3485);
+        let known = SocketAddr::v4(1, 2, 3, 5, 3485);
+        book.succeeded(known, 0);
+        for _ in 0..MAX_FAILURES_OF_UNKNOWN {
+            book.failed(unknown, 0);
+            book.failed(known, 0);
+        }
+        assert_eq!(None, book.get(&unknown));
+        assert!(book.get(&known).is_some());
+    }
+
+    #[test]
+    fn select_skips_peers_waiting_for_backoff() {
+        let mut book = AddressBook::default();
+        let failed = SocketAddr::v4(1, 2, 3, 4, 3485);
+        let fresh = SocketAddr::v4(1, 2, 3, 5, 3485);
+        book.failed(failed, 0);
+
+        let mut rng = OsRng::new().unwrap();
+        assert_eq!(vec![fresh], book.select(vec![failed, fresh], 2, 1, &mut rng));
+        let mut selected = book.select(vec![failed, fresh], 2, BASE_BACKOFF, &mut rng);
+        selected.sort();
+        assert_eq!(vec![failed, fresh], selected);
+    }
+
+    #[test]
+    fn select_prefers_known_peers_in_different_buckets() {
+        let mut book = AddressBook::default();
+        let known1 = SocketAddr::v4(1, 2, 3, 4, 3485);
+        let known2 = SocketAddr::v4(1, 2, 3, 5, 3485);
+        let other_bucket = SocketAddr::v4(5, 6, 7, 8, 3485);
+        let unknown = SocketAddr::v4(1, 2, 3, 6, 3485);
+        book.succeeded(known1, 10);
+        book.succeeded(known2, 20);
+        book.succeeded(other_bucket, 5);
+
+        let mut rng = OsRng::new().unwrap();
+        let candidates = vec![known1, known2, other_bucket, unknown];
+        assert_eq!(vec![known2, other_bucket], book.select(candidates.clone(), 2, 30, &mut rng));
+        assert_eq!(vec![known2, other_bucket, known1, unknown], book.select(candidates, 10, 30, &mut rng));
+    }
+
+    #[test]
+    fn encode_and_decode_address_entry() {
+        rlp_encode_and_decode_test!(AddressEntry {
+            last_seen: 10,
+            last_success: Some(5),
+            failures: 2,
+            next_attempt: 70,
+        });
+        rlp_encode_and_decode_test!(AddressEntry::default());
+    }
+}
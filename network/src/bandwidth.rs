@@ -0,0 +1,331 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// The order in which the queued extension messages are sent to a peer.
+///
+/// High priority messages are sent even if the global or the per-peer budget is used up.
+/// They still use the budget, so the other messages wait until it's refilled.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Priority {
+    Low,
+    Normal,
+    High,
+}
+
+/// The limits in bytes per second. `None` or a missing extension means unlimited.
+#[derive(Clone, Debug, Default)]
+pub struct BandwidthLimits {
+    pub upload: Option<usize>,
+    pub download: Option<usize>,
+    pub peer_upload: Option<usize>,
+    pub peer_download: Option<usize>,
+    pub extension_upload: HashMap<String, usize>,
+    pub extension_download: HashMap<String, usize>,
+}
+
+/// A token bucket which holds up to a second of traffic.
+///
+/// A message is allowed while any token is left, and the bucket goes into debt if the message is larger than the rest.
+/// This way a message larger than the limit doesn't block the stream forever.
+struct RateLimiter {
+    bytes_per_second: usize,
+    tokens: i64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    fn new(bytes_per_second: usize, now: Instant) -> Self {
+        Self {
+            bytes_per_second,
+            tokens: bytes_per_second as i64,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if now <= self.updated_at {
+            return
+        }
+        let elapsed = now - self.updated_at;
+        let refilled = (elapsed.as_micros() * self.bytes_per_second as u128 / 1_000_000) as i64;
+        if refilled == 0 {
+            return
+        }
+        self.tokens = ::std::cmp::min(self.tokens + refilled, self.bytes_per_second as i64);
+        self.updated_at = now;
+    }
+
+    fn is_available(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens > 0
+    }
+
+    fn consume(&mut self, bytes: usize, now: Instant) {
+        self.refill(now);
+        self.tokens -= bytes as i64;
+    }
+}
+
+/// The limits shared by all connections.
+pub struct Bandwidth {
+    upload: Option<Mutex<RateLimiter>>,
+    download: Option<Mutex<RateLimiter>>,
+    peer_upload: Option<usize>,
+    peer_download: Option<usize>,
+    extension_upload: HashMap<String, Mutex<RateLimiter>>,
+    extension_download: HashMap<String, Mutex<RateLimiter>>,
+}
+
+impl Bandwidth {
+    pub fn new(limits: &BandwidthLimits) -> Self {
+        let now = Instant::now();
+        let limiter = |bytes_per_second: usize| Mutex::new(RateLimiter::new(bytes_per_second, now));
+        Self {
+            upload: limits.upload.map(limiter),
+            download: limits.download.map(limiter),
+            peer_upload: limits.peer_upload,
+            peer_download: limits.peer_download,
+            extension_upload: limits
+                .extension_upload
+                .iter()
+                .map(|(name, limit)| (name.clone(), limiter(*limit)))
+                .collect(),
+            extension_download: limits
+                .extension_download
+                .iter()
+                .map(|(name, limit)| (name.clone(), limiter(*limit)))
+                .collect(),
+        }
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.upload.is_some()
+            || self.download.is_some()
+            || self.peer_upload.is_some()
+            || self.peer_download.is_some()
+            || !self.extension_upload.is_empty()
+            || !self.extension_download.is_empty()
+    }
+
+    /// Creates the budget of a new connection.
+    pub fn peer(bandwidth: &Arc<Self>) -> PeerBandwidth {
+        let now = Instant::now();
+        PeerBandwidth {
+            shared: Arc::clone(bandwidth),
+            upload: bandwidth.peer_upload.map(|limit| RateLimiter::new(limit, now)),
+            download: bandwidth.peer_download.map(|limit| RateLimiter::new(limit, now)),
+            paused_by: None,
+            read_paused: false,
+        }
+    }
+}
+
+fn is_available(limiter: Option<&Mutex<RateLimiter>>, now: Instant) -> bool {
+    limiter.map_or(true, |limiter| limiter.lock().is_available(now))
+}
+
+fn consume(limiter: Option<&Mutex<RateLimiter>>, bytes: usize, now: Instant) {
+    if let Some(limiter) = limiter {
+        limiter.lock().consume(bytes, now);
+    }
+}
+
+/// The budget of a connection, which also uses the shared limits.
+pub struct PeerBandwidth {
+    shared: Arc<Bandwidth>,
+    upload: Option<RateLimiter>,
+    download: Option<RateLimiter>,
+    /// The extension whose download limit stopped reading from the peer.
+    paused_by: Option<String>,
+    read_paused: bool,
+}
+
+impl PeerBandwidth {
+    /// Messages which don't belong to an extension are control messages, and they are never held back.
+    pub fn can_send(&mut self, extension_name: Option<&str>, priority: Priority) -> bool {
+        let extension_name = match extension_name {
+            Some(extension_name) => extension_name,
+            None => return true,
+        };
+        let now = Instant::now();
+        if !is_available(self.shared.extension_upload.get(extension_name), now) {
+            return false
+        }
+        if priority == Priority::High {
+            return true
+        }
+        is_available(self.shared.upload.as_ref(), now)
+            && self.upload.as_mut().map_or(true, |limiter| limiter.is_available(now))
+    }
+
+    pub fn on_sent(&mut self, extension_name: Option<&str>, bytes: usize) {
+        let now = Instant::now();
+        if let Some(extension_name) = extension_name {
+            consume(self.shared.extension_upload.get(extension_name), bytes, now);
+        }
+        consume(self.shared.upload.as_ref(), bytes, now);
+        if let Some(limiter) = self.upload.as_mut() {
+            limiter.consume(bytes, now);
+        }
+    }
+
+    pub fn can_receive(&mut self) -> bool {
+        let now = Instant::now();
+        if let Some(extension_name) = &self.paused_by {
+            if !is_available(self.shared.extension_download.get(extension_name), now) {
+                self.read_paused = true;
+                return false
+            }
+            self.paused_by = None;
+        }
+        self.read_paused = !is_available(self.shared.download.as_ref(), now)
+            || !self.download.as_mut().map_or(true, |limiter| limiter.is_available(now));
+        !self.read_paused
+    }
+
+    pub fn on_received(&mut self, bytes: usize) {
+        let now = Instant::now();
+        consume(self.shared.download.as_ref(), bytes, now);
+        if let Some(limiter) = self.download.as_mut() {
+            limiter.consume(bytes, now);
+        }
+    }
+
+    /// Reading from the peer stops while the extension is over its download limit,
+    /// because it's the peer that sends the traffic.
+    pub fn on_extension_received(&mut self, extension_name: &str, bytes: usize) {
+        let now = Instant::now();
+        if let Some(limiter) = self.shared.extension_download.get(extension_name) {
+            let mut limiter = limiter.lock();
+            limiter.consume(bytes, now);
+            if !limiter.is_available(now) {
+                self.paused_by = Some(extension_name.to_string());
+            }
+        }
+    }
+
+    pub fn is_read_paused(&self) -> bool {
+        self.read_paused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn limiter_allows_a_second_of_traffic() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1000, now);
+        assert!(limiter.is_available(now));
+        limiter.consume(1000, now);
+        assert!(!limiter.is_available(now));
+    }
+
+    #[test]
+    fn limiter_is_refilled_over_time() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1000, now);
+        limiter.consume(1500, now);
+        assert!(!limiter.is_available(now + Duration::from_millis(500)));
+        assert!(limiter.is_available(now + Duration::from_millis(501)));
+    }
+
+    #[test]
+    fn limiter_does_not_save_more_than_a_second() {
+        let now = Instant::now();
+        let mut limiter = RateLimiter::new(1000, now);
+        limiter.consume(0, now + Duration::from_secs(10));
+        limiter.consume(1001, now + Duration::from_secs(10));
+        assert!(!limiter.is_available(now + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn control_messages_are_not_limited() {
+        let bandwidth = Arc::new(Bandwidth::new(&BandwidthLimits {
+            upload: Some(10),
+            ..Default::default()
+        }));
+        let mut peer = Bandwidth::peer(&bandwidth);
+        peer.on_sent(None, 100);
+        assert!(peer.can_send(None, Priority::Low));
+        assert!(!peer.can_send(Some("block-propagation"), Priority::Normal));
+    }
+
+    #[test]
+    fn high_priority_ignores_the_shared_limit_but_uses_it_up() {
+        let bandwidth = Arc::new(Bandwidth::new(&BandwidthLimits {
+            upload: Some(100),
+            ..Default::default()
+        }));
+        let mut validator = Bandwidth::peer(&bandwidth);
+        let mut syncing = Bandwidth::peer(&bandwidth);
+        assert!(syncing.can_send(Some("block-propagation"), Priority::Normal));
+        syncing.on_sent(Some("block-propagation"), 100);
+
+        assert!(validator.can_send(Some("tendermint"), Priority::High));
+        validator.on_sent(Some("tendermint"), 10);
+        assert!(!syncing.can_send(Some("block-propagation"), Priority::Normal));
+    }
+
+    #[test]
+    fn peer_limit_does_not_affect_other_peers() {
+        let bandwidth = Arc::new(Bandwidth::new(&BandwidthLimits {
+            peer_upload: Some(100),
+            ..Default::default()
+        }));
+        let mut first = Bandwidth::peer(&bandwidth);
+        let mut second = Bandwidth::peer(&bandwidth);
+        first.on_sent(Some("block-propagation"), 100);
+        assert!(!first.can_send(Some("block-propagation"), Priority::Normal));
+        assert!(second.can_send(Some("block-propagation"), Priority::Normal));
+    }
+
+    #[test]
+    fn extension_limit_applies_to_the_extension_only() {
+        let mut extension_upload = HashMap::new();
+        extension_upload.insert("transaction-propagation".to_string(), 100);
+        let bandwidth = Arc::new(Bandwidth::new(&BandwidthLimits {
+            extension_upload,
+            ..Default::default()
+        }));
+        let mut peer = Bandwidth::peer(&bandwidth);
+        peer.on_sent(Some("transaction-propagation"), 100);
+        assert!(!peer.can_send(Some("transaction-propagation"), Priority::Low));
+        assert!(peer.can_send(Some("block-propagation"), Priority::Normal));
+    }
+
+    #[test]
+    fn extension_over_download_limit_pauses_reading() {
+        let mut extension_download = HashMap::new();
+        extension_download.insert("block-propagation".to_string(), 100);
+        let bandwidth = Arc::new(Bandwidth::new(&BandwidthLimits {
+            extension_download,
+            ..Default::default()
+        }));
+        let mut peer = Bandwidth::peer(&bandwidth);
+        assert!(peer.can_receive());
+        peer.on_extension_received("block-propagation", 200);
+        assert!(!peer.can_receive());
+        assert!(peer.is_read_paused());
+    }
+}
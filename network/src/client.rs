@@ -14,7 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::bandwidth::Priority;
 use crate::p2p::Message as P2pMessage;
+use crate::reputation::Behavior;
 use crate::{Api, IntoSocketAddr, NetworkExtension, NetworkExtensionResult, NodeId};
 use cio::IoChannel;
 use crossbeam_channel as crossbeam;
@@ -31,11 +33,13 @@ struct ClientApi {
     timer: TimerApi,
     name: &'static str,
     need_encryption: bool,
+    priority: Priority,
 }
 
 impl Api for ClientApi {
     fn send(&self, id: &NodeId, data: Arc<Bytes>) {
         let need_encryption = self.need_encryption;
+        let priority = self.priority;
         let extension_name = self.name;
         let node_id = *id;
         let bytes = data.len();
@@ -43,6 +47,7 @@ impl Api for ClientApi {
             node_id,
             extension_name,
             need_encryption,
+            priority,
             data,
         }) {
             cerror!(
@@ -58,6 +63,16 @@ impl Api for ClientApi {
         }
     }
 
+    fn report(&self, id: &NodeId, behavior: Behavior) {
+        ctrace!(NETAPI, "`{}` reports {:?} of {}", self.name, behavior, id.into_addr());
+        if let Err(err) = self.p2p_channel.send(P2pMessage::Report {
+            node_id: *id,
+            behavior,
+        }) {
+            cerror!(NETAPI, "`{}` cannot report {:?} of {} : {:?}", self.name, behavior, id.into_addr(), err);
+        }
+    }
+
     fn set_timer(&self, token: TimerToken, duration: Duration) -> NetworkExtensionResult<()> {
         self.timer.schedule_repeat(duration, token)?;
         Ok(())
@@ -131,6 +146,7 @@ impl Client {
                     let api = ClientApi {
                         name,
                         need_encryption: T::need_encryption(),
+                        priority: T::priority(),
                         p2p_channel,
                         timer,
                     };
@@ -287,6 +303,10 @@ mod tests {
             unimplemented!()
         }
 
+        fn report(&self, _id: &NodeId, _behavior: Behavior) {
+            unimplemented!()
+        }
+
         fn set_timer(&self, _timer_id: usize, _duration: Duration) -> NetworkExtensionResult<()> {
             unimplemented!()
         }
@@ -0,0 +1,118 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::fmt;
+
+/// Extension messages smaller than this are sent without compression.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+/// Compressed messages that would expand beyond this size are rejected.
+const MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+const SNAPPY_ID: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Compression {
+    Snappy,
+}
+
+impl Compression {
+    /// The algorithms this node supports, in the order of preference.
+    pub fn supported() -> Vec<Compression> {
+        vec![Compression::Snappy]
+    }
+
+    /// Picks the most preferred algorithm among the ones the peer offered.
+    pub fn select(offered: &[Compression]) -> Option<Compression> {
+        Self::supported().into_iter().find(|compression| offered.contains(compression))
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            SNAPPY_ID => Some(Compression::Snappy),
+            _ => None,
+        }
+    }
+
+    pub fn id(self) -> u8 {
+        match self {
+            Compression::Snappy => SNAPPY_ID,
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::Snappy => {
+                snap::Encoder::new().compress_vec(data).expect("Compressing a byte slice in memory never fails")
+            }
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            Compression::Snappy => {
+                let len = snap::decompress_len(data).map_err(|err| err.to_string())?;
+                if len > MAX_DECOMPRESSED_SIZE {
+                    return Err(format!("The decompressed message is too large: {} bytes", len))
+                }
+                snap::Decoder::new().decompress_vec(data).map_err(|err| err.to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compression::Snappy => write!(f, "snappy"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_round_trips() {
+        for compression in Compression::supported() {
+            assert_eq!(Some(compression), Compression::from_id(compression.id()));
+        }
+    }
+
+    #[test]
+    fn unknown_id_is_ignored() {
+        assert_eq!(None, Compression::from_id(0xFF));
+    }
+
+    #[test]
+    fn select_only_offered_algorithms() {
+        assert_eq!(None, Compression::select(&[]));
+        assert_eq!(Some(Compression::Snappy), Compression::select(&[Compression::Snappy]));
+    }
+
+    #[test]
+    fn snappy_round_trips() {
+        let data = vec![0x42; COMPRESSION_THRESHOLD * 4];
+        let compressed = Compression::Snappy.compress(&data);
+        assert!(compressed.len() < data.len());
+        assert_eq!(Ok(data), Compression::Snappy.decompress(&compressed));
+    }
+
+    #[test]
+    fn decompressing_garbage_fails() {
+        assert!(Compression::Snappy.decompress(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]).is_err());
+    }
+}
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::bandwidth::BandwidthLimits;
 use crate::filters::FilterEntry;
 use crate::SocketAddr;
 
@@ -25,4 +26,7 @@ pub struct Config {
     pub max_peers: usize,
     pub whitelist: Vec<FilterEntry>,
     pub blacklist: Vec<FilterEntry>,
+    pub bandwidth_limits: BandwidthLimits,
+    /// Whether to connect to the peers which don't know the authenticated handshake.
+    pub legacy_handshake: bool,
 }
@@ -15,7 +15,9 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::addr::SocketAddr;
+use crate::address_book::AddressEntry;
 use crate::filters::FilterEntry;
+use crate::reputation::Reputation;
 use cidr::IpCidr;
 use ckey::Public;
 use std::collections::HashMap;
@@ -48,6 +50,11 @@ pub trait Control: Send + Sync {
     fn get_blacklist(&self) -> Result<(Vec<FilterEntry>, bool), Error>;
 
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, Error>;
+
+    fn reputations(&self) -> Result<Vec<(IpAddr, Reputation)>, Error>;
+
+    fn address_book(&self) -> Result<Vec<(SocketAddr, AddressEntry)>, Error>;
+    fn remove_from_address_book(&self, addr: &SocketAddr) -> Result<bool, Error>;
 }
 
 #[derive(Clone, Debug)]
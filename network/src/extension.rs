@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::bandwidth::Priority;
+use crate::reputation::Behavior;
 use crate::NodeId;
 use cio::IoError;
 use ctimer::{TimerScheduleError, TimerToken};
@@ -48,6 +50,8 @@ pub type Result<T> = result::Result<T, Error>;
 
 pub trait Api {
     fn send(&self, node: &NodeId, message: Arc<Bytes>);
+    /// Reports the behavior of the node to adjust its reputation.
+    fn report(&self, node: &NodeId, behavior: Behavior);
 
     fn set_timer(&self, timer: TimerToken, d: Duration) -> Result<()>;
     fn set_timer_once(&self, timer: TimerToken, d: Duration) -> Result<()>;
@@ -58,6 +62,10 @@ pub trait Extension<Event: Send> {
     fn name() -> &'static str;
     fn need_encryption() -> bool;
     fn versions() -> &'static [u64];
+    /// The messages with a higher priority are sent first when the bandwidth is limited.
+    fn priority() -> Priority {
+        Priority::Normal
+    }
 
     fn on_node_added(&mut self, _node: &NodeId, _version: u64) {}
     fn on_node_removed(&mut self, _node: &NodeId) {}
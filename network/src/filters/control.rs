@@ -33,5 +33,9 @@ pub trait Control: Send + Sync {
     fn get_whitelist(&self) -> (Vec<FilterEntry>, bool);
     fn get_blacklist(&self) -> (Vec<FilterEntry>, bool);
 
+    /// Temporarily blocks the address regardless of the blacklist. The whitelisted addresses are not banned.
+    fn ban(&self, addr: IpAddr);
+    fn unban(&self, addr: &IpAddr);
+
     fn is_allowed(&self, addr: &IpAddr) -> bool;
 }
@@ -18,6 +18,7 @@ use super::control::Control;
 use super::filter::{Filter, FilterEntry};
 use cidr::IpCidr;
 use parking_lot::RwLock;
+use std::collections::HashSet;
 use std::net::IpAddr;
 use std::sync::Arc;
 
@@ -25,6 +26,7 @@ use std::sync::Arc;
 pub struct Filters {
     whitelist: RwLock<Filter>,
     blacklist: RwLock<Filter>,
+    banned: RwLock<HashSet<IpAddr>>,
 }
 
 impl Filters {
@@ -35,8 +37,14 @@ impl Filters {
         Arc::new(Self {
             whitelist: RwLock::new(whitelist),
             blacklist: RwLock::new(blacklist),
+            banned: Default::default(),
         })
     }
+
+    fn is_whitelisted(&self, addr: &IpAddr) -> bool {
+        let whitelist = self.whitelist.read();
+        whitelist.is_enabled() && whitelist.contains(addr)
+    }
 }
 
 impl Control for Filters {
@@ -98,11 +106,31 @@ impl Control for Filters {
         blacklist.status()
     }
 
+    fn ban(&self, addr: IpAddr) {
+        if self.is_whitelisted(&addr) {
+            cinfo!(NETFILTER, "{:?} is not banned since it is whitelisted", addr);
+            return
+        }
+        if self.banned.write().insert(addr) {
+            cinfo!(NETFILTER, "{:?} is banned", addr);
+        }
+    }
+
+    fn unban(&self, addr: &IpAddr) {
+        if self.banned.write().remove(addr) {
+            cinfo!(NETFILTER, "{:?} is unbanned", addr);
+        }
+    }
+
     fn is_allowed(&self, addr: &IpAddr) -> bool {
         let whitelist = self.whitelist.read();
         let blacklist = self.blacklist.read();
 
-        if whitelist.is_enabled() && !whitelist.contains(addr) {
+        if whitelist.is_enabled() {
+            if !whitelist.contains(addr) {
+                return false
+            }
+        } else if self.banned.read().contains(addr) {
             return false
         }
 
@@ -112,3 +140,32 @@ impl Control for Filters {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn whitelisted_addresses_are_not_banned() {
+        let whitelisted = IpAddr::from_str("1.2.3.4").unwrap();
+        let other = IpAddr::from_str("1.2.3.5").unwrap();
+        let filters = Filters::new(Vec::new(), Vec::new());
+        filters.ban(whitelisted);
+        filters.add_to_whitelist(IpCidr::from_str("1.2.3.4").unwrap(), None);
+        filters.enable_whitelist();
+
+        assert!(filters.is_allowed(&whitelisted));
+
+        filters.ban(other);
+        filters.disable_whitelist();
+        assert!(!filters.is_allowed(&other));
+        assert!(!filters.is_allowed(&whitelisted));
+
+        filters.unban(&whitelisted);
+        filters.enable_whitelist();
+        filters.ban(whitelisted);
+        filters.disable_whitelist();
+        assert!(filters.is_allowed(&whitelisted));
+    }
+}
@@ -38,16 +38,21 @@ extern crate rlp;
 extern crate rlp_derive;
 extern crate kvdb;
 extern crate never_type;
+extern crate snap;
 extern crate table as ctable;
 extern crate time;
 extern crate token_generator;
 
 mod addr;
+mod address_book;
+mod bandwidth;
 mod client;
+mod compression;
 mod config;
 mod extension;
 mod filters;
 mod node_id;
+mod reputation;
 mod routing_table;
 mod service;
 mod stream;
@@ -57,12 +62,15 @@ mod p2p;
 pub mod session;
 
 pub use crate::addr::SocketAddr;
+pub use crate::address_book::AddressEntry;
+pub use crate::bandwidth::{BandwidthLimits, Priority as MessagePriority};
 pub use crate::config::Config as NetworkConfig;
 pub use crate::control::{Control as NetworkControl, Error as NetworkControlError};
 pub use crate::extension::{
     Api, Error as NetworkExtensionError, Extension as NetworkExtension, Result as NetworkExtensionResult,
 };
 pub use crate::node_id::{IntoSocketAddr, NodeId};
+pub use crate::reputation::{Behavior as PeerBehavior, Reputation as PeerReputation};
 pub use crate::service::{Error as NetworkServiceError, Service as NetworkService};
 
 pub use self::p2p::{Handler, ManagingPeerdb};
@@ -18,7 +18,9 @@ use super::super::message::{Message, Version};
 use super::super::stream::SignedStream;
 use super::super::{ExtensionMessage, NegotiationMessage};
 use super::Result;
-use crate::session::Session;
+use crate::bandwidth::{PeerBandwidth, Priority};
+use crate::compression::{Compression, COMPRESSION_THRESHOLD};
+use crate::session::Transport;
 use crate::stream::Stream;
 use crate::SocketAddr;
 use cio::IoManager;
@@ -26,19 +28,28 @@ use mio::deprecated::EventLoop;
 use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
 use primitives::Bytes;
+use std::collections::HashMap;
 use std::io;
 use std::sync::Arc;
 
+/// The sizes of an extension message that was compressed before sending.
+pub struct CompressedSize {
+    pub original: usize,
+    pub compressed: usize,
+}
+
 pub struct EstablishedConnection {
     stream: SignedStream,
     peer_addr: SocketAddr,
+    compressions: HashMap<String, Compression>,
 }
 
 impl EstablishedConnection {
-    pub fn new(stream: Stream, session: Session, peer_addr: SocketAddr) -> Self {
+    pub fn new(stream: Stream, transport: Transport, peer_addr: SocketAddr, bandwidth: PeerBandwidth) -> Self {
         Self {
-            stream: SignedStream::new(stream, session),
+            stream: SignedStream::new(stream, transport, bandwidth),
             peer_addr,
+            compressions: HashMap::new(),
         }
     }
 
@@ -47,26 +58,69 @@ impl EstablishedConnection {
     }
 
     pub fn enqueue_negotiation_request(&mut self, name: String, extension_versions: Vec<Version>) -> usize {
-        self.write(&Message::Negotiation(NegotiationMessage::request(name, extension_versions)))
+        // Peers using the legacy transport don't understand the compression negotiation.
+        let compressions = match self.stream.transport() {
+            Transport::Legacy(_) => Vec::new(),
+            Transport::Secure(_) => Compression::supported(),
+        };
+        self.write(&Message::Negotiation(NegotiationMessage::request(name, extension_versions, compressions)))
+    }
+
+    /// Selects a compression among the offered ones and uses it for the extension's messages.
+    pub fn enqueue_negotiation_response(&mut self, name: String, version: u64, offered: &[Compression]) -> usize {
+        let compression = match self.stream.transport() {
+            Transport::Legacy(_) => None,
+            Transport::Secure(_) => Compression::select(offered),
+        };
+        if let Some(compression) = compression {
+            self.compressions.insert(name.clone(), compression);
+        }
+        self.write(&Message::Negotiation(NegotiationMessage::allowed(name, version, compression)))
     }
 
-    pub fn enqueue_negotiation_response(&mut self, name: String, version: u64) -> usize {
-        self.write(&Message::Negotiation(NegotiationMessage::allowed(name, version)))
+    pub fn set_compression(&mut self, name: String, compression: Compression) -> ::std::result::Result<(), String> {
+        match self.stream.transport() {
+            Transport::Legacy(_) => Err(format!("{} is negotiated for {} on the legacy transport", compression, name)),
+            Transport::Secure(_) => {
+                self.compressions.insert(name, compression);
+                Ok(())
+            }
+        }
     }
 
     pub fn enqueue_extension_message(
         &mut self,
         extension_name: String,
         need_encryption: bool,
+        priority: Priority,
         message: Arc<Bytes>,
-    ) -> Result<usize> {
-        let message = if need_encryption {
-            ExtensionMessage::encrypted_from_unencrypted_data(extension_name, &message, self.stream.session())?
-        } else {
-            ExtensionMessage::unencrypted(extension_name, message)
+    ) -> Result<(usize, Option<CompressedSize>)> {
+        let original = message.len();
+        let compression = self.compressions.get(&extension_name).copied();
+        let message = match (self.stream.transport(), compression) {
+            (Transport::Legacy(session), _) if need_encryption => {
+                ExtensionMessage::encrypted_from_unencrypted_data(extension_name, &message, session)?
+            }
+            (_, Some(compression)) if original >= COMPRESSION_THRESHOLD => {
+                ExtensionMessage::compressed(extension_name, compression, message)
+            }
+            // The secure transport encrypts all messages.
+            _ => ExtensionMessage::unencrypted(extension_name, message),
+        };
+        let compressed_size = match &message {
+            ExtensionMessage::Compressed {
+                compressed,
+                ..
+            } => Some(CompressedSize {
+                original,
+                compressed: compressed.len(),
+            }),
+            _ => None,
         };
 
-        Ok(self.write(&Message::Extension(message)))
+        let extension_name = message.extension_name().to_string();
+        let size = self.stream.write_extension(&Message::Extension(message), extension_name, priority);
+        Ok((size, compressed_size))
     }
 
     fn interest(&self) -> Ready {
@@ -83,11 +137,29 @@ impl EstablishedConnection {
     }
 
     pub fn receive(&mut self) -> Result<Option<Message>> {
-        Ok(self.stream.read()?)
+        let (message, size) = match self.stream.read()? {
+            Some(received) => received,
+            None => return Ok(None),
+        };
+        if let Message::Extension(extension_message) = &message {
+            self.stream.on_extension_received(extension_message.extension_name(), size);
+        }
+        Ok(Some(message))
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.stream.is_throttled()
+    }
+
+    pub fn unencrypted_data(&self, message: &ExtensionMessage) -> ::std::result::Result<Arc<Bytes>, String> {
+        match self.stream.transport() {
+            Transport::Legacy(session) => message.unencrypted_data(Some(session)),
+            Transport::Secure(_) => message.unencrypted_data(None),
+        }
     }
 
-    pub fn session(&self) -> &Session {
-        self.stream.session()
+    pub fn transport(&self) -> &Transport {
+        self.stream.transport()
     }
 
     pub fn register<Message>(&self, reg: Token, event_loop: &mut EventLoop<IoManager<Message>>) -> io::Result<()>
@@ -15,7 +15,8 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
-use crate::session::Session;
+use crate::bandwidth::PeerBandwidth;
+use crate::session::{Transport, Welcome};
 use crate::stream::Stream;
 use crate::SocketAddr;
 use cio::IoManager;
@@ -37,9 +38,9 @@ impl IncomingConnection {
         }
     }
 
-    pub fn establish(self, session: Session, port: u16) -> Result<EstablishedConnection> {
+    pub fn establish(self, transport: Transport, bandwidth: PeerBandwidth, port: u16) -> Result<EstablishedConnection> {
         let peer_addr = SocketAddr::new(self.stream.peer_addr()?.ip(), port);
-        Ok(EstablishedConnection::new(self.stream, session, peer_addr))
+        Ok(EstablishedConnection::new(self.stream, transport, peer_addr, bandwidth))
     }
 
     fn interest(&self) -> Ready {
@@ -53,6 +54,14 @@ impl IncomingConnection {
         })
     }
 
+    pub fn send_welcome(&mut self, recipient_static: Public, welcome: Welcome) -> usize {
+        self.stream.write(&IncomingMessage::Welcome {
+            recipient_ephemeral: welcome.ephemeral,
+            recipient_static,
+            signature: welcome.signature,
+        })
+    }
+
     pub fn send_nack(&mut self) -> usize {
         self.stream.write(&IncomingMessage::Nack)
     }
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use ckey::{NetworkId, Public};
+use ckey::{NetworkId, Public, SchnorrSignature};
 use primitives::Bytes;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
@@ -31,6 +31,13 @@ pub enum OutgoingMessage {
         network_id: NetworkId,
         initiator_port: u16,
     },
+    Hello {
+        initiator_ephemeral: Public,
+        initiator_static: Public,
+        network_id: NetworkId,
+        initiator_port: u16,
+        signature: SchnorrSignature,
+    },
 }
 
 #[derive(Debug, PartialEq)]
@@ -40,12 +47,19 @@ pub enum IncomingMessage {
         encrypted_nonce: Bytes,
     },
     Nack,
+    Welcome {
+        recipient_ephemeral: Public,
+        recipient_static: Public,
+        signature: SchnorrSignature,
+    },
 }
 
 const SYNC1_ID: u8 = 0x01;
 const SYNC2_ID: u8 = 0x02;
 const ACK_ID: u8 = 0x03;
 const NACK_ID: u8 = 0x04;
+const HELLO_ID: u8 = 0x05;
+const WELCOME_ID: u8 = 0x06;
 
 impl Encodable for OutgoingMessage {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -70,6 +84,21 @@ impl Encodable for OutgoingMessage {
                     .append(network_id)
                     .append(initiator_port);
             }
+            OutgoingMessage::Hello {
+                initiator_ephemeral,
+                initiator_static,
+                network_id,
+                initiator_port,
+                signature,
+            } => {
+                s.begin_list(6)
+                    .append(&HELLO_ID)
+                    .append(initiator_ephemeral)
+                    .append(initiator_static)
+                    .append(network_id)
+                    .append(initiator_port)
+                    .append(signature);
+            }
         }
     }
 }
@@ -106,6 +135,22 @@ impl Decodable for OutgoingMessage {
                     initiator_port: rlp.val_at(4)?,
                 })
             }
+            HELLO_ID => {
+                let item_count = rlp.item_count()?;
+                if item_count != 6 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 6,
+                        got: item_count,
+                    })
+                }
+                Ok(OutgoingMessage::Hello {
+                    initiator_ephemeral: rlp.val_at(1)?,
+                    initiator_static: rlp.val_at(2)?,
+                    network_id: rlp.val_at(3)?,
+                    initiator_port: rlp.val_at(4)?,
+                    signature: rlp.val_at(5)?,
+                })
+            }
             _ => Err(DecoderError::Custom("Invalid id")),
         }
     }
@@ -123,6 +168,17 @@ impl Encodable for IncomingMessage {
             IncomingMessage::Nack => {
                 s.begin_list(1).append(&NACK_ID);
             }
+            IncomingMessage::Welcome {
+                recipient_ephemeral,
+                recipient_static,
+                signature,
+            } => {
+                s.begin_list(4)
+                    .append(&WELCOME_ID)
+                    .append(recipient_ephemeral)
+                    .append(recipient_static)
+                    .append(signature);
+            }
         }
     }
 }
@@ -153,6 +209,20 @@ impl Decodable for IncomingMessage {
                 }
                 Ok(IncomingMessage::Nack)
             }
+            WELCOME_ID => {
+                let item_count = rlp.item_count()?;
+                if item_count != 4 {
+                    return Err(DecoderError::RlpInvalidLength {
+                        expected: 4,
+                        got: item_count,
+                    })
+                }
+                Ok(IncomingMessage::Welcome {
+                    recipient_ephemeral: rlp.val_at(1)?,
+                    recipient_static: rlp.val_at(2)?,
+                    signature: rlp.val_at(3)?,
+                })
+            }
             _ => Err(DecoderError::Custom("Invalid id")),
         }
     }
@@ -195,4 +265,24 @@ mod tests {
     fn encode_and_decode_nack() {
         rlp_encode_and_decode_test!(IncomingMessage::Nack);
     }
+
+    #[test]
+    fn encode_and_decode_hello() {
+        rlp_encode_and_decode_test!(OutgoingMessage::Hello {
+            initiator_ephemeral: Public::random(),
+            initiator_static: Public::random(),
+            network_id: "ab".into(),
+            initiator_port: 3100,
+            signature: SchnorrSignature::random(),
+        });
+    }
+
+    #[test]
+    fn encode_and_decode_welcome() {
+        rlp_encode_and_decode_test!(IncomingMessage::Welcome {
+            recipient_ephemeral: Public::random(),
+            recipient_static: Public::random(),
+            signature: SchnorrSignature::random(),
+        });
+    }
 }
@@ -25,7 +25,7 @@ use std::fmt;
 use std::io;
 use std::result;
 
-pub use self::established::EstablishedConnection;
+pub use self::established::{CompressedSize, EstablishedConnection};
 pub use self::incoming::IncomingConnection;
 pub use self::message::{IncomingMessage, OutgoingMessage};
 pub use self::outgoing::OutgoingConnection;
@@ -40,6 +40,7 @@ pub enum Error {
     IoError(io::Error),
     Decoder(DecoderError),
     InvalidSign,
+    QueueOverflow,
 }
 
 impl fmt::Display for Error {
@@ -49,6 +50,7 @@ impl fmt::Display for Error {
             Error::Decoder(err) => err.fmt(f),
             Error::IoError(err) => err.fmt(f),
             Error::InvalidSign => write!(f, "Invalid signature"),
+            Error::QueueOverflow => write!(f, "Too many messages are queued"),
         }
     }
 }
@@ -74,6 +76,7 @@ impl From<P2pStreamError> for Error {
             P2pStreamError::IoError(err) => Error::IoError(err),
             P2pStreamError::DecoderError(err) => Error::Decoder(err),
             P2pStreamError::InvalidSign => Error::InvalidSign,
+            P2pStreamError::QueueOverflow => Error::QueueOverflow,
         }
     }
 }
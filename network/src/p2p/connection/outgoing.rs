@@ -15,11 +15,12 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::{EstablishedConnection, IncomingMessage, OutgoingMessage, Result};
-use crate::session::Session;
+use crate::bandwidth::PeerBandwidth;
+use crate::session::{Initiator, SecureSession, Transport};
 use crate::stream::Stream;
 use crate::SocketAddr;
 use cio::IoManager;
-use ckey::{NetworkId, Public};
+use ckey::{KeyPair, NetworkId, Public, SchnorrSignature};
 use mio::deprecated::EventLoop;
 use mio::unix::UnixReady;
 use mio::{PollOpt, Ready, Token};
@@ -31,6 +32,9 @@ pub struct OutgoingConnection {
     network_id: NetworkId,
     initiator_port: u16,
     peer_addr: SocketAddr,
+    /// The node key if the connection uses the authenticated handshake
+    node_key: Option<KeyPair>,
+    initiator: Option<Initiator>,
 }
 
 impl OutgoingConnection {
@@ -40,6 +44,7 @@ impl OutgoingConnection {
         network_id: NetworkId,
         initiator_port: u16,
         peer_addr: SocketAddr,
+        node_key: Option<KeyPair>,
     ) -> Result<Self> {
         Ok(Self {
             stream,
@@ -47,9 +52,15 @@ impl OutgoingConnection {
             network_id,
             initiator_port,
             peer_addr,
+            node_key,
+            initiator: None,
         })
     }
 
+    pub fn is_secure(&self) -> bool {
+        self.node_key.is_some()
+    }
+
     fn interest(&self) -> Ready {
         Ready::writable() | Ready::readable() | UnixReady::hup()
     }
@@ -71,6 +82,32 @@ impl OutgoingConnection {
         }
     }
 
+    /// Sends `Hello` with a new ephemeral key.
+    pub fn send_hello(&mut self) -> ::std::result::Result<usize, String> {
+        let node_key = self.node_key.as_ref().ok_or_else(|| "The connection uses the legacy handshake".to_string())?;
+        let initiator = Initiator::new(node_key, &self.network_id, self.initiator_port)?;
+        let size = self.stream.write(&OutgoingMessage::Hello {
+            initiator_ephemeral: *initiator.ephemeral(),
+            initiator_static: *node_key.public(),
+            network_id: self.network_id,
+            initiator_port: self.initiator_port,
+            signature: *initiator.signature(),
+        });
+        self.initiator = Some(initiator);
+        Ok(size)
+    }
+
+    pub fn finish_handshake(
+        &mut self,
+        recipient_ephemeral: &Public,
+        recipient_static: &Public,
+        signature: &SchnorrSignature,
+    ) -> ::std::result::Result<SecureSession, String> {
+        let initiator =
+            self.initiator.take().ok_or_else(|| format!("Welcome from {} without hello", self.peer_addr))?;
+        initiator.finish(recipient_ephemeral, recipient_static, signature)
+    }
+
     pub fn flush(&mut self) -> Result<()> {
         self.stream.flush()?;
         Ok(())
@@ -84,9 +121,9 @@ impl OutgoingConnection {
         &self.peer_addr
     }
 
-    pub fn establish(self, session: Session) -> Result<EstablishedConnection> {
+    pub fn establish(self, transport: Transport, bandwidth: PeerBandwidth) -> Result<EstablishedConnection> {
         let peer_addr = self.stream.peer_addr()?;
-        Ok(EstablishedConnection::new(self.stream, session, peer_addr))
+        Ok(EstablishedConnection::new(self.stream, transport, peer_addr, bandwidth))
     }
 
     pub fn register<Message>(&self, reg: Token, event_loop: &mut EventLoop<IoManager<Message>>) -> io::Result<()>
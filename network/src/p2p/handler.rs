@@ -15,18 +15,22 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::connection::{
-    EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection, OutgoingMessage,
+    CompressedSize, EstablishedConnection, IncomingConnection, IncomingMessage, OutgoingConnection, OutgoingMessage,
 };
+use super::legacy_peers::{rejects_hello, LegacyPeers};
 use super::listener::Listener;
 use super::{NegotiationMessage, NetworkMessage};
+use crate::address_book::{AddressBook, AddressEntry};
+use crate::bandwidth::{Bandwidth, BandwidthLimits, Priority};
 use crate::client::Client;
 use crate::p2p::connection::Error as P2PConnectionError;
-use crate::session::Session;
+use crate::reputation::{unix_now, Behavior, Reputation, Reputations};
+use crate::session::{respond, Transport};
 use crate::stream::Stream;
-use crate::{FiltersControl, NodeId, RoutingTable, SocketAddr};
+use crate::{FiltersControl, IntoSocketAddr, NodeId, RoutingTable, SocketAddr};
 use ccrypto::error::SymmError;
 use cio::{IoChannel, IoContext, IoHandler, IoHandlerResult, IoManager, StreamToken, TimerToken};
-use ckey::NetworkId;
+use ckey::{KeyPair, NetworkId};
 use finally_block::finally;
 use mio::deprecated::EventLoop;
 use mio::{PollOpt, Ready, Token};
@@ -37,6 +41,7 @@ use rand::rngs::OsRng;
 use rand::Rng;
 use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::iter::FromIterator;
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -63,6 +68,8 @@ const LAST_OUTGOING: StreamToken = FIRST_OUTGOING + MAX_OUTGOING_CONNECTIONS - 1
 
 const CREATE_CONNECTIONS: TimerToken = 0;
 const CONNECT_TO_BOOTSTRAP: TimerToken = CREATE_CONNECTIONS + 1;
+const LIFT_EXPIRED_BANS: TimerToken = CONNECT_TO_BOOTSTRAP + 1;
+const RESUME_THROTTLED: TimerToken = LIFT_EXPIRED_BANS + 1;
 
 const FIRST_WAIT_SYNC: TimerToken = FIRST_INCOMING;
 const LAST_WAIT_SYNC: TimerToken = LAST_INCOMING;
@@ -74,14 +81,24 @@ const FIRST_TRY_SYNC: TimerToken = FIRST_OUTGOING + 1000;
 const LAST_TRY_SYNC: TimerToken = LAST_OUTGOING + 1000;
 
 const CREATE_CONNECTION_INTERVAL: Duration = Duration::from_secs(3);
+const LIFT_EXPIRED_BANS_INTERVAL: Duration = Duration::from_secs(60);
+const RESUME_THROTTLED_INTERVAL: Duration = Duration::from_millis(100);
 
 const RETRY_SYNC_MAX: Duration = Duration::from_secs(10); // T1
 const RTT: Duration = Duration::from_secs(10); // T2
 const WAIT_SYNC: Duration = Duration::from_secs(30); // T3 >> T1 + RTT
 
+const COMPRESSED_USAGE_PREFIX: &str = "compressed::";
+const UNCOMPRESSED_USAGE_PREFIX: &str = "uncompressed::";
+const COMPRESSION_RATIO_PREFIX: &str = "compression-ratio::";
+
 pub trait ManagingPeerdb: Send + Sync {
-    fn insert(&self, key: SocketAddr);
+    fn insert(&self, key: SocketAddr, entry: AddressEntry);
     fn delete(&self, key: &SocketAddr);
+    fn addresses(&self) -> Vec<(SocketAddr, AddressEntry)>;
+
+    fn reputations(&self) -> Vec<(IpAddr, Reputation)>;
+    fn update_reputation(&self, key: IpAddr, reputation: Reputation);
 }
 
 pub struct Handler {
@@ -102,8 +119,11 @@ pub struct Handler {
     incoming_tokens: Mutex<TokenGenerator>,
     outgoing_tokens: Mutex<TokenGenerator>,
 
-    establishing_incoming_session: Mutex<HashMap<StreamToken, (u16, Session)>>,
-    establishing_outgoing_session: Mutex<HashMap<StreamToken, Session>>,
+    establishing_incoming_session: Mutex<HashMap<StreamToken, (u16, Transport)>>,
+    establishing_outgoing_session: Mutex<HashMap<StreamToken, Transport>>,
+
+    node_key: KeyPair,
+    legacy_peers: RwLock<LegacyPeers>,
 
     routing_table: Arc<RoutingTable>,
     filters: Arc<dyn FiltersControl>,
@@ -116,14 +136,18 @@ pub struct Handler {
     bootstrap_addresses: Vec<SocketAddr>,
 
     network_usage_in_10_seconds: Mutex<HashMap<String, VecDeque<(Instant, usize)>>>,
+    bandwidth: Arc<Bandwidth>,
 
     min_peers: usize,
     max_peers: usize,
     peer_db: Box<dyn (ManagingPeerdb)>,
+    address_book: Mutex<AddressBook>,
+    reputations: Mutex<Reputations>,
     rng: Mutex<OsRng>,
 }
 
 impl Handler {
+    #[allow(clippy::too_many_arguments)]
     pub fn try_new(
         channel: IoChannel<Message>,
         network_id: NetworkId,
@@ -135,10 +159,18 @@ impl Handler {
         min_peers: usize,
         max_peers: usize,
         peer_db: Box<dyn ManagingPeerdb>,
+        node_key: KeyPair,
+        bandwidth_limits: &BandwidthLimits,
+        legacy_handshake: bool,
     ) -> ::std::result::Result<Self, String> {
         if MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS < max_peers {
             return Err(format!("Max peers must be less than {}", MAX_INBOUND_CONNECTIONS + MAX_OUTBOUND_CONNECTIONS))
         }
+        let address_book = AddressBook::new(peer_db.addresses());
+        let reputations = Reputations::new(peer_db.reputations());
+        for banned in reputations.banned(unix_now()) {
+            filters.ban(banned);
+        }
         Ok(Self {
             connecting_lock: Default::default(),
             channel,
@@ -160,6 +192,9 @@ impl Handler {
             establishing_incoming_session: Default::default(),
             establishing_outgoing_session: Default::default(),
 
+            node_key,
+            legacy_peers: RwLock::new(LegacyPeers::new(legacy_handshake)),
+
             routing_table,
             filters,
 
@@ -169,11 +204,14 @@ impl Handler {
             client,
 
             network_usage_in_10_seconds: Default::default(),
+            bandwidth: Arc::new(Bandwidth::new(bandwidth_limits)),
 
             bootstrap_addresses,
             min_peers,
             max_peers,
             peer_db,
+            address_book: Mutex::new(address_book),
+            reputations: Mutex::new(reputations),
             rng: Mutex::new(OsRng::new().unwrap()),
         })
     }
@@ -221,8 +259,19 @@ impl Handler {
             let mut outgoing_connections = self.outgoing_connections.write();
             // Please make sure there is no early return after it.
             let initiator_port = self.socket_address.port();
-            let con =
-                OutgoingConnection::new(stream, initiator_pub_key, self.network_id, initiator_port, socket_address)?;
+            let node_key = if self.legacy_peers.write().uses_legacy(&socket_address, Instant::now()) {
+                None
+            } else {
+                Some(self.node_key)
+            };
+            let con = OutgoingConnection::new(
+                stream,
+                initiator_pub_key,
+                self.network_id,
+                initiator_port,
+                socket_address,
+                node_key,
+            )?;
             let token = self
                 .outgoing_tokens
                 .lock()
@@ -238,6 +287,49 @@ impl Handler {
         Ok(())
     }
 
+    pub fn address_book(&self) -> Vec<(SocketAddr, AddressEntry)> {
+        self.address_book.lock().to_vec()
+    }
+
+    pub fn remove_from_address_book(&self, addr: &SocketAddr) -> bool {
+        let removed = self.address_book.lock().remove(addr);
+        if removed {
+            self.peer_db.delete(addr);
+        }
+        removed
+    }
+
+    fn update_address_book(
+        &self,
+        addr: SocketAddr,
+        update: fn(&mut AddressBook, SocketAddr, u64) -> Option<AddressEntry>,
+    ) {
+        let entry = update(&mut *self.address_book.lock(), addr, unix_now());
+        match entry {
+            Some(entry) => self.peer_db.insert(addr, entry),
+            None => {
+                cinfo!(NETWORK, "{} is removed from the address book because it failed too many times", addr);
+                self.peer_db.delete(&addr);
+            }
+        }
+    }
+
+    pub fn reputations(&self) -> Vec<(IpAddr, Reputation)> {
+        self.reputations.lock().to_vec()
+    }
+
+    fn disconnect_ip(&self, io: &IoContext<Message>, ip: &IpAddr) {
+        for addr in self.routing_table.established_addresses() {
+            if addr.ip() != *ip {
+                continue
+            }
+            if let Some(stream) = self.remote_node_ids_reverse.read().get(&addr.into()) {
+                io.deregister_stream(*stream);
+                cinfo!(NETWORK, "Disconnect banned {}:{}", addr, stream);
+            }
+        }
+    }
+
     pub fn recent_network_usage(&self) -> HashMap<String, usize> {
         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
         let mut result = HashMap::with_capacity(network_usage_in_10_seconds.len());
@@ -249,6 +341,17 @@ impl Handler {
                 result.insert(name.clone(), total);
             }
         }
+        // The ratios are the compressed sizes in percent of the original sizes.
+        let ratios: Vec<_> = result
+            .iter()
+            .filter(|(name, _)| name.starts_with(COMPRESSED_USAGE_PREFIX))
+            .filter_map(|(name, compressed)| {
+                let extension_name = &name[COMPRESSED_USAGE_PREFIX.len()..];
+                let original = result.get(&format!("{}{}", UNCOMPRESSED_USAGE_PREFIX, extension_name))?;
+                Some((format!("{}{}", COMPRESSION_RATIO_PREFIX, extension_name), compressed * 100 / original))
+            })
+            .collect();
+        result.extend(ratios);
         result
     }
 }
@@ -294,6 +397,10 @@ impl IoHandler<Message> for Handler {
         io.register_stream(ACCEPT);
         io.register_timer_once(CREATE_CONNECTIONS, CREATE_CONNECTION_INTERVAL);
         io.register_timer_once(CONNECT_TO_BOOTSTRAP, Duration::default());
+        io.register_timer(LIFT_EXPIRED_BANS, LIFT_EXPIRED_BANS_INTERVAL);
+        if self.bandwidth.is_limited() {
+            io.register_timer(RESUME_THROTTLED, RESUME_THROTTLED_INTERVAL);
+        }
         Ok(())
     }
 
@@ -316,11 +423,31 @@ impl IoHandler<Message> for Handler {
                     current_connections
                 };
 
-                let mut candidates = self.routing_table.candidates();
-                candidates.shuffle(&mut *self.rng.lock());
-                for addr in candidates.into_iter().take(self.min_peers - current_connections) {
+                // The known peers are tried even if the routing table forgot them.
+                let mut candidates: Vec<_> = self
+                    .address_book
+                    .lock()
+                    .addresses()
+                    .into_iter()
+                    .filter(|addr| !self.routing_table.is_establishing_or_established(addr))
+                    .filter(|addr| !self.routing_table.is_banned(addr))
+                    .filter(|addr| self.filters.is_allowed(&addr.ip()))
+                    .collect();
+                for addr in self.routing_table.candidates() {
+                    if !candidates.contains(&addr) {
+                        candidates.push(addr);
+                    }
+                }
+                let selected = self.address_book.lock().select(
+                    candidates,
+                    self.min_peers - current_connections,
+                    unix_now(),
+                    &mut *self.rng.lock(),
+                );
+                for addr in selected {
                     if let Err(err) = self.connect(io, addr) {
                         self.routing_table.remove(&addr);
+                        self.update_address_book(addr, AddressBook::failed);
                         cwarn!(NETWORK, "Cannot connect to {}: {:?}", addr, err);
                     }
                 }
@@ -368,6 +495,26 @@ impl IoHandler<Message> for Handler {
                 const CHECK_BOOTSTRAP_INTERVAL: Duration = Duration::from_secs(15);
                 io.register_timer_once(CONNECT_TO_BOOTSTRAP, CHECK_BOOTSTRAP_INTERVAL);
             }
+            LIFT_EXPIRED_BANS => {
+                let now = unix_now();
+                let lifted = self.reputations.lock().lift_expired_bans(now);
+                for (ip, reputation) in lifted {
+                    cinfo!(NETWORK, "The ban of {} is expired", ip);
+                    self.peer_db.update_reputation(ip, reputation);
+                    self.filters.unban(&ip);
+                }
+            }
+            RESUME_THROTTLED => {
+                // The streams are edge-triggered, so the throttled ones are registered again to get a new event.
+                let mut throttled: Vec<StreamToken> = Vec::new();
+                for connections in &[&self.inbound_connections, &self.outbound_connections] {
+                    let connections = connections.read();
+                    throttled.extend(connections.iter().filter(|(_, con)| con.is_throttled()).map(|(token, _)| *token));
+                }
+                for stream in throttled {
+                    io.update_registration(stream);
+                }
+            }
             FIRST_WAIT_SYNC..=LAST_WAIT_SYNC => {
                 cwarn!(NETWORK, "No sync message from {}", timer);
                 io.deregister_stream(wait_sync_stream(timer));
@@ -388,7 +535,17 @@ impl IoHandler<Message> for Handler {
                             return Err(err.into())
                         }
                     };
-                    let network_message_size = con.send_sync(maybe_remote_public);
+                    let network_message_size = if con.is_secure() {
+                        match con.send_hello() {
+                            Ok(network_message_size) => network_message_size,
+                            Err(err) => {
+                                io.deregister_stream(stream);
+                                return Err(err.into())
+                            }
+                        }
+                    } else {
+                        con.send_sync(maybe_remote_public)
+                    };
                     {
                         let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                         insert_network_usage(
@@ -430,11 +587,12 @@ impl IoHandler<Message> for Handler {
                 node_id,
                 extension_name,
                 need_encryption,
+                priority,
                 data,
             } => {
                 let stream =
                     *self.remote_node_ids_reverse.read().get(&node_id).ok_or_else(|| Error::InvalidNode(node_id))?;
-                let (network_message_size, peer_addr) = match stream {
+                let ((network_message_size, compressed_size), peer_addr) = match stream {
                     FIRST_OUTBOUND..=LAST_OUTBOUND => {
                         let mut outbound_connections = self.outbound_connections.write();
                         if let Some(con) = outbound_connections.get_mut(&stream) {
@@ -443,7 +601,12 @@ impl IoHandler<Message> for Handler {
                             });
 
                             (
-                                con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
+                                con.enqueue_extension_message(
+                                    extension_name.to_string(),
+                                    need_encryption,
+                                    priority,
+                                    data,
+                                )?,
                                 *con.peer_addr(),
                             )
                         } else {
@@ -457,7 +620,12 @@ impl IoHandler<Message> for Handler {
                                 io.update_registration(stream);
                             });
                             (
-                                con.enqueue_extension_message(extension_name.to_string(), need_encryption, data)?,
+                                con.enqueue_extension_message(
+                                    extension_name.to_string(),
+                                    need_encryption,
+                                    priority,
+                                    data,
+                                )?,
                                 *con.peer_addr(),
                             )
                         } else {
@@ -471,6 +639,24 @@ impl IoHandler<Message> for Handler {
                     network_usage_in_10_seconds.entry(format!("::{}@{}", extension_name, peer_addr)).or_default(),
                     network_message_size,
                 );
+                if let Some(CompressedSize {
+                    original,
+                    compressed,
+                }) = compressed_size
+                {
+                    insert_network_usage(
+                        network_usage_in_10_seconds
+                            .entry(format!("{}{}", UNCOMPRESSED_USAGE_PREFIX, extension_name))
+                            .or_default(),
+                        original,
+                    );
+                    insert_network_usage(
+                        network_usage_in_10_seconds
+                            .entry(format!("{}{}", COMPRESSED_USAGE_PREFIX, extension_name))
+                            .or_default(),
+                        compressed,
+                    );
+                }
             }
             Message::Disconnect(socket_address) => {
                 if let Some(stream) = self.remote_node_ids_reverse.read().get(&socket_address.into()) {
@@ -481,6 +667,25 @@ impl IoHandler<Message> for Handler {
                 }
                 self.routing_table.ban(socket_address);
             }
+            Message::Report {
+                node_id,
+                behavior,
+            } => {
+                let ip = node_id.into_addr().ip();
+                let (banned, reputation) = {
+                    let mut reputations = self.reputations.lock();
+                    let banned = reputations.report(ip, behavior, unix_now());
+                    (banned, reputations.get(&ip))
+                };
+                self.peer_db.update_reputation(ip, reputation);
+                if banned {
+                    cwarn!(NETWORK, "{} is banned by its reputation({})", ip, reputation.score);
+                    self.filters.ban(ip);
+                    if !self.filters.is_allowed(&ip) {
+                        self.disconnect_ip(io, &ip);
+                    }
+                }
+            }
             Message::ApplyFilters => {
                 for addr in self.routing_table.established_addresses() {
                     if !self.filters.is_allowed(&addr.ip()) {
@@ -499,7 +704,7 @@ impl IoHandler<Message> for Handler {
             } => {
                 let mut inbound_connections = self.inbound_connections.write();
                 let target = connection.peer_addr();
-                self.peer_db.insert(*target);
+                self.update_address_book(*target, AddressBook::succeeded);
                 if let Some(token) = self.inbound_tokens.lock().gen() {
                     let remote_node_id = connection.peer_addr().into();
                     assert_eq!(
@@ -529,6 +734,7 @@ impl IoHandler<Message> for Handler {
                 is_inbound: false,
             } => {
                 let mut outbound_connections = self.outbound_connections.write();
+                self.update_address_book(*connection.peer_addr(), AddressBook::succeeded);
                 if let Some(token) = self.outbound_tokens.lock().gen() {
                     let peer_addr = *connection.peer_addr();
                     let remote_node_id = peer_addr.into();
@@ -674,12 +880,13 @@ impl IoHandler<Message> for Handler {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
+                            let unencrypted = con.unencrypted_data(&msg)?;
                             self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
                         }
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
                             extension_name,
                             extension_versions,
+                            compressions,
                         })) => {
                             let versions = self
                                 .client
@@ -701,7 +908,8 @@ impl IoHandler<Message> for Handler {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
                             self.client.on_node_added(&extension_name, &remote_node_id, version);
-                            let network_message_size = con.enqueue_negotiation_response(extension_name, version);
+                            let network_message_size =
+                                con.enqueue_negotiation_response(extension_name, version, &compressions);
                             let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
                             insert_network_usage(
                                 network_usage_in_10_seconds
@@ -749,7 +957,7 @@ impl IoHandler<Message> for Handler {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
-                            let unencrypted = msg.unencrypted_data(con.session()).map_err(|e| format!("{:?}", e))?;
+                            let unencrypted = con.unencrypted_data(&msg)?;
                             self.client.on_message(msg.extension_name(), &remote_node_id, unencrypted);
                         }
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Request {
@@ -766,10 +974,14 @@ impl IoHandler<Message> for Handler {
                         Some(NetworkMessage::Negotiation(NegotiationMessage::Response {
                             extension_name,
                             allowed_version,
+                            compression,
                         })) => {
                             let remote_node_id = *self.remote_node_ids.read().get(&stream_token).unwrap_or_else(|| {
                                 unreachable!("Node id for {}:{} must exist", stream_token, con.peer_addr())
                             });
+                            if let Some(compression) = compression {
+                                con.set_compression(extension_name.clone(), compression)?;
+                            }
                             self.client.on_node_added(&extension_name, &remote_node_id, allowed_version);
                         }
                         None => {
@@ -795,6 +1007,10 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream_token);
                         }
                     };
+                    if let Err(P2PConnectionError::Decoder(_)) = &received {
+                        // Let the initiator know that its handshake is not understood.
+                        con.send_nack();
+                    }
                     match received? {
                         Some(OutgoingMessage::Sync1 {
                             initiator_pub_key,
@@ -802,6 +1018,11 @@ impl IoHandler<Message> for Handler {
                             initiator_port,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
+                            if !self.legacy_peers.read().is_allowed() {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(format!("{} uses the legacy handshake, which is refused", from).into())
+                            }
                             if network_id != self.network_id {
                                 io.deregister_stream(stream_token);
                                 should_update.store(false, Ordering::SeqCst);
@@ -815,7 +1036,7 @@ impl IoHandler<Message> for Handler {
                                 let t = self
                                     .establishing_incoming_session
                                     .lock()
-                                    .insert(stream_token, (initiator_port, session));
+                                    .insert(stream_token, (initiator_port, Transport::Legacy(session)));
                                 assert_eq!(None, t, "Cannot establish {}", initiator_port);
                                 io.clear_timer(wait_sync_timer(stream_token));
                                 should_update.store(false, Ordering::SeqCst);
@@ -840,6 +1061,11 @@ impl IoHandler<Message> for Handler {
                             initiator_port,
                         }) => {
                             let from = con.remote_addr(initiator_port)?;
+                            if !self.legacy_peers.read().is_allowed() {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(format!("{} uses the legacy handshake, which is refused", from).into())
+                            }
                             if network_id != self.network_id {
                                 should_update.store(false, Ordering::SeqCst);
                                 io.deregister_stream(stream_token);
@@ -854,7 +1080,62 @@ impl IoHandler<Message> for Handler {
                                 let t = self
                                     .establishing_incoming_session
                                     .lock()
-                                    .insert(stream_token, (initiator_port, session));
+                                    .insert(stream_token, (initiator_port, Transport::Legacy(session)));
+                                assert_eq!(None, t, "Cannot establish {}", initiator_port);
+                                io.clear_timer(wait_sync_timer(stream_token));
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                network_message_size
+                            } else {
+                                cinfo!(NETWORK, "Send nack to {}", from);
+                                let network_message_size = con.send_nack();
+                                io.register_timer_once(wait_sync_timer(stream_token), WAIT_SYNC);
+                                network_message_size
+                            };
+                            let mut network_usage_in_10_seconds = self.network_usage_in_10_seconds.lock();
+                            insert_network_usage(
+                                network_usage_in_10_seconds.entry(format!("handshake@{}", from)).or_default(),
+                                network_message_size,
+                            );
+                        }
+                        Some(OutgoingMessage::Hello {
+                            initiator_ephemeral,
+                            initiator_static,
+                            network_id,
+                            initiator_port,
+                            signature,
+                        }) => {
+                            let from = con.remote_addr(initiator_port)?;
+                            if network_id != self.network_id {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(format!("An invalid network id({}) from {}", network_id, from).into())
+                            }
+                            let (welcome, session) = match respond(
+                                &self.node_key,
+                                &initiator_ephemeral,
+                                &initiator_static,
+                                &network_id,
+                                initiator_port,
+                                &signature,
+                            ) {
+                                Ok(result) => result,
+                                Err(err) => {
+                                    should_update.store(false, Ordering::SeqCst);
+                                    io.deregister_stream(stream_token);
+                                    return Err(err.into())
+                                }
+                            };
+                            // The peer supports the authenticated handshake.
+                            self.legacy_peers.write().secured(&from);
+                            let is_secured = self.routing_table.set_recipient_secured(from, initiator_static)?;
+                            let network_message_size = if is_secured {
+                                cinfo!(NETWORK, "Send welcome to {}", from);
+                                let network_message_size = con.send_welcome(*self.node_key.public(), welcome);
+                                let t = self
+                                    .establishing_incoming_session
+                                    .lock()
+                                    .insert(stream_token, (initiator_port, Transport::Secure(session)));
                                 assert_eq!(None, t, "Cannot establish {}", initiator_port);
                                 io.clear_timer(wait_sync_timer(stream_token));
                                 should_update.store(false, Ordering::SeqCst);
@@ -896,17 +1177,30 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream_token);
                         }
                     };
+                    if con.is_secure() && rejects_hello(&received) && self.legacy_peers.read().is_allowed() {
+                        // The peer may not know the authenticated handshake.
+                        cinfo!(NETWORK, "The next connection to {} will use the legacy handshake", from);
+                        self.legacy_peers.write().rejected(from, Instant::now());
+                    }
                     match received? {
                         Some(IncomingMessage::Ack {
                             recipient_pub_key,
                             encrypted_nonce,
                         }) => {
+                            if con.is_secure() {
+                                should_update.store(false, Ordering::SeqCst);
+                                io.deregister_stream(stream_token);
+                                return Err(format!("{} sent ack for hello", from).into())
+                            }
                             let session = self.routing_table.set_initiator_establish(
                                 from,
                                 recipient_pub_key,
                                 &encrypted_nonce,
                             )?;
-                            let t = self.establishing_outgoing_session.lock().insert(stream_token, session);
+                            let t = self
+                                .establishing_outgoing_session
+                                .lock()
+                                .insert(stream_token, Transport::Legacy(session));
                             assert_eq!(None, t);
                             io.clear_timer(wait_ack_timer(stream_token));
                             io.clear_timer(retry_sync_timer(stream_token));
@@ -920,6 +1214,32 @@ impl IoHandler<Message> for Handler {
                             let timeout = self.rng.lock().gen_range(Duration::from_millis(1), RETRY_SYNC_MAX);
                             io.register_timer_once(retry_sync_timer(stream_token), timeout);
                         }
+                        Some(IncomingMessage::Welcome {
+                            recipient_ephemeral,
+                            recipient_static,
+                            signature,
+                        }) => {
+                            let finished = con.finish_handshake(&recipient_ephemeral, &recipient_static, &signature);
+                            let session = match finished {
+                                Ok(session) => session,
+                                Err(err) => {
+                                    should_update.store(false, Ordering::SeqCst);
+                                    io.deregister_stream(stream_token);
+                                    return Err(err.into())
+                                }
+                            };
+                            self.routing_table.set_initiator_secured(from, recipient_static)?;
+                            self.legacy_peers.write().secured(&from);
+                            let t = self
+                                .establishing_outgoing_session
+                                .lock()
+                                .insert(stream_token, Transport::Secure(session));
+                            assert_eq!(None, t);
+                            io.clear_timer(wait_ack_timer(stream_token));
+                            io.clear_timer(retry_sync_timer(stream_token));
+                            should_update.store(false, Ordering::SeqCst);
+                            io.deregister_stream(stream_token);
+                        }
                         None => {
                             should_update.store(false, Ordering::SeqCst);
                         }
@@ -943,6 +1263,9 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream);
                         }
                     }
+                    if let Err(P2PConnectionError::QueueOverflow) = &flush_result {
+                        io.deregister_stream(stream);
+                    }
                     flush_result?;
                 } else {
                     cdebug!(NETWORK, "Invalid inbound token({}) on write", stream);
@@ -956,6 +1279,9 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream);
                         }
                     }
+                    if let Err(P2PConnectionError::QueueOverflow) = &flush_result {
+                        io.deregister_stream(stream);
+                    }
                     flush_result?;
                 } else {
                     cdebug!(NETWORK, "Invalid outbound token({}) on write", stream);
@@ -969,6 +1295,9 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream);
                         }
                     }
+                    if let Err(P2PConnectionError::QueueOverflow) = &flush_result {
+                        io.deregister_stream(stream);
+                    }
                     flush_result?;
                 } else {
                     cdebug!(NETWORK, "Invalid incoming token({}) on write", stream);
@@ -982,6 +1311,9 @@ impl IoHandler<Message> for Handler {
                             io.deregister_stream(stream);
                         }
                     }
+                    if let Err(P2PConnectionError::QueueOverflow) = &flush_result {
+                        io.deregister_stream(stream);
+                    }
                     flush_result?;
                 } else {
                     cdebug!(NETWORK, "Invalid outgoing token({}) on write", stream);
@@ -1112,8 +1444,7 @@ impl IoHandler<Message> for Handler {
                     self.routing_table.remove(con.peer_addr());
                     self.inbound_tokens.lock().restore(stream);
                     ctrace!(NETWORK, "Inbound connect({}) removed", stream);
-                    let remove_target = con.peer_addr();
-                    self.peer_db.delete(&remove_target);
+                    self.update_address_book(*con.peer_addr(), AddressBook::seen);
                 } else {
                     cdebug!(NETWORK, "Invalid inbound token({}) on deregister", stream);
                 }
@@ -1128,8 +1459,7 @@ impl IoHandler<Message> for Handler {
                         unreachable!("{} has no node id", stream);
                     }
                     con.deregister(event_loop)?;
-                    let remove_target = con.peer_addr();
-                    self.peer_db.delete(&remove_target);
+                    self.update_address_book(*con.peer_addr(), AddressBook::seen);
                     self.routing_table.remove(con.peer_addr());
                     self.outbound_tokens.lock().restore(stream);
                     ctrace!(NETWORK, "Outbound connect({}) removed", stream);
@@ -1142,8 +1472,12 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = incoming_connections.remove(&stream) {
                     con.deregister(event_loop)?;
                     self.incoming_tokens.lock().restore(stream);
-                    if let Some((port, session)) = self.establishing_incoming_session.lock().remove(&stream) {
-                        let connection = con.establish(session, port)?;
+                    if let Some((port, transport)) = self.establishing_incoming_session.lock().remove(&stream) {
+                        if let Transport::Secure(session) = &transport {
+                            let remote_static = session.remote_static();
+                            cinfo!(NETWORK, "Incoming connect({}) is authenticated as {}", stream, remote_static);
+                        }
+                        let connection = con.establish(transport, port, Bandwidth::peer(&self.bandwidth))?;
                         {
                             let peer_addr = connection.peer_addr();
                             if !self.filters.is_allowed(&peer_addr.ip()) {
@@ -1171,8 +1505,12 @@ impl IoHandler<Message> for Handler {
                 if let Some(con) = outgoing_connections.remove(&stream) {
                     con.deregister(event_loop)?;
                     self.outgoing_tokens.lock().restore(stream);
-                    if let Some(session) = self.establishing_outgoing_session.lock().remove(&stream) {
-                        let connection = con.establish(session)?;
+                    if let Some(transport) = self.establishing_outgoing_session.lock().remove(&stream) {
+                        if let Transport::Secure(session) = &transport {
+                            let remote_static = session.remote_static();
+                            cinfo!(NETWORK, "Outgoing connect({}) is authenticated as {}", stream, remote_static);
+                        }
+                        let connection = con.establish(transport, Bandwidth::peer(&self.bandwidth))?;
                         {
                             let peer_addr = connection.peer_addr();
                             if !self.filters.is_allowed(&peer_addr.ip()) {
@@ -1190,6 +1528,7 @@ impl IoHandler<Message> for Handler {
                         ctrace!(NETWORK, "Outgoing connect({}) established", stream);
                     } else {
                         self.routing_table.remove(con.peer_addr());
+                        self.update_address_book(*con.peer_addr(), AddressBook::failed);
                         ctrace!(NETWORK, "Outgoing connect({}) removed", stream);
                     }
                 } else {
@@ -1216,9 +1555,14 @@ pub enum Message {
         node_id: NodeId,
         extension_name: &'static str,
         need_encryption: bool,
+        priority: Priority,
         data: Arc<Bytes>,
     },
     Disconnect(SocketAddr),
+    Report {
+        node_id: NodeId,
+        behavior: Behavior,
+    },
     ApplyFilters,
     Established {
         connection: EstablishedConnection,
@@ -0,0 +1,131 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::connection::{Error as P2PConnectionError, IncomingMessage};
+use crate::SocketAddr;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long the legacy handshake is used for a peer after it rejected the authenticated handshake.
+const LEGACY_EXPIRATION: Duration = Duration::from_secs(60 * 60);
+
+/// Returns whether the reply to `Hello` explicitly rejects the authenticated handshake.
+///
+/// A connection can be dropped or time out for many reasons, and an attacker on the path can make it so.
+/// Falling back on those would let the attacker downgrade the handshake, so only a `Nack` and a reply that
+/// cannot be decoded count.
+pub fn rejects_hello(received: &Result<Option<IncomingMessage>, P2PConnectionError>) -> bool {
+    match received {
+        Ok(Some(IncomingMessage::Nack)) => true,
+        Err(P2PConnectionError::Decoder(_)) => true,
+        _ => false,
+    }
+}
+
+/// The peers which rejected the authenticated handshake. The legacy handshake is used for them until it expires.
+pub struct LegacyPeers {
+    /// Whether the legacy handshake is allowed at all.
+    allowed: bool,
+    expirations: HashMap<SocketAddr, Instant>,
+}
+
+impl LegacyPeers {
+    pub fn new(allowed: bool) -> Self {
+        Self {
+            allowed,
+            expirations: Default::default(),
+        }
+    }
+
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// Returns whether the next connection to the peer uses the legacy handshake.
+    pub fn uses_legacy(&mut self, peer: &SocketAddr, now: Instant) -> bool {
+        match self.expirations.get(peer) {
+            Some(expiration) if now < *expiration => true,
+            Some(_) => {
+                self.expirations.remove(peer);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// The peer rejected the authenticated handshake.
+    pub fn rejected(&mut self, peer: SocketAddr, now: Instant) {
+        if self.allowed {
+            self.expirations.insert(peer, now + LEGACY_EXPIRATION);
+        }
+    }
+
+    /// The peer supports the authenticated handshake.
+    pub fn secured(&mut self, peer: &SocketAddr) {
+        self.expirations.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::DecoderError;
+    use std::io;
+
+    #[test]
+    fn only_explicit_rejections_fall_back() {
+        assert!(rejects_hello(&Ok(Some(IncomingMessage::Nack))));
+        assert!(rejects_hello(&Err(P2PConnectionError::Decoder(DecoderError::RlpIncorrectListLen))));
+
+        assert!(!rejects_hello(&Ok(None)));
+        let eof = io::Error::new(io::ErrorKind::ConnectionAborted, "EOF");
+        assert!(!rejects_hello(&Err(P2PConnectionError::IoError(eof))));
+        assert!(!rejects_hello(&Err(P2PConnectionError::InvalidSign)));
+    }
+
+    #[test]
+    fn fallback_expires() {
+        let peer = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let now = Instant::now();
+        let mut peers = LegacyPeers::new(true);
+        assert!(!peers.uses_legacy(&peer, now));
+
+        peers.rejected(peer, now);
+        assert!(peers.uses_legacy(&peer, now));
+        assert!(peers.uses_legacy(&peer, now + LEGACY_EXPIRATION - Duration::from_secs(1)));
+        assert!(!peers.uses_legacy(&peer, now + LEGACY_EXPIRATION));
+        assert!(!peers.uses_legacy(&peer, now));
+    }
+
+    #[test]
+    fn secured_peer_stops_the_fallback() {
+        let peer = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let now = Instant::now();
+        let mut peers = LegacyPeers::new(true);
+        peers.rejected(peer, now);
+        peers.secured(&peer);
+        assert!(!peers.uses_legacy(&peer, now));
+    }
+
+    #[test]
+    fn never_fall_back_if_the_legacy_handshake_is_refused() {
+        let peer = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let now = Instant::now();
+        let mut peers = LegacyPeers::new(false);
+        peers.rejected(peer, now);
+        assert!(!peers.uses_legacy(&peer, now));
+    }
+}
@@ -14,8 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::COMPRESSED_ID;
 use super::ENCRYPTED_ID;
 use super::UNENCRYPTED_ID;
+use crate::compression::Compression;
 use crate::session::Session;
 use ccrypto::aes;
 use ccrypto::error::SymmError;
@@ -34,6 +36,11 @@ pub enum Message {
         extension_name: String,
         data: Arc<Bytes>,
     },
+    Compressed {
+        extension_name: String,
+        compression: Compression,
+        compressed: Vec<u8>,
+    },
 }
 
 impl Message {
@@ -60,6 +67,19 @@ impl Message {
         }
     }
 
+    /// Falls back to an unencrypted message if the compression doesn't make the data smaller.
+    pub fn compressed(extension_name: String, compression: Compression, data: Arc<Bytes>) -> Self {
+        let compressed = compression.compress(&data);
+        if compressed.len() >= data.len() {
+            return Self::unencrypted(extension_name, data)
+        }
+        Message::Compressed {
+            extension_name,
+            compression,
+            compressed,
+        }
+    }
+
     #[cfg(test)]
     fn data(&self) -> &[u8] {
         match self {
@@ -71,19 +91,35 @@ impl Message {
                 data,
                 ..
             } => &data,
+            Message::Compressed {
+                compressed,
+                ..
+            } => &compressed,
         }
     }
 
-    pub fn unencrypted_data(&self, session: &Session) -> Result<Arc<Bytes>, SymmError> {
+    /// `session` is `None` in the secure transport, which encrypts the whole message,
+    /// so an extension message in it must not be encrypted again.
+    pub fn unencrypted_data(&self, session: Option<&Session>) -> Result<Arc<Bytes>, String> {
         match self {
             Message::Encrypted {
                 encrypted,
                 ..
-            } => Ok(Arc::new(aes::decrypt(encrypted, session.secret(), &session.nonce())?)),
+            } => {
+                let session = session.ok_or_else(|| "Encrypted extension message in the secure transport".to_string())?;
+                let decrypted =
+                    aes::decrypt(encrypted, session.secret(), &session.nonce()).map_err(|err| format!("{:?}", err))?;
+                Ok(Arc::new(decrypted))
+            }
             Message::Unencrypted {
                 data,
                 ..
             } => Ok(Arc::clone(data)),
+            Message::Compressed {
+                compression,
+                compressed,
+                ..
+            } => Ok(Arc::new(compression.decompress(compressed)?)),
         }
     }
 
@@ -97,6 +133,10 @@ impl Message {
                 extension_name,
                 ..
             } => &extension_name,
+            Message::Compressed {
+                extension_name,
+                ..
+            } => &extension_name,
         }
     }
 }
@@ -116,20 +156,37 @@ impl Encodable for Message {
             } => {
                 s.begin_list(3).append(&UNENCRYPTED_ID).append(extension_name).append(data.as_ref());
             }
+            Message::Compressed {
+                extension_name,
+                compression,
+                compressed,
+            } => {
+                s.begin_list(4)
+                    .append(&COMPRESSED_ID)
+                    .append(extension_name)
+                    .append(&compression.id())
+                    .append(compressed);
+            }
         }
     }
 }
 
 impl Decodable for Message {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let id = rlp.val_at(0)?;
+        let expected = if id == COMPRESSED_ID {
+            4
+        } else {
+            3
+        };
         let item_count = rlp.item_count()?;
-        if item_count != 3 {
+        if item_count != expected {
             return Err(DecoderError::RlpInvalidLength {
-                expected: 3,
+                expected,
                 got: item_count,
             })
         }
-        match rlp.val_at(0)? {
+        match id {
             ENCRYPTED_ID => Ok(Message::Encrypted {
                 extension_name: rlp.val_at(1)?,
                 encrypted: rlp.val_at(2)?,
@@ -138,6 +195,12 @@ impl Decodable for Message {
                 extension_name: rlp.val_at(1)?,
                 data: Arc::new(rlp.val_at(2)?),
             }),
+            COMPRESSED_ID => Ok(Message::Compressed {
+                extension_name: rlp.val_at(1)?,
+                compression: Compression::from_id(rlp.val_at(2)?)
+                    .ok_or(DecoderError::Custom("Unknown compression in extension message"))?,
+                compressed: rlp.val_at(3)?,
+            }),
             _ => Err(DecoderError::Custom("Invalid id in extension message")),
         }
     }
@@ -165,7 +228,7 @@ mod tests {
         let session = Session::new(shared_secret, nonce);
         let encrypted = Message::encrypted_from_unencrypted_data(extension_name, unencrypted_data, &session).unwrap();
         assert_ne!(unencrypted_data, encrypted.data());
-        assert_eq!(unencrypted_data, encrypted.unencrypted_data(&session).unwrap().as_slice());
+        assert_eq!(unencrypted_data, encrypted.unencrypted_data(Some(&session)).unwrap().as_slice());
     }
 
     #[test]
@@ -177,4 +240,38 @@ mod tests {
     fn encode_and_decode_unencrypted() {
         rlp_encode_and_decode_test!(Message::unencrypted("a".to_string(), Arc::new(vec![1, 2, 3, 4])));
     }
+
+    #[test]
+    fn encode_and_decode_compressed() {
+        let data = Arc::new(vec![0x42; 4096]);
+        let compressed = Message::compressed("a".to_string(), Compression::Snappy, data);
+        match compressed {
+            Message::Compressed {
+                ..
+            } => {}
+            _ => panic!("Repeated bytes must be compressed"),
+        }
+        rlp_encode_and_decode_test!(compressed);
+    }
+
+    #[test]
+    fn compressed_falls_back_to_unencrypted_if_not_smaller() {
+        let data = Arc::new(vec![1, 2, 3, 4]);
+        let message = Message::compressed("a".to_string(), Compression::Snappy, Arc::clone(&data));
+        assert_eq!(Message::unencrypted("a".to_string(), data), message);
+    }
+
+    #[test]
+    fn unencrypted_data_of_compressed_message_is_the_original_data() {
+        let data = Arc::new(vec![0x42; 4096]);
+        let compressed = Message::compressed("a".to_string(), Compression::Snappy, Arc::clone(&data));
+        assert_ne!(data.as_slice(), compressed.data());
+        assert_eq!(data, compressed.unencrypted_data(None).unwrap());
+    }
+
+    #[test]
+    fn encrypted_message_is_rejected_without_session() {
+        let encrypted = Message::encrypted("a".to_string(), vec![1, 2, 3, 4]);
+        assert!(encrypted.unencrypted_data(None).is_err());
+    }
 }
@@ -24,6 +24,7 @@ pub enum Message {
     Negotiation(NegotiationMessage),
 }
 
+use super::COMPRESSED_ID;
 use super::ENCRYPTED_ID;
 use super::REQUEST_ID;
 use super::RESPONSE_ID;
@@ -46,6 +47,7 @@ impl Decodable for Message {
             RESPONSE_ID => Ok(Message::Negotiation(Decodable::decode(rlp)?)),
             ENCRYPTED_ID => Ok(Message::Extension(Decodable::decode(rlp)?)),
             UNENCRYPTED_ID => Ok(Message::Extension(Decodable::decode(rlp)?)),
+            COMPRESSED_ID => Ok(Message::Extension(Decodable::decode(rlp)?)),
             _ => Err(DecoderError::Custom("unexpected protocol id")),
         }
     }
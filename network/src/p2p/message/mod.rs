@@ -35,9 +35,11 @@ pub const REQUEST_ID: u8 = 0x05;
 pub const RESPONSE_ID: u8 = 0x06;
 pub const ENCRYPTED_ID: u8 = 0x07;
 pub const UNENCRYPTED_ID: u8 = 0x08;
+pub const COMPRESSED_ID: u8 = 0x09;
 
 #[cfg(test)]
 mod tests {
+    use super::COMPRESSED_ID;
     use super::ENCRYPTED_ID;
     use super::REQUEST_ID;
     use super::RESPONSE_ID;
@@ -48,6 +50,7 @@ mod tests {
         assert_ne!(REQUEST_ID, RESPONSE_ID);
         assert_ne!(REQUEST_ID, ENCRYPTED_ID);
         assert_ne!(REQUEST_ID, UNENCRYPTED_ID);
+        assert_ne!(REQUEST_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -55,6 +58,7 @@ mod tests {
         assert_ne!(RESPONSE_ID, REQUEST_ID);
         assert_ne!(RESPONSE_ID, ENCRYPTED_ID);
         assert_ne!(RESPONSE_ID, UNENCRYPTED_ID);
+        assert_ne!(RESPONSE_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -62,6 +66,7 @@ mod tests {
         assert_ne!(ENCRYPTED_ID, REQUEST_ID);
         assert_ne!(ENCRYPTED_ID, RESPONSE_ID);
         assert_ne!(ENCRYPTED_ID, UNENCRYPTED_ID);
+        assert_ne!(ENCRYPTED_ID, COMPRESSED_ID);
     }
 
     #[test]
@@ -69,5 +74,14 @@ mod tests {
         assert_ne!(UNENCRYPTED_ID, REQUEST_ID);
         assert_ne!(UNENCRYPTED_ID, RESPONSE_ID);
         assert_ne!(UNENCRYPTED_ID, ENCRYPTED_ID);
+        assert_ne!(UNENCRYPTED_ID, COMPRESSED_ID);
+    }
+
+    #[test]
+    fn compressed_id_is_a_unique() {
+        assert_ne!(COMPRESSED_ID, REQUEST_ID);
+        assert_ne!(COMPRESSED_ID, RESPONSE_ID);
+        assert_ne!(COMPRESSED_ID, ENCRYPTED_ID);
+        assert_ne!(COMPRESSED_ID, UNENCRYPTED_ID);
     }
 }
@@ -17,6 +17,7 @@
 use super::Version;
 use super::REQUEST_ID;
 use super::RESPONSE_ID;
+use crate::compression::Compression;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
 #[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
@@ -24,25 +25,29 @@ pub enum Message {
     Request {
         extension_name: String,
         extension_versions: Vec<Version>,
+        compressions: Vec<Compression>,
     },
     Response {
         extension_name: String,
         allowed_version: Version,
+        compression: Option<Compression>,
     },
 }
 
 impl Message {
-    pub fn request(extension_name: String, extension_versions: Vec<Version>) -> Self {
+    pub fn request(extension_name: String, extension_versions: Vec<Version>, compressions: Vec<Compression>) -> Self {
         Message::Request {
             extension_name,
             extension_versions,
+            compressions,
         }
     }
 
-    pub fn allowed(extension_name: String, allowed_version: Version) -> Self {
+    pub fn allowed(extension_name: String, allowed_version: Version, compression: Option<Compression>) -> Self {
         Message::Response {
             extension_name,
             allowed_version,
+            compression,
         }
     }
 }
@@ -54,14 +59,34 @@ impl Encodable for Message {
             Message::Request {
                 extension_name,
                 extension_versions,
+                compressions,
             } => {
-                s.begin_list(3).append(&REQUEST_ID).append(extension_name).append_list(extension_versions);
+                // Peers that don't know compression expect exactly three items.
+                let item_count = if compressions.is_empty() {
+                    3
+                } else {
+                    4
+                };
+                s.begin_list(item_count).append(&REQUEST_ID).append(extension_name).append_list(extension_versions);
+                if !compressions.is_empty() {
+                    let ids: Vec<u8> = compressions.iter().map(|compression| compression.id()).collect();
+                    s.append_list(&ids);
+                }
             }
             Message::Response {
                 extension_name,
                 allowed_version,
+                compression,
             } => {
-                s.begin_list(3).append(&RESPONSE_ID).append(extension_name).append(allowed_version);
+                let item_count = if compression.is_some() {
+                    4
+                } else {
+                    3
+                };
+                s.begin_list(item_count).append(&RESPONSE_ID).append(extension_name).append(allowed_version);
+                if let Some(compression) = compression {
+                    s.append(&compression.id());
+                }
             }
         }
     }
@@ -70,21 +95,40 @@ impl Encodable for Message {
 impl Decodable for Message {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
         let item_count = rlp.item_count()?;
-        if item_count != 3 {
+        if item_count != 3 && item_count != 4 {
             return Err(DecoderError::RlpInvalidLength {
                 expected: 3,
                 got: item_count,
             })
         }
         match rlp.val_at(0)? {
-            REQUEST_ID => Ok(Message::Request {
-                extension_name: rlp.val_at(1)?,
-                extension_versions: rlp.list_at(2)?,
-            }),
-            RESPONSE_ID => Ok(Message::Response {
-                extension_name: rlp.val_at(1)?,
-                allowed_version: rlp.val_at(2)?,
-            }),
+            REQUEST_ID => {
+                let compressions = if item_count == 4 {
+                    // Algorithms this node doesn't know are not selectable, so they are dropped.
+                    rlp.list_at::<u8>(3)?.into_iter().filter_map(Compression::from_id).collect()
+                } else {
+                    Vec::new()
+                };
+                Ok(Message::Request {
+                    extension_name: rlp.val_at(1)?,
+                    extension_versions: rlp.list_at(2)?,
+                    compressions,
+                })
+            }
+            RESPONSE_ID => {
+                let compression = if item_count == 4 {
+                    let compression = Compression::from_id(rlp.val_at(3)?)
+                        .ok_or(DecoderError::Custom("Unknown compression in negotiation message"))?;
+                    Some(compression)
+                } else {
+                    None
+                };
+                Ok(Message::Response {
+                    extension_name: rlp.val_at(1)?,
+                    allowed_version: rlp.val_at(2)?,
+                    compression,
+                })
+            }
             _ => Err(DecoderError::Custom("Invalid id in negotiation message")),
         }
     }
@@ -99,12 +143,42 @@ mod tests {
     #[test]
     fn encode_and_decode_request() {
         let extension_name = "some-extension".to_string();
-        rlp_encode_and_decode_test!(Message::request(extension_name, vec![1, 2, 3]));
+        rlp_encode_and_decode_test!(Message::request(extension_name, vec![1, 2, 3], vec![]));
     }
 
     #[test]
     fn encode_and_decode_allowed() {
         let extension_name = "some-extension".to_string();
-        rlp_encode_and_decode_test!(Message::allowed(extension_name, 2));
+        rlp_encode_and_decode_test!(Message::allowed(extension_name, 2, None));
+    }
+
+    #[test]
+    fn encode_and_decode_request_with_compressions() {
+        let extension_name = "some-extension".to_string();
+        rlp_encode_and_decode_test!(Message::request(extension_name, vec![1, 2, 3], vec![Compression::Snappy]));
+    }
+
+    #[test]
+    fn encode_and_decode_allowed_with_compression() {
+        let extension_name = "some-extension".to_string();
+        rlp_encode_and_decode_test!(Message::allowed(extension_name, 2, Some(Compression::Snappy)));
+    }
+
+    #[test]
+    fn request_without_compressions_has_the_legacy_layout() {
+        let extension_name = "some-extension".to_string();
+        let encoded = rlp::encode(&Message::request(extension_name, vec![1, 2, 3], vec![]));
+        assert_eq!(3, Rlp::new(&encoded).item_count().unwrap());
+    }
+
+    #[test]
+    fn unknown_compressions_in_request_are_dropped() {
+        let extension_name = "some-extension".to_string();
+        let versions: Vec<Version> = vec![1];
+        let ids: Vec<u8> = vec![0xFF, Compression::Snappy.id()];
+        let mut s = RlpStream::new_list(4);
+        s.append(&REQUEST_ID).append(&extension_name).append_list(&versions).append_list(&ids);
+        let decoded: Message = rlp::decode(&s.out()).unwrap();
+        assert_eq!(Message::request(extension_name, vec![1], vec![Compression::Snappy]), decoded);
     }
 }
@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::Signature;
-use crate::session::Session;
+use crate::session::{SecureSession, Session};
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
 #[derive(Debug, PartialEq)]
@@ -25,10 +25,8 @@ pub struct SignedMessage {
 }
 
 impl SignedMessage {
-    pub fn new<M>(message: &M, session: &Session) -> Self
-    where
-        M: Encodable, {
-        let message = message.rlp_bytes();
+    /// `message` is the RLP encoded body.
+    pub fn new(message: Vec<u8>, session: &Session) -> Self {
         let signature = session.sign(&message);
         Self {
             message,
@@ -36,9 +34,22 @@ impl SignedMessage {
         }
     }
 
+    /// Encrypts the message, and puts the MAC in place of the signature.
+    pub fn sealed(message: &[u8], session: &mut SecureSession) -> Self {
+        let (message, signature) = session.seal(message);
+        Self {
+            message,
+            signature,
+        }
+    }
+
     pub fn is_valid(&self, session: &Session) -> bool {
         session.sign(&self.message) == self.signature
     }
+
+    pub fn open(&self, session: &mut SecureSession) -> Option<Vec<u8>> {
+        session.open(&self.message, &self.signature)
+    }
 }
 
 impl Encodable for SignedMessage {
@@ -16,6 +16,7 @@
 
 mod connection;
 mod handler;
+mod legacy_peers;
 mod listener;
 mod message;
 mod stream;
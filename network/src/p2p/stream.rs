@@ -15,12 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use super::SignedMessage;
-use crate::session::Session;
+use crate::bandwidth::{PeerBandwidth, Priority};
+use crate::session::Transport;
 use crate::stream::{Error as StreamError, Stream};
 use crate::SocketAddr;
 use mio::event::Evented;
 use mio::{Poll, PollOpt, Ready, Token};
 use rlp::{Decodable, DecoderError, Encodable, Rlp};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 use std::fmt;
 use std::io;
 
@@ -29,6 +31,8 @@ pub enum Error {
     IoError(io::Error),
     DecoderError(DecoderError),
     InvalidSign,
+    /// The peer doesn't take the messages that cannot be dropped.
+    QueueOverflow,
 }
 
 impl fmt::Display for Error {
@@ -36,7 +40,7 @@ impl fmt::Display for Error {
         match self {
             Error::IoError(err) => err.fmt(f),
             Error::DecoderError(err) => err.fmt(f),
-            Error::InvalidSign => fmt::Debug::fmt(&self, f),
+            Error::InvalidSign | Error::QueueOverflow => fmt::Debug::fmt(&self, f),
         }
     }
 }
@@ -64,50 +68,218 @@ impl From<DecoderError> for Error {
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// The maximum size of the message bodies queued in a class.
+const MAX_QUEUED_BYTES: usize = 16 * 1024 * 1024;
+
+/// Control messages, such as the negotiation, go before all extension messages.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Class {
+    Extension(Priority),
+    Control,
+}
+
+impl Class {
+    /// The transactions and the block sync messages are sent again by the extensions when they are lost.
+    fn is_droppable(self) -> bool {
+        match self {
+            Class::Extension(Priority::Low) | Class::Extension(Priority::Normal) => true,
+            Class::Extension(Priority::High) | Class::Control => false,
+        }
+    }
+}
+
+struct Queued {
+    extension_name: Option<String>,
+    priority: Priority,
+    body: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Queue {
+    messages: VecDeque<Queued>,
+    /// The total size of the queued bodies.
+    size: usize,
+}
+
+impl Queue {
+    fn push_back(&mut self, queued: Queued) {
+        self.size += queued.body.len();
+        self.messages.push_back(queued);
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Queued> {
+        let queued = self.messages.remove(index)?;
+        self.size -= queued.body.len();
+        Some(queued)
+    }
+
+    /// Drops the oldest messages until the queued bodies fit in the limit, and returns how many are dropped.
+    /// The last message is kept even if it doesn't fit.
+    fn drop_oldest(&mut self, limit: usize) -> usize {
+        let mut dropped = 0;
+        while self.size > limit && self.messages.len() > 1 {
+            self.remove(0);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// The messages are queued unsigned, and signed when they are passed to the stream.
+/// The secure transport must seal the messages in the order they are sent.
 pub struct SignedStream {
     stream: Stream,
-    session: Session,
+    transport: Transport,
+    bandwidth: PeerBandwidth,
+    queues: BTreeMap<Class, Queue>,
+    /// Whether a class which cannot drop its messages exceeded `MAX_QUEUED_BYTES`.
+    overflowed: bool,
 }
 
 impl SignedStream {
-    pub fn new(stream: Stream, session: Session) -> Self {
+    pub fn new(stream: Stream, transport: Transport, bandwidth: PeerBandwidth) -> Self {
         Self {
             stream,
-            session,
+            transport,
+            bandwidth,
+            queues: Default::default(),
+            overflowed: false,
         }
     }
 
-    pub fn read<M>(&mut self) -> Result<Option<M>>
+    /// Returns the message with the size of its body.
+    /// It returns `None` without reading while the download budget is used up.
+    pub fn read<M>(&mut self) -> Result<Option<(M, usize)>>
     where
         M: ?Sized + Decodable, {
+        if !self.bandwidth.can_receive() {
+            return Ok(None)
+        }
         if let Some(signed) = self.stream.read::<SignedMessage>()? {
-            if !signed.is_valid(&self.session) {
-                return Err(Error::InvalidSign)
-            }
-            let rlp = Rlp::new(&signed.message);
-            Ok(Some(rlp.as_val::<M>()?))
+            let size = signed.message.len();
+            self.bandwidth.on_received(size);
+            let message = match &mut self.transport {
+                Transport::Legacy(session) => {
+                    if !signed.is_valid(session) {
+                        return Err(Error::InvalidSign)
+                    }
+                    signed.message
+                }
+                Transport::Secure(session) => signed.open(session).ok_or(Error::InvalidSign)?,
+            };
+            let rlp = Rlp::new(&message);
+            Ok(Some((rlp.as_val::<M>()?, size)))
         } else {
             Ok(None)
         }
     }
 
+    pub fn on_extension_received(&mut self, extension_name: &str, bytes: usize) {
+        self.bandwidth.on_extension_received(extension_name, bytes);
+    }
+
+    /// Queues a control message, and returns the size of its body.
     pub fn write<M>(&mut self, message: &M) -> usize
     where
         M: Encodable, {
-        self.stream.write(&SignedMessage::new(message, &self.session))
+        self.enqueue(Class::Control, None, Priority::High, message.rlp_bytes())
+    }
+
+    /// Queues a message of the extension, and returns the size of its body.
+    pub fn write_extension<M>(&mut self, message: &M, extension_name: String, priority: Priority) -> usize
+    where
+        M: Encodable, {
+        self.enqueue(Class::Extension(priority), Some(extension_name), priority, message.rlp_bytes())
+    }
+
+    /// Queues a message. When the class exceeds `MAX_QUEUED_BYTES`, its oldest messages are dropped if it's
+    /// droppable. Otherwise, the stream is closed on the next flush.
+    fn enqueue(&mut self, class: Class, extension_name: Option<String>, priority: Priority, body: Vec<u8>) -> usize {
+        let size = body.len();
+        let queue = self.queues.entry(class).or_default();
+        queue.push_back(Queued {
+            extension_name,
+            priority,
+            body,
+        });
+        if queue.size > MAX_QUEUED_BYTES {
+            if class.is_droppable() {
+                let dropped = queue.drop_oldest(MAX_QUEUED_BYTES);
+                cdebug!(NETWORK, "Dropped {} queued messages of {:?}", dropped, class);
+            } else {
+                self.overflowed = true;
+            }
+        }
+        size
     }
 
+    /// Passes the queued messages to the stream one by one, so that a message queued later with a higher priority
+    /// doesn't wait behind the messages queued before it.
     pub fn flush(&mut self) -> Result<()> {
-        self.stream.flush()?;
-        Ok(())
+        if self.overflowed {
+            return Err(Error::QueueOverflow)
+        }
+        loop {
+            self.stream.flush()?;
+            if self.stream.has_pending_writes() {
+                return Ok(())
+            }
+            let queued = match self.next_sendable() {
+                Some(queued) => queued,
+                None => return Ok(()),
+            };
+            self.bandwidth.on_sent(queued.extension_name.as_deref(), queued.body.len());
+            let signed = match &mut self.transport {
+                Transport::Legacy(session) => SignedMessage::new(queued.body, session),
+                Transport::Secure(session) => SignedMessage::sealed(&queued.body, session),
+            };
+            self.stream.write(&signed);
+        }
+    }
+
+    /// Takes the first message of the highest class that the budget allows.
+    /// Once a message is held back, the later messages of the same extension are held back too to keep the order.
+    fn next_sendable(&mut self) -> Option<Queued> {
+        let bandwidth = &mut self.bandwidth;
+        for queue in self.queues.values_mut().rev() {
+            let mut held_back = HashSet::new();
+            let mut sendable = None;
+            for (index, queued) in queue.messages.iter().enumerate() {
+                if held_back.contains(&queued.extension_name) {
+                    continue
+                }
+                if bandwidth.can_send(queued.extension_name.as_deref(), queued.priority) {
+                    sendable = Some(index);
+                    break
+                }
+                held_back.insert(queued.extension_name.clone());
+            }
+            if let Some(index) = sendable {
+                return queue.remove(index)
+            }
+        }
+        None
+    }
+
+    fn has_queued(&self) -> bool {
+        self.queues.values().any(|queue| !queue.is_empty())
+    }
+
+    /// Whether the stream waits for the budget to be refilled.
+    pub fn is_throttled(&self) -> bool {
+        self.has_queued() || self.bandwidth.is_read_paused()
     }
 
     pub fn peer_addr(&self) -> Result<SocketAddr> {
         Ok(self.stream.peer_addr()?)
     }
 
-    pub fn session(&self) -> &Session {
-        &self.session
+    pub fn transport(&self) -> &Transport {
+        &self.transport
     }
 
     pub fn shutdown(&self) -> io::Result<()> {
@@ -122,11 +294,17 @@ impl From<SignedStream> for Stream {
 }
 
 impl Evented for SignedStream {
-    fn register(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+    fn register(&self, poll: &Poll, token: Token, mut interest: Ready, opts: PollOpt) -> io::Result<()> {
+        if self.has_queued() {
+            interest |= Ready::writable();
+        }
         self.stream.register(poll, token, interest, opts)
     }
 
-    fn reregister(&self, poll: &Poll, token: Token, interest: Ready, opts: PollOpt) -> io::Result<()> {
+    fn reregister(&self, poll: &Poll, token: Token, mut interest: Ready, opts: PollOpt) -> io::Result<()> {
+        if self.has_queued() {
+            interest |= Ready::writable();
+        }
         self.stream.reregister(poll, token, interest, opts)
     }
 
@@ -134,3 +312,55 @@ impl Evented for SignedStream {
         self.stream.deregister(poll)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(extension_name: &str, size: usize) -> Queued {
+        Queued {
+            extension_name: Some(extension_name.to_string()),
+            priority: Priority::Low,
+            body: vec![0; size],
+        }
+    }
+
+    fn extension_names(queue: &Queue) -> Vec<&str> {
+        queue.messages.iter().map(|queued| queued.extension_name.as_deref().unwrap()).collect()
+    }
+
+    #[test]
+    fn oldest_messages_are_dropped_over_the_limit() {
+        let mut queue = Queue::default();
+        queue.push_back(queued("a", 40));
+        queue.push_back(queued("b", 40));
+        queue.push_back(queued("c", 40));
+        assert_eq!(120, queue.size);
+
+        assert_eq!(1, queue.drop_oldest(100));
+        assert_eq!(vec!["b", "c"], extension_names(&queue));
+        assert_eq!(80, queue.size);
+
+        assert_eq!(0, queue.drop_oldest(100));
+        assert_eq!(80, queue.size);
+    }
+
+    #[test]
+    fn the_last_message_is_kept() {
+        let mut queue = Queue::default();
+        queue.push_back(queued("a", 40));
+        queue.push_back(queued("b", 200));
+
+        assert_eq!(1, queue.drop_oldest(100));
+        assert_eq!(vec!["b"], extension_names(&queue));
+        assert_eq!(200, queue.size);
+    }
+
+    #[test]
+    fn only_the_low_and_normal_priority_messages_are_droppable() {
+        assert!(Class::Extension(Priority::Low).is_droppable());
+        assert!(Class::Extension(Priority::Normal).is_droppable());
+        assert!(!Class::Extension(Priority::High).is_droppable());
+        assert!(!Class::Control.is_droppable());
+    }
+}
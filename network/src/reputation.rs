@@ -0,0 +1,227 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+use std::cmp::min;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The score that a new peer starts with.
+pub const INITIAL_REPUTATION: i64 = 0;
+/// The score cannot be accumulated beyond it by good behaviors.
+pub const MAX_REPUTATION: i64 = 100;
+/// The peer is banned if its score goes down to it.
+pub const BAN_THRESHOLD: i64 = -100;
+/// How long a banned peer stays in the blacklist, in seconds.
+pub const BAN_DURATION: u64 = 60 * 60;
+
+/// The behaviors of peers that extensions report to the network.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Behavior {
+    /// The peer sent a useful message, such as a valid response.
+    Good,
+    /// The peer sent a message that cannot be decoded.
+    UndecodableMessage,
+    /// The peer sent a response that doesn't match the request.
+    InvalidResponse,
+    /// The peer sent a block or a header that failed the verification.
+    InvalidBlock,
+    /// The peer sent a message with an invalid signature.
+    InvalidSignature,
+}
+
+impl Behavior {
+    pub fn score(self) -> i64 {
+        match self {
+            Behavior::Good => 1,
+            Behavior::UndecodableMessage => -20,
+            Behavior::InvalidResponse => -20,
+            Behavior::InvalidBlock => -50,
+            Behavior::InvalidSignature => -50,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Reputation {
+    pub score: i64,
+    /// The UNIX timestamp when the ban is lifted.
+    pub banned_until: Option<u64>,
+}
+
+impl Default for Reputation {
+    fn default() -> Self {
+        Self {
+            score: INITIAL_REPUTATION,
+            banned_until: None,
+        }
+    }
+}
+
+impl Encodable for Reputation {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        // The score is stored as its two's complement representation.
+        s.begin_list(2).append(&(self.score as u64)).append(&self.banned_until.unwrap_or(0));
+    }
+}
+
+impl Decodable for Reputation {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        let item_count = rlp.item_count()?;
+        if item_count != 2 {
+            return Err(DecoderError::RlpIncorrectListLen {
+                got: item_count,
+                expected: 2,
+            })
+        }
+        let score: u64 = rlp.val_at(0)?;
+        let banned_until: u64 = rlp.val_at(1)?;
+        Ok(Self {
+            score: score as i64,
+            banned_until: if banned_until == 0 {
+                None
+            } else {
+                Some(banned_until)
+            },
+        })
+    }
+}
+
+/// The reputations are kept by IP, the unit of bans, so a peer cannot reset its score by changing its port.
+#[derive(Default)]
+pub struct Reputations {
+    peers: HashMap<IpAddr, Reputation>,
+}
+
+impl Reputations {
+    pub fn new(reputations: Vec<(IpAddr, Reputation)>) -> Self {
+        Self {
+            peers: reputations.into_iter().collect(),
+        }
+    }
+
+    pub fn get(&self, ip: &IpAddr) -> Reputation {
+        self.peers.get(ip).cloned().unwrap_or_default()
+    }
+
+    /// Applies the behavior to the score of the peer.
+    /// Returns true if the peer is newly banned.
+    pub fn report(&mut self, ip: IpAddr, behavior: Behavior, now: u64) -> bool {
+        let reputation = self.peers.entry(ip).or_default();
+        if reputation.banned_until.is_some() {
+            return false
+        }
+        reputation.score = min(reputation.score + behavior.score(), MAX_REPUTATION);
+        if reputation.score > BAN_THRESHOLD {
+            return false
+        }
+        reputation.banned_until = Some(now + BAN_DURATION);
+        true
+    }
+
+    /// Returns the peers that are banned at the given time.
+    pub fn banned(&self, now: u64) -> Vec<IpAddr> {
+        self.peers
+            .iter()
+            .filter(|(_, reputation)| reputation.banned_until.map_or(false, |until| now < until))
+            .map(|(ip, _)| *ip)
+            .collect()
+    }
+
+    /// Lifts the bans expired at the given time and returns the peers.
+    /// The peers get a half of the threshold, so they are banned again more easily.
+    pub fn lift_expired_bans(&mut self, now: u64) -> Vec<(IpAddr, Reputation)> {
+        let mut lifted = Vec::new();
+        for (ip, reputation) in self.peers.iter_mut() {
+            match reputation.banned_until {
+                Some(until) if until <= now => {
+                    reputation.banned_until = None;
+                    reputation.score = BAN_THRESHOLD / 2;
+                    lifted.push((*ip, *reputation));
+                }
+                _ => {}
+            }
+        }
+        lifted
+    }
+
+    pub fn to_vec(&self) -> Vec<(IpAddr, Reputation)> {
+        let mut reputations: Vec<_> = self.peers.iter().map(|(ip, reputation)| (*ip, *reputation)).collect();
+        reputations.sort_unstable_by_key(|(ip, _)| *ip);
+        reputations
+    }
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).expect("There is no time machine.").as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rlp::rlp_encode_and_decode_test;
+
+    #[test]
+    fn peers_are_banned_below_the_threshold() {
+        let mut reputations = Reputations::default();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+
+        assert!(!reputations.report(ip, Behavior::InvalidBlock, 10));
+        assert!(reputations.report(ip, Behavior::InvalidBlock, 10));
+        assert_eq!(Some(10 + BAN_DURATION), reputations.get(&ip).banned_until);
+        assert_eq!(vec![ip], reputations.banned(10));
+
+        // A banned peer is not reported again.
+        assert!(!reputations.report(ip, Behavior::InvalidSignature, 11));
+        assert_eq!(-100, reputations.get(&ip).score);
+    }
+
+    #[test]
+    fn good_behaviors_are_capped() {
+        let mut reputations = Reputations::default();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        for _ in 0..(MAX_REPUTATION + 10) {
+            reputations.report(ip, Behavior::Good, 0);
+        }
+        assert_eq!(MAX_REPUTATION, reputations.get(&ip).score);
+    }
+
+    #[test]
+    fn expired_bans_are_lifted() {
+        let mut reputations = Reputations::default();
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        reputations.report(ip, Behavior::InvalidBlock, 0);
+        reputations.report(ip, Behavior::InvalidBlock, 0);
+
+        assert_eq!(Vec::<(IpAddr, Reputation)>::new(), reputations.lift_expired_bans(BAN_DURATION - 1));
+        let lifted = Reputation {
+            score: BAN_THRESHOLD / 2,
+            banned_until: None,
+        };
+        assert_eq!(vec![(ip, lifted)], reputations.lift_expired_bans(BAN_DURATION));
+        assert_eq!(Vec::<IpAddr>::new(), reputations.banned(BAN_DURATION));
+    }
+
+    #[test]
+    fn encode_and_decode_reputation() {
+        rlp_encode_and_decode_test!(Reputation {
+            score: -42,
+            banned_until: Some(1_000),
+        });
+        rlp_encode_and_decode_test!(Reputation::default());
+    }
+}
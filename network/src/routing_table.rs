@@ -34,6 +34,7 @@ enum SecretOrigin {
 
 // Candidate -> Registered -> Establishing2 -> Established
 //                 ->         Establishing1 -> Established
+// The authenticated handshake moves Candidate, Registered, Establishing1 or Establishing2 to Secured.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::large_enum_variant))]
 enum State {
@@ -57,6 +58,11 @@ enum State {
         secret_origin: SecretOrigin,
         nonce: Nonce,
     },
+    /// Established with the authenticated handshake. The session keys are kept by the connection.
+    Secured {
+        local_key_pair: KeyPair,
+        remote_static: Public,
+    },
     Banned,
 }
 
@@ -77,6 +83,10 @@ impl State {
                 local_key_pair,
                 ..
             } => Some(local_key_pair.public()),
+            State::Secured {
+                local_key_pair,
+                ..
+            } => Some(local_key_pair.public()),
             State::Banned => None,
         }
     }
@@ -97,6 +107,10 @@ impl State {
                 remote_public,
                 ..
             } => Some(remote_public),
+            State::Secured {
+                remote_static,
+                ..
+            } => Some(remote_static),
             State::Banned => None,
         }
     }
@@ -154,19 +168,23 @@ impl RoutingTable {
             Some(State::Established {
                 ..
             }) => true,
+            Some(State::Secured {
+                ..
+            }) => true,
             _ => false,
         }
     }
 
     pub fn is_established(&self, target: &SocketAddr) -> bool {
         let entries = self.entries.read();
-        if let Some(State::Established {
-            ..
-        }) = entries.get(target)
-        {
-            true
-        } else {
-            false
+        match entries.get(target) {
+            Some(State::Established {
+                ..
+            }) => true,
+            Some(State::Secured {
+                ..
+            }) => true,
+            _ => false,
         }
     }
 
@@ -207,15 +225,14 @@ impl RoutingTable {
         let entries = self.entries.read();
         entries
             .iter()
-            .filter_map(|(addr, state)| {
-                if let State::Established {
+            .filter_map(|(addr, state)| match state {
+                State::Established {
                     ..
-                } = state
-                {
-                    Some(addr)
-                } else {
-                    None
-                }
+                } => Some(addr),
+                State::Secured {
+                    ..
+                } => Some(addr),
+                _ => None,
             })
             .cloned()
             .collect()
@@ -302,6 +319,9 @@ impl RoutingTable {
             }
             State::Established {
                 ..
+            }
+            | State::Secured {
+                ..
             } => return Err("Cannot try establish. current state: established".to_string()),
             State::Establishing1(_) => return Err("Cannot try establish. current state: establishing1".to_string()),
             State::Establishing2 {
@@ -565,6 +585,9 @@ impl RoutingTable {
             } => return Err("Cannot try establish. current state: registered".to_string()),
             State::Established {
                 ..
+            }
+            | State::Secured {
+                ..
             } => return Err("Cannot try establish. current state: established".to_string()),
             State::Banned {
                 ..
@@ -574,19 +597,61 @@ impl RoutingTable {
         Ok(())
     }
 
+    /// Returns false if the recipient is also trying to connect to the initiator.
+    pub fn set_recipient_secured(&self, target: SocketAddr, remote_static: Public) -> Result<bool, String> {
+        let mut entries = self.entries.write();
+        let entry = entries.entry(target).or_default();
+        let new_state = match entry {
+            State::Candidate(local_key_pair)
+            | State::Registered {
+                local_key_pair,
+                ..
+            } => State::Secured {
+                local_key_pair: *local_key_pair,
+                remote_static,
+            },
+            State::Establishing1(_)
+            | State::Establishing2 {
+                ..
+            } => return Ok(false),
+            _ => return Err("Cannot establish a connection for Recipient".to_string()),
+        };
+        *entry = new_state;
+        Ok(true)
+    }
+
+    pub fn set_initiator_secured(&self, target: SocketAddr, remote_static: Public) -> Result<(), String> {
+        let mut entries = self.entries.write();
+        let entry = entries.entry(target).or_default();
+        let new_state = match entry {
+            State::Establishing1(local_key_pair)
+            | State::Establishing2 {
+                local_key_pair,
+                ..
+            } => State::Secured {
+                local_key_pair: *local_key_pair,
+                remote_static,
+            },
+            _ => return Err("Initiator is not establishing".to_string()),
+        };
+        *entry = new_state;
+        Ok(())
+    }
+
     // true if the connection is established
     pub fn ban(&self, target: SocketAddr) -> bool {
         let mut entries = self.entries.write();
         let entry = entries.entry(target).or_default();
         let mut new_state = State::Banned;
         std::mem::swap(&mut new_state, entry);
-        if let State::Established {
-            ..
-        } = new_state
-        {
-            true
-        } else {
-            false
+        match new_state {
+            State::Established {
+                ..
+            } => true,
+            State::Secured {
+                ..
+            } => true,
+            _ => false,
         }
     }
 
@@ -672,4 +737,28 @@ mod tests {
         let nonce = ::std::u128::MAX;
         encrypt_and_decrypt(secret, nonce);
     }
+
+    #[test]
+    fn secured_connection_is_established() {
+        let routing_table = RoutingTable::new();
+        let target = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let remote_static = Public::random();
+        assert_eq!(Ok(true), routing_table.set_recipient_secured(target, remote_static));
+        assert!(routing_table.is_established(&target));
+        assert_eq!(vec![target], routing_table.established_addresses());
+        assert!(routing_table.try_establish(target).is_err());
+    }
+
+    #[test]
+    fn recipient_yields_to_its_own_connection() {
+        let routing_table = RoutingTable::new();
+        let target = SocketAddr::v4(127, 0, 0, 1, 3485);
+        let remote_static = Public::random();
+        assert_eq!(Ok(None), routing_table.try_establish(target));
+        assert_eq!(Ok(false), routing_table.set_recipient_secured(target, remote_static));
+        assert!(!routing_table.is_established(&target));
+
+        assert_eq!(Ok(()), routing_table.set_initiator_secured(target, remote_static));
+        assert!(routing_table.is_established(&target));
+    }
 }
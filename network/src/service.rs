@@ -14,14 +14,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::address_book::AddressEntry;
+use crate::bandwidth::BandwidthLimits;
 use crate::client::Client;
 use crate::control::{Control, Error as ControlError};
 use crate::filters::{FilterEntry, FiltersControl};
+use crate::reputation::Reputation;
 use crate::routing_table::RoutingTable;
 use crate::{p2p, Api, ManagingPeerdb, NetworkExtension, SocketAddr};
 use cidr::IpCidr;
 use cio::{IoError, IoService};
-use ckey::{NetworkId, Public};
+use ckey::{KeyPair, NetworkId, Public};
 use crossbeam_channel::Sender;
 use ctimer::TimerLoop;
 use std::collections::HashMap;
@@ -37,6 +40,7 @@ pub struct Service {
 }
 
 impl Service {
+    #[allow(clippy::too_many_arguments)]
     pub fn start(
         network_id: NetworkId,
         timer_loop: TimerLoop,
@@ -47,6 +51,9 @@ impl Service {
         filters_control: Arc<dyn FiltersControl>,
         routing_table: Arc<RoutingTable>,
         peer_db: Box<dyn ManagingPeerdb>,
+        node_key: KeyPair,
+        bandwidth_limits: &BandwidthLimits,
+        legacy_handshake: bool,
     ) -> Result<Arc<Self>, Error> {
         let p2p = IoService::start("P2P")?;
 
@@ -63,6 +70,9 @@ impl Service {
             min_peers,
             max_peers,
             peer_db,
+            node_key,
+            bandwidth_limits,
+            legacy_handshake,
         )?);
         p2p.register_handler(p2p_handler.clone())?;
 
@@ -201,6 +211,18 @@ impl Control for Service {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>, ControlError> {
         Ok(self.p2p_handler.recent_network_usage())
     }
+
+    fn reputations(&self) -> Result<Vec<(IpAddr, Reputation)>, ControlError> {
+        Ok(self.p2p_handler.reputations())
+    }
+
+    fn address_book(&self) -> Result<Vec<(SocketAddr, AddressEntry)>, ControlError> {
+        Ok(self.p2p_handler.address_book())
+    }
+
+    fn remove_from_address_book(&self, addr: &SocketAddr) -> Result<bool, ControlError> {
+        Ok(self.p2p_handler.remove_from_address_book(addr))
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,261 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use super::secure_session::{CipherState, SecureSession};
+use ccrypto::{blake256, Blake};
+use ckey::{
+    exchange, sign_schnorr, verify_schnorr, Generator, KeyPair, NetworkId, Public, Random, SchnorrSignature, Secret,
+};
+use primitives::H256;
+use rlp::RlpStream;
+
+/// The version of the handshake which authenticates the node keys.
+/// The version 0 is the handshake with `Sync` and `Ack`.
+pub const HANDSHAKE_VERSION: u64 = 1;
+
+const PROTOCOL_NAME: &[u8] = b"CodeChain_SignedXX_secp256k1_AES256CTR_BLAKE2b";
+
+fn hello_hash(
+    initiator_ephemeral: &Public,
+    initiator_static: &Public,
+    network_id: &NetworkId,
+    initiator_port: u16,
+) -> H256 {
+    let mut s = RlpStream::new_list(6);
+    s.append(&PROTOCOL_NAME.to_vec())
+        .append(&HANDSHAKE_VERSION)
+        .append(initiator_ephemeral)
+        .append(initiator_static)
+        .append(network_id)
+        .append(&initiator_port);
+    blake256(s.out())
+}
+
+fn welcome_hash(hello_hash: &H256, recipient_ephemeral: &Public, recipient_static: &Public) -> H256 {
+    let mut s = RlpStream::new_list(3);
+    s.append(hello_hash).append(recipient_ephemeral).append(recipient_static);
+    blake256(s.out())
+}
+
+fn derive_key(handshake_hash: &H256, shared_secret: &Secret, label: &[u8]) -> Secret {
+    let mut data = handshake_hash.to_vec();
+    data.extend_from_slice(label);
+    Blake::blake_with_key(&data, shared_secret)
+}
+
+fn split(handshake_hash: &H256, shared_secret: &Secret, remote_static: Public, is_initiator: bool) -> SecureSession {
+    let initiator_to_recipient = CipherState::new(
+        derive_key(handshake_hash, shared_secret, b"initiator-encryption"),
+        derive_key(handshake_hash, shared_secret, b"initiator-mac"),
+    );
+    let recipient_to_initiator = CipherState::new(
+        derive_key(handshake_hash, shared_secret, b"recipient-encryption"),
+        derive_key(handshake_hash, shared_secret, b"recipient-mac"),
+    );
+    if is_initiator {
+        SecureSession::new(remote_static, initiator_to_recipient, recipient_to_initiator)
+    } else {
+        SecureSession::new(remote_static, recipient_to_initiator, initiator_to_recipient)
+    }
+}
+
+fn exchange_ephemerals(remote: &Public, local: &KeyPair) -> Result<Secret, String> {
+    exchange(remote, local.private()).map_err(|e| format!("Cannot exchange key: {:?}", e))
+}
+
+/// The state of the initiator between sending `Hello` and receiving `Welcome`.
+pub struct Initiator {
+    ephemeral: KeyPair,
+    hello_hash: H256,
+    signature: SchnorrSignature,
+}
+
+impl Initiator {
+    pub fn new(static_key: &KeyPair, network_id: &NetworkId, initiator_port: u16) -> Result<Self, String> {
+        let ephemeral = Random.generate().map_err(|e| format!("Cannot generate an ephemeral key: {:?}", e))?;
+        let hello_hash = hello_hash(ephemeral.public(), static_key.public(), network_id, initiator_port);
+        let signature =
+            sign_schnorr(static_key.private(), &hello_hash).map_err(|e| format!("Cannot sign hello: {:?}", e))?;
+        Ok(Self {
+            ephemeral,
+            hello_hash,
+            signature,
+        })
+    }
+
+    pub fn ephemeral(&self) -> &Public {
+        self.ephemeral.public()
+    }
+
+    pub fn signature(&self) -> &SchnorrSignature {
+        &self.signature
+    }
+
+    pub fn finish(
+        &self,
+        recipient_ephemeral: &Public,
+        recipient_static: &Public,
+        signature: &SchnorrSignature,
+    ) -> Result<SecureSession, String> {
+        let welcome_hash = welcome_hash(&self.hello_hash, recipient_ephemeral, recipient_static);
+        if !verify_schnorr(recipient_static, signature, &welcome_hash).unwrap_or(false) {
+            return Err(format!("Invalid welcome signature from {}", recipient_static))
+        }
+        let shared_secret = exchange_ephemerals(recipient_ephemeral, &self.ephemeral)?;
+        Ok(split(&welcome_hash, &shared_secret, *recipient_static, true))
+    }
+}
+
+pub struct Welcome {
+    pub ephemeral: Public,
+    pub signature: SchnorrSignature,
+}
+
+/// Verifies `Hello` from the initiator, and creates `Welcome` and the session for it.
+pub fn respond(
+    static_key: &KeyPair,
+    initiator_ephemeral: &Public,
+    initiator_static: &Public,
+    network_id: &NetworkId,
+    initiator_port: u16,
+    signature: &SchnorrSignature,
+) -> Result<(Welcome, SecureSession), String> {
+    let hello_hash = hello_hash(initiator_ephemeral, initiator_static, network_id, initiator_port);
+    if !verify_schnorr(initiator_static, signature, &hello_hash).unwrap_or(false) {
+        return Err(format!("Invalid hello signature from {}", initiator_static))
+    }
+    let ephemeral = Random.generate().map_err(|e| format!("Cannot generate an ephemeral key: {:?}", e))?;
+    let welcome_hash = welcome_hash(&hello_hash, ephemeral.public(), static_key.public());
+    let signature =
+        sign_schnorr(static_key.private(), &welcome_hash).map_err(|e| format!("Cannot sign welcome: {:?}", e))?;
+    let shared_secret = exchange_ephemerals(initiator_ephemeral, &ephemeral)?;
+    let welcome = Welcome {
+        ephemeral: *ephemeral.public(),
+        signature,
+    };
+    Ok((welcome, split(&welcome_hash, &shared_secret, *initiator_static, false)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(
+        initiator_key: &KeyPair,
+        recipient_key: &KeyPair,
+    ) -> Result<(SecureSession, SecureSession), String> {
+        let network_id = NetworkId::default();
+        let initiator = Initiator::new(initiator_key, &network_id, 3485)?;
+        let (welcome, recipient_session) = respond(
+            recipient_key,
+            initiator.ephemeral(),
+            initiator_key.public(),
+            &network_id,
+            3485,
+            initiator.signature(),
+        )?;
+        let initiator_session = initiator.finish(&welcome.ephemeral, recipient_key.public(), &welcome.signature)?;
+        Ok((initiator_session, recipient_session))
+    }
+
+    #[test]
+    fn both_sides_derive_the_same_keys() {
+        let initiator_key = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let (mut initiator, mut recipient) = handshake(&initiator_key, &recipient_key).unwrap();
+        assert_eq!(recipient_key.public(), initiator.remote_static());
+        assert_eq!(initiator_key.public(), recipient.remote_static());
+
+        let (encrypted, mac) = initiator.seal(b"from the initiator");
+        assert_eq!(Some(b"from the initiator".to_vec()), recipient.open(&encrypted, &mac));
+        let (encrypted, mac) = recipient.seal(b"from the recipient");
+        assert_eq!(Some(b"from the recipient".to_vec()), initiator.open(&encrypted, &mac));
+    }
+
+    #[test]
+    fn directions_use_different_keys() {
+        let initiator_key = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let (mut initiator, mut recipient) = handshake(&initiator_key, &recipient_key).unwrap();
+        let (encrypted, mac) = initiator.seal(b"reflected");
+        assert_eq!(None, initiator.open(&encrypted, &mac));
+        assert!(recipient.open(&encrypted, &mac).is_some());
+    }
+
+    #[test]
+    fn sessions_of_different_handshakes_are_independent() {
+        let initiator_key = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let (mut initiator1, _) = handshake(&initiator_key, &recipient_key).unwrap();
+        let (_, mut recipient2) = handshake(&initiator_key, &recipient_key).unwrap();
+        let (encrypted, mac) = initiator1.seal(b"data");
+        assert_eq!(None, recipient2.open(&encrypted, &mac));
+    }
+
+    #[test]
+    fn hello_signed_by_another_key_is_rejected() {
+        let network_id = NetworkId::default();
+        let initiator_key = Random.generate().unwrap();
+        let impersonated = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let initiator = Initiator::new(&initiator_key, &network_id, 3485).unwrap();
+        assert!(respond(
+            &recipient_key,
+            initiator.ephemeral(),
+            impersonated.public(),
+            &network_id,
+            3485,
+            initiator.signature()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn hello_for_another_port_is_rejected() {
+        let network_id = NetworkId::default();
+        let initiator_key = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let initiator = Initiator::new(&initiator_key, &network_id, 3485).unwrap();
+        assert!(respond(
+            &recipient_key,
+            initiator.ephemeral(),
+            initiator_key.public(),
+            &network_id,
+            3486,
+            initiator.signature()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn welcome_signed_by_another_key_is_rejected() {
+        let network_id = NetworkId::default();
+        let initiator_key = Random.generate().unwrap();
+        let recipient_key = Random.generate().unwrap();
+        let impersonated = Random.generate().unwrap();
+        let initiator = Initiator::new(&initiator_key, &network_id, 3485).unwrap();
+        let (welcome, _) = respond(
+            &recipient_key,
+            initiator.ephemeral(),
+            initiator_key.public(),
+            &network_id,
+            3485,
+            initiator.signature(),
+        )
+        .unwrap();
+        assert!(initiator.finish(&welcome.ephemeral, impersonated.public(), &welcome.signature).is_err());
+    }
+}
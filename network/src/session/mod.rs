@@ -14,8 +14,21 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+mod handshake;
+mod secure_session;
 #[cfg_attr(feature = "cargo-clippy", allow(clippy::module_inception))]
 mod session;
 
 pub type Nonce = u128;
+pub use self::handshake::{respond, Initiator, Welcome, HANDSHAKE_VERSION};
+pub use self::secure_session::{CipherState, SecureSession};
 pub use self::session::Session;
+
+/// The keys which protect the messages of an established connection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transport {
+    /// Established by the handshake version 0, which shares a secret and a nonce for both directions.
+    Legacy(Session),
+    /// Established by the authenticated handshake.
+    Secure(SecureSession),
+}
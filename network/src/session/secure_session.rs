@@ -0,0 +1,185 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::aes;
+use ccrypto::Blake;
+use ckey::{Public, Secret};
+use primitives::H256;
+
+/// Both sides replace the keys of a direction whenever this number of messages has been sent in it.
+pub const REKEY_INTERVAL: u64 = 1 << 16;
+
+/// The keys and the message counter of one direction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CipherState {
+    encryption_key: Secret,
+    mac_key: Secret,
+    counter: u64,
+}
+
+impl CipherState {
+    pub fn new(encryption_key: Secret, mac_key: Secret) -> Self {
+        Self {
+            encryption_key,
+            mac_key,
+            counter: 0,
+        }
+    }
+
+    pub fn counter(&self) -> u64 {
+        self.counter
+    }
+
+    fn iv(&self) -> u128 {
+        // The lower 64 bits are left for the block counter of AES-CTR.
+        u128::from(self.counter) << 64
+    }
+
+    fn mac(&self, encrypted: &[u8]) -> H256 {
+        let mut data = Vec::with_capacity(8 + encrypted.len());
+        data.extend_from_slice(&self.counter.to_be_bytes());
+        data.extend_from_slice(encrypted);
+        Blake::blake_with_key(&data, &self.mac_key)
+    }
+
+    pub fn seal(&mut self, data: &[u8]) -> (Vec<u8>, H256) {
+        let encrypted =
+            aes::encrypt(data, &self.encryption_key, &self.iv()).expect("The encryption key is 32 bytes long");
+        let mac = self.mac(&encrypted);
+        self.advance();
+        (encrypted, mac)
+    }
+
+    /// Returns `None` if the message is forged, replayed or reordered.
+    pub fn open(&mut self, encrypted: &[u8], mac: &H256) -> Option<Vec<u8>> {
+        if self.mac(encrypted) != *mac {
+            return None
+        }
+        let decrypted = aes::decrypt(encrypted, &self.encryption_key, &self.iv()).ok()?;
+        self.advance();
+        Some(decrypted)
+    }
+
+    fn advance(&mut self) {
+        self.counter += 1;
+        if self.counter % REKEY_INTERVAL == 0 {
+            self.rekey();
+        }
+    }
+
+    /// Replaces the keys with one-way derivations of them.
+    /// The messages sent before the rekeying cannot be decrypted with the new keys.
+    pub fn rekey(&mut self) {
+        self.encryption_key = Blake::blake_with_key(b"rekey-encryption", &self.encryption_key);
+        self.mac_key = Blake::blake_with_key(b"rekey-mac", &self.mac_key);
+    }
+}
+
+/// The session established by the authenticated handshake.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecureSession {
+    remote_static: Public,
+    sending: CipherState,
+    receiving: CipherState,
+}
+
+impl SecureSession {
+    pub fn new(remote_static: Public, sending: CipherState, receiving: CipherState) -> Self {
+        Self {
+            remote_static,
+            sending,
+            receiving,
+        }
+    }
+
+    /// The node key of the peer, which is authenticated during the handshake.
+    pub fn remote_static(&self) -> &Public {
+        &self.remote_static
+    }
+
+    pub fn seal(&mut self, data: &[u8]) -> (Vec<u8>, H256) {
+        self.sending.seal(data)
+    }
+
+    pub fn open(&mut self, encrypted: &[u8], mac: &H256) -> Option<Vec<u8>> {
+        self.receiving.open(encrypted, mac)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_pair() -> (CipherState, CipherState) {
+        let encryption_key = Secret::random();
+        let mac_key = Secret::random();
+        (CipherState::new(encryption_key, mac_key), CipherState::new(encryption_key, mac_key))
+    }
+
+    #[test]
+    fn seal_and_open() {
+        let (mut sender, mut receiver) = create_pair();
+        for i in 0..10u8 {
+            let data = vec![i; 100];
+            let (encrypted, mac) = sender.seal(&data);
+            assert_eq!(Some(data), receiver.open(&encrypted, &mac));
+        }
+        assert_eq!(10, sender.counter());
+        assert_eq!(10, receiver.counter());
+    }
+
+    #[test]
+    fn same_data_is_encrypted_differently() {
+        let (mut sender, _) = create_pair();
+        let data = b"some short data".to_vec();
+        let (encrypted1, _) = sender.seal(&data);
+        let (encrypted2, _) = sender.seal(&data);
+        assert_ne!(encrypted1, encrypted2);
+    }
+
+    #[test]
+    fn tampered_message_is_rejected() {
+        let (mut sender, mut receiver) = create_pair();
+        let (mut encrypted, mac) = sender.seal(b"some short data");
+        encrypted[0] ^= 1;
+        assert_eq!(None, receiver.open(&encrypted, &mac));
+        assert_eq!(0, receiver.counter());
+    }
+
+    #[test]
+    fn replayed_message_is_rejected() {
+        let (mut sender, mut receiver) = create_pair();
+        let (encrypted, mac) = sender.seal(b"some short data");
+        assert!(receiver.open(&encrypted, &mac).is_some());
+        assert_eq!(None, receiver.open(&encrypted, &mac));
+    }
+
+    #[test]
+    fn keys_are_replaced_periodically() {
+        let (mut sender, mut receiver) = create_pair();
+        let initial = sender.clone();
+        for _ in 0..REKEY_INTERVAL {
+            let (encrypted, mac) = sender.seal(&[]);
+            assert!(receiver.open(&encrypted, &mac).is_some());
+        }
+        assert_ne!(initial.encryption_key, sender.encryption_key);
+        assert_ne!(initial.mac_key, sender.mac_key);
+
+        let data = b"data after rekeying".to_vec();
+        let (encrypted, mac) = sender.seal(&data);
+        assert_eq!(Some(data), receiver.open(&encrypted, &mac));
+    }
+}
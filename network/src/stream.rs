@@ -322,6 +322,10 @@ impl Stream {
         self.try_stream.write.clear();
     }
 
+    pub fn has_pending_writes(&self) -> bool {
+        !self.try_stream.write.is_empty()
+    }
+
     fn read_bytes(&mut self) -> Result<Option<Vec<u8>>> {
         self.try_stream.read_bytes()
     }
@@ -21,6 +21,7 @@ use ckeystore::Error as KeystoreError;
 use cnetwork::control::Error as NetworkControlError;
 use cstate::StateError;
 use ctypes::errors::{HistoryError, RuntimeError, SyntaxError};
+use cvm::TemplateError;
 use jsonrpc_core::{Error, ErrorCode, Value};
 use rlp::DecoderError;
 use rustc_serialize::hex::FromHexError as HexError;
@@ -70,6 +71,15 @@ mod codes {
     pub const ASSET_TRANSACTION_ONLY_IN_EXECUTE_TRANSACITON: i64 = -32047;
     pub const STATE_NOT_EXIST: i64 = -32048;
     pub const ACTION_DATA_HANDLER_NOT_FOUND: i64 = -32049;
+    pub const SELF_NOMINATION_NOT_AVAILABLE: i64 = -32050;
+    pub const INVALID_SCRIPT: i64 = -32051;
+    pub const INVALID_SCRIPT_TEMPLATE: i64 = -32052;
+    pub const MEM_POOL_FULL: i64 = -32053;
+    pub const MEM_POOL_MEMORY_LIMIT: i64 = -32054;
+    pub const SIGNER_LIMIT_REACHED: i64 = -32055;
+    pub const NOT_PENDING: i64 = -32056;
+    pub const CPU_MINING_NOT_AVAILABLE: i64 = -32057;
+    pub const PARENT_DROPPED: i64 = -32058;
     pub const UNKNOWN_ERROR: i64 = -32099;
 }
 
@@ -165,6 +175,30 @@ pub fn transaction_core<T: Into<CoreError>>(error: T) -> Error {
             message: "Too Cheap to Replace".into(),
             data: Some(Value::String(format!("{:?}", error))),
         },
+        CoreError::History(error @ HistoryError::LimitReached) => Error {
+            code: ErrorCode::ServerError(codes::MEM_POOL_FULL),
+            message: "Mem Pool Full".into(),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
+        CoreError::History(error @ HistoryError::MemoryLimitReached) => Error {
+            code: ErrorCode::ServerError(codes::MEM_POOL_MEMORY_LIMIT),
+            message: "Mem Pool Memory Limit Reached".into(),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
+        CoreError::History(
+            error @ HistoryError::SignerLimitReached {
+                ..
+            },
+        ) => Error {
+            code: ErrorCode::ServerError(codes::SIGNER_LIMIT_REACHED),
+            message: format!("Signer Limit Reached: {}", error),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
+        CoreError::History(error @ HistoryError::ParentDropped) => Error {
+            code: ErrorCode::ServerError(codes::PARENT_DROPPED),
+            message: "Parent Dropped".into(),
+            data: Some(Value::String(format!("{:?}", error))),
+        },
         CoreError::History(
             error @ HistoryError::Old {
                 ..
@@ -175,6 +209,11 @@ pub fn transaction_core<T: Into<CoreError>>(error: T) -> Error {
             data: Some(Value::String(format!("{:?}", error))),
         },
         CoreError::Syntax(SyntaxError::InvalidCustomAction(err)) => invalid_custom_action(err),
+        CoreError::Syntax(SyntaxError::InvalidScript(err)) => Error {
+            code: ErrorCode::ServerError(codes::INVALID_SCRIPT),
+            message: format!("Invalid Script: {}", err),
+            data: Some(Value::String(err)),
+        },
         _ => unknown_error,
     }
 }
@@ -284,6 +323,14 @@ pub fn state_not_exist() -> Error {
     }
 }
 
+pub fn self_nomination_not_available() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::SELF_NOMINATION_NOT_AVAILABLE),
+        message: "Self nomination is not available. It requires PBFT type engine and an engine signer.".into(),
+        data: None,
+    }
+}
+
 pub fn invalid_custom_action(err: String) -> Error {
     Error {
         code: ErrorCode::ServerError(codes::ACTION_DATA_HANDLER_NOT_FOUND),
@@ -292,6 +339,30 @@ pub fn invalid_custom_action(err: String) -> Error {
     }
 }
 
+pub fn script_template(error: &TemplateError) -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::INVALID_SCRIPT_TEMPLATE),
+        message: format!("Invalid script template: {}", error),
+        data: None,
+    }
+}
+
+pub fn not_pending() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::NOT_PENDING),
+        message: "The transaction is not in the mem pool".into(),
+        data: None,
+    }
+}
+
+pub fn cpu_mining_not_available() -> Error {
+    Error {
+        code: ErrorCode::ServerError(codes::CPU_MINING_NOT_AVAILABLE),
+        message: "CPU mining is not available. It requires PoW type engine.".into(),
+        data: None,
+    }
+}
+
 /// Internal error signifying a logic error in code.
 /// Should not be used when function can just fail
 /// because of invalid parameters or incomplete node state.
@@ -16,7 +16,10 @@
 
 use super::super::errors;
 use super::super::traits::Chain;
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    AssetScheme, Block, BlockNumberAndHash, ExecuteVMResult, LockScript, OwnedAsset, ScriptTemplate, Text, Transaction,
+    UnsignedTransaction, Witness,
+};
 use ccore::{
     AccountData, AssetClient, BlockId, EngineInfo, ExecuteClient, MiningBlockChainClient, Shard, TermInfo, TextClient,
 };
@@ -25,8 +28,9 @@ use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{public_to_address, NetworkId, PlatformAddress, Public};
 use cstate::FindActionHandler;
+use cvm::{lock_script_hash, ScriptTemplate as ScriptTemplateType};
 use ctypes::transaction::{Action, ShardTransaction as ShardTransactionType};
-use ctypes::{BlockHash, BlockNumber, ShardId, Tracker, TxHash};
+use ctypes::{apply_base_fee_multiplier, BlockHash, BlockNumber, ShardId, Tracker, TxHash};
 use jsonrpc_core::Result;
 use primitives::{Bytes as BytesArray, H160, H256};
 use std::convert::{TryFrom, TryInto};
@@ -255,8 +259,10 @@ where
         }
         // Unlike other RPCs, use the latest parameters if the block number is `null`.
         let block_id = block_number.map(|n| (n - 1).into()).unwrap_or(BlockId::Latest);
-        if let Some(common_parameters) = self.client.common_params(block_id) {
-            Ok(match action_type.as_str() {
+        let common_parameters = self.client.common_params(block_id);
+        let base_fee_multiplier = self.client.base_fee_multiplier(block_id);
+        if let (Some(common_parameters), Some(base_fee_multiplier)) = (common_parameters, base_fee_multiplier) {
+            let min_cost = match action_type.as_str() {
                 "mintAsset" => Some(common_parameters.min_asset_mint_cost()),
                 "transferAsset" => Some(common_parameters.min_asset_transfer_cost()),
                 "changeAssetScheme" => Some(common_parameters.min_asset_scheme_change_cost()),
@@ -273,7 +279,8 @@ where
                 "custom" => Some(common_parameters.min_custom_transaction_cost()),
 
                 _ => None,
-            })
+            };
+            Ok(min_cost.map(|min_cost| apply_base_fee_multiplier(min_cost, base_fee_multiplier)))
         } else {
             Ok(None)
         }
@@ -333,7 +340,7 @@ where
         tx: UnsignedTransaction,
         params: Vec<Vec<BytesArray>>,
         indices: Vec<usize>,
-    ) -> Result<Vec<String>> {
+    ) -> Result<Vec<ExecuteVMResult>> {
         let action = tx.action.try_into().map_err(errors::conversion)?;
         if let Action::TransferAsset {
             inputs,
@@ -341,9 +348,40 @@ where
         } = &action
         {
             let transaction = Option::<ShardTransactionType>::from(action.clone()).unwrap();
-            Ok(self.client.execute_vm(&transaction, inputs, &params, &indices).map_err(errors::core)?)
+            let results = self.client.execute_vm(&transaction, inputs, &params, &indices).map_err(errors::core)?;
+            Ok(results
+                .into_iter()
+                .map(|(result, cost)| ExecuteVMResult {
+                    result,
+                    cost,
+                })
+                .collect())
         } else {
             Err(errors::transfer_only())
         }
     }
+
+    fn build_lock_script(&self, template: ScriptTemplate) -> Result<LockScript> {
+        let template = ScriptTemplateType::from(template);
+        let lock_script = template.lock_script().map_err(|err| errors::script_template(&err))?;
+        Ok(LockScript {
+            lock_script_hash: lock_script_hash(&lock_script),
+            lock_script,
+            parameters: template.parameters(),
+        })
+    }
+
+    fn build_unlock_script(&self, template: ScriptTemplate, tag: BytesArray, witness: Witness) -> Result<BytesArray> {
+        ScriptTemplateType::from(template)
+            .unlock_script(&tag, &witness.into())
+            .map_err(|err| errors::script_template(&err))
+    }
+
+    fn recognize_lock_script(
+        &self,
+        lock_script: BytesArray,
+        parameters: Vec<BytesArray>,
+    ) -> Result<Option<ScriptTemplate>> {
+        Ok(ScriptTemplateType::recognize(&lock_script, &parameters).map(ScriptTemplate::from))
+    }
 }
@@ -16,10 +16,10 @@
 
 use super::super::errors;
 use super::super::traits::Devel;
-use super::super::types::{TPSTestOption, TPSTestSetting};
+use super::super::types::{CpuMiningStatus, TPSTestOption, TPSTestSetting};
 use ccore::{
-    BlockId, DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient, SignedTransaction,
-    TermInfo, COL_STATE,
+    BlockId, CpuMiner, DatabaseClient, EngineClient, EngineInfo, MinerService, MiningBlockChainClient,
+    SignedTransaction, TermInfo, COL_STATE,
 };
 use ccrypto::Blake;
 use cjson::bytes::Bytes;
@@ -49,19 +49,26 @@ pub struct DevelClient<C, M> {
     db: Arc<dyn KeyValueDB>,
     miner: Arc<M>,
     block_sync: Option<EventSender<BlockSyncEvent>>,
+    cpu_miner: Option<Arc<CpuMiner>>,
 }
 
 impl<C, M> DevelClient<C, M>
 where
     C: DatabaseClient,
 {
-    pub fn new(client: Arc<C>, miner: Arc<M>, block_sync: Option<EventSender<BlockSyncEvent>>) -> Self {
+    pub fn new(
+        client: Arc<C>,
+        miner: Arc<M>,
+        block_sync: Option<EventSender<BlockSyncEvent>>,
+        cpu_miner: Option<Arc<CpuMiner>>,
+    ) -> Self {
         let db = client.database();
         Self {
             client,
             db,
             miner,
             block_sync,
+            cpu_miner,
         }
     }
 }
@@ -367,4 +374,25 @@ where
         let end_time = PreciseTime::now();
         Ok(tps(count, start_time, end_time))
     }
+
+    fn start_cpu_mining(&self, threads: Option<usize>) -> Result<()> {
+        let cpu_miner = self.cpu_miner.as_ref().ok_or_else(errors::cpu_mining_not_available)?;
+        let threads = threads.unwrap_or(1);
+        if threads == 0 {
+            return Err(jsonrpc_core::Error::invalid_params("CPU mining requires at least one thread"))
+        }
+        cpu_miner.start(threads);
+        Ok(())
+    }
+
+    fn stop_cpu_mining(&self) -> Result<()> {
+        let cpu_miner = self.cpu_miner.as_ref().ok_or_else(errors::cpu_mining_not_available)?;
+        cpu_miner.stop();
+        Ok(())
+    }
+
+    fn get_cpu_mining_status(&self) -> Result<CpuMiningStatus> {
+        let cpu_miner = self.cpu_miner.as_ref().ok_or_else(errors::cpu_mining_not_available)?;
+        Ok(cpu_miner.status().into())
+    }
 }
@@ -16,6 +16,8 @@
 
 use super::super::errors;
 use super::super::traits::Engine;
+use super::super::types::{DoubleVoteEvidence, SelfNominationStatus};
+use ccore::stake::SelfNominationManager;
 use ccore::{BlockId, EngineInfo, MinerService, StateInfo};
 use cjson::bytes::{Bytes, WithoutPrefix};
 use ckey::PlatformAddress;
@@ -29,6 +31,7 @@ where
     M: MinerService, {
     client: Arc<C>,
     miner: Arc<M>,
+    self_nomination: Option<Arc<SelfNominationManager>>,
 }
 
 impl<C, M> EngineClient<C, M>
@@ -36,10 +39,11 @@ where
     C: EngineInfo + StateInfo + FindActionHandler,
     M: MinerService,
 {
-    pub fn new(client: Arc<C>, miner: Arc<M>) -> Self {
+    pub fn new(client: Arc<C>, miner: Arc<M>, self_nomination: Option<Arc<SelfNominationManager>>) -> Self {
         Self {
             client,
             miner,
+            self_nomination,
         }
     }
 }
@@ -89,4 +93,22 @@ where
             Err(e) => Err(errors::transaction_core(e)),
         }
     }
+
+    fn get_double_vote_evidences(&self) -> Result<Vec<DoubleVoteEvidence>> {
+        Ok(self.client.double_vote_evidences().into_iter().map(From::from).collect())
+    }
+
+    fn get_self_nomination_status(&self) -> Result<Option<SelfNominationStatus>> {
+        let network_id = self.client.network_id();
+        Ok(self
+            .self_nomination
+            .as_ref()
+            .map(|manager| SelfNominationStatus::from_core(manager.status(), network_id)))
+    }
+
+    fn set_self_nomination_enabled(&self, enabled: bool) -> Result<()> {
+        let manager = self.self_nomination.as_ref().ok_or_else(errors::self_nomination_not_available)?;
+        manager.set_enabled(enabled);
+        Ok(())
+    }
 }
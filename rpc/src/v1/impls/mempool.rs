@@ -16,29 +16,39 @@
 
 use super::super::errors;
 use super::super::traits::Mempool;
-use super::super::types::{MemPoolMinFees, PendingTransactions};
-use ccore::{BlockChainClient, EngineInfo, MiningBlockChainClient, SignedTransaction};
+use super::super::types::{MemPoolMinFees, PendingTransactions, ReplaceTransactionResult, UnsignedTransaction};
+use ccore::{
+    AccountData, AccountProvider, BlockChainClient, EngineInfo, MinerService, MiningBlockChainClient,
+    SignedTransaction, TermInfo,
+};
 use cjson::bytes::Bytes;
-use ckey::{Address, PlatformAddress};
+use ckey::{Address, Password, PlatformAddress};
+use ctypes::transaction::IncompleteTransaction;
 use ctypes::{Tracker, TxHash};
 use jsonrpc_core::Result;
 use rlp::Rlp;
+use std::convert::TryInto;
 use std::sync::Arc;
-pub struct MempoolClient<C> {
+pub struct MempoolClient<C, M> {
     client: Arc<C>,
+    miner: Arc<M>,
+    account_provider: Arc<AccountProvider>,
 }
 
-impl<C> MempoolClient<C> {
-    pub fn new(client: Arc<C>) -> Self {
+impl<C, M> MempoolClient<C, M> {
+    pub fn new(client: Arc<C>, miner: Arc<M>, account_provider: Arc<AccountProvider>) -> Self {
         MempoolClient {
             client,
+            miner,
+            account_provider,
         }
     }
 }
 
-impl<C> Mempool for MempoolClient<C>
+impl<C, M> Mempool for MempoolClient<C, M>
 where
-    C: BlockChainClient + MiningBlockChainClient + EngineInfo + 'static,
+    C: BlockChainClient + MiningBlockChainClient + AccountData + EngineInfo + TermInfo + 'static,
+    M: MinerService + 'static,
 {
     fn send_signed_transaction(&self, raw: Bytes) -> Result<TxHash> {
         Rlp::new(&raw.into_vec())
@@ -55,6 +65,51 @@ where
             .map(Into::into)
     }
 
+    fn replace_transaction(
+        &self,
+        transaction_hash: TxHash,
+        tx: UnsignedTransaction,
+        passphrase: Option<Password>,
+    ) -> Result<ReplaceTransactionResult> {
+        // The seq of the replaced transaction is used.
+        let (tx, _seq): (IncompleteTransaction, Option<u64>) = tx.try_into()?;
+        let signed = self
+            .miner
+            .replace_transaction(
+                self.client.as_ref(),
+                self.account_provider.as_ref(),
+                &transaction_hash,
+                tx,
+                passphrase,
+            )
+            .map_err(errors::transaction_core)?
+            .ok_or_else(errors::not_pending)?;
+        Ok(ReplaceTransactionResult {
+            hash: signed.hash(),
+            seq: signed.seq,
+            fee: signed.fee.into(),
+            replaced: transaction_hash,
+        })
+    }
+
+    fn cancel_transaction(
+        &self,
+        transaction_hash: TxHash,
+        passphrase: Option<Password>,
+    ) -> Result<ReplaceTransactionResult> {
+        let signed = self
+            .miner
+            .cancel_transaction(self.client.as_ref(), self.account_provider.as_ref(), &transaction_hash, passphrase)
+            .map_err(errors::transaction_core)?
+            .ok_or_else(errors::not_pending)?;
+        Ok(ReplaceTransactionResult {
+            hash: signed.hash(),
+            seq: signed.seq,
+            fee: signed.fee.into(),
+            replaced: transaction_hash,
+        })
+    }
+
     fn get_transaction_results_by_tracker(&self, tracker: Tracker) -> Result<Vec<bool>> {
         Ok(self
             .client
@@ -16,9 +16,9 @@
 
 use super::super::errors;
 use super::super::traits::Miner;
-use super::super::types::Work;
+use super::super::types::{StratumWorker, Work};
 use ccore::block::IsBlock;
-use ccore::{EngineClient, EngineInfo, MinerService, MiningBlockChainClient, TermInfo};
+use ccore::{EngineClient, EngineInfo, MinerService, MiningBlockChainClient, Stratum, TermInfo};
 use cjson::bytes::Bytes;
 use ctypes::BlockHash;
 use jsonrpc_core::Result;
@@ -27,13 +27,15 @@ use std::sync::Arc;
 pub struct MinerClient<C, M> {
     client: Arc<C>,
     miner: Arc<M>,
+    stratum: Option<Arc<Stratum>>,
 }
 
 impl<C, M> MinerClient<C, M> {
-    pub fn new(client: Arc<C>, miner: Arc<M>) -> Self {
+    pub fn new(client: Arc<C>, miner: Arc<M>, stratum: Option<Arc<Stratum>>) -> Self {
         Self {
             client,
             miner,
+            stratum,
         }
     }
 }
@@ -73,4 +75,11 @@ where
         let seal = seal.iter().cloned().map(Into::into).collect();
         Ok(self.miner.submit_seal(&*self.client, pow_hash, seal).is_ok())
     }
+
+    fn get_stratum_workers(&self) -> Result<Vec<StratumWorker>> {
+        Ok(match &self.stratum {
+            Some(stratum) => stratum.worker_stats().into_iter().map(From::from).collect(),
+            None => Vec::new(),
+        })
+    }
 }
@@ -16,7 +16,7 @@
 
 use super::super::errors;
 use super::super::traits::Net;
-use super::super::types::FilterStatus;
+use super::super::types::{AddressBookEntry, FilterStatus, PeerReputation};
 use cidr::IpCidr;
 use ckey::Public;
 use cnetwork::{NetworkControl, SocketAddr};
@@ -129,4 +129,36 @@ impl Net for NetClient {
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>> {
         Ok(self.network_control.recent_network_usage().map_err(|e| errors::network_control(&e))?)
     }
+
+    fn get_reputations(&self) -> Result<Vec<PeerReputation>> {
+        let reputations = self.network_control.reputations().map_err(|e| errors::network_control(&e))?;
+        Ok(reputations
+            .into_iter()
+            .map(|(address, reputation)| PeerReputation {
+                address,
+                score: reputation.score,
+                banned_until: reputation.banned_until,
+            })
+            .collect())
+    }
+
+    fn get_address_book(&self) -> Result<Vec<AddressBookEntry>> {
+        let entries = self.network_control.address_book().map_err(|e| errors::network_control(&e))?;
+        Ok(entries
+            .into_iter()
+            .map(|(address, entry)| AddressBookEntry {
+                address: address.into(),
+                last_seen: entry.last_seen,
+                last_success: entry.last_success,
+                failures: entry.failures,
+                next_attempt: entry.next_attempt,
+            })
+            .collect())
+    }
+
+    fn remove_from_address_book(&self, address: IpAddr, port: u16) -> Result<bool> {
+        self.network_control
+            .remove_from_address_book(&SocketAddr::new(address, port))
+            .map_err(|e| errors::network_control(&e))
+    }
 }
@@ -14,7 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{AssetScheme, Block, BlockNumberAndHash, OwnedAsset, Text, Transaction, UnsignedTransaction};
+use super::super::types::{
+    AssetScheme, Block, BlockNumberAndHash, ExecuteVMResult, LockScript, OwnedAsset, ScriptTemplate, Text, Transaction,
+    UnsignedTransaction, Witness,
+};
 use cjson::scheme::Params;
 use cjson::uint::Uint;
 use ckey::{NetworkId, PlatformAddress, Public};
@@ -188,5 +191,21 @@ pub trait Chain {
         tx: UnsignedTransaction,
         params: Vec<Vec<BytesArray>>,
         indices: Vec<usize>,
-    ) -> Result<Vec<String>>;
+    ) -> Result<Vec<ExecuteVMResult>>;
+
+    /// Builds the lock script and the parameters of a standard script
+    #[rpc(name = "chain_buildLockScript")]
+    fn build_lock_script(&self, template: ScriptTemplate) -> Result<LockScript>;
+
+    /// Builds the unlock script of a standard script from the signatures made with the tag
+    #[rpc(name = "chain_buildUnlockScript")]
+    fn build_unlock_script(&self, template: ScriptTemplate, tag: BytesArray, witness: Witness) -> Result<BytesArray>;
+
+    /// Finds the standard script of the lock script and the parameters
+    #[rpc(name = "chain_recognizeLockScript")]
+    fn recognize_lock_script(
+        &self,
+        lock_script: BytesArray,
+        parameters: Vec<BytesArray>,
+    ) -> Result<Option<ScriptTemplate>>;
 }
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::TPSTestSetting;
+use super::super::types::{CpuMiningStatus, TPSTestSetting};
 use cjson::bytes::Bytes;
 use ctypes::BlockHash;
 use jsonrpc_core::Result;
@@ -46,4 +46,13 @@ pub trait Devel {
 
     #[rpc(name = "devel_testTPS")]
     fn test_tps(&self, setting: TPSTestSetting) -> Result<f64>;
+
+    #[rpc(name = "devel_startCpuMining")]
+    fn start_cpu_mining(&self, threads: Option<usize>) -> Result<()>;
+
+    #[rpc(name = "devel_stopCpuMining")]
+    fn stop_cpu_mining(&self) -> Result<()>;
+
+    #[rpc(name = "devel_getCpuMiningStatus")]
+    fn get_cpu_mining_status(&self) -> Result<CpuMiningStatus>;
 }
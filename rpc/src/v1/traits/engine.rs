@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use super::super::types::{DoubleVoteEvidence, SelfNominationStatus};
 use cjson::bytes::{Bytes, WithoutPrefix};
 use ckey::PlatformAddress;
 use jsonrpc_core::Result;
@@ -40,4 +41,16 @@ pub trait Engine {
         key_fragment: Bytes,
         block_number: Option<u64>,
     ) -> Result<Option<WithoutPrefix<Bytes>>>;
+
+    /// Gets the double votes found by this node and the transactions reporting them.
+    #[rpc(name = "engine_getDoubleVoteEvidences")]
+    fn get_double_vote_evidences(&self) -> Result<Vec<DoubleVoteEvidence>>;
+
+    /// Gets the state of the self nomination of the engine signer.
+    #[rpc(name = "engine_getSelfNominationStatus")]
+    fn get_self_nomination_status(&self) -> Result<Option<SelfNominationStatus>>;
+
+    /// Enables or pauses the self nomination of the engine signer.
+    #[rpc(name = "engine_setSelfNominationEnabled")]
+    fn set_self_nomination_enabled(&self, enabled: bool) -> Result<()>;
 }
@@ -14,9 +14,9 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::{MemPoolMinFees, PendingTransactions};
+use super::super::types::{MemPoolMinFees, PendingTransactions, ReplaceTransactionResult, UnsignedTransaction};
 use cjson::bytes::Bytes;
-use ckey::PlatformAddress;
+use ckey::{Password, PlatformAddress};
 use ctypes::{Tracker, TxHash};
 use jsonrpc_core::Result;
 
@@ -26,6 +26,23 @@ pub trait Mempool {
     #[rpc(name = "mempool_sendSignedTransaction")]
     fn send_signed_transaction(&self, raw: Bytes) -> Result<TxHash>;
 
+    /// Replaces the pending transaction with the given one, raising the fee enough to replace it.
+    #[rpc(name = "mempool_replaceTransaction")]
+    fn replace_transaction(
+        &self,
+        transaction_hash: TxHash,
+        tx: UnsignedTransaction,
+        passphrase: Option<Password>,
+    ) -> Result<ReplaceTransactionResult>;
+
+    /// Cancels the pending transaction by replacing it with a Pay of zero quantity to its signer.
+    #[rpc(name = "mempool_cancelTransaction")]
+    fn cancel_transaction(
+        &self,
+        transaction_hash: TxHash,
+        passphrase: Option<Password>,
+    ) -> Result<ReplaceTransactionResult>;
+
     /// Gets transaction results with given transaction tracker.
     #[rpc(name = "mempool_getTransactionResultsByTracker")]
     fn get_transaction_results_by_tracker(&self, tracker: Tracker) -> Result<Vec<bool>>;
@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::Work;
+use super::super::types::{StratumWorker, Work};
 use cjson::bytes::Bytes;
 use ctypes::BlockHash;
 use jsonrpc_core::Result;
@@ -26,4 +26,7 @@ pub trait Miner {
 
     #[rpc(name = "miner_submitWork")]
     fn submit_work(&self, pow_hash: BlockHash, seal: Vec<Bytes>) -> Result<bool>;
+
+    #[rpc(name = "miner_getStratumWorkers")]
+    fn get_stratum_workers(&self) -> Result<Vec<StratumWorker>>;
 }
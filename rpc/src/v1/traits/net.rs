@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::super::types::FilterStatus;
+use super::super::types::{AddressBookEntry, FilterStatus, PeerReputation};
 use cidr::IpCidr;
 use ckey::Public;
 use jsonrpc_core::Result;
@@ -79,4 +79,13 @@ pub trait Net {
 
     #[rpc(name = "net_recentNetworkUsage")]
     fn recent_network_usage(&self) -> Result<HashMap<String, usize>>;
+
+    #[rpc(name = "net_getReputations")]
+    fn get_reputations(&self) -> Result<Vec<PeerReputation>>;
+
+    #[rpc(name = "net_getAddressBook")]
+    fn get_address_book(&self) -> Result<Vec<AddressBookEntry>>;
+
+    #[rpc(name = "net_removeFromAddressBook")]
+    fn remove_from_address_book(&self, addr: IpAddr, port: u16) -> Result<bool>;
 }
@@ -0,0 +1,46 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use cjson::bytes::Bytes;
+use ctypes::TxHash;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoubleVoteEvidence {
+    signer_index: usize,
+    height: u64,
+    view: u64,
+    step: u8,
+    message1: Bytes,
+    message2: Bytes,
+    transaction_hash: Option<TxHash>,
+}
+
+impl From<ccore::DoubleVoteEvidence> for DoubleVoteEvidence {
+    fn from(evidence: ccore::DoubleVoteEvidence) -> Self {
+        let round = evidence.message1.round();
+        Self {
+            signer_index: evidence.message1.signer_index(),
+            height: round.height,
+            view: round.view,
+            step: round.step.number(),
+            message1: rlp::encode(&evidence.message1).into(),
+            message2: rlp::encode(&evidence.message2).into(),
+            transaction_hash: evidence.report,
+        }
+    }
+}
@@ -20,7 +20,10 @@ mod asset_input;
 mod asset_output;
 mod asset_scheme;
 mod block;
+mod double_vote_evidence;
 mod mem_pool;
+mod script_template;
+mod self_nomination;
 mod text;
 mod transaction;
 mod unsigned_transaction;
@@ -35,12 +38,16 @@ pub use self::asset::OwnedAsset;
 pub use self::asset_scheme::AssetScheme;
 pub use self::block::Block;
 pub use self::block::BlockNumberAndHash;
+pub use self::double_vote_evidence::DoubleVoteEvidence;
 pub use self::mem_pool::MemPoolMinFees;
+pub use self::script_template::{LockScript, ScriptTemplate, Witness};
+pub use self::self_nomination::SelfNominationStatus;
 pub use self::text::Text;
 pub use self::transaction::{PendingTransactions, Transaction};
 pub use self::unsigned_transaction::UnsignedTransaction;
-pub use self::work::Work;
+pub use self::work::{CpuMiningStatus, StratumWorker, Work};
 
+use cjson::uint::Uint;
 use ctypes::TxHash;
 use serde::de::{self, Deserialize, Deserializer};
 
@@ -50,12 +57,44 @@ pub struct FilterStatus {
     pub enabled: bool,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressBookEntry {
+    pub address: ::std::net::SocketAddr,
+    pub last_seen: u64,
+    pub last_success: Option<u64>,
+    pub failures: u32,
+    pub next_attempt: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerReputation {
+    pub address: ::std::net::IpAddr,
+    pub score: i64,
+    pub banned_until: Option<u64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SendTransactionResult {
     pub hash: TxHash,
     pub seq: u64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplaceTransactionResult {
+    pub hash: TxHash,
+    pub seq: u64,
+    pub fee: Uint,
+    pub replaced: TxHash,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecuteVMResult {
+    pub result: String,
+    pub cost: u64,
+}
+
 #[derive(Debug)]
 pub enum TPSTestOption {
     PayOnly,
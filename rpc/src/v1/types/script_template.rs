@@ -0,0 +1,223 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ckey::{Public, Signature};
+use ctypes::transaction::Timelock;
+use cvm::{ScriptTemplate as ScriptTemplateType, TimelockType, Witness as WitnessType};
+use primitives::{Bytes, H160, H256};
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum ScriptTemplate {
+    P2pkh {
+        #[serde(rename = "publicKeyHash")]
+        public_key_hash: H160,
+    },
+    P2pkhBurn {
+        #[serde(rename = "publicKeyHash")]
+        public_key_hash: H160,
+    },
+    Multisig {
+        required: u8,
+        #[serde(rename = "publicKeys")]
+        public_keys: Vec<Public>,
+    },
+    HashTimelock {
+        hash: H256,
+        recipient: Public,
+        refunder: Public,
+        timelock: Timelock,
+    },
+}
+
+impl From<ScriptTemplateType> for ScriptTemplate {
+    fn from(from: ScriptTemplateType) -> Self {
+        match from {
+            ScriptTemplateType::P2pkh {
+                public_key_hash,
+            } => ScriptTemplate::P2pkh {
+                public_key_hash,
+            },
+            ScriptTemplateType::P2pkhBurn {
+                public_key_hash,
+            } => ScriptTemplate::P2pkhBurn {
+                public_key_hash,
+            },
+            ScriptTemplateType::Multisig {
+                required,
+                publics,
+            } => ScriptTemplate::Multisig {
+                required,
+                public_keys: publics,
+            },
+            ScriptTemplateType::HashTimelock {
+                hash,
+                recipient,
+                refunder,
+                timelock_type,
+                timelock,
+            } => ScriptTemplate::HashTimelock {
+                hash,
+                recipient,
+                refunder,
+                timelock: match timelock_type {
+                    TimelockType::Block => Timelock::Block(timelock),
+                    TimelockType::BlockAge => Timelock::BlockAge(timelock),
+                    TimelockType::Time => Timelock::Time(timelock),
+                    TimelockType::TimeAge => Timelock::TimeAge(timelock),
+                },
+            },
+        }
+    }
+}
+
+impl From<ScriptTemplate> for ScriptTemplateType {
+    fn from(from: ScriptTemplate) -> Self {
+        match from {
+            ScriptTemplate::P2pkh {
+                public_key_hash,
+            } => ScriptTemplateType::P2pkh {
+                public_key_hash,
+            },
+            ScriptTemplate::P2pkhBurn {
+                public_key_hash,
+            } => ScriptTemplateType::P2pkhBurn {
+                public_key_hash,
+            },
+            ScriptTemplate::Multisig {
+                required,
+                public_keys,
+            } => ScriptTemplateType::Multisig {
+                required,
+                publics: public_keys,
+            },
+            ScriptTemplate::HashTimelock {
+                hash,
+                recipient,
+                refunder,
+                timelock,
+            } => {
+                let (timelock_type, timelock) = match timelock {
+                    Timelock::Block(value) => (TimelockType::Block, value),
+                    Timelock::BlockAge(value) => (TimelockType::BlockAge, value),
+                    Timelock::Time(value) => (TimelockType::Time, value),
+                    Timelock::TimeAge(value) => (TimelockType::TimeAge, value),
+                };
+                ScriptTemplateType::HashTimelock {
+                    hash,
+                    recipient,
+                    refunder,
+                    timelock_type,
+                    timelock,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Witness {
+    Signature {
+        signature: Signature,
+        #[serde(rename = "publicKey")]
+        public_key: Public,
+    },
+    Signatures {
+        signatures: Vec<Signature>,
+    },
+    Preimage {
+        signature: Signature,
+        preimage: Bytes,
+    },
+    Refund {
+        signature: Signature,
+    },
+}
+
+impl From<Witness> for WitnessType {
+    fn from(from: Witness) -> Self {
+        match from {
+            Witness::Signature {
+                signature,
+                public_key,
+            } => WitnessType::Signature {
+                signature,
+                public: public_key,
+            },
+            Witness::Signatures {
+                signatures,
+            } => WitnessType::Signatures(signatures),
+            Witness::Preimage {
+                signature,
+                preimage,
+            } => WitnessType::Preimage {
+                signature,
+                preimage,
+            },
+            Witness::Refund {
+                signature,
+            } => WitnessType::Refund {
+                signature,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockScript {
+    pub lock_script: Bytes,
+    pub lock_script_hash: H160,
+    pub parameters: Vec<Bytes>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_hash_timelock() {
+        let template: ScriptTemplate = serde_json::from_str(&format!(
+            r#"{{"type":"hashTimelock","hash":"0x{}","recipient":"0x{}","refunder":"0x{}","timelock":{{"type":"blockAge","value":10}}}}"#,
+            "11".repeat(32),
+            "22".repeat(64),
+            "33".repeat(64)
+        ))
+        .unwrap();
+        assert_eq!(
+            ScriptTemplateType::from(template),
+            ScriptTemplateType::HashTimelock {
+                hash: H256::from([0x11; 32]),
+                recipient: Public::from([0x22; 64]),
+                refunder: Public::from([0x33; 64]),
+                timelock_type: TimelockType::BlockAge,
+                timelock: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn serialize_p2pkh() {
+        let template = ScriptTemplate::from(ScriptTemplateType::P2pkh {
+            public_key_hash: H160::zero(),
+        });
+        assert_eq!(
+            serde_json::to_string(&template).unwrap(),
+            r#"{"type":"p2pkh","publicKeyHash":"0x0000000000000000000000000000000000000000"}"#
+        );
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccore::stake::{NextAction, SelfNominationStatus as CoreSelfNominationStatus};
+use ckey::{NetworkId, PlatformAddress};
+use ctypes::TxHash;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfNominationStatus {
+    enabled: bool,
+    address: PlatformAddress,
+    deposit: u64,
+    nomination_ends_at: Option<u64>,
+    target_deposit: u64,
+    next_action: &'static str,
+    next_deposit: Option<u64>,
+    blocked_reason: Option<String>,
+    next_check_at: u64,
+    last_transaction_hash: Option<TxHash>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+}
+
+impl SelfNominationStatus {
+    pub fn from_core(status: CoreSelfNominationStatus, network_id: NetworkId) -> Self {
+        let (next_action, next_deposit, blocked_reason) = match status.next_action {
+            NextAction::Nothing => ("nothing", None, None),
+            NextAction::Nominate {
+                deposit,
+            } => ("nominate", Some(deposit), None),
+            NextAction::WaitForTransaction(_) => ("waitForTransaction", None, None),
+            NextAction::Blocked(reason) => ("blocked", None, Some(reason)),
+            NextAction::Paused => ("paused", None, None),
+        };
+        Self {
+            enabled: status.enabled,
+            address: PlatformAddress::new_v1(network_id, status.address),
+            deposit: status.deposit,
+            nomination_ends_at: status.nomination_ends_at,
+            target_deposit: status.target_deposit,
+            next_action,
+            next_deposit,
+            blocked_reason,
+            next_check_at: status.next_check_at,
+            last_transaction_hash: status.last_transaction,
+            last_error: status.last_error,
+            consecutive_failures: status.consecutive_failures,
+        }
+    }
+}
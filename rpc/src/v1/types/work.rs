@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use ccore::{CpuMiningStatus as CoreCpuMiningStatus, StratumWorkerStats};
 use primitives::{H256, U256};
 
 #[derive(Debug, Serialize)]
@@ -22,3 +23,47 @@ pub struct Work {
     pub pow_hash: H256,
     pub target: U256,
 }
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StratumWorker {
+    pub worker_id: String,
+    pub share_divisor: u64,
+    pub valid_shares: u64,
+    pub stale_shares: u64,
+    pub invalid_shares: u64,
+    pub blocks: u64,
+}
+
+impl From<StratumWorkerStats> for StratumWorker {
+    fn from(stats: StratumWorkerStats) -> Self {
+        Self {
+            worker_id: stats.worker_id,
+            share_divisor: stats.share_divisor,
+            valid_shares: stats.valid_shares,
+            stale_shares: stats.stale_shares,
+            invalid_shares: stats.invalid_shares,
+            blocks: stats.blocks,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuMiningStatus {
+    pub running: bool,
+    pub threads: usize,
+    pub hashes: usize,
+    pub blocks: usize,
+}
+
+impl From<CoreCpuMiningStatus> for CpuMiningStatus {
+    fn from(status: CoreCpuMiningStatus) -> Self {
+        Self {
+            running: status.running,
+            threads: status.threads,
+            hashes: status.hashes,
+            blocks: status.blocks,
+        }
+    }
+}
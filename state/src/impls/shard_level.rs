@@ -28,7 +28,7 @@ use ctypes::transaction::{
 };
 use ctypes::util::unexpected::Mismatch;
 use ctypes::{BlockNumber, ShardId, Tracker};
-use cvm::{decode, execute, ChainTimeInfo, ScriptResult, VMConfig};
+use cvm::{decode, execute, ChainTimeInfo, RuntimeError as VMRuntimeError, ScriptResult, VMConfig};
 use merkle_trie::{Result as TrieResult, TrieError, TrieFactory};
 use primitives::{Bytes, H160, H256};
 use std::cell::{RefCell, RefMut};
@@ -103,6 +103,7 @@ impl<'db> ShardLevelState<'db> {
         shard_users: &[Address],
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -146,6 +147,7 @@ impl<'db> ShardLevelState<'db> {
                     inputs,
                     outputs,
                     client,
+                    vm_config,
                     parent_block_number,
                     parent_block_timestamp,
                 )
@@ -187,7 +189,15 @@ impl<'db> ShardLevelState<'db> {
                 ..
             } => {
                 assert_eq!(burn.prev_out.shard_id, self.shard_id);
-                self.unwrap_ccc(&transaction, sender, burn, client, parent_block_number, parent_block_timestamp)
+                self.unwrap_ccc(
+                    &transaction,
+                    sender,
+                    burn,
+                    client,
+                    vm_config,
+                    parent_block_number,
+                    parent_block_timestamp,
+                )
             }
             ShardTransaction::WrapCCC {
                 tx_hash,
@@ -273,6 +283,7 @@ impl<'db> ShardLevelState<'db> {
         inputs: &[AssetTransferInput],
         outputs: &[AssetTransferOutput],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -291,6 +302,7 @@ impl<'db> ShardLevelState<'db> {
                 sender,
                 approvers,
                 client,
+                vm_config,
                 parent_block_number,
                 parent_block_timestamp,
             )?;
@@ -524,6 +536,7 @@ impl<'db> ShardLevelState<'db> {
         sender: &Address,
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -548,7 +561,7 @@ impl<'db> ShardLevelState<'db> {
                 &asset.parameters(),
                 &lock_script,
                 to_hash,
-                VMConfig::default(),
+                vm_config,
                 input,
                 burn,
                 client,
@@ -558,6 +571,10 @@ impl<'db> ShardLevelState<'db> {
             // FIXME : Deliver full decode error
             _ => return Err(RuntimeError::InvalidScript.into()),
         };
+        if let Err(VMRuntimeError::InactiveInstruction) = script_result {
+            // The instructions of the later eras cannot be decoded before the era.
+            return Err(RuntimeError::InvalidScript.into())
+        }
 
         match (script_result, burn) {
             (Ok(ScriptResult::Burnt), true) => Ok(()),
@@ -614,6 +631,7 @@ impl<'db> ShardLevelState<'db> {
         sender: &Address,
         burn: &AssetTransferInput,
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -626,6 +644,7 @@ impl<'db> ShardLevelState<'db> {
             sender,
             &approvers,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
         )?;
@@ -739,6 +758,7 @@ impl<'db> ShardState for ShardLevelState<'db> {
         shard_users: &[Address],
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -751,6 +771,7 @@ impl<'db> ShardState for ShardLevelState<'db> {
             shard_users,
             approvers,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
         );
@@ -826,7 +847,10 @@ mod tests {
 
         let transaction_tracker = transaction.tracker();
         let asset_type = Blake::blake(*transaction_tracker);
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, approver: approver }),
@@ -853,7 +877,10 @@ mod tests {
         let transaction_tracker = transaction.tracker();
         let asset_type = Blake::blake(*transaction_tracker);
 
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: ::std::u64::MAX, approver: approver }),
@@ -880,14 +907,17 @@ mod tests {
 
         let transaction_tracker = transaction.tracker();
         let asset_type = Blake::blake(*transaction_tracker);
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         assert_eq!(
             Err(StateError::Runtime(RuntimeError::AssetSchemeDuplicated {
                 tracker: transaction_tracker,
                 shard_id: SHARD_ID
             })),
-            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -912,7 +942,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, approver: approver }),
@@ -927,7 +957,7 @@ mod tests {
 
         assert_eq!(
             Err(StateError::Runtime(RuntimeError::NotApproved(approver))),
-            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -951,7 +981,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -969,7 +999,10 @@ mod tests {
         );
         let transfer_tracker = transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -980,6 +1013,38 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn instructions_of_later_eras_are_invalid_scripts() {
+        let sender = address();
+        let mut state_db = RefCell::new(get_temp_state_db());
+        let mut shard_cache = ShardCache::default();
+        let mut state = get_temp_shard_state(&mut state_db, SHARD_ID, &mut shard_cache);
+
+        // PUSH 1 PUSH 1 ADD
+        let lock_script = vec![0x30, 0x01, 0x30, 0x01, 0x40];
+        let lock_script_hash = cvm::lock_script_hash(&lock_script);
+        let mint = asset_mint!(asset_mint_output!(lock_script_hash, supply: 30), "metadata".to_string());
+        let mint_tracker = mint.tracker();
+        let asset_type = Blake::blake(*mint_tracker);
+
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
+
+        let transfer = asset_transfer!(
+            inputs: asset_transfer_inputs![(asset_out_point!(mint_tracker, 0, asset_type, 30), lock_script)],
+            asset_transfer_outputs![(H160::random(), asset_type, 30)]
+        );
+        assert_eq!(
+            Err(StateError::Runtime(RuntimeError::InvalidScript)),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
+
+        let vm_config = VMConfig {
+            era: cvm::EXTENDED_INSTRUCTIONS_ERA,
+            ..VMConfig::default()
+        };
+        assert_eq!(Ok(()), state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), vm_config, 0, 0));
+    }
+
     #[test]
     fn mint_and_transfer_allowed() {
         let sender = address();
@@ -1001,7 +1066,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, allowed_script_hashes: allowed_script_hashes}),
@@ -1018,7 +1083,10 @@ mod tests {
         );
         let transfer_tracker = transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, allowed_script_hashes: allowed_script_hashes}),
@@ -1049,7 +1117,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
 
         check_shard_level_state!(state, [
@@ -1065,7 +1133,7 @@ mod tests {
 
         assert_eq!(
             Err(StateError::Runtime(RuntimeError::ScriptNotAllowed(lock_script_hash))),
-            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -1089,7 +1157,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -1102,7 +1170,7 @@ mod tests {
 
         let burn_tracker = burn.tracker();
 
-        assert_eq!(Ok(()), state.apply(&burn, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&burn, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: 0 }),
@@ -1126,7 +1194,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -1146,7 +1214,10 @@ mod tests {
         );
         let transfer_tracker = transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -1161,7 +1232,7 @@ mod tests {
         );
         let burn_tracker = burn.tracker();
 
-        assert_eq!(Ok(()), state.apply(&burn, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&burn, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount - burn_amount }),
@@ -1190,7 +1261,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, registrar: registrar }),
@@ -1209,7 +1280,10 @@ mod tests {
         );
         let transfer_tracker = transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&transfer, &registrar, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer, &registrar, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, registrar: registrar }),
@@ -1237,7 +1311,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, registrar: registrar }),
@@ -1248,7 +1322,10 @@ mod tests {
             asset_transfer!(burns: asset_transfer_inputs![(asset_out_point!(mint_tracker, 0, asset_type, amount))]);
         let burn_tracker = burn.tracker();
 
-        assert_eq!(Ok(()), state.apply(&burn, &registrar, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&burn, &registrar, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: 0, registrar: registrar }),
@@ -1271,7 +1348,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -1292,7 +1369,7 @@ mod tests {
                 expected: 30,
                 got: 20
             })),
-            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -1323,7 +1400,7 @@ mod tests {
         let mint_tracker2 = mint2.tracker();
         let asset_type2 = Blake::blake(*mint_tracker2);
 
-        assert_eq!(Ok(()), state.apply(&mint1, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint1, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type1) => { metadata: metadata1, supply: amount }),
@@ -1331,7 +1408,7 @@ mod tests {
             (asset: (mint_tracker1, 0) => { asset_type: asset_type1, quantity: amount })
         ]);
 
-        assert_eq!(Ok(()), state.apply(&mint2, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint2, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type1) => { metadata: metadata1, supply: amount }),
@@ -1354,7 +1431,7 @@ mod tests {
                     expected: asset_type1,
                 }
             })),
-            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -1382,7 +1459,10 @@ mod tests {
         let asset_type = H160::zero();
 
         assert_eq!(*wrap_ccc_tracker, *tx_hash);
-        assert_eq!(Ok(()), state.apply(&wrap_ccc, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&wrap_ccc, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { supply: amount }),
@@ -1395,7 +1475,10 @@ mod tests {
             sender
         );
 
-        assert_eq!(Ok(()), state.apply(&unwrap_ccc, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&unwrap_ccc, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { supply: amount - unwrap_amount }),
@@ -1418,7 +1501,10 @@ mod tests {
         let wrap_ccc_tracker = wrap_ccc.tracker();
 
         assert_eq!(*wrap_ccc_tracker, *tx_hash);
-        assert_eq!(Ok(()), state.apply(&wrap_ccc, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&wrap_ccc, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         let asset_type = H160::zero();
 
@@ -1439,7 +1525,10 @@ mod tests {
         );
         let transfer_tracker = transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { supply: amount }),
@@ -1455,7 +1544,10 @@ mod tests {
             sender
         );
 
-        assert_eq!(Ok(()), state.apply(&unwrap_ccc, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&unwrap_ccc, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { supply: amount - unwrap_amount }),
@@ -1480,7 +1572,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata.clone(), supply: amount }),
@@ -1501,7 +1593,7 @@ mod tests {
                 expected: lock_script_hash,
                 found: Blake::blake(&failed_lock_script),
             }))),
-            state.apply(&failed_transfer, &sender, &[sender], &[], &get_test_client(), 0, 0)
+            state.apply(&failed_transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -1521,7 +1613,10 @@ mod tests {
         );
         let successful_transfer_tracker = successful_transfer.tracker();
 
-        assert_eq!(Ok(()), state.apply(&successful_transfer, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&successful_transfer, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount }),
@@ -1552,7 +1647,10 @@ mod tests {
         let transaction_tracker = transaction.tracker();
         let asset_type = Blake::blake(*transaction_tracker);
 
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: ::std::u64::MAX }),
@@ -1582,7 +1680,7 @@ mod tests {
 
         assert_eq!(
             Err(StateError::Runtime(RuntimeError::InsufficientPermission)),
-            state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), 0, 0)
+            state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), VMConfig::default(), 0, 0)
         );
 
         check_shard_level_state!(state, [
@@ -1619,7 +1717,10 @@ mod tests {
             (asset: (transaction_tracker, 0))
         ]);
 
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: ::std::u64::MAX }),
@@ -1655,7 +1756,10 @@ mod tests {
             (asset: (transaction_tracker, 0))
         ]);
 
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&transaction, &sender, &shard_users, &approvers, &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: ::std::u64::MAX }),
@@ -1683,7 +1787,7 @@ mod tests {
         let transaction_tracker = transaction.tracker();
         let asset_type = Blake::blake(*transaction_tracker);
 
-        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&transaction, &sender, &[], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: ::std::u64::MAX, approver: approver }),
@@ -1712,7 +1816,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, approver, registrar: registrar }),
@@ -1730,7 +1834,19 @@ mod tests {
             registrar: None,
             allowed_script_hashes: Vec::new(),
         };
-        assert_eq!(Ok(()), state.apply(&change_asset_scheme, &sender, &[], &[registrar], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(
+                &change_asset_scheme,
+                &sender,
+                &[],
+                &[registrar],
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0
+            )
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: "New metadata".to_string(), supply: amount, approver: approver, registrar }),
@@ -1759,7 +1875,7 @@ mod tests {
         let mint_tracker = mint.tracker();
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), 0, 0));
+        assert_eq!(Ok(()), state.apply(&mint, &sender, &[sender], &[], &get_test_client(), VMConfig::default(), 0, 0));
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: metadata, supply: amount, approver, registrar: registrar }),
@@ -1780,7 +1896,10 @@ mod tests {
         };
         let supply_tracker = increase_supply.tracker();
 
-        assert_eq!(Ok(()), state.apply(&increase_supply, &sender, &[], &[registrar], &get_test_client(), 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&increase_supply, &sender, &[], &[registrar], &get_test_client(), VMConfig::default(), 0, 0)
+        );
 
         check_shard_level_state!(state, [
             (scheme: (asset_type) => { metadata: "metadata".to_string(), supply: amount + new_supply, approver, registrar: registrar }),
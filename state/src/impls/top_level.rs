@@ -55,7 +55,7 @@ use ctypes::util::unexpected::Mismatch;
 #[cfg(test)]
 use ctypes::Tracker;
 use ctypes::{BlockNumber, CommonParams, ShardId, TxHash};
-use cvm::ChainTimeInfo;
+use cvm::{ChainTimeInfo, VMConfig};
 use kvdb::DBTransaction;
 use merkle_trie::{Result as TrieResult, TrieError, TrieFactory};
 #[cfg(test)]
@@ -256,6 +256,7 @@ impl TopLevelState {
         signed_hash: &TxHash,
         signer_public: &Public,
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
         current_block_timestamp: u64,
@@ -266,6 +267,7 @@ impl TopLevelState {
             signed_hash,
             signer_public,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
             current_block_timestamp,
@@ -297,6 +299,7 @@ impl TopLevelState {
         signed_hash: &TxHash,
         signer_public: &Public,
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
         current_block_timestamp: u64,
@@ -339,6 +342,7 @@ impl TopLevelState {
             &fee_payer,
             signer_public,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
             current_block_timestamp,
@@ -364,6 +368,7 @@ impl TopLevelState {
         fee_payer: &Address,
         signer_public: &Public,
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
         _current_block_timestamp: u64,
@@ -463,6 +468,7 @@ impl TopLevelState {
                     fee_payer,
                     &[],
                     client,
+                    vm_config,
                     parent_block_number,
                     parent_block_timestamp,
                 )?;
@@ -553,6 +559,7 @@ impl TopLevelState {
             fee_payer,
             &approvers,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
         )
@@ -564,6 +571,7 @@ impl TopLevelState {
         sender: &Address,
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -574,6 +582,7 @@ impl TopLevelState {
                 sender,
                 approvers,
                 client,
+                vm_config,
                 parent_block_number,
                 parent_block_timestamp,
             )?;
@@ -588,6 +597,7 @@ impl TopLevelState {
         sender: &Address,
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()> {
@@ -602,6 +612,7 @@ impl TopLevelState {
             &shard_users,
             approvers,
             client,
+            vm_config,
             parent_block_number,
             parent_block_timestamp,
         )
@@ -971,6 +982,12 @@ impl TopState for TopLevelState {
         Ok(())
     }
 
+    fn update_base_fee_multiplier(&mut self, base_fee_multiplier: u64) -> StateResult<()> {
+        let mut metadata = self.get_metadata_mut()?;
+        metadata.set_base_fee_multiplier(base_fee_multiplier);
+        Ok(())
+    }
+
     fn update_action_data(&mut self, key: &H256, data: Bytes) -> StateResult<()> {
         let mut action_data = self.get_action_data_mut(key)?;
         *action_data = data.into();
@@ -1378,7 +1395,7 @@ mod tests_tx {
                 expected: 0,
                 found: 2
             }))),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1401,7 +1418,7 @@ mod tests_tx {
                 cost: 5,
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1418,7 +1435,10 @@ mod tests_tx {
 
         let receiver = 1u64.into();
         let tx = transaction!(fee: 5, pay!(receiver, 10));
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 5)),
@@ -1435,7 +1455,10 @@ mod tests_tx {
         set_top_level_state!(state, [(account: sender => balance: 5)]);
 
         let tx = transaction!(fee: 5, set_regular_key!(key));
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 0, key: key))
@@ -1458,7 +1481,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, pay!(regular_account, 10));
         assert_eq!(
             Err(RuntimeError::InvalidTransferDestination.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1494,7 +1517,7 @@ mod tests_tx {
         let tx = transaction!(seq: 0, fee: 11, unwrap_ccc_tx);
         assert_eq!(
             Err(RuntimeError::InvalidTransferDestination.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1514,7 +1537,10 @@ mod tests_tx {
         let key = regular_keypair.public();
         let tx = transaction!(fee: 5, set_regular_key!(*key));
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 10, key: *key))
@@ -1524,7 +1550,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&tx, &H256::random().into(), regular_keypair.public(), &get_test_client(), 0, 0, 0)
+            state.apply(
+                &tx,
+                &H256::random().into(),
+                regular_keypair.public(),
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
 
         check_top_level_state!(state, [
@@ -1548,7 +1583,10 @@ mod tests_tx {
         let key = regular_keypair.public();
         let tx = transaction!(fee: 5, set_regular_key!(*key));
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 10, key: *key)),
@@ -1558,7 +1596,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, set_regular_key!(*key));
         assert_eq!(
             Err(RuntimeError::RegularKeyAlreadyInUse.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public2, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public2, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1581,7 +1619,7 @@ mod tests_tx {
         let tx = transaction! (fee: 5, set_regular_key!(sender_public2));
         assert_eq!(
             Err(RuntimeError::RegularKeyAlreadyInUseAsPlatformAccount.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1604,7 +1642,10 @@ mod tests_tx {
 
         let (_, regular_public2, _) = address();
         let tx = transaction! (fee: 5, set_regular_key!(regular_public2));
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         assert_eq!(Ok(false), state.regular_account_exists_and_not_null(&regular_public));
         check_top_level_state!(state, [
@@ -1643,7 +1684,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&transfer_tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &regular_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 25 - 11))
@@ -1688,7 +1738,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&transfer_tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &regular_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 25 - 11))
@@ -1712,7 +1771,10 @@ mod tests_tx {
         assert_eq!(Ok(false), state.regular_account_exists_and_not_null(&regular_public));
 
         let tx = transaction!(fee: 5, Action::CreateShard { users: vec![] });
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 20)),
             (account: regular_address => (seq: 1, balance: 20 - 5)),
@@ -1735,7 +1797,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, pay!(regular_address, 5));
         assert_eq!(
             Err(RuntimeError::InvalidTransferDestination.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1759,7 +1821,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, pay!(receiver_address, 5));
         assert_eq!(
             Err(RuntimeError::CannotUseMasterKey.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1786,7 +1848,7 @@ mod tests_tx {
                 cost: 30,
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1823,7 +1885,10 @@ mod tests_tx {
         let asset_type = Blake::blake(*transaction_tracker);
         let tx = transaction!(fee: 11, transaction);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 11)),
@@ -1859,7 +1924,10 @@ mod tests_tx {
         let asset_type = Blake::blake(*transaction_tracker);
         let tx = transaction!(fee: 5, transaction);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 5)),
@@ -1889,7 +1957,19 @@ mod tests_tx {
         let mint_tx = transaction!(fee: 20, mint);
         let asset_type = Blake::blake(*mint_tracker);
 
-        assert_eq!(Ok(()), state.apply(&mint_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(
+                &mint_tx,
+                &H256::random().into(),
+                &sender_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 120 - 20)),
@@ -1914,7 +1994,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&transfer_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &sender_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
 
         check_top_level_state!(state, [
@@ -1953,7 +2042,10 @@ mod tests_tx {
         );
         let tx = transaction!(fee: 11, transaction.clone());
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 11))
@@ -1967,7 +2059,7 @@ mod tests_tx {
                 shard_id
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -1995,7 +2087,10 @@ mod tests_tx {
         let tx = transaction!(fee: 11, wrap_ccc!(lock_script_hash, quantity));
         let tx_hash = tx.hash();
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         let asset_type = H160::zero();
         check_top_level_state!(state, [
@@ -2010,7 +2105,10 @@ mod tests_tx {
         );
         let tx = transaction!(seq: 1, fee: 11, unwrap_ccc_tx);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 2, balance: 100 - 11 - 30 - 11)),
@@ -2039,7 +2137,10 @@ mod tests_tx {
         let tx = transaction!(fee: 11, wrap_ccc!(lock_script_hash, quantity));
         let tx_hash = tx.hash();
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         let asset_type = H160::zero();
         check_top_level_state!(state, [
@@ -2064,7 +2165,7 @@ mod tests_tx {
                 found: Blake::blake(&failed_lock_script),
             })
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2098,7 +2199,7 @@ mod tests_tx {
                 cost: 30,
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2126,7 +2227,10 @@ mod tests_tx {
         let tx = transaction!(fee: 11, wrap_ccc!(lock_script_hash, quantity));
         let tx_hash = tx.hash();
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         let asset_type = H160::zero();
         check_top_level_state!(state, [
@@ -2148,7 +2252,10 @@ mod tests_tx {
 
         let tx = transaction!(seq: 1, fee: 11, transfer_tx);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 2, balance: 100 - 30 - 11 - 11)),
@@ -2164,7 +2271,10 @@ mod tests_tx {
         );
         let tx = transaction!(seq: 2, fee: 11, unwrap_ccc_tx);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 3, balance: 100 - 30 - 11 - 11 - 11 + 5)),
@@ -2193,7 +2303,19 @@ mod tests_tx {
         let store_tx = transaction!(fee: 10, store!(content.clone(), sender, signature));
         let dummy_signed_hash = TxHash::from(H256::random());
 
-        assert_eq!(Ok(()), state.apply(&store_tx, &dummy_signed_hash, &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(
+                &store_tx,
+                &dummy_signed_hash,
+                &sender_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 10)),
@@ -2205,7 +2327,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&remove_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &remove_tx,
+                &H256::random().into(),
+                &sender_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
 
         check_top_level_state!(state, [
@@ -2232,7 +2363,8 @@ mod tests_tx {
 
         let tx = transaction!(fee: 10, store!(content.clone(), sender, signature));
 
-        match state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0) {
+        match state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        {
             Err(StateError::Runtime(RuntimeError::TextVerificationFail(_))) => {}
             err => panic!("The transaction must fail with text verification failure, but {:?}", err),
         }
@@ -2248,7 +2380,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::TextVerificationFail("Certifier and signer are different".to_string()).into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2275,7 +2407,16 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::TextNotExist.into()),
-            state.apply(&remove_tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &remove_tx,
+                &H256::random().into(),
+                &sender_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
 
         check_top_level_state!(state, [
@@ -2333,7 +2474,10 @@ mod tests_tx {
         assert_eq!(Ok(None), state.shard_id_by_hash(&signed_hash1));
         assert_eq!(Ok(None), state.shard_id_by_hash(&signed_hash2));
 
-        assert_eq!(Ok(()), state.apply(&tx1, &signed_hash1, &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx1, &signed_hash1, &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         assert_eq!(Ok(None), state.shard_id_by_hash(&invalid_hash));
         assert_eq!(Ok(Some(0)), state.shard_id_by_hash(&signed_hash1));
@@ -2345,7 +2489,10 @@ mod tests_tx {
             (shard: 1)
         ]);
 
-        assert_eq!(Ok(()), state.apply(&tx2, &signed_hash2, &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx2, &signed_hash2, &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
         assert_eq!(Ok(None), state.shard_id_by_hash(&invalid_hash));
         assert_eq!(Ok(Some(0)), state.shard_id_by_hash(&signed_hash1));
         assert_eq!(Ok(Some(1)), state.shard_id_by_hash(&signed_hash2));
@@ -2386,7 +2533,10 @@ mod tests_tx {
         assert_eq!(Ok(None), state.shard_id_by_hash(&signed_hash1));
         assert_eq!(Ok(None), state.shard_id_by_hash(&signed_hash2));
 
-        assert_eq!(Ok(()), state.apply(&tx1, &signed_hash1, &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx1, &signed_hash1, &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         assert_eq!(Ok(None), state.shard_id_by_hash(&invalid_hash));
         assert_eq!(Ok(Some(2)), state.shard_id_by_hash(&signed_hash1));
@@ -2401,7 +2551,10 @@ mod tests_tx {
         ]);
 
         let tx2 = transaction!(seq: 1, fee: 5, Action::CreateShard { users: vec![] });
-        assert_eq!(Ok(()), state.apply(&tx2, &signed_hash2, &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx2, &signed_hash2, &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
         assert_eq!(Ok(None), state.shard_id_by_hash(&invalid_hash));
         assert_eq!(Ok(Some(2)), state.shard_id_by_hash(&signed_hash1));
         assert_eq!(Ok(Some(3)), state.shard_id_by_hash(&signed_hash2));
@@ -2425,7 +2578,10 @@ mod tests_tx {
         ]);
 
         let tx = transaction!(fee: 5, Action::CreateShard { users: vec![] });
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         let invalid_shard_id = 3;
         check_top_level_state!(state, [
@@ -2447,7 +2603,10 @@ mod tests_tx {
         ]);
 
         let tx = transaction!(fee: 5, Action::CreateShard { users: users.clone() });
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         let invalid_shard_id = 3;
         check_top_level_state!(state, [
@@ -2480,7 +2639,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::InvalidShardId(0).into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2516,7 +2675,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::InvalidShardId(100).into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 120))
@@ -2540,7 +2699,10 @@ mod tests_tx {
         ]);
 
         let tx = transaction!(fee: 5, set_shard_owners!(owners.clone()));
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 5)),
@@ -2567,7 +2729,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, set_shard_owners!(owners));
         assert_eq!(
             Err(RuntimeError::NewOwnersMustContainSender.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 100)),
@@ -2595,7 +2757,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::InsufficientPermission.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2622,7 +2784,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::InvalidShardId(invalid_shard_id).into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2651,7 +2813,7 @@ mod tests_tx {
         let tx = transaction!(fee: 5, set_shard_owners!(owners));
         assert_eq!(
             Err(StateError::Runtime(RuntimeError::InsufficientPermission)),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -2686,7 +2848,10 @@ mod tests_tx {
 
         let tx = transaction!(fee: 20, mint);
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
 
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 20)),
@@ -2716,7 +2881,10 @@ mod tests_tx {
 
         let tx = transaction!(fee: 5, set_shard_users!(new_users));
 
-        assert_eq!(Ok(()), state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0));
+        assert_eq!(
+            Ok(()),
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
+        );
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 100 - 5))
         ]);
@@ -2748,7 +2916,7 @@ mod tests_tx {
 
         assert_eq!(
             Err(RuntimeError::InsufficientPermission.into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 100)),
@@ -2805,7 +2973,16 @@ mod tests_tx {
                 got: 10,
             }
             .into()),
-            state.apply(&transfer_tx, &H256::random().into(), &signer_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &signer_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25)),
@@ -2873,7 +3050,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&transfer_tx, &H256::random().into(), &signer_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &signer_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 25 - 11)),
@@ -2943,7 +3129,16 @@ mod tests_tx {
 
         assert_eq!(
             Ok(()),
-            state.apply(&transfer_tx, &H256::random().into(), &signer_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &signer_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 1, balance: 25 - 11)),
@@ -3017,7 +3212,16 @@ mod tests_tx {
                 shard_id: shard3,
             }
             .into()),
-            state.apply(&transfer_tx, &H256::random().into(), &signer_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &signer_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25)),
@@ -3094,7 +3298,16 @@ mod tests_tx {
                 got: 10,
             }
             .into()),
-            state.apply(&transfer_tx, &H256::random().into(), &signer_public, &get_test_client(), 0, 0, 0)
+            state.apply(
+                &transfer_tx,
+                &H256::random().into(),
+                &signer_public,
+                &get_test_client(),
+                VMConfig::default(),
+                0,
+                0,
+                0
+            )
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25)),
@@ -3129,7 +3342,7 @@ mod tests_tx {
                 name: "shard user".to_string(),
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25))
@@ -3158,7 +3371,7 @@ mod tests_tx {
                 name: "shard user".to_string(),
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25))
@@ -3187,7 +3400,7 @@ mod tests_tx {
                 name: "shard owner".to_string(),
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &regular_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
         check_top_level_state!(state, [
             (account: sender => (seq: 0, balance: 25))
@@ -3226,7 +3439,7 @@ mod tests_tx {
                 name: "approver of asset".to_string(),
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
@@ -3269,7 +3482,7 @@ mod tests_tx {
                 name: "registrar of asset".to_string(),
             }
             .into()),
-            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), 0, 0, 0)
+            state.apply(&tx, &H256::random().into(), &sender_public, &get_test_client(), VMConfig::default(), 0, 0, 0)
         );
 
         check_top_level_state!(state, [
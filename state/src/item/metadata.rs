@@ -15,7 +15,7 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::CacheableItem;
-use ctypes::{CommonParams, ShardId, TxHash};
+use ctypes::{CommonParams, ShardId, TxHash, BASE_FEE_MULTIPLIER_UNIT};
 use primitives::H256;
 use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 
@@ -33,6 +33,7 @@ pub struct Metadata {
     term: TermMetadata,
     seq: u64,
     params: Option<CommonParams>,
+    base_fee_multiplier: u64,
 }
 
 impl Metadata {
@@ -44,6 +45,7 @@ impl Metadata {
             term: Default::default(),
             seq: 0,
             params: None,
+            base_fee_multiplier: BASE_FEE_MULTIPLIER_UNIT,
         }
     }
 
@@ -92,6 +94,15 @@ impl Metadata {
         self.params = Some(params);
     }
 
+    /// The multiplier of the minimum fees in the per-mille unit.
+    pub fn base_fee_multiplier(&self) -> u64 {
+        self.base_fee_multiplier
+    }
+
+    pub fn set_base_fee_multiplier(&mut self, base_fee_multiplier: u64) {
+        self.base_fee_multiplier = base_fee_multiplier;
+    }
+
     pub fn increase_term_id(&mut self, last_term_finished_block_num: u64) {
         assert!(self.term.last_term_finished_block_num < last_term_finished_block_num);
         self.term.last_term_finished_block_num = last_term_finished_block_num;
@@ -128,6 +139,7 @@ impl Encodable for Metadata {
         const INITIAL_LEN: usize = 4;
         const TERM_LEN: usize = 2;
         const PARAMS_LEN: usize = 2;
+        const BASE_FEE_LEN: usize = 1;
         let mut len = INITIAL_LEN;
 
         let term_changed = self.term != Default::default();
@@ -136,12 +148,16 @@ impl Encodable for Metadata {
         }
 
         let params_changed = self.seq != 0;
-        if params_changed {
+        let base_fee_changed = self.base_fee_multiplier != BASE_FEE_MULTIPLIER_UNIT;
+        if params_changed || base_fee_changed {
             if !term_changed {
                 len += TERM_LEN;
             }
             len += PARAMS_LEN;
         }
+        if base_fee_changed {
+            len += BASE_FEE_LEN;
+        }
         s.begin_list(len)
             .append(&PREFIX)
             .append(&self.number_of_shards)
@@ -150,21 +166,28 @@ impl Encodable for Metadata {
         if term_changed {
             s.append(&self.term.last_term_finished_block_num).append(&self.term.current_term_id);
         }
-        if params_changed {
+        if params_changed || base_fee_changed {
             if !term_changed {
                 const DEFAULT_LAST_TERM_FINISHED_BLOCK_NUM: u64 = 0;
                 const DEFAULT_CURRENT_TERM_ID: u64 = 0;
                 s.append(&DEFAULT_LAST_TERM_FINISHED_BLOCK_NUM).append(&DEFAULT_CURRENT_TERM_ID);
             }
-            s.append(&self.seq).append(self.params.as_ref().unwrap());
+            s.append(&self.seq);
+            match &self.params {
+                Some(params) => s.append(params),
+                None => s.append_empty_data(),
+            };
+        }
+        if base_fee_changed {
+            s.append(&self.base_fee_multiplier);
         }
     }
 }
 
 impl Decodable for Metadata {
     fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
-        let (term, seq, params) = match rlp.item_count()? {
-            4 => (TermMetadata::default(), 0, None),
+        let (term, seq, params, base_fee_multiplier) = match rlp.item_count()? {
+            4 => (TermMetadata::default(), 0, None, BASE_FEE_MULTIPLIER_UNIT),
             6 => (
                 TermMetadata {
                     last_term_finished_block_num: rlp.val_at(4)?,
@@ -172,6 +195,7 @@ impl Decodable for Metadata {
                 },
                 0,
                 None,
+                BASE_FEE_MULTIPLIER_UNIT,
             ),
             8 => (
                 TermMetadata {
@@ -180,6 +204,20 @@ impl Decodable for Metadata {
                 },
                 rlp.val_at(6)?,
                 Some(rlp.val_at(7)?),
+                BASE_FEE_MULTIPLIER_UNIT,
+            ),
+            9 => (
+                TermMetadata {
+                    last_term_finished_block_num: rlp.val_at(4)?,
+                    current_term_id: rlp.val_at(5)?,
+                },
+                rlp.val_at(6)?,
+                if rlp.at(7)?.is_empty() {
+                    None
+                } else {
+                    Some(rlp.val_at(7)?)
+                },
+                rlp.val_at(8)?,
             ),
             item_count => {
                 return Err(DecoderError::RlpInvalidLength {
@@ -200,6 +238,7 @@ impl Decodable for Metadata {
             term,
             seq,
             params,
+            base_fee_multiplier,
         })
     }
 }
@@ -265,6 +304,7 @@ mod tests {
             term: Default::default(),
             seq: 0,
             params: None,
+            base_fee_multiplier: BASE_FEE_MULTIPLIER_UNIT,
         };
         let mut rlp = RlpStream::new_list(4);
         rlp.append(&PREFIX).append(&10u16).append(&1u16).append_list::<H256, H256>(&[]);
@@ -280,6 +320,7 @@ mod tests {
             term: Default::default(),
             seq: 3,
             params: Some(CommonParams::default_for_test()),
+            base_fee_multiplier: BASE_FEE_MULTIPLIER_UNIT,
         };
         rlp_encode_and_decode_test!(metadata);
     }
@@ -296,6 +337,7 @@ mod tests {
             },
             seq: 0,
             params: None,
+            base_fee_multiplier: BASE_FEE_MULTIPLIER_UNIT,
         };
         rlp_encode_and_decode_test!(metadata);
     }
@@ -312,6 +354,38 @@ mod tests {
             },
             seq: 3,
             params: Some(CommonParams::default_for_test()),
+            base_fee_multiplier: BASE_FEE_MULTIPLIER_UNIT,
+        };
+        rlp_encode_and_decode_test!(metadata);
+    }
+
+    #[test]
+    fn metadata_with_base_fee_multiplier_without_seq() {
+        let metadata = Metadata {
+            number_of_shards: 10,
+            number_of_initial_shards: 1,
+            hashes: vec![],
+            term: Default::default(),
+            seq: 0,
+            params: None,
+            base_fee_multiplier: 1250,
+        };
+        rlp_encode_and_decode_test!(metadata);
+    }
+
+    #[test]
+    fn metadata_with_term_seq_and_base_fee_multiplier() {
+        let metadata = Metadata {
+            number_of_shards: 10,
+            number_of_initial_shards: 1,
+            hashes: vec![],
+            term: TermMetadata {
+                last_term_finished_block_num: 1,
+                current_term_id: 100,
+            },
+            seq: 3,
+            params: Some(CommonParams::default_for_test()),
+            base_fee_multiplier: 1250,
         };
         rlp_encode_and_decode_test!(metadata);
     }
@@ -21,7 +21,7 @@ use crate::{
 use ckey::{public_to_address, Address, Public, Signature};
 use ctypes::transaction::ShardTransaction;
 use ctypes::{BlockNumber, CommonParams, ShardId, Tracker, TxHash};
-use cvm::ChainTimeInfo;
+use cvm::{ChainTimeInfo, VMConfig};
 use merkle_trie::Result as TrieResult;
 use primitives::{Bytes, H160, H256};
 
@@ -142,6 +142,7 @@ pub trait ShardState {
         shard_owners: &[Address],
         approvers: &[Address],
         client: &C,
+        vm_config: VMConfig,
         parent_block_number: BlockNumber,
         parent_block_timestamp: u64,
     ) -> StateResult<()>;
@@ -177,6 +178,7 @@ pub trait TopState {
     fn remove_text(&mut self, key: &TxHash, sig: &Signature) -> StateResult<()>;
 
     fn increase_term_id(&mut self, last_term_finished_block_num: u64) -> StateResult<()>;
+    fn update_base_fee_multiplier(&mut self, base_fee_multiplier: u64) -> StateResult<()>;
 
     fn update_action_data(&mut self, key: &H256, data: Bytes) -> StateResult<()>;
     fn remove_action_data(&mut self, key: &H256);
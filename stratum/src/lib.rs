@@ -35,6 +35,7 @@ extern crate tokio_core;
 extern crate tokio_io;
 
 mod traits;
+mod vardiff;
 
 pub use crate::traits::{Error, JobDispatcher, PushWorkHandler, ServiceConfiguration};
 
@@ -45,15 +46,38 @@ use jsonrpc_tcp_server::{
     Dispatcher, MetaExtractor, PushMessageError, RequestContext, Server as JsonRpcServer,
     ServerBuilder as JsonRpcServerBuilder,
 };
+use crate::vardiff::VarDiff;
 use parking_lot::RwLock;
 use primitives::H256;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 
 type RpcResult = Result<jsonrpc_core::Value, jsonrpc_core::Error>;
 
 const NOTIFY_COUNTER_INITIAL: u32 = 16;
+/// The number of recent jobs whose shares are still accepted.
+const JOB_HISTORY_SIZE: usize = 16;
+
+/// Share accounting of a worker.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorkerStats {
+    pub worker_id: String,
+    /// The share target of the worker is `share_divisor` times easier than the block's.
+    pub share_divisor: u64,
+    pub valid_shares: u64,
+    pub stale_shares: u64,
+    pub invalid_shares: u64,
+    /// The number of shares that sealed a block.
+    pub blocks: u64,
+}
+
+/// An authorized connection.
+struct Worker {
+    worker_id: String,
+    vardiff: VarDiff,
+}
 
 /// Container which owns rpc server and stratum implementation
 pub struct Stratum {
@@ -82,6 +106,9 @@ impl Stratum {
             job_que: RwLock::new(HashSet::new()),
             dispatcher,
             workers: Arc::new(RwLock::new(HashMap::new())),
+            jobs: RwLock::new(VecDeque::new()),
+            job_counter: RwLock::new(0),
+            stats: RwLock::new(HashMap::new()),
             secret,
             notify_counter: RwLock::new(NOTIFY_COUNTER_INITIAL),
         });
@@ -106,6 +133,13 @@ impl Stratum {
 
         Ok(stratum)
     }
+
+    /// Share accounting of the workers that have been authorized.
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        let mut stats: Vec<_> = self.implementation.stats.read().values().cloned().collect();
+        stats.sort_by(|a, b| a.worker_id.cmp(&b.worker_id));
+        stats
+    }
 }
 
 impl PushWorkHandler for Stratum {
@@ -116,6 +150,10 @@ impl PushWorkHandler for Stratum {
     fn push_work(&self, payloads: Vec<String>) -> Result<(), Error> {
         self.implementation.push_work(payloads, &self.tcp_dispatcher)
     }
+
+    fn push_job(&self, pow_hash: H256) -> Result<(), Error> {
+        self.implementation.push_job(pow_hash, &self.tcp_dispatcher)
+    }
 }
 
 impl Drop for Stratum {
@@ -134,8 +172,14 @@ struct StratumImpl {
     job_que: RwLock<HashSet<SocketAddr>>,
     /// Payload manager
     dispatcher: Arc<dyn JobDispatcher>,
-    /// Authorized workers (socket - worker_id)
-    workers: Arc<RwLock<HashMap<SocketAddr, String>>>,
+    /// Authorized workers (socket - worker)
+    workers: Arc<RwLock<HashMap<SocketAddr, Worker>>>,
+    /// Recent jobs (job_id - pow_hash), the last one is the current job
+    jobs: RwLock<VecDeque<(String, H256)>>,
+    /// The last job id
+    job_counter: RwLock<u64>,
+    /// Share accounting (worker_id - stats)
+    stats: RwLock<HashMap<String, WorkerStats>>,
     /// Secret if any
     secret: Option<H256>,
     /// Dispatch notify counter
@@ -182,61 +226,197 @@ impl StratumRpc for StratumImpl {
                     }
                 }
                 ctrace!(STRATUM, "New worker #{} registered", worker_id);
-                self.workers.write().insert(*meta.addr(), worker_id);
+                let worker = Worker {
+                    worker_id: worker_id.clone(),
+                    vardiff: VarDiff::new(Instant::now()),
+                };
+                {
+                    let mut stats = self.stats.write();
+                    let stats = stats.entry(worker_id.clone()).or_insert_with(|| WorkerStats {
+                        worker_id,
+                        ..Default::default()
+                    });
+                    stats.share_divisor = worker.vardiff.share_divisor();
+                }
+                self.workers.write().insert(*meta.addr(), worker);
                 to_value(true)
             })
             .map(|v| v.expect("Only true/false is returned and it's always serializable"))
     }
 
     /// rpc method `mining.submit`
+    ///
+    /// The params are either `[job_id, seal]` or `[pow_hash, seal]`.
+    /// Shares submitted with a pow hash have to meet the target of the block.
     fn submit(&self, params: Params, meta: SocketMetadata) -> RpcResult {
-        let workers = self.workers.read();
-        if !workers.contains_key(&meta.addr) {
-            return Err(Error::UnauthorizedWorker.into())
-        }
+        let (worker_id, share_divisor) = match self.workers.read().get(&meta.addr) {
+            Some(worker) => (worker.worker_id.clone(), worker.vardiff.share_divisor()),
+            None => return Err(Error::UnauthorizedWorker.into()),
+        };
 
-        params.parse::<(H256, Vec<Bytes>)>().and_then(|(pow_hash, seal)| {
-            let seal = seal.iter().cloned().map(Into::into).collect();
-            match self.dispatcher.submit((pow_hash, seal)) {
-                Ok(()) => {
-                    self.update_peers(&meta.tcp_dispatcher.expect("tcp_dispatcher is always initialized"));
-                    Ok(jsonrpc_core::Value::Null)
+        let (pow_hash, seal, share_divisor) = match params.clone().parse::<(H256, Vec<Bytes>)>() {
+            Ok((pow_hash, seal)) => (pow_hash, seal, 1),
+            Err(_) => {
+                let (job_id, seal) = params.parse::<(String, Vec<Bytes>)>()?;
+                let pow_hash = self.jobs.read().iter().find(|(id, _)| *id == job_id).map(|(_, pow_hash)| *pow_hash);
+                match pow_hash {
+                    Some(pow_hash) => (pow_hash, seal, share_divisor),
+                    None => {
+                        cdebug!(STRATUM, "Worker #{} submitted a share of an unknown job {}", worker_id, job_id);
+                        self.record_share(&worker_id, &Err(Error::StaleShare));
+                        return Err(Error::StaleShare.into())
+                    }
                 }
-                Err(submit_err) => {
-                    cwarn!(STRATUM, "Error while submitting share: {:?}", submit_err);
-                    Err(submit_err.into())
+            }
+        };
+        let seal = seal.iter().cloned().map(Into::into).collect();
+        let result = self.dispatcher.submit_share((pow_hash, seal), share_divisor);
+        self.record_share(&worker_id, &result);
+        match result {
+            Ok(sealed) => {
+                let tcp_dispatcher = meta.tcp_dispatcher.expect("tcp_dispatcher is always initialized");
+                self.retarget_on_share(&meta.addr, &tcp_dispatcher);
+                if sealed {
+                    self.update_peers(&tcp_dispatcher);
                 }
+                Ok(jsonrpc_core::Value::Null)
             }
-        })
+            Err(submit_err) => {
+                cwarn!(STRATUM, "Error while submitting share: {:?}", submit_err);
+                Err(submit_err.into())
+            }
+        }
     }
 }
 
 impl StratumImpl {
-    /// Helper method
-    fn update_peers(&self, tcp_dispatcher: &Dispatcher) {
-        if let Some(job) = self.dispatcher.job() {
-            if let Err(e) = self.push_work_all(job.as_str(), tcp_dispatcher) {
-                warn!("Failed to update some of the peers: {:?}", e);
+    fn record_share(&self, worker_id: &str, result: &Result<bool, Error>) {
+        let mut stats = self.stats.write();
+        let stats = match stats.get_mut(worker_id) {
+            Some(stats) => stats,
+            None => return,
+        };
+        match result {
+            Ok(true) => {
+                stats.valid_shares += 1;
+                stats.blocks += 1;
             }
+            Ok(false) => stats.valid_shares += 1,
+            Err(Error::StaleShare) => stats.stale_shares += 1,
+            Err(_) => stats.invalid_shares += 1,
+        }
+    }
+
+    /// Pushes the current job again if the share target of the worker is changed.
+    fn retarget_on_share(&self, addr: &SocketAddr, tcp_dispatcher: &Dispatcher) {
+        let (worker_id, share_divisor) = {
+            let mut workers = self.workers.write();
+            let worker = match workers.get_mut(addr) {
+                Some(worker) => worker,
+                None => return,
+            };
+            if !worker.vardiff.on_share(Instant::now()) {
+                return
+            }
+            (worker.worker_id.clone(), worker.vardiff.share_divisor())
+        };
+        ctrace!(STRATUM, "Share divisor of worker #{} is changed to {}", worker_id, share_divisor);
+        if let Some(stats) = self.stats.write().get_mut(&worker_id) {
+            stats.share_divisor = share_divisor;
+        }
+        let current_job = self.jobs.read().back().cloned();
+        if let Some((job_id, pow_hash)) = current_job {
+            if let Some(payload) = self.dispatcher.job_with_id(&job_id, pow_hash, share_divisor) {
+                let message = self.notify_message(&payload);
+                if let Err(e) = tcp_dispatcher.push_message(addr, message) {
+                    cwarn!(STRATUM, "Error while pushing work to {}: {:?}", addr, e);
+                }
+            }
+        }
+    }
+
+    fn next_request_id(&self) -> u32 {
+        let mut counter = self.notify_counter.write();
+        if *counter == ::std::u32::MAX {
+            *counter = NOTIFY_COUNTER_INITIAL;
+        } else {
+            *counter += 1
+        }
+        *counter
+    }
+
+    fn notify_message(&self, payload: &str) -> String {
+        format!("{{ \"id\": {}, \"method\": \"mining.notify\", \"params\": {} }}", self.next_request_id(), payload)
+    }
+
+    fn push_job(&self, pow_hash: H256, tcp_dispatcher: &Dispatcher) -> Result<(), Error> {
+        let job_id = {
+            let mut counter = self.job_counter.write();
+            *counter += 1;
+            format!("{:x}", *counter)
+        };
+        {
+            let mut jobs = self.jobs.write();
+            jobs.push_back((job_id.clone(), pow_hash));
+            if jobs.len() > JOB_HISTORY_SIZE {
+                jobs.pop_front();
+            }
+        }
+        self.notify_job(&job_id, pow_hash, Some(Instant::now()), tcp_dispatcher);
+        Ok(())
+    }
+
+    /// Pushes the job to all workers with their own share targets.
+    /// The share targets are retargeted first if `retarget_at` is given.
+    fn notify_job(&self, job_id: &str, pow_hash: H256, retarget_at: Option<Instant>, tcp_dispatcher: &Dispatcher) {
+        let mut hup_peers = HashSet::new();
+        {
+            let mut workers = self.workers.write();
+            let mut stats = self.stats.write();
+            ctrace!(STRATUM, "pushing job {} for {} workers", job_id, workers.len());
+            for (addr, worker) in workers.iter_mut() {
+                if let Some(now) = retarget_at {
+                    worker.vardiff.retarget(now);
+                }
+                let share_divisor = worker.vardiff.share_divisor();
+                if let Some(stats) = stats.get_mut(&worker.worker_id) {
+                    stats.share_divisor = share_divisor;
+                }
+                let payload = match self.dispatcher.job_with_id(job_id, pow_hash, share_divisor) {
+                    Some(payload) => payload,
+                    None => continue,
+                };
+                match tcp_dispatcher.push_message(addr, self.notify_message(&payload)) {
+                    Err(PushMessageError::NoSuchPeer) => {
+                        ctrace!(STRATUM, "Worker no longer connected: {}", addr);
+                        hup_peers.insert(*addr);
+                    }
+                    Err(e) => {
+                        cwarn!(STRATUM, "Unexpected transport error: {:?}", e);
+                    }
+                    Ok(_) => {}
+                }
+            }
+            for hup_peer in hup_peers {
+                workers.remove(&hup_peer);
+            }
+        }
+    }
+
+    /// Pushes the current job again, in the same format as `push_job`.
+    fn update_peers(&self, tcp_dispatcher: &Dispatcher) {
+        let current_job = self.jobs.read().back().cloned();
+        if let Some((job_id, pow_hash)) = current_job {
+            self.notify_job(&job_id, pow_hash, None, tcp_dispatcher);
         }
     }
 
     fn push_work_all(&self, payload: &str, tcp_dispatcher: &Dispatcher) -> Result<(), Error> {
         let hup_peers = {
             let workers = self.workers.read();
-            let next_request_id = {
-                let mut counter = self.notify_counter.write();
-                if *counter == ::std::u32::MAX {
-                    *counter = NOTIFY_COUNTER_INITIAL;
-                } else {
-                    *counter += 1
-                }
-                *counter
-            };
 
             let mut hup_peers = HashSet::with_capacity(0); // most of the cases won't be needed, hence avoid allocation
-            let workers_msg =
-                format!("{{ \"id\": {}, \"method\": \"mining.notify\", \"params\": {} }}", next_request_id, payload);
+            let workers_msg = self.notify_message(payload);
             ctrace!(STRATUM, "pushing work for {} workers (payload: '{}')", workers.len(), &workers_msg);
             for (ref addr, _) in workers.iter() {
                 ctrace!(STRATUM, "pusing work to {}", addr);
@@ -575,4 +755,44 @@ mod tests {
             response
         );
     }
+
+    #[test]
+    fn count_stale_share_of_unknown_job() {
+        let addr = get_available_test_addr(19700, 19800);
+        let stratum =
+            Stratum::start(&addr, Arc::new(DummyManager::build().of_initial(r#"["dummy authorize payload"]"#)), None)
+                .expect("There should be no error starting stratum");
+
+        let mut auth_request =
+            br#"{"jsonrpc": "2.0", "method": "mining.authorize", "params": ["miner1", ""], "id": 1}"#.to_vec();
+        auth_request.extend(b"\n");
+
+        let mut submit_request =
+            br#"{"jsonrpc": "2.0", "method": "mining.submit", "params": ["ff", ["0x56642f04d519ae3262c7ba6facf1c5b11450ebaeb7955337cfbc45420d573077"]], "id": 2}"#
+                .to_vec();
+        submit_request.extend(b"\n");
+
+        let mut core = Core::new().expect("Tokio Core should be created with no errors");
+        let timeout1 = Timeout::new(::std::time::Duration::from_millis(100), &core.handle())
+            .expect("There should be a timeout produced in message test");
+        let mut buffer = vec![0u8; 2048];
+        let mut buffer2 = vec![0u8; 2048];
+        let stream = TcpStream::connect(&addr, &core.handle())
+            .and_then(|stream| io::write_all(stream, &auth_request))
+            .and_then(|(stream, _)| io::read(stream, &mut buffer))
+            .and_then(|(stream, ..)| timeout1.join(future::ok(stream)))
+            .and_then(|(_, stream)| io::write_all(stream, &submit_request))
+            .and_then(|(stream, _)| io::read(stream, &mut buffer2))
+            .and_then(|(_, read_buf, len)| future::ok(read_buf[0..len].to_vec()));
+
+        let response = String::from_utf8(core.run(stream).expect("Core should run with no errors"))
+            .expect("Response should be utf-8");
+        assert_eq!("{\"jsonrpc\":\"2.0\",\"error\":{\"code\":24,\"message\":\"Stale share\"},\"id\":2}\n", response);
+
+        let stats = stratum.worker_stats();
+        assert_eq!(1, stats.len());
+        assert_eq!("miner1", stats[0].worker_id);
+        assert_eq!(1, stats[0].stale_shares);
+        assert_eq!(0, stats[0].valid_shares);
+    }
 }
@@ -25,6 +25,7 @@ pub enum Error {
     InternalError,
     PowHashInvalid,
     PowInvalid,
+    StaleShare,
     UnauthorizedWorker,
     NoWork,
     NoWorkers,
@@ -50,6 +51,7 @@ impl From<Error> for JsonError {
             Error::PowHashInvalid => (21, "Invalid Pow hash".to_string()),
             Error::PowInvalid => (22, "Invalid the nonce".to_string()),
             Error::UnauthorizedWorker => (23, "Unauthorized worker".to_string()),
+            Error::StaleShare => (24, "Stale share".to_string()),
             _ => (20, "Internal error".to_string()),
         };
 
@@ -75,17 +77,30 @@ pub trait JobDispatcher: Send + Sync {
     fn job(&self) -> Option<String> {
         None
     }
+    // json for the job `job_id`, whose share target is `share_divisor` times easier than the block's
+    fn job_with_id(&self, _job_id: &str, _pow_hash: H256, _share_divisor: u64) -> Option<String> {
+        None
+    }
     // miner job result
     fn submit(&self, payload: (H256, Vec<Bytes>)) -> Result<(), Error>;
+    // share of a worker whose share target is `share_divisor` times easier than the block's
+    // returns whether the share also sealed the block
+    fn submit_share(&self, payload: (H256, Vec<Bytes>), _share_divisor: u64) -> Result<bool, Error> {
+        self.submit(payload).map(|()| true)
+    }
 }
 
 /// Interface that can handle requests to push job for workers
 pub trait PushWorkHandler: Send + Sync {
     /// push the same work package for all workers (`payload`: json of pow-specific set of work specification)
+    /// The payload is sent as is, so it should follow the format of `JobDispatcher::job_with_id`.
     fn push_work_all(&self, payload: String) -> Result<(), Error>;
 
     /// push the work packages worker-wise (`payload`: json of pow-specific set of work specification)
     fn push_work(&self, payloads: Vec<String>) -> Result<(), Error>;
+
+    /// assign a new job id to `pow_hash` and push the job to all workers with their own share targets
+    fn push_job(&self, pow_hash: H256) -> Result<(), Error>;
 }
 
 pub struct ServiceConfiguration {
@@ -0,0 +1,119 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+/// Workers start with shares 16 times easier than the block.
+pub const INITIAL_SHARE_DIVISOR: u64 = 16;
+const MAX_SHARE_DIVISOR: u64 = 1 << 20;
+/// The interval between the shares of a worker that the server aims for.
+const TARGET_SHARE_INTERVAL: Duration = Duration::from_secs(10);
+const RETARGET_SHARES: u32 = 8;
+
+/// Adjusts the share target of a worker to keep its shares coming at `TARGET_SHARE_INTERVAL`.
+///
+/// The share target is expressed as a divisor of the block score:
+/// the larger the divisor is, the easier the shares are.
+pub struct VarDiff {
+    share_divisor: u64,
+    shares: u32,
+    since: Instant,
+}
+
+impl VarDiff {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            share_divisor: INITIAL_SHARE_DIVISOR,
+            shares: 0,
+            since: now,
+        }
+    }
+
+    pub fn share_divisor(&self) -> u64 {
+        self.share_divisor
+    }
+
+    /// Records a valid share and returns true if the divisor is changed.
+    pub fn on_share(&mut self, now: Instant) -> bool {
+        self.shares += 1;
+        self.retarget(now)
+    }
+
+    /// Returns true if the divisor is changed.
+    pub fn retarget(&mut self, now: Instant) -> bool {
+        let elapsed = now.duration_since(self.since);
+        let expected = TARGET_SHARE_INTERVAL * RETARGET_SHARES;
+        let divisor = if self.shares >= RETARGET_SHARES && elapsed * 2 < expected {
+            (self.share_divisor / 2).max(1)
+        } else if elapsed > expected * 2 {
+            (self.share_divisor * 2).min(MAX_SHARE_DIVISOR)
+        } else if self.shares >= RETARGET_SHARES {
+            self.share_divisor
+        } else {
+            return false
+        };
+        self.shares = 0;
+        self.since = now;
+        let changed = divisor != self.share_divisor;
+        self.share_divisor = divisor;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_worker_gets_harder_shares() {
+        let start = Instant::now();
+        let mut vardiff = VarDiff::new(start);
+        for i in 1..RETARGET_SHARES {
+            assert!(!vardiff.on_share(start + Duration::from_secs(u64::from(i))));
+        }
+        assert!(vardiff.on_share(start + Duration::from_secs(u64::from(RETARGET_SHARES))));
+        assert_eq!(INITIAL_SHARE_DIVISOR / 2, vardiff.share_divisor());
+    }
+
+    #[test]
+    fn slow_worker_gets_easier_shares() {
+        let start = Instant::now();
+        let mut vardiff = VarDiff::new(start);
+        assert!(!vardiff.retarget(start + TARGET_SHARE_INTERVAL));
+        assert!(vardiff.retarget(start + TARGET_SHARE_INTERVAL * RETARGET_SHARES * 3));
+        assert_eq!(INITIAL_SHARE_DIVISOR * 2, vardiff.share_divisor());
+    }
+
+    #[test]
+    fn steady_worker_keeps_the_divisor() {
+        let start = Instant::now();
+        let mut vardiff = VarDiff::new(start);
+        for i in 1..=RETARGET_SHARES {
+            assert!(!vardiff.on_share(start + TARGET_SHARE_INTERVAL * i));
+        }
+        assert_eq!(INITIAL_SHARE_DIVISOR, vardiff.share_divisor());
+    }
+
+    #[test]
+    fn divisor_is_at_least_one() {
+        let start = Instant::now();
+        let mut vardiff = VarDiff::new(start);
+        for _ in 0..100 {
+            vardiff.on_share(start);
+        }
+        assert_eq!(1, vardiff.share_divisor());
+    }
+}
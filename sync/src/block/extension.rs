@@ -21,7 +21,7 @@ use ccore::{
     Block, BlockChainClient, BlockChainTrait, BlockId, BlockImportError, ChainNotify, Client, ImportBlock, ImportError,
     UnverifiedTransaction,
 };
-use cnetwork::{Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId};
+use cnetwork::{Api, EventSender, IntoSocketAddr, NetworkExtension, NodeId, PeerBehavior};
 use cstate::FindActionHandler;
 use ctimer::TimerToken;
 use ctypes::header::{Header, Seal};
@@ -291,6 +291,7 @@ impl NetworkExtension<Event> for Extension {
             }
         } else {
             cinfo!(SYNC, "Invalid message from peer {}", id);
+            self.api.report(id, PeerBehavior::UndecodableMessage);
         }
     }
 
@@ -581,8 +582,10 @@ impl Extension {
             }
 
             if !self.is_valid_response(&request, &response) {
+                self.api.report(from, PeerBehavior::InvalidResponse);
                 return
             }
+            self.api.report(from, PeerBehavior::Good);
 
             match response {
                 ResponseMessage::Headers(headers) => {
@@ -697,6 +700,11 @@ impl Extension {
             match self.client.import_header(header) {
                 Err(BlockImportError::Import(ImportError::AlreadyInChain)) => exists.push(hash),
                 Err(BlockImportError::Import(ImportError::AlreadyQueued)) => queued.push(hash),
+                Err(BlockImportError::Block(err)) => {
+                    cwarn!(SYNC, "Received an invalid header({}) from {}: {:?}", hash, from, err);
+                    self.api.report(from, PeerBehavior::InvalidBlock);
+                    break
+                }
                 // FIXME: handle import errors
                 Err(err) => {
                     cwarn!(SYNC, "Cannot import header({}): {:?}", hash, err);
@@ -16,7 +16,7 @@
 
 use super::message::Message;
 use ccore::{BlockChainClient, UnverifiedTransaction};
-use cnetwork::{Api, NetworkExtension, NodeId};
+use cnetwork::{Api, MessagePriority, NetworkExtension, NodeId};
 use ctimer::TimerToken;
 use ctypes::TxHash;
 use never_type::Never;
@@ -83,6 +83,10 @@ impl NetworkExtension<Never> for Extension {
         &VERSIONS
     }
 
+    fn priority() -> MessagePriority {
+        MessagePriority::Low
+    }
+
     fn on_node_added(&mut self, token: &NodeId, _version: u64) {
         self.peers.insert(*token, KnownTxs::default());
     }
@@ -66,6 +66,21 @@ pub struct CommonParams {
     max_candidate_metadata_size: usize,
 
     era: u64,
+
+    /// Maximum cost of running the scripts of an input. 0 means no limit.
+    max_script_cost: u64,
+
+    /// The base fee multiplier moves at most 1/`base_fee_change_denominator` per block.
+    base_fee_change_denominator: u64,
+}
+
+/// The base fee multiplier is stored in the per-mille unit.
+pub const BASE_FEE_MULTIPLIER_UNIT: u64 = 1000;
+
+/// Scales the minimum fee by the base fee multiplier.
+pub fn apply_base_fee_multiplier(min_fee: u64, base_fee_multiplier: u64) -> u64 {
+    let scaled = u128::from(min_fee) * u128::from(base_fee_multiplier) / u128::from(BASE_FEE_MULTIPLIER_UNIT);
+    scaled.min(u128::from(u64::max_value())) as u64
 }
 
 impl CommonParams {
@@ -176,6 +191,38 @@ impl CommonParams {
         self.era
     }
 
+    /// It is zero if the parameters don't limit the cost of scripts.
+    pub fn max_script_cost(&self) -> u64 {
+        self.max_script_cost
+    }
+
+    /// It is zero if the fee market is disabled.
+    pub fn base_fee_change_denominator(&self) -> u64 {
+        self.base_fee_change_denominator
+    }
+
+    /// Calculates the base fee multiplier of the next block from the body size of the current block.
+    ///
+    /// The multiplier goes up when the body is larger than the half of `max_body_size` and goes down otherwise.
+    /// It never goes below `BASE_FEE_MULTIPLIER_UNIT`.
+    pub fn next_base_fee_multiplier(&self, multiplier: u64, body_size: usize) -> u64 {
+        if self.base_fee_change_denominator == 0 {
+            return BASE_FEE_MULTIPLIER_UNIT
+        }
+        let target = (self.max_body_size / 2).max(1) as u128;
+        let body_size = body_size as u128;
+        let multiplier = u128::from(multiplier.max(BASE_FEE_MULTIPLIER_UNIT));
+        let denominator = u128::from(self.base_fee_change_denominator);
+        let next = if body_size > target {
+            let delta = (multiplier * (body_size - target) / target / denominator).max(1);
+            multiplier.saturating_add(delta)
+        } else {
+            let delta = multiplier * (target - body_size) / target / denominator;
+            multiplier - delta
+        };
+        next.max(u128::from(BASE_FEE_MULTIPLIER_UNIT)).min(u128::from(u64::max_value())) as u64
+    }
+
     pub fn verify(&self) -> Result<(), String> {
         if self.term_seconds != 0 {
             if self.nomination_expiration == 0 {
@@ -241,14 +288,23 @@ impl CommonParams {
 const DEFAULT_PARAMS_SIZE: usize = 23;
 const NUMBER_OF_STAKE_PARAMS: usize = 9;
 const NUMBER_OF_ERA_PARAMS: usize = 1;
+const NUMBER_OF_SCRIPT_PARAMS: usize = 1;
+const NUMBER_OF_FEE_MARKET_PARAMS: usize = 1;
 const STAKE_PARAM_SIZE: usize = DEFAULT_PARAMS_SIZE + NUMBER_OF_STAKE_PARAMS;
 const ERA_PARAM_SIZE: usize = STAKE_PARAM_SIZE + NUMBER_OF_ERA_PARAMS;
+const SCRIPT_PARAM_SIZE: usize = ERA_PARAM_SIZE + NUMBER_OF_SCRIPT_PARAMS;
+const FEE_MARKET_PARAM_SIZE: usize = SCRIPT_PARAM_SIZE + NUMBER_OF_FEE_MARKET_PARAMS;
 
-const VALID_SIZE: &[usize] = &[DEFAULT_PARAMS_SIZE, STAKE_PARAM_SIZE, ERA_PARAM_SIZE];
+const VALID_SIZE: &[usize] =
+    &[DEFAULT_PARAMS_SIZE, STAKE_PARAM_SIZE, ERA_PARAM_SIZE, SCRIPT_PARAM_SIZE, FEE_MARKET_PARAM_SIZE];
 
 impl From<Params> for CommonParams {
     fn from(p: Params) -> Self {
-        let size = if p.era.is_some() {
+        let size = if p.base_fee_change_denominator.is_some() {
+            FEE_MARKET_PARAM_SIZE
+        } else if p.max_script_cost.is_some() {
+            SCRIPT_PARAM_SIZE
+        } else if p.era.is_some() {
             ERA_PARAM_SIZE
         } else if p.term_seconds.is_some() {
             STAKE_PARAM_SIZE
@@ -290,6 +346,8 @@ impl From<Params> for CommonParams {
             min_deposit: p.min_deposit.map(From::from).unwrap_or_default(),
             max_candidate_metadata_size: p.max_candidate_metadata_size.map(From::from).unwrap_or_default(),
             era: p.era.map(From::from).unwrap_or_default(),
+            max_script_cost: p.max_script_cost.map(From::from).unwrap_or_default(),
+            base_fee_change_denominator: p.base_fee_change_denominator.map(From::from).unwrap_or_default(),
         }
     }
 }
@@ -337,6 +395,12 @@ impl From<CommonParams> for Params {
         if p.size >= ERA_PARAM_SIZE {
             result.era = Some(p.era().into());
         }
+        if p.size >= SCRIPT_PARAM_SIZE {
+            result.max_script_cost = Some(p.max_script_cost().into());
+        }
+        if p.size >= FEE_MARKET_PARAM_SIZE {
+            result.base_fee_change_denominator = Some(p.base_fee_change_denominator().into());
+        }
         result
     }
 }
@@ -382,6 +446,12 @@ impl Encodable for CommonParams {
         if self.size >= ERA_PARAM_SIZE {
             s.append(&self.era);
         }
+        if self.size >= SCRIPT_PARAM_SIZE {
+            s.append(&self.max_script_cost);
+        }
+        if self.size >= FEE_MARKET_PARAM_SIZE {
+            s.append(&self.base_fee_change_denominator);
+        }
     }
 }
 
@@ -451,6 +521,18 @@ impl Decodable for CommonParams {
             Default::default()
         };
 
+        let max_script_cost = if size >= SCRIPT_PARAM_SIZE {
+            rlp.val_at(33)?
+        } else {
+            Default::default()
+        };
+
+        let base_fee_change_denominator = if size >= FEE_MARKET_PARAM_SIZE {
+            rlp.val_at(34)?
+        } else {
+            Default::default()
+        };
+
         Ok(Self {
             size,
             max_extra_data_size,
@@ -486,6 +568,8 @@ impl Decodable for CommonParams {
             min_deposit,
             max_candidate_metadata_size,
             era,
+            max_script_cost,
+            base_fee_change_denominator,
         })
     }
 }
@@ -534,6 +618,10 @@ impl CommonParams {
         self.min_deposit = min_deposit;
         self.max_candidate_metadata_size = max_candidate_metadata_size;
     }
+
+    pub fn set_era_for_test(&mut self, era: u64) {
+        self.era = era;
+    }
 }
 
 #[cfg(test)]
@@ -878,4 +966,91 @@ mod tests {
 
         assert_eq!(params, deserialized.into());
     }
+
+    #[test]
+    fn params_from_json_with_max_script_cost() {
+        let params = Params {
+            max_script_cost: Some(10_000.into()),
+            ..Params::default()
+        };
+        let deserialized = CommonParams::from(params.clone());
+        assert_eq!(deserialized.size, SCRIPT_PARAM_SIZE);
+        assert_eq!(deserialized.max_script_cost, 10_000);
+        assert_eq!(deserialized.era, 0);
+        rlp_encode_and_decode_test!(deserialized);
+
+        assert_eq!(
+            Params {
+                term_seconds: Some(0.into()),
+                nomination_expiration: Some(0.into()),
+                custody_period: Some(0.into()),
+                release_period: Some(0.into()),
+                max_num_of_validators: Some(0.into()),
+                min_num_of_validators: Some(0.into()),
+                delegation_threshold: Some(0.into()),
+                min_deposit: Some(0.into()),
+                max_candidate_metadata_size: Some(0.into()),
+                era: Some(0.into()),
+                ..params
+            },
+            deserialized.into(),
+            "Convert back will fill default values"
+        );
+    }
+
+    #[test]
+    fn max_script_cost_of_zero_is_no_limit() {
+        let mut params = CommonParams::default_for_test();
+        params.size = SCRIPT_PARAM_SIZE;
+        assert_eq!(params.verify(), Ok(()));
+        params.max_script_cost = 1;
+        assert_eq!(params.verify(), Ok(()));
+    }
+
+    #[test]
+    fn fee_market_does_not_require_max_script_cost() {
+        let params = Params {
+            base_fee_change_denominator: Some(8.into()),
+            ..Params::default()
+        };
+        let deserialized = CommonParams::from(params);
+        assert_eq!(deserialized.size, FEE_MARKET_PARAM_SIZE);
+        assert_eq!(deserialized.max_script_cost, 0);
+        assert_eq!(deserialized.verify(), Ok(()));
+        rlp_encode_and_decode_test!(deserialized);
+    }
+
+    #[test]
+    fn params_from_json_with_base_fee_change_denominator() {
+        let params = Params {
+            max_script_cost: Some(10_000.into()),
+            base_fee_change_denominator: Some(8.into()),
+            ..Params::default()
+        };
+        let deserialized = CommonParams::from(params.clone());
+        assert_eq!(deserialized.size, FEE_MARKET_PARAM_SIZE);
+        assert_eq!(deserialized.base_fee_change_denominator, 8);
+        assert_eq!(deserialized.max_script_cost, 10_000);
+        rlp_encode_and_decode_test!(deserialized);
+    }
+
+    #[test]
+    fn base_fee_multiplier_does_not_change_if_the_fee_market_is_disabled() {
+        let mut params = CommonParams::default_for_test();
+        params.max_body_size = 1000;
+        assert_eq!(BASE_FEE_MULTIPLIER_UNIT, params.next_base_fee_multiplier(BASE_FEE_MULTIPLIER_UNIT, 1000));
+        assert_eq!(BASE_FEE_MULTIPLIER_UNIT, params.next_base_fee_multiplier(2 * BASE_FEE_MULTIPLIER_UNIT, 0));
+    }
+
+    #[test]
+    fn base_fee_multiplier_follows_the_body_size() {
+        let mut params = CommonParams::default_for_test();
+        params.max_body_size = 1000;
+        params.base_fee_change_denominator = 8;
+        assert_eq!(1125, params.next_base_fee_multiplier(BASE_FEE_MULTIPLIER_UNIT, 1000));
+        assert_eq!(1001, params.next_base_fee_multiplier(BASE_FEE_MULTIPLIER_UNIT, 501));
+        assert_eq!(1125, params.next_base_fee_multiplier(1125, 500));
+        assert_eq!(1000, params.next_base_fee_multiplier(1125, 0));
+        assert_eq!(BASE_FEE_MULTIPLIER_UNIT, params.next_base_fee_multiplier(BASE_FEE_MULTIPLIER_UNIT, 0));
+    }
 }
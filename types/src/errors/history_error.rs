@@ -25,6 +25,8 @@ use std::fmt::{Display, Formatter, Result as FormatResult};
 pub enum Error {
     /// Transaction was not imported to the queue because limit has been reached.
     LimitReached,
+    /// Transaction was not imported to the queue because the memory limit has been reached.
+    MemoryLimitReached,
     /// Transaction is not valid anymore (state already has higher seq)
     Old,
     Timelocked {
@@ -36,10 +38,16 @@ pub enum Error {
     TooCheapToReplace,
     /// Transaction is already imported to the queue
     TransactionAlreadyImported,
+    /// The signer already has too many transactions in the queue
+    SignerLimitReached {
+        limit: u64,
+    },
     TransferExpired {
         expiration: u64,
         timestamp: u64,
     },
+    /// The pending transaction whose shard outputs are spent by the transaction is dropped
+    ParentDropped,
 }
 
 #[derive(Clone, Copy)]
@@ -51,6 +59,9 @@ enum ErrorID {
     TooCheapToReplace = 6,
     TxAlreadyImported = 7,
     TransferExpired = 8,
+    MemoryLimitReached = 9,
+    SignerLimitReached = 10,
+    ParentDropped = 11,
 }
 
 impl Encodable for ErrorID {
@@ -69,6 +80,9 @@ impl Decodable for ErrorID {
             6 => Ok(ErrorID::TooCheapToReplace),
             7 => Ok(ErrorID::TxAlreadyImported),
             8 => Ok(ErrorID::TransferExpired),
+            9 => Ok(ErrorID::MemoryLimitReached),
+            10 => Ok(ErrorID::SignerLimitReached),
+            11 => Ok(ErrorID::ParentDropped),
             _ => Err(DecoderError::Custom("Unexpected ErrorID Value")),
         }
     }
@@ -86,6 +100,9 @@ impl TaggedRlp for RlpHelper {
             ErrorID::TooCheapToReplace => 1,
             ErrorID::TxAlreadyImported => 1,
             ErrorID::TransferExpired => 3,
+            ErrorID::MemoryLimitReached => 1,
+            ErrorID::SignerLimitReached => 2,
+            ErrorID::ParentDropped => 1,
         })
     }
 }
@@ -94,6 +111,7 @@ impl Encodable for Error {
     fn rlp_append(&self, s: &mut RlpStream) {
         match self {
             Error::LimitReached => RlpHelper::new_tagged_list(s, ErrorID::LimitReached),
+            Error::MemoryLimitReached => RlpHelper::new_tagged_list(s, ErrorID::MemoryLimitReached),
             Error::Old => RlpHelper::new_tagged_list(s, ErrorID::Old),
             Error::Timelocked {
                 timelock,
@@ -101,10 +119,14 @@ impl Encodable for Error {
             } => RlpHelper::new_tagged_list(s, ErrorID::Timelocked).append(timelock).append(remaining_time),
             Error::TooCheapToReplace => RlpHelper::new_tagged_list(s, ErrorID::TooCheapToReplace),
             Error::TransactionAlreadyImported => RlpHelper::new_tagged_list(s, ErrorID::TxAlreadyImported),
+            Error::SignerLimitReached {
+                limit,
+            } => RlpHelper::new_tagged_list(s, ErrorID::SignerLimitReached).append(limit),
             Error::TransferExpired {
                 expiration,
                 timestamp,
             } => RlpHelper::new_tagged_list(s, ErrorID::TransferExpired).append(expiration).append(timestamp),
+            Error::ParentDropped => RlpHelper::new_tagged_list(s, ErrorID::ParentDropped),
         };
     }
 }
@@ -114,6 +136,7 @@ impl Decodable for Error {
         let tag = rlp.val_at(0)?;
         let error = match tag {
             ErrorID::LimitReached => Error::LimitReached,
+            ErrorID::MemoryLimitReached => Error::MemoryLimitReached,
             ErrorID::Old => Error::Old,
             ErrorID::Timelocked => Error::Timelocked {
                 timelock: rlp.val_at(1)?,
@@ -121,10 +144,14 @@ impl Decodable for Error {
             },
             ErrorID::TooCheapToReplace => Error::TooCheapToReplace,
             ErrorID::TxAlreadyImported => Error::TransactionAlreadyImported,
+            ErrorID::SignerLimitReached => Error::SignerLimitReached {
+                limit: rlp.val_at(1)?,
+            },
             ErrorID::TransferExpired => Error::TransferExpired {
                 expiration: rlp.val_at(1)?,
                 timestamp: rlp.val_at(2)?,
             },
+            ErrorID::ParentDropped => Error::ParentDropped,
         };
         RlpHelper::check_size(rlp, tag)?;
         Ok(error)
@@ -135,6 +162,7 @@ impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> FormatResult {
         match self {
             Error::LimitReached => write!(f, "Transaction limit reached"),
+            Error::MemoryLimitReached => write!(f, "Memory limit of the mem pool reached"),
             Error::Old => write!(f, "No longer valid"),
             Error::Timelocked {
                 timelock,
@@ -146,6 +174,9 @@ impl Display for Error {
             ),
             Error::TooCheapToReplace => write!(f, "Fee too low to replace"),
             Error::TransactionAlreadyImported => write!(f, "The transaction is already imported"),
+            Error::SignerLimitReached {
+                limit,
+            } => write!(f, "The signer already has {} transactions in the queue", limit),
             Error::TransferExpired {
                 expiration,
                 timestamp,
@@ -154,6 +185,7 @@ impl Display for Error {
                 "The TransferAsset transaction is expired. Expiration: {}, Block timestamp: {}",
                 expiration, timestamp
             ),
+            Error::ParentDropped => write!(f, "The pending transaction whose outputs are spent is dropped"),
         }
     }
 }
@@ -42,6 +42,8 @@ pub enum Error {
     /// AssetType format error
     InvalidAssetType(H160),
     InvalidCustomAction(String),
+    /// The static verification of the scripts failed. It has the diagnostics.
+    InvalidScript(String),
     /// Invalid network ID given.
     InvalidNetworkId(NetworkId),
     InvalidApproval(String),
@@ -82,6 +84,7 @@ enum ErrorID {
     DisabledTransaction = 30,
     InvalidSignerOfWRAPCCC = 31,
     InvalidCustomAction = 32,
+    InvalidScript = 33,
 }
 
 impl Encodable for ErrorID {
@@ -110,6 +113,7 @@ impl Decodable for ErrorID {
             30 => Ok(ErrorID::DisabledTransaction),
             31 => Ok(ErrorID::InvalidSignerOfWRAPCCC),
             32 => Ok(ErrorID::InvalidCustomAction),
+            33 => Ok(ErrorID::InvalidScript),
             _ => Err(DecoderError::Custom("Unexpected ErrorID Value")),
         }
     }
@@ -128,6 +132,7 @@ impl TaggedRlp for RlpHelper {
             ErrorID::InsufficientFee => 3,
             ErrorID::InvalidAssetType => 2,
             ErrorID::InvalidCustomAction => 2,
+            ErrorID::InvalidScript => 2,
             ErrorID::InvalidNetworkID => 2,
             ErrorID::InvalidApproval => 2,
             ErrorID::MetadataTooBig => 1,
@@ -159,6 +164,7 @@ impl Encodable for Error {
             } => RlpHelper::new_tagged_list(s, ErrorID::InsufficientFee).append(minimal).append(got),
             Error::InvalidAssetType(addr) => RlpHelper::new_tagged_list(s, ErrorID::InvalidAssetType).append(addr),
             Error::InvalidCustomAction(err) => RlpHelper::new_tagged_list(s, ErrorID::InvalidCustomAction).append(err),
+            Error::InvalidScript(err) => RlpHelper::new_tagged_list(s, ErrorID::InvalidScript).append(err),
             Error::InvalidNetworkId(network_id) => {
                 RlpHelper::new_tagged_list(s, ErrorID::InvalidNetworkID).append(network_id)
             }
@@ -191,6 +197,7 @@ impl Decodable for Error {
             },
             ErrorID::InvalidAssetType => Error::InvalidAssetType(rlp.val_at(1)?),
             ErrorID::InvalidCustomAction => Error::InvalidCustomAction(rlp.val_at(1)?),
+            ErrorID::InvalidScript => Error::InvalidScript(rlp.val_at(1)?),
             ErrorID::InvalidNetworkID => Error::InvalidNetworkId(rlp.val_at(1)?),
             ErrorID::InvalidApproval => Error::InvalidApproval(rlp.val_at(1)?),
             ErrorID::MetadataTooBig => Error::MetadataTooBig,
@@ -224,6 +231,7 @@ impl Display for Error {
             } => write!(f, "Insufficient fee. Min={}, Given={}", minimal, got),
             Error::InvalidAssetType(addr) => write!(f, "Asset type is invalid: {}", addr),
             Error::InvalidCustomAction(err) => write!(f, "Invalid custom action: {}", err),
+            Error::InvalidScript(err) => write!(f, "Invalid script: {}", err),
             Error::InvalidNetworkId(network_id) => write!(f, "{} is an invalid network id", network_id),
             Error::InvalidApproval(err) => write!(f, "Transaction has an invalid approval :{}", err),
             Error::MetadataTooBig => write!(f, "Metadata size is too big."),
@@ -41,7 +41,7 @@ pub type BlockNumber = u64;
 pub type ShardId = u16;
 
 pub use block_hash::BlockHash;
-pub use common_params::CommonParams;
+pub use common_params::{apply_base_fee_multiplier, CommonParams, BASE_FEE_MULTIPLIER_UNIT};
 pub use header::Header;
 pub use tracker::Tracker;
 pub use tx_hash::TxHash;
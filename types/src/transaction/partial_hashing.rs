@@ -14,12 +14,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::AssetTransferInput;
+use super::{AssetTransferInput, AssetTransferOutput};
 use crate::util::tag::Tag;
 use primitives::H256;
 
 pub trait PartialHashing {
     fn hash_partially(&self, tag: Tag, cur: &AssetTransferInput, burn: bool) -> Result<H256, HashingError>;
+
+    /// The outputs that the scripts can inspect.
+    fn outputs(&self) -> &[AssetTransferOutput] {
+        &[]
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -324,6 +324,16 @@ impl PartialHashing for ShardTransaction {
             _ => unreachable!(),
         }
     }
+
+    fn outputs(&self) -> &[AssetTransferOutput] {
+        match self {
+            ShardTransaction::TransferAsset {
+                outputs,
+                ..
+            } => outputs,
+            _ => &[],
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -0,0 +1,532 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! A text format for scripts.
+//!
+//! Each instruction is written as its mnemonic followed by its immediate value, if any.
+//! Mnemonics are case-insensitive, and `;` starts a comment that lasts until the end of the line.
+//!
+//! ```text
+//! ; pay to public key
+//! PUSHB 0x0102...  ; public key
+//! CHKSIG
+//! JZ fail          ; jumps may refer to a label instead of a count
+//! PUSH 1
+//! fail:
+//! ```
+//!
+//! Integers are written in decimal or in hexadecimal with a `0x` prefix, and blobs are always hexadecimal.
+//! The immediate value of `CHKTIMELOCK` is one of `BLOCK`, `BLOCK_AGE`, `TIME` and `TIME_AGE`.
+
+use ckey::hex::FromHex;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::decoder::{decode, DecoderError};
+use crate::encoder::{encode, encoded_size};
+use crate::executor::TimelockType;
+use crate::instruction::Instruction;
+
+#[derive(Debug, PartialEq)]
+pub enum AssemblerError {
+    UnknownMnemonic {
+        line: usize,
+        mnemonic: String,
+    },
+    MissingOperand {
+        line: usize,
+        mnemonic: String,
+    },
+    InvalidOperand {
+        line: usize,
+        operand: String,
+    },
+    BlobTooLong {
+        line: usize,
+        length: usize,
+    },
+    DuplicatedLabel {
+        line: usize,
+        label: String,
+    },
+    UnknownLabel {
+        line: usize,
+        label: String,
+    },
+    /// Jumps can only skip forward, and at most 255 instructions.
+    UnreachableLabel {
+        line: usize,
+        label: String,
+    },
+}
+
+impl fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssemblerError::UnknownMnemonic {
+                line,
+                mnemonic,
+            } => write!(f, "line {}: Unknown mnemonic {}", line, mnemonic),
+            AssemblerError::MissingOperand {
+                line,
+                mnemonic,
+            } => write!(f, "line {}: {} requires an operand", line, mnemonic),
+            AssemblerError::InvalidOperand {
+                line,
+                operand,
+            } => write!(f, "line {}: Invalid operand {}", line, operand),
+            AssemblerError::BlobTooLong {
+                line,
+                length,
+            } => write!(f, "line {}: A blob cannot be longer than 255 bytes, but it has {} bytes", line, length),
+            AssemblerError::DuplicatedLabel {
+                line,
+                label,
+            } => write!(f, "line {}: Label {} is already defined", line, label),
+            AssemblerError::UnknownLabel {
+                line,
+                label,
+            } => write!(f, "line {}: Label {} is not defined", line, label),
+            AssemblerError::UnreachableLabel {
+                line,
+                label,
+            } => write!(f, "line {}: Label {} cannot be reached by a forward jump", line, label),
+        }
+    }
+}
+
+enum JumpKind {
+    Jmp,
+    Jnz,
+    Jz,
+}
+
+enum Parsed {
+    Instruction(Instruction),
+    Jump {
+        kind: JumpKind,
+        label: String,
+    },
+}
+
+/// Assembles the source into the script bytes that `decode` accepts.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssemblerError> {
+    let instructions = parse(source)?;
+    Ok(encode(&instructions).expect("The length of blobs is checked while parsing"))
+}
+
+fn parse(source: &str) -> Result<Vec<Instruction>, AssemblerError> {
+    let mut parsed: Vec<(usize, Parsed)> = Vec::new();
+    let mut labels: HashMap<String, usize> = HashMap::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let code = match line.find(';') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut tokens = code.split_whitespace();
+        while let Some(token) = tokens.next() {
+            if token.ends_with(':') {
+                let label = &token[..token.len() - 1];
+                if label.is_empty() {
+                    return Err(AssemblerError::InvalidOperand {
+                        line: line_number,
+                        operand: token.to_string(),
+                    })
+                }
+                if labels.insert(label.to_string(), parsed.len()).is_some() {
+                    return Err(AssemblerError::DuplicatedLabel {
+                        line: line_number,
+                        label: label.to_string(),
+                    })
+                }
+                continue
+            }
+            let instruction = parse_instruction(line_number, token, &mut tokens)?;
+            parsed.push((line_number, instruction));
+        }
+    }
+
+    parsed
+        .into_iter()
+        .enumerate()
+        .map(|(index, (line, parsed))| match parsed {
+            Parsed::Instruction(instruction) => Ok(instruction),
+            Parsed::Jump {
+                kind,
+                label,
+            } => {
+                let target = *labels.get(&label).ok_or_else(|| AssemblerError::UnknownLabel {
+                    line,
+                    label: label.clone(),
+                })?;
+                // The executor moves to the next instruction after skipping.
+                let skip = target.checked_sub(index + 1).filter(|skip| *skip <= u8::max_value() as usize).ok_or(
+                    AssemblerError::UnreachableLabel {
+                        line,
+                        label,
+                    },
+                )? as u8;
+                Ok(match kind {
+                    JumpKind::Jmp => Instruction::Jmp(skip),
+                    JumpKind::Jnz => Instruction::Jnz(skip),
+                    JumpKind::Jz => Instruction::Jz(skip),
+                })
+            }
+        })
+        .collect()
+}
+
+fn parse_instruction<'a>(
+    line: usize,
+    mnemonic: &str,
+    operands: &mut impl Iterator<Item = &'a str>,
+) -> Result<Parsed, AssemblerError> {
+    let mnemonic = mnemonic.to_uppercase();
+    let mut operand = || {
+        operands.next().ok_or_else(|| AssemblerError::MissingOperand {
+            line,
+            mnemonic: mnemonic.clone(),
+        })
+    };
+    let instruction = match mnemonic.as_str() {
+        "NOP" => Instruction::Nop,
+        "BURN" => Instruction::Burn,
+        "SUCCESS" => Instruction::Success,
+        "FAIL" => Instruction::Fail,
+        "NOT" => Instruction::Not,
+        "EQ" => Instruction::Eq,
+        "JMP" | "JNZ" | "JZ" => {
+            let operand = operand()?;
+            let kind = match mnemonic.as_str() {
+                "JMP" => JumpKind::Jmp,
+                "JNZ" => JumpKind::Jnz,
+                _ => JumpKind::Jz,
+            };
+            if let Some(skip) = parse_u8(operand) {
+                match kind {
+                    JumpKind::Jmp => Instruction::Jmp(skip),
+                    JumpKind::Jnz => Instruction::Jnz(skip),
+                    JumpKind::Jz => Instruction::Jz(skip),
+                }
+            } else {
+                return Ok(Parsed::Jump {
+                    kind,
+                    label: operand.to_string(),
+                })
+            }
+        }
+        "PUSH" => Instruction::Push(parse_u8_operand(line, operand()?)?),
+        "POP" => Instruction::Pop,
+        "PUSHB" => {
+            let operand = operand()?;
+            let invalid_operand = || AssemblerError::InvalidOperand {
+                line,
+                operand: operand.to_string(),
+            };
+            if !operand.starts_with("0x") {
+                return Err(invalid_operand())
+            }
+            let blob = operand[2..].from_hex().map_err(|_| invalid_operand())?;
+            if blob.len() > u8::max_value() as usize {
+                return Err(AssemblerError::BlobTooLong {
+                    line,
+                    length: blob.len(),
+                })
+            }
+            Instruction::PushB(blob)
+        }
+        "DUP" => Instruction::Dup,
+        "SWAP" => Instruction::Swap,
+        "COPY" => Instruction::Copy(parse_u8_operand(line, operand()?)?),
+        "DROP" => Instruction::Drop(parse_u8_operand(line, operand()?)?),
+        "SIZE" => Instruction::Size,
+        "ADD" => Instruction::Add,
+        "SUB" => Instruction::Sub,
+        "MUL" => Instruction::Mul,
+        "DIV" => Instruction::Div,
+        "MOD" => Instruction::Mod,
+        "LT" => Instruction::Lt,
+        "GT" => Instruction::Gt,
+        "LE" => Instruction::Le,
+        "GE" => Instruction::Ge,
+        "MIN" => Instruction::Min,
+        "MAX" => Instruction::Max,
+        "WITHIN" => Instruction::Within,
+        "CHKSIG" => Instruction::ChkSig,
+        "CHKMULTISIG" => Instruction::ChkMultiSig,
+        "CHKSCHNORR" => Instruction::ChkSchnorr,
+        "CHKAGGSCHNORR" => Instruction::ChkAggSchnorr,
+        "BLAKE256" => Instruction::Blake256,
+        "SHA256" => Instruction::Sha256,
+        "RIPEMD160" => Instruction::Ripemd160,
+        "KECCAK256" => Instruction::Keccak256,
+        "BLAKE160" => Instruction::Blake160,
+        "CHKTIMELOCK" => {
+            let operand = operand()?;
+            let timelock_type = match operand.to_uppercase().as_str() {
+                "BLOCK" | "1" => TimelockType::Block,
+                "BLOCK_AGE" | "2" => TimelockType::BlockAge,
+                "TIME" | "3" => TimelockType::Time,
+                "TIME_AGE" | "4" => TimelockType::TimeAge,
+                _ => {
+                    return Err(AssemblerError::InvalidOperand {
+                        line,
+                        operand: operand.to_string(),
+                    })
+                }
+            };
+            Instruction::ChkTimelock(timelock_type)
+        }
+        "OUTCOUNT" => Instruction::OutCount,
+        "OUTLOCKHASH" => Instruction::OutLockHash,
+        "OUTASSETTYPE" => Instruction::OutAssetType,
+        "OUTQUANTITY" => Instruction::OutQuantity,
+        _ => {
+            return Err(AssemblerError::UnknownMnemonic {
+                line,
+                mnemonic,
+            })
+        }
+    };
+    Ok(Parsed::Instruction(instruction))
+}
+
+fn parse_u8(operand: &str) -> Option<u8> {
+    if operand.starts_with("0x") {
+        u8::from_str_radix(&operand[2..], 16).ok()
+    } else {
+        operand.parse().ok()
+    }
+}
+
+fn parse_u8_operand(line: usize, operand: &str) -> Result<u8, AssemblerError> {
+    parse_u8(operand).ok_or_else(|| AssemblerError::InvalidOperand {
+        line,
+        operand: operand.to_string(),
+    })
+}
+
+/// Prints one instruction per line, prefixed with its byte offset.
+/// Jumps are annotated with the offset they land on.
+/// The output is valid assembly: the offsets are read back as labels.
+pub fn disassemble(script: &[u8]) -> Result<String, DecoderError> {
+    let instructions = decode(script)?;
+    let offsets: Vec<usize> = instructions
+        .iter()
+        .scan(0, |offset, instruction| {
+            let current = *offset;
+            *offset += encoded_size(instruction);
+            Some(current)
+        })
+        .collect();
+
+    let mut result = String::new();
+    for (index, (instruction, offset)) in instructions.iter().zip(offsets.iter()).enumerate() {
+        result.push_str(&format!("{:04x}: {}", offset, instruction));
+        let skip = match instruction {
+            Instruction::Jmp(skip) | Instruction::Jnz(skip) | Instruction::Jz(skip) => Some(*skip as usize),
+            _ => None,
+        };
+        if let Some(skip) = skip {
+            match offsets.get(index + 1 + skip) {
+                Some(target) => result.push_str(&format!(" ; -> {:04x}", target)),
+                None => result.push_str(" ; -> end"),
+            }
+        }
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opcode;
+
+    #[test]
+    fn assemble_every_instruction() {
+        let source = "
+            NOP BURN SUCCESS FAIL NOT EQ JMP 1 JNZ 2 JZ 0x03 PUSH 255 POP PUSHB 0x PUSHB 0xabcd DUP SWAP COPY 1 DROP 2
+            SIZE ADD SUB MUL DIV MOD LT GT LE GE MIN MAX WITHIN
+            CHKSIG CHKMULTISIG CHKSCHNORR CHKAGGSCHNORR BLAKE256 SHA256 RIPEMD160 KECCAK256 BLAKE160
+            CHKTIMELOCK BLOCK CHKTIMELOCK block_age CHKTIMELOCK TIME CHKTIMELOCK 4
+            OUTCOUNT OUTLOCKHASH OUTASSETTYPE OUTQUANTITY
+        ";
+        let script = assemble(source).unwrap();
+        let instructions = decode(&script).unwrap();
+        assert_eq!(instructions.len(), 47);
+        assert_eq!(instructions[6], Instruction::Jmp(1));
+        assert_eq!(instructions[8], Instruction::Jz(3));
+        assert_eq!(instructions[12], Instruction::PushB(vec![0xab, 0xcd]));
+        assert_eq!(instructions[40], Instruction::ChkTimelock(TimelockType::BlockAge));
+
+        let printed: Vec<_> = instructions.iter().map(ToString::to_string).collect();
+        assert_eq!(assemble(&printed.join("\n")), Ok(script));
+    }
+
+    #[test]
+    fn mnemonics_are_case_insensitive() {
+        assert_eq!(assemble("push 1 ChkSig"), Ok(vec![opcode::PUSH, 1, opcode::CHKSIG]));
+    }
+
+    #[test]
+    fn comments() {
+        let source = "
+            ; leading comment
+            PUSH 1 ; PUSH 2
+            ;NOP
+        ";
+        assert_eq!(assemble(source), Ok(vec![opcode::PUSH, 1]));
+    }
+
+    #[test]
+    fn labels() {
+        let source = "
+            PUSH 1
+            JZ fail
+            PUSH 2
+            JMP end
+            fail: PUSH 3
+            end:
+        ";
+        let script = assemble(source).unwrap();
+        assert_eq!(
+            decode(&script),
+            Ok(vec![
+                Instruction::Push(1),
+                Instruction::Jz(2),
+                Instruction::Push(2),
+                Instruction::Jmp(1),
+                Instruction::Push(3),
+            ])
+        );
+    }
+
+    #[test]
+    fn backward_jump() {
+        assert_eq!(
+            assemble("start: NOP JMP start"),
+            Err(AssemblerError::UnreachableLabel {
+                line: 1,
+                label: "start".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn jump_too_far() {
+        let mut source = "JMP end\n".to_string();
+        source.push_str(&"NOP\n".repeat(255));
+        source.push_str("end:");
+        assert!(assemble(&source).is_ok());
+
+        let mut source = "JMP end2\n".to_string() + &"NOP\n".repeat(256) + "end2:";
+        assert_eq!(
+            assemble(&source),
+            Err(AssemblerError::UnreachableLabel {
+                line: 1,
+                label: "end2".to_string(),
+            })
+        );
+        source.push_str(" end2:");
+        assert_eq!(
+            assemble(&source),
+            Err(AssemblerError::DuplicatedLabel {
+                line: 258,
+                label: "end2".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            assemble("NOP\nPUSHX 1"),
+            Err(AssemblerError::UnknownMnemonic {
+                line: 2,
+                mnemonic: "PUSHX".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble("PUSH"),
+            Err(AssemblerError::MissingOperand {
+                line: 1,
+                mnemonic: "PUSH".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble("PUSH 256"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "256".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble("PUSHB abcd"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "abcd".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble("PUSHB 0xabc"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "0xabc".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble(&format!("PUSHB 0x{}", "00".repeat(256))),
+            Err(AssemblerError::BlobTooLong {
+                line: 1,
+                length: 256,
+            })
+        );
+        assert_eq!(
+            assemble("CHKTIMELOCK 5"),
+            Err(AssemblerError::InvalidOperand {
+                line: 1,
+                operand: "5".to_string(),
+            })
+        );
+        assert_eq!(
+            assemble("JZ nowhere"),
+            Err(AssemblerError::UnknownLabel {
+                line: 1,
+                label: "nowhere".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn disassemble_with_offsets() {
+        let script = assemble("PUSHB 0x0102 JZ end PUSH 1 JMP 5 end: CHKSIG").unwrap();
+        assert_eq!(
+            disassemble(&script),
+            Ok("0000: PUSHB 0x0102\n0004: JZ 2 ; -> 000a\n0006: PUSH 1\n0008: JMP 5 ; -> end\n000a: CHKSIG\n"
+                .to_string())
+        );
+        assert_eq!(disassemble(&[opcode::PUSHB, 2, 0]), Err(DecoderError::ScriptTooShort));
+    }
+
+    #[test]
+    fn disassembly_is_assemblable() {
+        let script = assemble("PUSH 1 JNZ a NOP a: CHKTIMELOCK TIME_AGE PUSHB 0xff").unwrap();
+        assert_eq!(assemble(&disassemble(&script).unwrap()), Ok(script));
+    }
+}
@@ -13,6 +13,8 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use std::fmt;
+
 use crate::executor::TimelockType;
 use crate::instruction::Instruction;
 use crate::opcode;
@@ -24,6 +26,16 @@ pub enum DecoderError {
     InvalidImmediateValue(u8),
 }
 
+impl fmt::Display for DecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecoderError::ScriptTooShort => write!(f, "Script is too short"),
+            DecoderError::InvalidOpCode(code) => write!(f, "Invalid opcode 0x{:02x}", code),
+            DecoderError::InvalidImmediateValue(val) => write!(f, "Invalid immediate value {}", val),
+        }
+    }
+}
+
 pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, DecoderError> {
     let mut iter = bytes.iter();
     let mut result = Vec::new();
@@ -71,8 +83,23 @@ pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, DecoderError> {
                 let val = *iter.next().ok_or(DecoderError::ScriptTooShort)?;
                 result.push(Instruction::Drop(val));
             }
+            opcode::SIZE => result.push(Instruction::Size),
+            opcode::ADD => result.push(Instruction::Add),
+            opcode::SUB => result.push(Instruction::Sub),
+            opcode::MUL => result.push(Instruction::Mul),
+            opcode::DIV => result.push(Instruction::Div),
+            opcode::MOD => result.push(Instruction::Mod),
+            opcode::LT => result.push(Instruction::Lt),
+            opcode::GT => result.push(Instruction::Gt),
+            opcode::LE => result.push(Instruction::Le),
+            opcode::GE => result.push(Instruction::Ge),
+            opcode::MIN => result.push(Instruction::Min),
+            opcode::MAX => result.push(Instruction::Max),
+            opcode::WITHIN => result.push(Instruction::Within),
             opcode::CHKSIG => result.push(Instruction::ChkSig),
             opcode::CHKMULTISIG => result.push(Instruction::ChkMultiSig),
+            opcode::CHKSCHNORR => result.push(Instruction::ChkSchnorr),
+            opcode::CHKAGGSCHNORR => result.push(Instruction::ChkAggSchnorr),
             opcode::BLAKE256 => result.push(Instruction::Blake256),
             opcode::SHA256 => result.push(Instruction::Sha256),
             opcode::RIPEMD160 => result.push(Instruction::Ripemd160),
@@ -91,6 +118,10 @@ pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, DecoderError> {
 
                 result.push(Instruction::ChkTimelock(timelock_type));
             }
+            opcode::OUTCOUNT => result.push(Instruction::OutCount),
+            opcode::OUTLOCKHASH => result.push(Instruction::OutLockHash),
+            opcode::OUTASSETTYPE => result.push(Instruction::OutAssetType),
+            opcode::OUTQUANTITY => result.push(Instruction::OutQuantity),
             invalid_opcode => return Err(DecoderError::InvalidOpCode(invalid_opcode)),
         }
     }
@@ -147,13 +178,32 @@ mod tests {
     test_no_argument_opcode!(SWAP, Swap);
     test_one_argument_opcode!(COPY, Copy);
     test_one_argument_opcode!(DROP, Drop);
+    test_no_argument_opcode!(SIZE, Size);
+    test_no_argument_opcode!(ADD, Add);
+    test_no_argument_opcode!(SUB, Sub);
+    test_no_argument_opcode!(MUL, Mul);
+    test_no_argument_opcode!(DIV, Div);
+    test_no_argument_opcode!(MOD, Mod);
+    test_no_argument_opcode!(LT, Lt);
+    test_no_argument_opcode!(GT, Gt);
+    test_no_argument_opcode!(LE, Le);
+    test_no_argument_opcode!(GE, Ge);
+    test_no_argument_opcode!(MIN, Min);
+    test_no_argument_opcode!(MAX, Max);
+    test_no_argument_opcode!(WITHIN, Within);
     test_no_argument_opcode!(CHKSIG, ChkSig);
     test_no_argument_opcode!(CHKMULTISIG, ChkMultiSig);
+    test_no_argument_opcode!(CHKSCHNORR, ChkSchnorr);
+    test_no_argument_opcode!(CHKAGGSCHNORR, ChkAggSchnorr);
     test_no_argument_opcode!(BLAKE256, Blake256);
     test_no_argument_opcode!(SHA256, Sha256);
     test_no_argument_opcode!(RIPEMD160, Ripemd160);
     test_no_argument_opcode!(KECCAK256, Keccak256);
     test_no_argument_opcode!(BLAKE160, Blake160);
+    test_no_argument_opcode!(OUTCOUNT, OutCount);
+    test_no_argument_opcode!(OUTLOCKHASH, OutLockHash);
+    test_no_argument_opcode!(OUTASSETTYPE, OutAssetType);
+    test_no_argument_opcode!(OUTQUANTITY, OutQuantity);
 
     #[test]
     #[allow(non_snake_case)]
@@ -0,0 +1,136 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+use ccrypto::Blake;
+use primitives::H160;
+
+use crate::instruction::Instruction;
+use crate::opcode;
+
+#[derive(Debug, PartialEq)]
+pub enum EncoderError {
+    BlobTooLong(usize),
+}
+
+pub fn encode(instructions: &[Instruction]) -> Result<Vec<u8>, EncoderError> {
+    let mut result = Vec::with_capacity(instructions.iter().map(encoded_size).sum());
+    for instruction in instructions {
+        match instruction {
+            Instruction::Nop => result.push(opcode::NOP),
+            Instruction::Burn => result.push(opcode::BURN),
+            Instruction::Success => result.push(opcode::SUCCESS),
+            Instruction::Fail => result.push(opcode::FAIL),
+            Instruction::Not => result.push(opcode::NOT),
+            Instruction::Eq => result.push(opcode::EQ),
+            Instruction::Jmp(val) => result.extend_from_slice(&[opcode::JMP, *val]),
+            Instruction::Jnz(val) => result.extend_from_slice(&[opcode::JNZ, *val]),
+            Instruction::Jz(val) => result.extend_from_slice(&[opcode::JZ, *val]),
+            Instruction::Push(val) => result.extend_from_slice(&[opcode::PUSH, *val]),
+            Instruction::Pop => result.push(opcode::POP),
+            Instruction::PushB(blob) => {
+                if blob.len() > u8::max_value() as usize {
+                    return Err(EncoderError::BlobTooLong(blob.len()))
+                }
+                result.extend_from_slice(&[opcode::PUSHB, blob.len() as u8]);
+                result.extend_from_slice(blob);
+            }
+            Instruction::Dup => result.push(opcode::DUP),
+            Instruction::Swap => result.push(opcode::SWAP),
+            Instruction::Copy(val) => result.extend_from_slice(&[opcode::COPY, *val]),
+            Instruction::Drop(val) => result.extend_from_slice(&[opcode::DROP, *val]),
+            Instruction::Size => result.push(opcode::SIZE),
+            Instruction::Add => result.push(opcode::ADD),
+            Instruction::Sub => result.push(opcode::SUB),
+            Instruction::Mul => result.push(opcode::MUL),
+            Instruction::Div => result.push(opcode::DIV),
+            Instruction::Mod => result.push(opcode::MOD),
+            Instruction::Lt => result.push(opcode::LT),
+            Instruction::Gt => result.push(opcode::GT),
+            Instruction::Le => result.push(opcode::LE),
+            Instruction::Ge => result.push(opcode::GE),
+            Instruction::Min => result.push(opcode::MIN),
+            Instruction::Max => result.push(opcode::MAX),
+            Instruction::Within => result.push(opcode::WITHIN),
+            Instruction::ChkSig => result.push(opcode::CHKSIG),
+            Instruction::ChkMultiSig => result.push(opcode::CHKMULTISIG),
+            Instruction::ChkSchnorr => result.push(opcode::CHKSCHNORR),
+            Instruction::ChkAggSchnorr => result.push(opcode::CHKAGGSCHNORR),
+            Instruction::Blake256 => result.push(opcode::BLAKE256),
+            Instruction::Sha256 => result.push(opcode::SHA256),
+            Instruction::Ripemd160 => result.push(opcode::RIPEMD160),
+            Instruction::Keccak256 => result.push(opcode::KECCAK256),
+            Instruction::Blake160 => result.push(opcode::BLAKE160),
+            Instruction::ChkTimelock(timelock_type) => {
+                result.extend_from_slice(&[opcode::CHKTIMELOCK, timelock_type.clone() as u8])
+            }
+            Instruction::OutCount => result.push(opcode::OUTCOUNT),
+            Instruction::OutLockHash => result.push(opcode::OUTLOCKHASH),
+            Instruction::OutAssetType => result.push(opcode::OUTASSETTYPE),
+            Instruction::OutQuantity => result.push(opcode::OUTQUANTITY),
+        }
+    }
+    Ok(result)
+}
+
+/// The number of bytes the instruction occupies in an encoded script.
+pub(crate) fn encoded_size(instruction: &Instruction) -> usize {
+    match instruction {
+        Instruction::PushB(blob) => 2 + blob.len(),
+        Instruction::Jmp(_)
+        | Instruction::Jnz(_)
+        | Instruction::Jz(_)
+        | Instruction::Push(_)
+        | Instruction::Copy(_)
+        | Instruction::Drop(_)
+        | Instruction::ChkTimelock(_) => 2,
+        _ => 1,
+    }
+}
+
+/// The hash that an asset output commits to as `lock_script_hash`.
+pub fn lock_script_hash(lock_script: &[u8]) -> H160 {
+    H160::blake(lock_script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decoder::decode;
+    use crate::executor::TimelockType;
+
+    #[test]
+    fn encode_and_decode() {
+        let instructions = vec![
+            Instruction::Nop,
+            Instruction::PushB(vec![]),
+            Instruction::PushB(vec![0xab; 255]),
+            Instruction::Push(3),
+            Instruction::Jz(1),
+            Instruction::Copy(2),
+            Instruction::Within,
+            Instruction::ChkTimelock(TimelockType::TimeAge),
+            Instruction::ChkSig,
+        ];
+        let encoded = encode(&instructions).unwrap();
+        assert_eq!(encoded.len(), instructions.iter().map(encoded_size).sum::<usize>());
+        assert_eq!(decode(&encoded), Ok(instructions));
+    }
+
+    #[test]
+    fn blob_too_long() {
+        assert_eq!(encode(&[Instruction::PushB(vec![0; 256])]), Err(EncoderError::BlobTooLong(256)));
+    }
+}
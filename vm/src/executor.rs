@@ -14,16 +14,31 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use ccrypto::{blake256, keccak256, ripemd160, sha256, Blake};
-use ckey::{verify, Public, Signature, SIGNATURE_LENGTH};
-use ctypes::transaction::{AssetTransferInput, HashingError, PartialHashing};
+use ckey::{
+    aggregate_schnorr_publics, verify, verify_schnorr, Public, SchnorrSignature, Signature, SCHNORR_SIGNATURE_LENGTH,
+    SIGNATURE_LENGTH,
+};
+use ctypes::transaction::{AssetTransferInput, AssetTransferOutput, HashingError, PartialHashing};
 use ctypes::util::tag::Tag;
-use ctypes::{BlockNumber, Tracker};
+use ctypes::{BlockNumber, CommonParams, Tracker};
 use primitives::{H160, H256};
+use std::fmt;
 
 use crate::instruction::{has_expensive_opcodes, is_valid_unlock_script, Instruction};
 
 const DEFAULT_MAX_MEMORY: usize = 1024;
 
+/// The cost of every instruction.
+pub(crate) const INSTRUCTION_COST: u64 = 1;
+/// The additional cost of hashing instructions, charged on top of the cost per 32 bytes of the input.
+pub(crate) const HASH_COST: u64 = 10;
+pub(crate) const HASH_WORD_COST: u64 = 1;
+/// The additional cost of a signature verification.
+/// CHKMULTISIG is charged this cost for each public key since it may try all of them.
+pub(crate) const SIGNATURE_VERIFICATION_COST: u64 = 100;
+/// The additional cost of adding a public key to the aggregated key of CHKAGGSCHNORR.
+pub(crate) const KEY_AGGREGATION_COST: u64 = 10;
+
 #[derive(Debug, Clone, PartialEq)]
 #[repr(u8)]
 pub enum TimelockType {
@@ -33,14 +48,48 @@ pub enum TimelockType {
     TimeAge = 0x04,
 }
 
+impl fmt::Display for TimelockType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TimelockType::Block => "BLOCK",
+            TimelockType::BlockAge => "BLOCK_AGE",
+            TimelockType::Time => "TIME",
+            TimelockType::TimeAge => "TIME_AGE",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Config {
     pub max_memory: usize,
+    pub max_cost: u64,
+    /// The era of the chain. Instructions activated in a later era are rejected.
+    pub era: u64,
 }
 
 impl Default for Config {
+    /// The configuration of a chain whose common params have neither the era nor the maximum script cost.
     fn default() -> Self {
         Self {
             max_memory: DEFAULT_MAX_MEMORY,
+            max_cost: u64::max_value(),
+            era: 0,
+        }
+    }
+}
+
+impl<'a> From<&'a CommonParams> for Config {
+    /// The cost is not limited if the parameters don't have the maximum script cost.
+    fn from(params: &'a CommonParams) -> Self {
+        let max_cost = match params.max_script_cost() {
+            0 => u64::max_value(),
+            max_cost => max_cost,
+        };
+        Self {
+            max_cost,
+            era: params.era(),
+            ..Default::default()
         }
     }
 }
@@ -61,6 +110,14 @@ pub enum RuntimeError {
     InvalidFilter,
     InvalidSigCount,
     InvalidTimelockType,
+    /// The result of an arithmetic instruction doesn't fit in an integer.
+    Overflow,
+    DivisionByZero,
+    /// The script costs more than `Config::max_cost`.
+    OutOfCost,
+    InvalidKeyCount,
+    /// The script has an instruction which is not activated in `Config::era`.
+    InactiveInstruction,
 }
 
 impl From<HashingError> for RuntimeError {
@@ -104,6 +161,14 @@ impl From<bool> for Item {
     }
 }
 
+impl From<u64> for Item {
+    fn from(val: u64) -> Item {
+        let bytes = val.to_be_bytes();
+        let leading_zeros = bytes.iter().take_while(|b| **b == 0).count();
+        Item(bytes[leading_zeros..].to_vec())
+    }
+}
+
 impl From<Item> for bool {
     fn from(item: Item) -> Self {
         item.as_ref().iter().any(|b| b != &0)
@@ -161,6 +226,37 @@ impl Stack {
     }
 }
 
+struct CostMeter {
+    used: u64,
+    max_cost: u64,
+}
+
+impl CostMeter {
+    fn new(max_cost: u64) -> Self {
+        Self {
+            used: 0,
+            max_cost,
+        }
+    }
+
+    fn charge(&mut self, cost: u64) -> Result<(), RuntimeError> {
+        self.used = self.used.saturating_add(cost);
+        if self.used > self.max_cost {
+            Err(RuntimeError::OutOfCost)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn hash_cost(input: &Item) -> u64 {
+    hash_cost_of_size(input.len())
+}
+
+pub(crate) fn hash_cost_of_size(size: usize) -> u64 {
+    HASH_COST + (size as u64 + 31) / 32 * HASH_WORD_COST
+}
+
 pub fn execute<C>(
     unlock: &[Instruction],
     params: &[Vec<u8>],
@@ -173,6 +269,58 @@ pub fn execute<C>(
     parent_block_number: BlockNumber,
     parent_block_timestamp: u64,
 ) -> Result<ScriptResult, RuntimeError>
+where
+    C: ChainTimeInfo, {
+    execute_with_cost(unlock, params, lock, tx, config, cur, burn, client, parent_block_number, parent_block_timestamp)
+        .0
+}
+
+/// Executes the scripts and returns the cost spent until the execution stopped along with the result.
+pub fn execute_with_cost<C>(
+    unlock: &[Instruction],
+    params: &[Vec<u8>],
+    lock: &[Instruction],
+    tx: &dyn PartialHashing,
+    config: Config,
+    cur: &AssetTransferInput,
+    burn: bool,
+    client: &C,
+    parent_block_number: BlockNumber,
+    parent_block_timestamp: u64,
+) -> (Result<ScriptResult, RuntimeError>, u64)
+where
+    C: ChainTimeInfo, {
+    let mut meter = CostMeter::new(config.max_cost);
+    let result = run(
+        unlock,
+        params,
+        lock,
+        tx,
+        config,
+        cur,
+        burn,
+        client,
+        parent_block_number,
+        parent_block_timestamp,
+        &mut meter,
+    );
+    (result, meter.used)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run<C>(
+    unlock: &[Instruction],
+    params: &[Vec<u8>],
+    lock: &[Instruction],
+    tx: &dyn PartialHashing,
+    config: Config,
+    cur: &AssetTransferInput,
+    burn: bool,
+    client: &C,
+    parent_block_number: BlockNumber,
+    parent_block_timestamp: u64,
+    meter: &mut CostMeter,
+) -> Result<ScriptResult, RuntimeError>
 where
     C: ChainTimeInfo, {
     // FIXME: don't merge scripts
@@ -185,12 +333,17 @@ where
         return Ok(ScriptResult::Fail)
     }
 
+    if lock.iter().any(|instruction| instruction.activation_era() > config.era) {
+        return Err(RuntimeError::InactiveInstruction)
+    }
+
     let param_scripts: Vec<_> = params.iter().map(|p| Instruction::PushB(p.clone())).rev().collect();
     let script = [unlock, &param_scripts, lock].concat();
 
     let mut stack = Stack::new(config);
     let mut pc = 0;
     while pc < script.len() {
+        meter.charge(INSTRUCTION_COST)?;
         match &script[pc] {
             Instruction::Nop => {}
             Instruction::Burn => return Ok(ScriptResult::Burnt),
@@ -245,7 +398,64 @@ where
             Instruction::Drop(index) => {
                 stack.remove(*index as usize)?;
             }
+            Instruction::Size => {
+                let top = stack.pop()?;
+                let size = top.len() as u64;
+                stack.push(top)?;
+                stack.push(Item::from(size))?;
+            }
+            Instruction::Add => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.checked_add(b).ok_or(RuntimeError::Overflow)?))?;
+            }
+            Instruction::Sub => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.checked_sub(b).ok_or(RuntimeError::Overflow)?))?;
+            }
+            Instruction::Mul => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.checked_mul(b).ok_or(RuntimeError::Overflow)?))?;
+            }
+            Instruction::Div => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.checked_div(b).ok_or(RuntimeError::DivisionByZero)?))?;
+            }
+            Instruction::Mod => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.checked_rem(b).ok_or(RuntimeError::DivisionByZero)?))?;
+            }
+            Instruction::Lt => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a < b))?;
+            }
+            Instruction::Gt => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a > b))?;
+            }
+            Instruction::Le => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a <= b))?;
+            }
+            Instruction::Ge => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a >= b))?;
+            }
+            Instruction::Min => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.min(b)))?;
+            }
+            Instruction::Max => {
+                let (a, b) = pop_two_integers(&mut stack)?;
+                stack.push(Item::from(a.max(b)))?;
+            }
+            Instruction::Within => {
+                let max = read_u64(stack.pop()?)?;
+                let min = read_u64(stack.pop()?)?;
+                let value = read_u64(stack.pop()?)?;
+                stack.push(Item::from(min <= value && value < max))?;
+            }
             Instruction::ChkSig => {
+                meter.charge(SIGNATURE_VERIFICATION_COST)?;
                 let pubkey = Public::from_slice(stack.pop()?.assert_len(64)?.as_ref());
                 let tag = Tag::try_new(stack.pop()?.as_ref().to_vec())?;
                 let tx_hash = tx.hash_partially(tag, cur, burn)?;
@@ -259,6 +469,7 @@ where
             Instruction::ChkMultiSig => {
                 // Get n pubkey. If there are more than six pubkeys, return error.
                 let n = stack.pop()?.assert_len(1)?.as_ref()[0] as usize;
+                meter.charge(SIGNATURE_VERIFICATION_COST * n as u64)?;
 
                 let mut pubkey: Vec<Public> = Vec::with_capacity(n);
                 for _ in 0..n {
@@ -286,24 +497,59 @@ where
                 };
                 stack.push(Item(vec![result]))?;
             }
+            Instruction::ChkSchnorr => {
+                meter.charge(SIGNATURE_VERIFICATION_COST)?;
+                let pubkey = Public::from_slice(stack.pop()?.assert_len(64)?.as_ref());
+                let tag = Tag::try_new(stack.pop()?.as_ref().to_vec())?;
+                let tx_hash = tx.hash_partially(tag, cur, burn)?;
+                let signature = SchnorrSignature::from(stack.pop()?.assert_len(SCHNORR_SIGNATURE_LENGTH)?.as_ref());
+                let result = verify_schnorr(&pubkey, &signature, &tx_hash) == Ok(true);
+                stack.push(Item::from(result))?;
+            }
+            Instruction::ChkAggSchnorr => {
+                let n = stack.pop()?.assert_len(1)?.as_ref()[0] as usize;
+                if n == 0 {
+                    return Err(RuntimeError::InvalidKeyCount)
+                }
+                meter.charge(SIGNATURE_VERIFICATION_COST + KEY_AGGREGATION_COST * n as u64)?;
+
+                let mut pubkeys: Vec<Public> = Vec::with_capacity(n);
+                for _ in 0..n {
+                    pubkeys.push(Public::from_slice(stack.pop()?.assert_len(64)?.as_ref()));
+                }
+                let tag = Tag::try_new(stack.pop()?.as_ref().to_vec())?;
+                let tx_hash = tx.hash_partially(tag, cur, burn)?;
+                let signature = SchnorrSignature::from(stack.pop()?.assert_len(SCHNORR_SIGNATURE_LENGTH)?.as_ref());
+                // The sum of the keys can be invalid, e.g. when the keys cancel out each other.
+                let result = match aggregate_schnorr_publics(&pubkeys) {
+                    Ok(aggregated) => verify_schnorr(&aggregated, &signature, &tx_hash) == Ok(true),
+                    Err(_) => false,
+                };
+                stack.push(Item::from(result))?;
+            }
             Instruction::Blake256 => {
                 let value = stack.pop()?;
+                meter.charge(hash_cost(&value))?;
                 stack.push(Item(blake256(value).to_vec()))?;
             }
             Instruction::Sha256 => {
                 let value = stack.pop()?;
+                meter.charge(hash_cost(&value))?;
                 stack.push(Item(sha256(value).to_vec()))?;
             }
             Instruction::Ripemd160 => {
                 let value = stack.pop()?;
+                meter.charge(hash_cost(&value))?;
                 stack.push(Item(ripemd160(value).to_vec()))?;
             }
             Instruction::Keccak256 => {
                 let value = stack.pop()?;
+                meter.charge(hash_cost(&value))?;
                 stack.push(Item(keccak256(value).to_vec()))?;
             }
             Instruction::Blake160 => {
                 let value = stack.pop()?;
+                meter.charge(hash_cost(&value))?;
                 stack.push(Item(H160::blake(value).to_vec()))?;
             }
             Instruction::ChkTimelock(timelock_type) => {
@@ -332,6 +578,19 @@ where
                     }
                 }
             }
+            Instruction::OutCount => stack.push(Item::from(tx.outputs().len() as u64))?,
+            Instruction::OutLockHash => {
+                let output = pop_output(&mut stack, tx)?;
+                stack.push(Item(output.lock_script_hash.to_vec()))?;
+            }
+            Instruction::OutAssetType => {
+                let output = pop_output(&mut stack, tx)?;
+                stack.push(Item(output.asset_type.to_vec()))?;
+            }
+            Instruction::OutQuantity => {
+                let output = pop_output(&mut stack, tx)?;
+                stack.push(Item::from(output.quantity))?;
+            }
         }
         pc += 1;
     }
@@ -353,6 +612,19 @@ fn read_u64(value_item: Item) -> Result<u64, RuntimeError> {
     Ok(u64::from_be_bytes(value_bytes))
 }
 
+/// Pops an index and returns the output of the transaction at the index.
+fn pop_output<'a>(stack: &mut Stack, tx: &'a dyn PartialHashing) -> Result<&'a AssetTransferOutput, RuntimeError> {
+    let index = read_u64(stack.pop()?)?;
+    tx.outputs().get(index as usize).ok_or(RuntimeError::IndexOutOfBound)
+}
+
+/// Pops `b` and then `a`, so the operands are in the order they were pushed.
+fn pop_two_integers(stack: &mut Stack) -> Result<(u64, u64), RuntimeError> {
+    let b = read_u64(stack.pop()?)?;
+    let a = read_u64(stack.pop()?)?;
+    Ok((a, b))
+}
+
 #[inline]
 fn check_multi_sig(tx_hash: &H256, mut pubkey: Vec<Public>, mut signatures: Vec<Signature>) -> bool {
     while let Some(sig) = signatures.pop() {
@@ -411,6 +683,19 @@ mod tests {
         assert!(result);
     }
 
+    #[test]
+    fn convert_integer_without_leading_zeros() {
+        assert_eq!(Vec::<u8>::new(), Item::from(0u64).as_ref());
+        assert_eq!(vec![1], Item::from(1u64).as_ref());
+        assert_eq!(vec![1, 0], Item::from(0x100u64).as_ref());
+        assert_eq!(vec![0xff; 8], Item::from(u64::max_value()).as_ref());
+    }
+
+    #[test]
+    fn read_9_bytes() {
+        assert_eq!(Err(RuntimeError::TypeMismatch), read_u64(Item(vec![0, 0, 0, 0, 0, 0, 0, 0, 1])));
+    }
+
     #[test]
     fn read_0_0_0_0_0_0_0_1() {
         assert_eq!(Ok(0x0000_0000_0000_0001), read_u64(Item(vec![0, 0, 0, 0, 0, 0, 0, 1])));
@@ -13,8 +13,14 @@
 //
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
+use ckey::hex::ToHex;
+use std::fmt;
+
 use crate::executor::TimelockType;
 
+/// The era of `CommonParams` from which the instructions added after the launch are available.
+pub const EXTENDED_INSTRUCTIONS_ERA: u64 = 1;
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Instruction {
     Nop,
@@ -33,14 +39,126 @@ pub enum Instruction {
     Swap,
     Copy(u8),
     Drop(u8),
+    Size,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Min,
+    Max,
+    Within,
     ChkSig,
     ChkMultiSig,
+    ChkSchnorr,
+    ChkAggSchnorr,
     Blake256,
     Sha256,
     Ripemd160,
     Keccak256,
     Blake160,
     ChkTimelock(TimelockType),
+    OutCount,
+    OutLockHash,
+    OutAssetType,
+    OutQuantity,
+}
+
+impl Instruction {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "NOP",
+            Instruction::Burn => "BURN",
+            Instruction::Success => "SUCCESS",
+            Instruction::Fail => "FAIL",
+            Instruction::Not => "NOT",
+            Instruction::Eq => "EQ",
+            Instruction::Jmp(_) => "JMP",
+            Instruction::Jnz(_) => "JNZ",
+            Instruction::Jz(_) => "JZ",
+            Instruction::Push(_) => "PUSH",
+            Instruction::Pop => "POP",
+            Instruction::PushB(_) => "PUSHB",
+            Instruction::Dup => "DUP",
+            Instruction::Swap => "SWAP",
+            Instruction::Copy(_) => "COPY",
+            Instruction::Drop(_) => "DROP",
+            Instruction::Size => "SIZE",
+            Instruction::Add => "ADD",
+            Instruction::Sub => "SUB",
+            Instruction::Mul => "MUL",
+            Instruction::Div => "DIV",
+            Instruction::Mod => "MOD",
+            Instruction::Lt => "LT",
+            Instruction::Gt => "GT",
+            Instruction::Le => "LE",
+            Instruction::Ge => "GE",
+            Instruction::Min => "MIN",
+            Instruction::Max => "MAX",
+            Instruction::Within => "WITHIN",
+            Instruction::ChkSig => "CHKSIG",
+            Instruction::ChkMultiSig => "CHKMULTISIG",
+            Instruction::ChkSchnorr => "CHKSCHNORR",
+            Instruction::ChkAggSchnorr => "CHKAGGSCHNORR",
+            Instruction::Blake256 => "BLAKE256",
+            Instruction::Sha256 => "SHA256",
+            Instruction::Ripemd160 => "RIPEMD160",
+            Instruction::Keccak256 => "KECCAK256",
+            Instruction::Blake160 => "BLAKE160",
+            Instruction::ChkTimelock(_) => "CHKTIMELOCK",
+            Instruction::OutCount => "OUTCOUNT",
+            Instruction::OutLockHash => "OUTLOCKHASH",
+            Instruction::OutAssetType => "OUTASSETTYPE",
+            Instruction::OutQuantity => "OUTQUANTITY",
+        }
+    }
+
+    /// The era from which the instruction is available.
+    pub fn activation_era(&self) -> u64 {
+        match self {
+            Instruction::Size
+            | Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::Mod
+            | Instruction::Lt
+            | Instruction::Gt
+            | Instruction::Le
+            | Instruction::Ge
+            | Instruction::Min
+            | Instruction::Max
+            | Instruction::Within
+            | Instruction::ChkSchnorr
+            | Instruction::ChkAggSchnorr
+            | Instruction::OutCount
+            | Instruction::OutLockHash
+            | Instruction::OutAssetType
+            | Instruction::OutQuantity => EXTENDED_INSTRUCTIONS_ERA,
+            _ => 0,
+        }
+    }
+}
+
+/// Formats the instruction in the syntax accepted by the assembler.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Jmp(val)
+            | Instruction::Jnz(val)
+            | Instruction::Jz(val)
+            | Instruction::Push(val)
+            | Instruction::Copy(val)
+            | Instruction::Drop(val) => write!(f, "{} {}", self.mnemonic(), val),
+            Instruction::PushB(blob) => write!(f, "{} 0x{}", self.mnemonic(), blob.to_hex()),
+            Instruction::ChkTimelock(timelock_type) => write!(f, "{} {}", self.mnemonic(), timelock_type),
+            _ => f.write_str(self.mnemonic()),
+        }
+    }
 }
 
 pub fn is_valid_unlock_script(instrs: &[Instruction]) -> bool {
@@ -52,8 +170,13 @@ pub fn is_valid_unlock_script(instrs: &[Instruction]) -> bool {
 }
 
 pub fn has_expensive_opcodes(instrs: &[Instruction]) -> bool {
-    let count =
-        instrs.iter().filter(|instr| instr == &&Instruction::ChkSig || instr == &&Instruction::ChkMultiSig).count();
+    let count = instrs
+        .iter()
+        .filter(|instr| match instr {
+            Instruction::ChkSig | Instruction::ChkMultiSig | Instruction::ChkSchnorr | Instruction::ChkAggSchnorr => true,
+            _ => false,
+        })
+        .count();
     count >= 6
 }
 
@@ -23,11 +23,21 @@ extern crate rlp;
 #[cfg(test)]
 extern crate secp256k1;
 
+mod assembler;
 mod decoder;
+mod encoder;
 mod executor;
 mod instruction;
 mod opcode;
+mod template;
+mod verifier;
 
+pub use crate::assembler::{assemble, disassemble, AssemblerError};
 pub use crate::decoder::{decode, DecoderError};
-pub use crate::executor::{execute, ChainTimeInfo, Config as VMConfig, RuntimeError, ScriptResult, TimelockType};
-pub use crate::instruction::Instruction;
+pub use crate::encoder::{encode, lock_script_hash, EncoderError};
+pub use crate::executor::{
+    execute, execute_with_cost, ChainTimeInfo, Config as VMConfig, RuntimeError, ScriptResult, TimelockType,
+};
+pub use crate::instruction::{Instruction, EXTENDED_INSTRUCTIONS_ERA};
+pub use crate::template::{ScriptTemplate, TemplateError, Witness};
+pub use crate::verifier::{lint_lock_script, verify_script, Diagnostic, ScriptReport, MAX_STACK_DEPTH};
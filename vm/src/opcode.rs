@@ -30,11 +30,30 @@ pub const DUP: u8 = 0x33;
 pub const SWAP: u8 = 0x34;
 pub const COPY: u8 = 0x35;
 pub const DROP: u8 = 0x36;
+pub const SIZE: u8 = 0x37;
+pub const ADD: u8 = 0x40;
+pub const SUB: u8 = 0x41;
+pub const MUL: u8 = 0x42;
+pub const DIV: u8 = 0x43;
+pub const MOD: u8 = 0x44;
+pub const LT: u8 = 0x45;
+pub const GT: u8 = 0x46;
+pub const LE: u8 = 0x47;
+pub const GE: u8 = 0x48;
+pub const MIN: u8 = 0x49;
+pub const MAX: u8 = 0x4a;
+pub const WITHIN: u8 = 0x4b;
 pub const CHKSIG: u8 = 0x80;
 pub const CHKMULTISIG: u8 = 0x81;
+pub const CHKSCHNORR: u8 = 0x82;
+pub const CHKAGGSCHNORR: u8 = 0x83;
 pub const BLAKE256: u8 = 0x90;
 pub const SHA256: u8 = 0x91;
 pub const RIPEMD160: u8 = 0x92;
 pub const KECCAK256: u8 = 0x93;
 pub const BLAKE160: u8 = 0x94;
 pub const CHKTIMELOCK: u8 = 0xb0;
+pub const OUTCOUNT: u8 = 0xc0;
+pub const OUTLOCKHASH: u8 = 0xc1;
+pub const OUTASSETTYPE: u8 = 0xc2;
+pub const OUTQUANTITY: u8 = 0xc3;
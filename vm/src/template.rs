@@ -0,0 +1,492 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! The standard lock scripts. A template knows its lock script and parameters,
+//! and builds the unlock script from the signatures.
+
+use ckey::{Public, Signature};
+use primitives::{H160, H256};
+use std::fmt;
+
+use crate::decoder::decode;
+use crate::encoder::{encode, lock_script_hash, EncoderError};
+use crate::executor::TimelockType;
+use crate::instruction::Instruction;
+
+/// CHKMULTISIG accepts at most this number of signatures.
+const MAX_MULTISIG_SIGNATURES: u8 = 6;
+/// Skipping 255 instructions is the conventional jump to the end of the script.
+pub(crate) const JUMP_TO_END: u8 = 0xff;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptTemplate {
+    /// The owner of the public key whose blake-160 hash is the parameter can spend the asset.
+    P2pkh {
+        public_key_hash: H160,
+    },
+    /// The same as P2pkh, except that the asset can only be burnt.
+    P2pkhBurn {
+        public_key_hash: H160,
+    },
+    /// `required` signatures of the public keys can spend the asset.
+    Multisig {
+        required: u8,
+        publics: Vec<Public>,
+    },
+    /// The recipient can spend the asset with the preimage of the SHA-256 hash.
+    /// The refunder can spend the asset once the timelock is met.
+    HashTimelock {
+        hash: H256,
+        recipient: Public,
+        refunder: Public,
+        timelock_type: TimelockType,
+        timelock: u64,
+    },
+}
+
+/// What an unlock script provides to a template.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Witness {
+    /// Unlocks P2pkh and P2pkhBurn.
+    Signature {
+        signature: Signature,
+        public: Public,
+    },
+    /// Unlocks Multisig. The signatures must be ordered the same way as the public keys.
+    Signatures(Vec<Signature>),
+    /// Unlocks HashTimelock by the recipient.
+    Preimage {
+        signature: Signature,
+        preimage: Vec<u8>,
+    },
+    /// Unlocks HashTimelock by the refunder.
+    Refund {
+        signature: Signature,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TemplateError {
+    /// The number of required signatures of Multisig is 0, more than the public keys or more than 6.
+    InvalidRequiredSignatures(u8),
+    TooManyPublics(usize),
+    /// The witness is for another template, or has a different number of signatures.
+    WitnessMismatch,
+    Encoder(EncoderError),
+}
+
+impl From<EncoderError> for TemplateError {
+    fn from(err: EncoderError) -> Self {
+        TemplateError::Encoder(err)
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::InvalidRequiredSignatures(required) => {
+                write!(f, "{} signatures cannot be required", required)
+            }
+            TemplateError::TooManyPublics(count) => write!(f, "{} public keys are too many", count),
+            TemplateError::WitnessMismatch => write!(f, "The witness doesn't match the template"),
+            TemplateError::Encoder(EncoderError::BlobTooLong(length)) => {
+                write!(f, "A blob of {} bytes cannot be pushed", length)
+            }
+        }
+    }
+}
+
+impl ScriptTemplate {
+    pub fn lock_script(&self) -> Result<Vec<u8>, TemplateError> {
+        Ok(encode(&self.lock_instructions()?)?)
+    }
+
+    pub fn lock_script_hash(&self) -> Result<H160, TemplateError> {
+        Ok(lock_script_hash(&self.lock_script()?))
+    }
+
+    /// The parameters of the asset outputs locked by the template.
+    pub fn parameters(&self) -> Vec<Vec<u8>> {
+        match self {
+            ScriptTemplate::P2pkh {
+                public_key_hash,
+            }
+            | ScriptTemplate::P2pkhBurn {
+                public_key_hash,
+            } => vec![public_key_hash.to_vec()],
+            ScriptTemplate::Multisig {
+                ..
+            }
+            | ScriptTemplate::HashTimelock {
+                ..
+            } => Vec::new(),
+        }
+    }
+
+    /// Builds the unlock script. `tag` is the signature tag that the signatures are made with.
+    pub fn unlock_script(&self, tag: &[u8], witness: &Witness) -> Result<Vec<u8>, TemplateError> {
+        let mut instructions = vec![Instruction::PushB(tag.to_vec())];
+        match (self, witness) {
+            (
+                ScriptTemplate::P2pkh {
+                    ..
+                },
+                Witness::Signature {
+                    signature,
+                    public,
+                },
+            )
+            | (
+                ScriptTemplate::P2pkhBurn {
+                    ..
+                },
+                Witness::Signature {
+                    signature,
+                    public,
+                },
+            ) => {
+                instructions.insert(0, Instruction::PushB(signature.to_vec()));
+                instructions.push(Instruction::PushB(public.to_vec()));
+            }
+            (
+                ScriptTemplate::Multisig {
+                    required,
+                    ..
+                },
+                Witness::Signatures(signatures),
+            ) => {
+                if signatures.len() != *required as usize {
+                    return Err(TemplateError::WitnessMismatch)
+                }
+                // CHKMULTISIG pops the signatures, so the first one is pushed last.
+                instructions.extend(signatures.iter().rev().map(|signature| Instruction::PushB(signature.to_vec())));
+            }
+            (
+                ScriptTemplate::HashTimelock {
+                    ..
+                },
+                Witness::Preimage {
+                    signature,
+                    preimage,
+                },
+            ) => {
+                instructions.insert(0, Instruction::PushB(signature.to_vec()));
+                instructions.push(Instruction::PushB(preimage.clone()));
+                instructions.push(Instruction::Push(1));
+            }
+            (
+                ScriptTemplate::HashTimelock {
+                    ..
+                },
+                Witness::Refund {
+                    signature,
+                },
+            ) => {
+                instructions.insert(0, Instruction::PushB(signature.to_vec()));
+                instructions.push(Instruction::Push(0));
+            }
+            _ => return Err(TemplateError::WitnessMismatch),
+        }
+        Ok(encode(&instructions)?)
+    }
+
+    /// Finds the template of the lock script and the parameters.
+    pub fn recognize(lock_script: &[u8], parameters: &[Vec<u8>]) -> Option<Self> {
+        let instructions = decode(lock_script).ok()?;
+        let template = match parameters {
+            [hash] if hash.len() == 20 => {
+                let public_key_hash = H160::from(hash.as_slice());
+                if instructions.last() == Some(&Instruction::Burn) {
+                    ScriptTemplate::P2pkhBurn {
+                        public_key_hash,
+                    }
+                } else {
+                    ScriptTemplate::P2pkh {
+                        public_key_hash,
+                    }
+                }
+            }
+            [] if instructions.last() == Some(&Instruction::ChkMultiSig) => recognize_multisig(&instructions)?,
+            [] => recognize_hash_timelock(&instructions)?,
+            _ => return None,
+        };
+        // The template must build exactly the same script.
+        match template.lock_script() {
+            Ok(ref script) if script.as_slice() == lock_script => Some(template),
+            _ => None,
+        }
+    }
+
+    fn lock_instructions(&self) -> Result<Vec<Instruction>, TemplateError> {
+        let p2pkh = vec![
+            Instruction::Copy(1),
+            Instruction::Blake160,
+            Instruction::Eq,
+            Instruction::Jz(JUMP_TO_END),
+            Instruction::ChkSig,
+        ];
+        Ok(match self {
+            ScriptTemplate::P2pkh {
+                ..
+            } => p2pkh,
+            ScriptTemplate::P2pkhBurn {
+                ..
+            } => [p2pkh, vec![Instruction::Jz(JUMP_TO_END), Instruction::Burn]].concat(),
+            ScriptTemplate::Multisig {
+                required,
+                publics,
+            } => {
+                if publics.len() > u8::max_value() as usize {
+                    return Err(TemplateError::TooManyPublics(publics.len()))
+                }
+                if *required == 0 || *required as usize > publics.len() || *required > MAX_MULTISIG_SIGNATURES {
+                    return Err(TemplateError::InvalidRequiredSignatures(*required))
+                }
+                let mut instructions = vec![Instruction::Push(*required)];
+                // CHKMULTISIG pops the public keys, so the first one is pushed last.
+                instructions.extend(publics.iter().rev().map(|public| Instruction::PushB(public.to_vec())));
+                instructions.push(Instruction::Push(publics.len() as u8));
+                instructions.push(Instruction::ChkMultiSig);
+                instructions
+            }
+            ScriptTemplate::HashTimelock {
+                hash,
+                recipient,
+                refunder,
+                timelock_type,
+                timelock,
+            } => vec![
+                // The unlock script pushes true to claim with the preimage, and false to refund.
+                Instruction::Jz(7),
+                Instruction::Sha256,
+                Instruction::PushB(hash.to_vec()),
+                Instruction::Eq,
+                Instruction::Jz(JUMP_TO_END),
+                Instruction::PushB(recipient.to_vec()),
+                Instruction::ChkSig,
+                Instruction::Jmp(5),
+                push_integer(*timelock),
+                Instruction::ChkTimelock(timelock_type.clone()),
+                Instruction::Jz(JUMP_TO_END),
+                Instruction::PushB(refunder.to_vec()),
+                Instruction::ChkSig,
+            ],
+        })
+    }
+}
+
+fn recognize_multisig(instructions: &[Instruction]) -> Option<ScriptTemplate> {
+    // PUSH required, PUSHB public..., PUSH count, CHKMULTISIG
+    let (_, rest) = instructions.split_last()?;
+    let (_, rest) = rest.split_last()?;
+    let (required, publics) = rest.split_first()?;
+    match required {
+        Instruction::Push(required) => Some(ScriptTemplate::Multisig {
+            required: *required,
+            publics: publics.iter().rev().map(pushed_public).collect::<Option<_>>()?,
+        }),
+        _ => None,
+    }
+}
+
+fn recognize_hash_timelock(instructions: &[Instruction]) -> Option<ScriptTemplate> {
+    if instructions.len() != 13 {
+        return None
+    }
+    match (&instructions[2], &instructions[5], &instructions[8], &instructions[9], &instructions[11]) {
+        (Instruction::PushB(hash), recipient, timelock, Instruction::ChkTimelock(timelock_type), refunder)
+            if hash.len() == 32 =>
+        {
+            Some(ScriptTemplate::HashTimelock {
+                hash: H256::from(hash.as_slice()),
+                recipient: pushed_public(recipient)?,
+                refunder: pushed_public(refunder)?,
+                timelock_type: timelock_type.clone(),
+                timelock: pushed_integer(timelock)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Pushes the integer with the shortest encoding.
+fn push_integer(value: u64) -> Instruction {
+    let bytes = value.to_be_bytes();
+    let leading_zeros = bytes.iter().take_while(|b| **b == 0).count();
+    match &bytes[leading_zeros..] {
+        [byte] => Instruction::Push(*byte),
+        trimmed => Instruction::PushB(trimmed.to_vec()),
+    }
+}
+
+fn pushed_integer(instruction: &Instruction) -> Option<u64> {
+    match instruction {
+        Instruction::Push(value) => Some(u64::from(*value)),
+        Instruction::PushB(blob) if blob.len() <= 8 => {
+            Some(blob.iter().fold(0, |acc, byte| (acc << 8) | u64::from(*byte)))
+        }
+        _ => None,
+    }
+}
+
+fn pushed_public(instruction: &Instruction) -> Option<Public> {
+    match instruction {
+        Instruction::PushB(blob) if blob.len() == 64 => Some(Public::from_slice(blob)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ckey::{sign, Generator, KeyPair, Random};
+
+    use super::*;
+
+    #[test]
+    fn standard_script_hashes() {
+        // The hashes in the specification of the address.
+        let p2pkh = ScriptTemplate::P2pkh {
+            public_key_hash: H160::random(),
+        };
+        assert_eq!(p2pkh.lock_script().unwrap(), vec![0x35, 0x01, 0x94, 0x11, 0x22, 0xff, 0x80]);
+        assert_eq!(p2pkh.lock_script_hash().unwrap(), H160::from("5f5960a7bca6ceeeb0c97bc717562914e7a1de04"));
+
+        let p2pkh_burn = ScriptTemplate::P2pkhBurn {
+            public_key_hash: H160::random(),
+        };
+        assert_eq!(p2pkh_burn.lock_script_hash().unwrap(), H160::from("37572bdcc22d39a59c0d12d301f6271ba3fdd451"));
+    }
+
+    fn templates() -> Vec<ScriptTemplate> {
+        let publics: Vec<Public> = (0..3).map(|_| *Random.generate().unwrap().public()).collect();
+        vec![
+            ScriptTemplate::P2pkh {
+                public_key_hash: H160::random(),
+            },
+            ScriptTemplate::P2pkhBurn {
+                public_key_hash: H160::random(),
+            },
+            ScriptTemplate::Multisig {
+                required: 2,
+                publics: publics.clone(),
+            },
+            ScriptTemplate::HashTimelock {
+                hash: H256::random(),
+                recipient: publics[0],
+                refunder: publics[1],
+                timelock_type: TimelockType::BlockAge,
+                timelock: 100,
+            },
+            ScriptTemplate::HashTimelock {
+                hash: H256::random(),
+                recipient: publics[0],
+                refunder: publics[1],
+                timelock_type: TimelockType::Time,
+                timelock: 1_600_000_000,
+            },
+        ]
+    }
+
+    #[test]
+    fn recognize_templates() {
+        for template in templates() {
+            let lock_script = template.lock_script().unwrap();
+            assert_eq!(ScriptTemplate::recognize(&lock_script, &template.parameters()), Some(template));
+        }
+    }
+
+    #[test]
+    fn recognize_nothing() {
+        let p2pkh = ScriptTemplate::P2pkh {
+            public_key_hash: H160::random(),
+        };
+        let lock_script = p2pkh.lock_script().unwrap();
+        assert_eq!(ScriptTemplate::recognize(&lock_script, &[]), None);
+        assert_eq!(ScriptTemplate::recognize(&lock_script, &[vec![0; 19]]), None);
+        assert_eq!(ScriptTemplate::recognize(&[0x30, 0x01], &[]), None);
+        assert_eq!(ScriptTemplate::recognize(&[0xff], &[]), None);
+    }
+
+    #[test]
+    fn invalid_multisig() {
+        let publics: Vec<Public> = (0..7).map(|_| *Random.generate().unwrap().public()).collect();
+        for required in &[0, 4, 7] {
+            let template = ScriptTemplate::Multisig {
+                required: *required,
+                publics: publics[..3].to_vec(),
+            };
+            assert_eq!(template.lock_script(), Err(TemplateError::InvalidRequiredSignatures(*required)));
+        }
+        let template = ScriptTemplate::Multisig {
+            required: 6,
+            publics,
+        };
+        assert!(template.lock_script().is_ok());
+    }
+
+    #[test]
+    fn unlock_script_of_p2pkh() {
+        let keypair: KeyPair = Random.generate().unwrap();
+        let signature = sign(keypair.private(), &H256::random()).unwrap();
+        let template = ScriptTemplate::P2pkh {
+            public_key_hash: H160::random(),
+        };
+        let unlock_script = template
+            .unlock_script(
+                &[0b11],
+                &Witness::Signature {
+                    signature,
+                    public: *keypair.public(),
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            decode(&unlock_script).unwrap(),
+            vec![
+                Instruction::PushB(signature.to_vec()),
+                Instruction::PushB(vec![0b11]),
+                Instruction::PushB(keypair.public().to_vec())
+            ]
+        );
+        assert_eq!(
+            template.unlock_script(&[0b11], &Witness::Signatures(vec![signature])),
+            Err(TemplateError::WitnessMismatch)
+        );
+    }
+
+    #[test]
+    fn unlock_script_of_multisig() {
+        let publics: Vec<Public> = (0..3).map(|_| *Random.generate().unwrap().public()).collect();
+        let template = ScriptTemplate::Multisig {
+            required: 2,
+            publics,
+        };
+        let signatures = vec![Signature::random(), Signature::random()];
+        let unlock_script = template.unlock_script(&[0b11], &Witness::Signatures(signatures.clone())).unwrap();
+        assert_eq!(
+            decode(&unlock_script).unwrap(),
+            vec![
+                Instruction::PushB(vec![0b11]),
+                Instruction::PushB(signatures[1].to_vec()),
+                Instruction::PushB(signatures[0].to_vec())
+            ]
+        );
+        assert_eq!(
+            template.unlock_script(&[0b11], &Witness::Signatures(signatures[..1].to_vec())),
+            Err(TemplateError::WitnessMismatch)
+        );
+    }
+}
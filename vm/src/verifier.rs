@@ -0,0 +1,685 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Static verification of scripts, run before a transaction is accepted.
+//!
+//! Jumps only go forward, so the verifier visits every instruction once after all of its predecessors,
+//! merging the stacks that reach it. The stack is tracked as the sizes of the items, so the depth and
+//! the memory usage are the worst case of all paths. The parameters of the asset are stored in the state
+//! and aren't pushed by the verifier, so the bounds don't count them. The cost is the most expensive path,
+//! counting only the sizes and the key counts known before the execution.
+
+use std::fmt;
+
+use crate::decoder::{decode, DecoderError};
+use crate::encoder::encoded_size;
+use crate::executor::{hash_cost_of_size, Config, INSTRUCTION_COST, KEY_AGGREGATION_COST, SIGNATURE_VERIFICATION_COST};
+use crate::instruction::Instruction;
+use crate::template::JUMP_TO_END;
+
+/// The maximum stack depth in the specification.
+pub const MAX_STACK_DEPTH: usize = 1024;
+
+/// A problem found by `verify_script`. The offsets are the byte offsets shown by `disassemble`.
+#[derive(Debug, PartialEq)]
+pub enum Diagnostic {
+    InvalidUnlockScript(DecoderError),
+    InvalidLockScript(DecoderError),
+    /// The unlock script has an instruction other than PUSH and PUSHB.
+    NotPushOnly {
+        offset: usize,
+    },
+    /// PUSHB pushes a single byte, which PUSH does with a shorter encoding. Found by `lint_lock_script`.
+    NonCanonicalPush {
+        offset: usize,
+    },
+    /// The jump goes beyond the end of the lock script.
+    /// Skipping 255 instructions is accepted since the standard scripts use it to jump to the end.
+    /// Found by `lint_lock_script`.
+    InvalidJumpTarget {
+        offset: usize,
+    },
+    /// SUCCESS or BURN is not reachable from the start of the lock script. Found by `lint_lock_script`.
+    UnreachableTerminator {
+        offset: usize,
+    },
+    /// The instruction is not activated in the era of the configuration.
+    InactiveInstruction {
+        offset: usize,
+        era: u64,
+    },
+    StackTooDeep {
+        offset: usize,
+        depth: usize,
+    },
+    OutOfMemory {
+        offset: usize,
+        usage: usize,
+    },
+    /// The script can cost more than `Config::max_cost`.
+    OutOfCost {
+        offset: usize,
+        cost: u64,
+    },
+}
+
+impl Diagnostic {
+    /// Whether the scripts fail on every path. The other diagnostics are the worst cases of some paths,
+    /// which may never be executed.
+    pub fn is_fatal(&self) -> bool {
+        match self {
+            Diagnostic::InvalidUnlockScript(_)
+            | Diagnostic::InvalidLockScript(_)
+            | Diagnostic::NotPushOnly {
+                ..
+            }
+            | Diagnostic::InactiveInstruction {
+                ..
+            } => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Diagnostic::InvalidUnlockScript(err) => write!(f, "The unlock script is invalid: {}", err),
+            Diagnostic::InvalidLockScript(err) => write!(f, "The lock script is invalid: {}", err),
+            Diagnostic::NotPushOnly {
+                offset,
+            } => write!(f, "{:04x} of the unlock script: only PUSH and PUSHB are allowed", offset),
+            Diagnostic::NonCanonicalPush {
+                offset,
+            } => write!(f, "{:04x}: PUSHB of a single byte must be PUSH", offset),
+            Diagnostic::InvalidJumpTarget {
+                offset,
+            } => write!(f, "{:04x}: the jump goes beyond the end of the script", offset),
+            Diagnostic::UnreachableTerminator {
+                offset,
+            } => write!(f, "{:04x}: unreachable SUCCESS or BURN", offset),
+            Diagnostic::InactiveInstruction {
+                offset,
+                era,
+            } => write!(f, "{:04x}: the instruction is available from era {}", offset, era),
+            Diagnostic::StackTooDeep {
+                offset,
+                depth,
+            } => write!(f, "{:04x}: the stack can be {} items deep, over the limit {}", offset, depth, MAX_STACK_DEPTH),
+            Diagnostic::OutOfMemory {
+                offset,
+                usage,
+            } => write!(f, "{:04x}: the stack can use {} bytes of memory", offset, usage),
+            Diagnostic::OutOfCost {
+                offset,
+                cost,
+            } => write!(f, "{:04x}: the script can cost {}", offset, cost),
+        }
+    }
+}
+
+/// The worst case of the scripts found by `verify_script`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScriptReport {
+    pub max_stack_depth: usize,
+    pub max_memory: usize,
+    pub max_cost: u64,
+}
+
+/// An item of the stack seen by the verifier.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Slot {
+    /// The upper bound of the size.
+    size: usize,
+    /// The value of a constant item of a single byte. The verifier uses it to know how many items
+    /// CHKMULTISIG and CHKAGGSCHNORR pop.
+    byte: Option<u8>,
+}
+
+impl Slot {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            byte: None,
+        }
+    }
+
+    fn constant(blob: &[u8]) -> Self {
+        Self {
+            size: blob.len(),
+            byte: if blob.len() == 1 {
+                Some(blob[0])
+            } else {
+                None
+            },
+        }
+    }
+}
+
+const BOOLEAN_SIZE: usize = 1;
+const INTEGER_SIZE: usize = 8;
+
+struct AbstractStack(Vec<Slot>);
+
+impl AbstractStack {
+    /// Popping an empty stack fails at runtime unless the parameters are there.
+    /// Their sizes are unknown, so the popped item is considered empty.
+    fn pop(&mut self) -> Slot {
+        self.0.pop().unwrap_or_default()
+    }
+
+    fn pop_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.pop();
+        }
+    }
+
+    fn push(&mut self, slot: Slot) {
+        self.0.push(slot)
+    }
+
+    fn memory(&self) -> usize {
+        self.0.iter().map(|slot| slot.size).sum()
+    }
+
+    /// Makes the stack cover both stacks. Items are matched from the top.
+    fn merge(&mut self, other: &AbstractStack) {
+        if self.0.len() < other.0.len() {
+            let extra = other.0.len() - self.0.len();
+            let mut merged = other.0[..extra].to_vec();
+            merged.append(&mut self.0);
+            self.0 = merged;
+        }
+        let skip = self.0.len() - other.0.len();
+        for (mine, theirs) in self.0[skip..].iter_mut().zip(other.0.iter()) {
+            mine.size = mine.size.max(theirs.size);
+            if mine.byte != theirs.byte {
+                mine.byte = None;
+            }
+        }
+    }
+}
+
+/// Verifies the scripts of an input without executing them.
+pub fn verify_script(unlock: &[u8], lock: &[u8], config: Config) -> Result<ScriptReport, Vec<Diagnostic>> {
+    let unlock = decode(unlock).map_err(|err| vec![Diagnostic::InvalidUnlockScript(err)])?;
+    let lock = decode(lock).map_err(|err| vec![Diagnostic::InvalidLockScript(err)])?;
+
+    let mut diagnostics = Vec::new();
+    let mut initial = AbstractStack(Vec::with_capacity(unlock.len()));
+    for (instruction, offset) in unlock.iter().zip(instruction_offsets(&unlock)) {
+        match instruction {
+            Instruction::Push(val) => initial.push(Slot::constant(&[*val])),
+            Instruction::PushB(blob) => initial.push(Slot::constant(blob)),
+            _ => diagnostics.push(Diagnostic::NotPushOnly {
+                offset,
+            }),
+        }
+    }
+
+    let offsets: Vec<usize> = instruction_offsets(&lock).collect();
+    let initial_cost = unlock.len() as u64 * INSTRUCTION_COST;
+    let mut report = ScriptReport {
+        max_stack_depth: initial.0.len(),
+        max_memory: initial.memory(),
+        max_cost: initial_cost,
+    };
+    let mut stacks: Vec<Option<(AbstractStack, u64)>> = (0..lock.len()).map(|_| None).collect();
+    if !stacks.is_empty() {
+        stacks[0] = Some((initial, initial_cost));
+    }
+    let mut too_deep = false;
+    let mut out_of_memory = false;
+    let mut out_of_cost = false;
+
+    for (index, instruction) in lock.iter().enumerate() {
+        let offset = offsets[index];
+        let era = instruction.activation_era();
+        if era > config.era {
+            diagnostics.push(Diagnostic::InactiveInstruction {
+                offset,
+                era,
+            });
+        }
+        let (mut stack, mut cost) = match stacks[index].take() {
+            Some(reached) => reached,
+            None => continue,
+        };
+        cost = cost.saturating_add(instruction_cost(instruction, &stack));
+        step(instruction, &mut stack);
+
+        let depth = stack.0.len();
+        let memory = stack.memory();
+        report.max_stack_depth = report.max_stack_depth.max(depth);
+        report.max_memory = report.max_memory.max(memory);
+        report.max_cost = report.max_cost.max(cost);
+        if depth > MAX_STACK_DEPTH && !too_deep {
+            too_deep = true;
+            diagnostics.push(Diagnostic::StackTooDeep {
+                offset,
+                depth,
+            });
+        }
+        if memory > config.max_memory && !out_of_memory {
+            out_of_memory = true;
+            diagnostics.push(Diagnostic::OutOfMemory {
+                offset,
+                usage: memory,
+            });
+        }
+        if cost > config.max_cost && !out_of_cost {
+            out_of_cost = true;
+            diagnostics.push(Diagnostic::OutOfCost {
+                offset,
+                cost,
+            });
+        }
+
+        for successor in successors(index, instruction) {
+            if let Some(Some((existing, existing_cost))) = stacks.get_mut(successor) {
+                existing.merge(&stack);
+                *existing_cost = (*existing_cost).max(cost);
+            } else if successor < lock.len() {
+                stacks[successor] = Some((AbstractStack(stack.0.clone()), cost));
+            }
+        }
+    }
+
+    if diagnostics.is_empty() {
+        Ok(report)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+/// Checks the style of a lock script before it's used for a new asset.
+///
+/// The lock scripts of the existing assets are already committed, so `verify_script` doesn't check them.
+pub fn lint_lock_script(lock: &[u8]) -> Vec<Diagnostic> {
+    let lock = match decode(lock) {
+        Ok(lock) => lock,
+        Err(err) => return vec![Diagnostic::InvalidLockScript(err)],
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut reachable = vec![false; lock.len()];
+    if !reachable.is_empty() {
+        reachable[0] = true;
+    }
+    for ((index, instruction), offset) in lock.iter().enumerate().zip(instruction_offsets(&lock)) {
+        if let Instruction::PushB(blob) = instruction {
+            if blob.len() == 1 {
+                diagnostics.push(Diagnostic::NonCanonicalPush {
+                    offset,
+                });
+            }
+        }
+        match instruction {
+            Instruction::Jmp(skip) | Instruction::Jnz(skip) | Instruction::Jz(skip)
+                if index + 1 + *skip as usize > lock.len() && *skip != JUMP_TO_END =>
+            {
+                diagnostics.push(Diagnostic::InvalidJumpTarget {
+                    offset,
+                })
+            }
+            _ => {}
+        }
+
+        if !reachable[index] {
+            if *instruction == Instruction::Success || *instruction == Instruction::Burn {
+                diagnostics.push(Diagnostic::UnreachableTerminator {
+                    offset,
+                });
+            }
+            continue
+        }
+        for successor in successors(index, instruction) {
+            if let Some(reached) = reachable.get_mut(successor) {
+                *reached = true;
+            }
+        }
+    }
+    diagnostics
+}
+
+/// The indices of the instructions which can be executed after the instruction at the index.
+fn successors(index: usize, instruction: &Instruction) -> Vec<usize> {
+    match instruction {
+        Instruction::Burn | Instruction::Success | Instruction::Fail => vec![],
+        Instruction::Jmp(skip) => vec![index + 1 + *skip as usize],
+        Instruction::Jnz(skip) | Instruction::Jz(skip) => vec![index + 1, index + 1 + *skip as usize],
+        _ => vec![index + 1],
+    }
+}
+
+fn instruction_offsets<'a>(instructions: &'a [Instruction]) -> impl Iterator<Item = usize> + 'a {
+    instructions.iter().scan(0, |offset, instruction| {
+        let current = *offset;
+        *offset += encoded_size(instruction);
+        Some(current)
+    })
+}
+
+/// The cost charged by the executor for the instruction on the stack.
+/// The unknown sizes and key counts are counted as zero.
+fn instruction_cost(instruction: &Instruction, stack: &AbstractStack) -> u64 {
+    let top = stack.0.last();
+    let extra = match instruction {
+        Instruction::ChkSig | Instruction::ChkSchnorr => SIGNATURE_VERIFICATION_COST,
+        Instruction::ChkMultiSig => {
+            let n = top.and_then(|slot| slot.byte).unwrap_or_default();
+            SIGNATURE_VERIFICATION_COST * u64::from(n)
+        }
+        Instruction::ChkAggSchnorr => {
+            let n = top.and_then(|slot| slot.byte).unwrap_or_default();
+            SIGNATURE_VERIFICATION_COST + KEY_AGGREGATION_COST * u64::from(n)
+        }
+        Instruction::Blake256
+        | Instruction::Sha256
+        | Instruction::Ripemd160
+        | Instruction::Keccak256
+        | Instruction::Blake160 => hash_cost_of_size(top.map_or(0, |slot| slot.size)),
+        _ => 0,
+    };
+    INSTRUCTION_COST + extra
+}
+
+/// Applies the instruction to the stack.
+fn step(instruction: &Instruction, stack: &mut AbstractStack) {
+    match instruction {
+        Instruction::Nop | Instruction::Burn | Instruction::Success | Instruction::Fail | Instruction::Jmp(_) => {}
+        Instruction::Not | Instruction::ChkTimelock(_) => {
+            stack.pop();
+            stack.push(Slot::new(BOOLEAN_SIZE));
+        }
+        Instruction::Eq | Instruction::Lt | Instruction::Gt | Instruction::Le | Instruction::Ge => {
+            stack.pop_n(2);
+            stack.push(Slot::new(BOOLEAN_SIZE));
+        }
+        Instruction::Jnz(_) | Instruction::Jz(_) | Instruction::Pop => {
+            stack.pop();
+        }
+        Instruction::Push(val) => stack.push(Slot::constant(&[*val])),
+        Instruction::PushB(blob) => stack.push(Slot::constant(blob)),
+        Instruction::Dup => {
+            let top = stack.0.last().cloned().unwrap_or_default();
+            stack.push(top);
+        }
+        Instruction::Swap => {
+            let len = stack.0.len();
+            if len >= 2 {
+                stack.0.swap(len - 1, len - 2);
+            }
+        }
+        Instruction::Copy(index) => {
+            let len = stack.0.len();
+            let item = if (*index as usize) < len {
+                stack.0[len - 1 - *index as usize].clone()
+            } else {
+                Slot::default()
+            };
+            stack.push(item);
+        }
+        Instruction::Drop(index) => {
+            if (*index as usize) < stack.0.len() {
+                stack.0.remove(*index as usize);
+            }
+        }
+        Instruction::Size | Instruction::OutCount => stack.push(Slot::new(INTEGER_SIZE)),
+        Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::Min
+        | Instruction::Max => {
+            stack.pop_n(2);
+            stack.push(Slot::new(INTEGER_SIZE));
+        }
+        Instruction::Within | Instruction::ChkSig | Instruction::ChkSchnorr => {
+            stack.pop_n(3);
+            stack.push(Slot::new(BOOLEAN_SIZE));
+        }
+        Instruction::ChkMultiSig => {
+            // The counts are usually constants of the lock script. Otherwise, the items are left in the stack,
+            // which only overestimates the memory.
+            if let Some(n) = stack.pop().byte {
+                stack.pop_n(n as usize);
+                if let Some(m) = stack.pop().byte {
+                    stack.pop_n(m as usize + 1);
+                }
+            }
+            stack.push(Slot::new(BOOLEAN_SIZE));
+        }
+        Instruction::ChkAggSchnorr => {
+            if let Some(n) = stack.pop().byte {
+                stack.pop_n(n as usize + 2);
+            }
+            stack.push(Slot::new(BOOLEAN_SIZE));
+        }
+        Instruction::Blake256 | Instruction::Sha256 | Instruction::Keccak256 => {
+            stack.pop();
+            stack.push(Slot::new(32));
+        }
+        Instruction::Ripemd160 | Instruction::Blake160 | Instruction::OutLockHash | Instruction::OutAssetType => {
+            stack.pop();
+            stack.push(Slot::new(20));
+        }
+        Instruction::OutQuantity => {
+            stack.pop();
+            stack.push(Slot::new(INTEGER_SIZE));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::instruction::EXTENDED_INSTRUCTIONS_ERA;
+    use crate::template::ScriptTemplate;
+    use primitives::H160;
+
+    fn verify(unlock: &str, lock: &str) -> Result<ScriptReport, Vec<Diagnostic>> {
+        verify_script(&assemble(unlock).unwrap(), &assemble(lock).unwrap(), Config::default())
+    }
+
+    #[test]
+    fn pay_to_public_key_hash() {
+        let signature = format!("0x{}", "ab".repeat(65));
+        let unlock = format!("PUSHB {} PUSH 3", signature);
+        let lock = format!("COPY 0x01 BLAKE160 PUSHB 0x{} EQ JZ fail CHKSIG SUCCESS fail: FAIL", "cd".repeat(20));
+        let report = verify(&unlock, &lock).unwrap();
+        assert_eq!(report.max_stack_depth, 4);
+        assert_eq!(report.max_memory, 65 + 1 + 65);
+        // Two pushes, COPY, BLAKE160 of 65 bytes, PUSHB, EQ, JZ, CHKSIG and SUCCESS.
+        assert_eq!(report.max_cost, 2 + 1 + 14 + 1 + 1 + 1 + 101 + 1);
+    }
+
+    #[test]
+    fn standard_scripts() {
+        let unlock = assemble(&format!("PUSHB 0x{} PUSHB 0x03 PUSHB 0x{}", "ab".repeat(65), "cd".repeat(64))).unwrap();
+        let p2pkh = ScriptTemplate::P2pkh {
+            public_key_hash: H160::random(),
+        };
+        assert!(verify_script(&unlock, &p2pkh.lock_script().unwrap(), Config::default()).is_ok());
+        let p2pkh_burn = ScriptTemplate::P2pkhBurn {
+            public_key_hash: H160::random(),
+        };
+        assert!(verify_script(&unlock, &p2pkh_burn.lock_script().unwrap(), Config::default()).is_ok());
+    }
+
+    #[test]
+    fn unlock_script_must_be_push_only() {
+        assert_eq!(
+            verify("PUSH 1 DUP", "EQ"),
+            Err(vec![Diagnostic::NotPushOnly {
+                offset: 2,
+            }])
+        );
+    }
+
+    fn lint(lock: &str) -> Vec<Diagnostic> {
+        lint_lock_script(&assemble(lock).unwrap())
+    }
+
+    #[test]
+    fn single_byte_pushb_is_not_canonical() {
+        assert_eq!(lint("PUSHB 0x"), vec![]);
+        assert_eq!(
+            lint("PUSH 1 PUSHB 0x01 EQ"),
+            vec![Diagnostic::NonCanonicalPush {
+                offset: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn committed_lock_script_is_not_linted() {
+        assert!(verify("", "PUSH 1 PUSHB 0x01 EQ").is_ok());
+        assert!(verify("PUSH 1", "JMP 1").is_ok());
+        assert!(verify("PUSH 1", "JMP end SUCCESS end:").is_ok());
+    }
+
+    #[test]
+    fn jump_beyond_the_end() {
+        assert_eq!(lint("JMP 0"), vec![]);
+        assert_eq!(lint("JZ 255"), vec![]);
+        assert_eq!(
+            lint("JMP 1"),
+            vec![Diagnostic::InvalidJumpTarget {
+                offset: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn unreachable_success() {
+        assert_eq!(
+            lint("JMP end SUCCESS NOP BURN end:"),
+            vec![
+                Diagnostic::UnreachableTerminator {
+                    offset: 2,
+                },
+                Diagnostic::UnreachableTerminator {
+                    offset: 4,
+                },
+            ]
+        );
+        assert_eq!(lint("JZ end SUCCESS end: BURN"), vec![]);
+    }
+
+    #[test]
+    fn worst_case_of_branches() {
+        let big = format!("0x{}", "00".repeat(200));
+        let lock = format!("JZ small PUSHB {} PUSHB {} JMP end small: PUSH 1 end: PUSHB {} POP", big, big, big);
+        let report = verify("PUSH 0", &lock).unwrap();
+        assert_eq!(report.max_stack_depth, 3);
+        assert_eq!(report.max_memory, 600);
+    }
+
+    #[test]
+    fn out_of_memory() {
+        let big = format!("0x{}", "00".repeat(255));
+        let lock = format!("PUSHB {} DUP DUP DUP DUP", big);
+        assert_eq!(
+            verify("", &lock),
+            Err(vec![Diagnostic::OutOfMemory {
+                offset: 260,
+                usage: 1275,
+            }])
+        );
+    }
+
+    #[test]
+    fn stack_too_deep() {
+        let lock = "PUSHB 0x ".repeat(MAX_STACK_DEPTH + 1);
+        assert_eq!(
+            verify("", &lock),
+            Err(vec![Diagnostic::StackTooDeep {
+                offset: 2 * MAX_STACK_DEPTH,
+                depth: MAX_STACK_DEPTH + 1,
+            }])
+        );
+    }
+
+    #[test]
+    fn worst_cases_are_not_fatal() {
+        let lock = "PUSHB 0x ".repeat(MAX_STACK_DEPTH + 1);
+        assert!(verify("", &lock).unwrap_err().iter().all(|diagnostic| !diagnostic.is_fatal()));
+        assert!(verify("PUSH 1 DUP", "EQ").unwrap_err().iter().all(Diagnostic::is_fatal));
+    }
+
+    #[test]
+    fn counts_of_multisig_are_tracked() {
+        let public = format!("PUSHB 0x{} ", "ab".repeat(64));
+        let signature = format!("PUSHB 0x{} ", "cd".repeat(65));
+        let unlock = format!("PUSH 3 {}{}", signature, signature);
+        let lock = format!("PUSH 2 {}{}{}PUSH 3 CHKMULTISIG", public, public, public);
+        let report = verify(&unlock, &lock).unwrap();
+        assert_eq!(report.max_stack_depth, 3 + 5);
+        assert_eq!(report.max_memory, 1 + 130 + 1 + 192 + 1);
+    }
+
+    #[test]
+    fn instructions_of_later_eras_are_inactive() {
+        let lock = assemble("PUSH 1 PUSH 2 ADD PUSH 3 EQ").unwrap();
+        let config = Config {
+            era: 0,
+            ..Config::default()
+        };
+        assert_eq!(
+            verify_script(&[], &lock, config),
+            Err(vec![Diagnostic::InactiveInstruction {
+                offset: 4,
+                era: EXTENDED_INSTRUCTIONS_ERA,
+            }])
+        );
+        let config = Config {
+            era: EXTENDED_INSTRUCTIONS_ERA,
+            ..Config::default()
+        };
+        assert!(verify_script(&[], &lock, config).is_ok());
+    }
+
+    #[test]
+    fn cost_of_the_most_expensive_path() {
+        let unlock = assemble("PUSH 1").unwrap();
+        let lock = assemble("JZ cheap PUSH 2 PUSH 3 PUSH 2 CHKMULTISIG SUCCESS cheap: FAIL").unwrap();
+        let report = verify_script(&unlock, &lock, Config::default()).unwrap();
+        assert_eq!(report.max_cost, 1 + 1 + 3 + 201 + 1);
+
+        let config = Config {
+            max_cost: 1 + 1 + 3 + 200,
+            ..Config::default()
+        };
+        assert_eq!(
+            verify_script(&unlock, &lock, config),
+            Err(vec![Diagnostic::OutOfCost {
+                offset: 8,
+                cost: 1 + 1 + 3 + 201,
+            }])
+        );
+    }
+
+    #[test]
+    fn invalid_scripts() {
+        assert_eq!(
+            verify_script(&[0xff], &[], Config::default()),
+            Err(vec![Diagnostic::InvalidUnlockScript(DecoderError::InvalidOpCode(0xff))])
+        );
+    }
+}
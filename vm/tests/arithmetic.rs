@@ -0,0 +1,228 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_key as ckey;
+extern crate codechain_types as ctypes;
+extern crate codechain_vm as cvm;
+extern crate primitives;
+
+mod common;
+
+use ckey::NetworkId;
+use common::TestClient;
+use ctypes::transaction::{AssetOutPoint, AssetTransferInput, ShardTransaction};
+use cvm::Instruction;
+use cvm::{execute, RuntimeError, ScriptResult, VMConfig, EXTENDED_INSTRUCTIONS_ERA};
+use primitives::H160;
+
+const MAX: [u8; 8] = [0xff; 8];
+
+fn run(operands: &[&[u8]], lock: &[Instruction]) -> Result<ScriptResult, RuntimeError> {
+    run_in_era(operands, lock, EXTENDED_INSTRUCTIONS_ERA)
+}
+
+fn run_in_era(operands: &[&[u8]], lock: &[Instruction], era: u64) -> Result<ScriptResult, RuntimeError> {
+    let unlock: Vec<_> = operands.iter().map(|operand| Instruction::PushB(operand.to_vec())).collect();
+    let transaction = ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    };
+    let input = AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 0,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    };
+    let config = VMConfig {
+        era,
+        ..VMConfig::default()
+    };
+    execute(&unlock, &[], lock, &transaction, config, &input, false, &TestClient::default(), 0, 0)
+}
+
+/// Checks the result of the instruction is exactly the expected bytes.
+fn calculate(instruction: Instruction, operands: &[&[u8]], expected: &[u8]) -> Result<ScriptResult, RuntimeError> {
+    run(operands, &[instruction, Instruction::PushB(expected.to_vec()), Instruction::Eq])
+}
+
+#[test]
+fn add() {
+    assert_eq!(calculate(Instruction::Add, &[&[1], &[2]], &[3]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Add, &[&[0xff], &[1]], &[1, 0]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Add, &[&[], &[]], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Add, &[&[0, 0, 1], &[0, 2]], &[3]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Add, &[&MAX, &[]], &MAX), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Add, &[&[1], &[2]], &[0, 3]), Ok(ScriptResult::Fail));
+}
+
+#[test]
+fn add_overflow() {
+    assert_eq!(calculate(Instruction::Add, &[&MAX, &[1]], &[]), Err(RuntimeError::Overflow));
+}
+
+#[test]
+fn sub() {
+    assert_eq!(calculate(Instruction::Sub, &[&[5], &[3]], &[2]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Sub, &[&[3], &[3]], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Sub, &[&[1, 0], &[1]], &[0xff]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Sub, &[&[3], &[5]], &[]), Err(RuntimeError::Overflow));
+}
+
+#[test]
+fn mul() {
+    assert_eq!(calculate(Instruction::Mul, &[&[6], &[7]], &[42]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mul, &[&[1, 0], &[1, 0]], &[1, 0, 0]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mul, &[&MAX, &[]], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mul, &[&MAX, &[1]], &MAX), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mul, &[&[1, 0, 0, 0, 0], &[1, 0, 0, 0, 0]], &[]), Err(RuntimeError::Overflow));
+}
+
+#[test]
+fn div() {
+    assert_eq!(calculate(Instruction::Div, &[&[7], &[2]], &[3]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Div, &[&[1], &[2]], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Div, &[&MAX, &MAX], &[1]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Div, &[&[7], &[]], &[]), Err(RuntimeError::DivisionByZero));
+    assert_eq!(calculate(Instruction::Div, &[&[7], &[0, 0]], &[]), Err(RuntimeError::DivisionByZero));
+}
+
+#[test]
+fn modulo() {
+    assert_eq!(calculate(Instruction::Mod, &[&[7], &[2]], &[1]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mod, &[&[6], &[3]], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mod, &[&[2], &[7]], &[2]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Mod, &[&[7], &[]], &[]), Err(RuntimeError::DivisionByZero));
+}
+
+#[test]
+fn comparison() {
+    let cases: &[(&[u8], &[u8], [bool; 4])] = &[
+        // a, b, [a < b, a > b, a <= b, a >= b]
+        (&[1], &[2], [true, false, true, false]),
+        (&[2], &[2], [false, false, true, true]),
+        (&[0, 2], &[2], [false, false, true, true]),
+        (&[2], &[1], [false, true, false, true]),
+        (&[], &[0], [false, false, true, true]),
+        (&[1, 0], &[0xff], [false, true, false, true]),
+        (&MAX, &[], [false, true, false, true]),
+    ];
+    let instructions = [Instruction::Lt, Instruction::Gt, Instruction::Le, Instruction::Ge];
+    for (a, b, expected) in cases {
+        for (instruction, expected) in instructions.iter().zip(expected.iter()) {
+            let result = if *expected {
+                ScriptResult::Unlocked
+            } else {
+                ScriptResult::Fail
+            };
+            assert_eq!(run(&[*a, *b], &[instruction.clone()]), Ok(result), "{:?} {:?} {:?}", a, instruction, b);
+        }
+    }
+}
+
+#[test]
+fn min_max() {
+    assert_eq!(calculate(Instruction::Min, &[&[1], &[2]], &[1]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Min, &[&[2], &[1]], &[1]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Min, &[&[0, 0, 1], &[2]], &[1]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Min, &[&[], &MAX], &[]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Max, &[&[1], &[2]], &[2]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Max, &[&[2], &[1]], &[2]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Max, &[&[0, 2], &[1]], &[2]), Ok(ScriptResult::Unlocked));
+    assert_eq!(calculate(Instruction::Max, &[&[], &MAX], &MAX), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn within() {
+    // min <= value < max
+    assert_eq!(run(&[&[5], &[1], &[10]], &[Instruction::Within]), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[1], &[1], &[10]], &[Instruction::Within]), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[10], &[1], &[10]], &[Instruction::Within]), Ok(ScriptResult::Fail));
+    assert_eq!(run(&[&[0], &[1], &[10]], &[Instruction::Within]), Ok(ScriptResult::Fail));
+    assert_eq!(run(&[&[5], &[5], &[5]], &[Instruction::Within]), Ok(ScriptResult::Fail));
+    assert_eq!(run(&[&[5], &[10], &[1]], &[Instruction::Within]), Ok(ScriptResult::Fail));
+}
+
+#[test]
+fn size() {
+    let lock = |expected: &[u8]| {
+        vec![
+            Instruction::Size,
+            Instruction::PushB(expected.to_vec()),
+            Instruction::Eq,
+            Instruction::Swap,
+            Instruction::Pop,
+        ]
+    };
+    assert_eq!(run(&[&[]], &lock(&[])), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[0]], &lock(&[1])), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[0xab; 32]], &lock(&[32])), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[0xab; 256]], &lock(&[1, 0])), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(&[&[0xab; 32]], &lock(&[0, 32])), Ok(ScriptResult::Fail));
+}
+
+#[test]
+fn size_keeps_the_item() {
+    let lock = vec![Instruction::Size, Instruction::Pop, Instruction::PushB(vec![1, 2, 3]), Instruction::Eq];
+    assert_eq!(run(&[&[1, 2, 3]], &lock), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn integer_longer_than_8_bytes() {
+    let long: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0, 1];
+    let instructions = [
+        Instruction::Add,
+        Instruction::Sub,
+        Instruction::Mul,
+        Instruction::Div,
+        Instruction::Mod,
+        Instruction::Lt,
+        Instruction::Gt,
+        Instruction::Le,
+        Instruction::Ge,
+        Instruction::Min,
+        Instruction::Max,
+    ];
+    for instruction in &instructions {
+        assert_eq!(run(&[long, &[1]], &[instruction.clone()]), Err(RuntimeError::TypeMismatch), "{:?}", instruction);
+        assert_eq!(run(&[&[1], long], &[instruction.clone()]), Err(RuntimeError::TypeMismatch), "{:?}", instruction);
+    }
+    assert_eq!(run(&[long, &[1], &[2]], &[Instruction::Within]), Err(RuntimeError::TypeMismatch));
+    assert_eq!(run(&[&[1], long, &[2]], &[Instruction::Within]), Err(RuntimeError::TypeMismatch));
+    assert_eq!(run(&[&[1], &[1], long], &[Instruction::Within]), Err(RuntimeError::TypeMismatch));
+}
+
+#[test]
+fn arithmetic_underflow() {
+    assert_eq!(run(&[&[1]], &[Instruction::Add]), Err(RuntimeError::StackUnderflow));
+    assert_eq!(run(&[&[1], &[2]], &[Instruction::Within]), Err(RuntimeError::StackUnderflow));
+    assert_eq!(run(&[], &[Instruction::Size]), Err(RuntimeError::StackUnderflow));
+}
+
+#[test]
+fn inactive_before_the_era() {
+    let era = EXTENDED_INSTRUCTIONS_ERA;
+    assert_eq!(run_in_era(&[&[1], &[2]], &[Instruction::Add], era - 1), Err(RuntimeError::InactiveInstruction));
+    assert_eq!(run_in_era(&[&[1]], &[Instruction::Size], era - 1), Err(RuntimeError::InactiveInstruction));
+    assert_eq!(run_in_era(&[&[1], &[2]], &[Instruction::Add], era), Ok(ScriptResult::Unlocked));
+}
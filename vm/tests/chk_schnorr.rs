@@ -0,0 +1,209 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_crypto as ccrypto;
+extern crate codechain_key as ckey;
+extern crate codechain_types as ctypes;
+extern crate codechain_vm as cvm;
+extern crate primitives;
+extern crate rlp;
+
+mod common;
+
+use ccrypto::{blake128, blake256_with_key};
+use ckey::{aggregate_schnorr_privates, sign_schnorr, Generator, KeyPair, NetworkId, Random};
+use common::TestClient;
+use ctypes::transaction::{AssetOutPoint, AssetTransferInput, ShardTransaction};
+use cvm::Instruction;
+use cvm::{execute, execute_with_cost, RuntimeError, ScriptResult, VMConfig, EXTENDED_INSTRUCTIONS_ERA};
+use primitives::{H160, H256};
+use rlp::Encodable;
+
+fn dummy_tx() -> ShardTransaction {
+    ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    }
+}
+
+fn dummy_input() -> AssetTransferInput {
+    AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 0,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    }
+}
+
+fn message() -> H256 {
+    blake256_with_key(&dummy_tx().rlp_bytes(), &blake128(&[0b11 as u8]))
+}
+
+fn pubkey(keypair: &KeyPair) -> Vec<u8> {
+    <&[u8]>::from(keypair.public()).to_vec()
+}
+
+fn config() -> VMConfig {
+    VMConfig {
+        era: EXTENDED_INSTRUCTIONS_ERA,
+        ..VMConfig::default()
+    }
+}
+
+fn run(unlock_script: &[Instruction], lock_script: &[Instruction]) -> Result<ScriptResult, RuntimeError> {
+    let client = TestClient::default();
+    execute(unlock_script, &[], lock_script, &dummy_tx(), config(), &dummy_input(), false, &client, 0, 0)
+}
+
+fn agg_schnorr_lock_script(keypairs: &[&KeyPair]) -> Vec<Instruction> {
+    let mut lock_script: Vec<_> = keypairs.iter().rev().map(|keypair| Instruction::PushB(pubkey(keypair))).collect();
+    lock_script.push(Instruction::PushB(vec![keypairs.len() as u8]));
+    lock_script.push(Instruction::ChkAggSchnorr);
+    lock_script
+}
+
+#[test]
+fn valid_schnorr_signature() {
+    let keypair = Random.generate().unwrap();
+    let signature = sign_schnorr(keypair.private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = vec![Instruction::PushB(pubkey(&keypair)), Instruction::ChkSchnorr];
+
+    assert_eq!(run(&unlock_script, &lock_script), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn schnorr_signature_of_another_key() {
+    let keypair = Random.generate().unwrap();
+    let another = Random.generate().unwrap();
+    let signature = sign_schnorr(another.private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = vec![Instruction::PushB(pubkey(&keypair)), Instruction::ChkSchnorr];
+
+    assert_eq!(run(&unlock_script, &lock_script), Ok(ScriptResult::Fail));
+}
+
+#[test]
+fn ecdsa_signature_is_not_a_schnorr_signature() {
+    let keypair = Random.generate().unwrap();
+    let signature = ckey::sign(keypair.private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = vec![Instruction::PushB(pubkey(&keypair)), Instruction::ChkSchnorr];
+
+    assert_eq!(run(&unlock_script, &lock_script), Err(RuntimeError::TypeMismatch));
+}
+
+#[test]
+fn schnorr_is_inactive_before_the_era() {
+    let keypairs: Vec<KeyPair> = (0..2).map(|_| Random.generate().unwrap()).collect();
+    let signature = sign_schnorr(keypairs[0].private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let schnorr = vec![Instruction::PushB(pubkey(&keypairs[0])), Instruction::ChkSchnorr];
+    let agg_schnorr = agg_schnorr_lock_script(&keypairs.iter().collect::<Vec<_>>());
+    let config = VMConfig {
+        era: EXTENDED_INSTRUCTIONS_ERA - 1,
+        ..VMConfig::default()
+    };
+    let client = TestClient::default();
+    for lock_script in &[schnorr, agg_schnorr] {
+        assert_eq!(
+            execute(&unlock_script, &[], lock_script, &dummy_tx(), config, &dummy_input(), false, &client, 0, 0),
+            Err(RuntimeError::InactiveInstruction)
+        );
+    }
+}
+
+#[test]
+fn valid_aggregated_schnorr_signature() {
+    let keypairs: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let privates: Vec<_> = keypairs.iter().map(|keypair| *keypair.private()).collect();
+    let aggregated = aggregate_schnorr_privates(&privates).unwrap();
+    let signature = sign_schnorr(&aggregated, &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = agg_schnorr_lock_script(&keypairs.iter().collect::<Vec<_>>());
+
+    assert_eq!(run(&unlock_script, &lock_script), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn aggregated_schnorr_signature_of_partial_keys() {
+    let keypairs: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let privates: Vec<_> = keypairs[..2].iter().map(|keypair| *keypair.private()).collect();
+    let aggregated = aggregate_schnorr_privates(&privates).unwrap();
+    let signature = sign_schnorr(&aggregated, &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = agg_schnorr_lock_script(&keypairs.iter().collect::<Vec<_>>());
+
+    assert_eq!(run(&unlock_script, &lock_script), Ok(ScriptResult::Fail));
+}
+
+#[test]
+fn aggregated_schnorr_signature_without_keys() {
+    let keypair = Random.generate().unwrap();
+    let signature = sign_schnorr(keypair.private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+    let lock_script = vec![Instruction::PushB(vec![0]), Instruction::ChkAggSchnorr];
+
+    assert_eq!(run(&unlock_script, &lock_script), Err(RuntimeError::InvalidKeyCount));
+}
+
+#[test]
+fn cost_of_schnorr_signatures() {
+    let client = TestClient::default();
+    let keypairs: Vec<KeyPair> = (0..3).map(|_| Random.generate().unwrap()).collect();
+    let signature = sign_schnorr(keypairs[0].private(), &message()).unwrap().to_vec();
+    let unlock_script = vec![Instruction::PushB(signature), Instruction::PushB(vec![0b11 as u8])];
+
+    let lock_script = vec![Instruction::PushB(pubkey(&keypairs[0])), Instruction::ChkSchnorr];
+    let (result, cost) = execute_with_cost(
+        &unlock_script,
+        &[],
+        &lock_script,
+        &dummy_tx(),
+        config(),
+        &dummy_input(),
+        false,
+        &client,
+        0,
+        0,
+    );
+    assert_eq!(result, Ok(ScriptResult::Unlocked));
+    assert_eq!(cost, 4 + 100);
+
+    let lock_script = agg_schnorr_lock_script(&keypairs.iter().collect::<Vec<_>>());
+    let (result, cost) = execute_with_cost(
+        &unlock_script,
+        &[],
+        &lock_script,
+        &dummy_tx(),
+        config(),
+        &dummy_input(),
+        false,
+        &client,
+        0,
+        0,
+    );
+    assert_eq!(result, Ok(ScriptResult::Fail));
+    assert_eq!(cost, 7 + 100 + 3 * 10);
+}
@@ -0,0 +1,130 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_key as ckey;
+extern crate codechain_types as ctypes;
+extern crate codechain_vm as cvm;
+extern crate primitives;
+
+mod common;
+
+use ckey::NetworkId;
+use common::TestClient;
+use ctypes::transaction::{AssetOutPoint, AssetTransferInput, ShardTransaction};
+use cvm::Instruction;
+use cvm::{execute, execute_with_cost, RuntimeError, ScriptResult, VMConfig};
+use primitives::H160;
+
+fn dummy_tx() -> ShardTransaction {
+    ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: Vec::new(),
+        outputs: Vec::new(),
+    }
+}
+
+fn dummy_input() -> AssetTransferInput {
+    AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 0,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    }
+}
+
+fn config(max_cost: u64) -> VMConfig {
+    VMConfig {
+        max_cost,
+        ..VMConfig::default()
+    }
+}
+
+fn run(
+    unlock: &[Instruction],
+    params: &[Vec<u8>],
+    lock: &[Instruction],
+    config: VMConfig,
+) -> (Result<ScriptResult, RuntimeError>, u64) {
+    execute_with_cost(unlock, params, lock, &dummy_tx(), config, &dummy_input(), false, &TestClient::default(), 0, 0)
+}
+
+#[test]
+fn every_instruction_costs() {
+    let unlock = [Instruction::Push(1)];
+    let lock = [Instruction::Dup, Instruction::Eq];
+    assert_eq!(run(&unlock, &[], &lock, VMConfig::default()), (Ok(ScriptResult::Unlocked), 3));
+    assert_eq!(run(&unlock, &[vec![1]], &lock, VMConfig::default()), (Ok(ScriptResult::Fail), 4));
+}
+
+#[test]
+fn stops_when_the_cost_exceeds_the_limit() {
+    let unlock = [Instruction::Push(1)];
+    let lock = [Instruction::Dup, Instruction::Eq];
+    assert_eq!(run(&unlock, &[], &lock, config(3)), (Ok(ScriptResult::Unlocked), 3));
+    assert_eq!(run(&unlock, &[], &lock, config(2)), (Err(RuntimeError::OutOfCost), 3));
+    assert_eq!(
+        execute(&unlock, &[], &lock, &dummy_tx(), config(2), &dummy_input(), false, &TestClient::default(), 0, 0),
+        Err(RuntimeError::OutOfCost)
+    );
+}
+
+#[test]
+fn hashing_costs_by_the_size() {
+    let lock = [Instruction::Blake256, Instruction::Pop, Instruction::Push(1)];
+    assert_eq!(run(&[Instruction::PushB(vec![])], &[], &lock, VMConfig::default()), (Ok(ScriptResult::Unlocked), 14));
+    assert_eq!(
+        run(&[Instruction::PushB(vec![0; 32])], &[], &lock, VMConfig::default()),
+        (Ok(ScriptResult::Unlocked), 15)
+    );
+    assert_eq!(
+        run(&[Instruction::PushB(vec![0; 33])], &[], &lock, VMConfig::default()),
+        (Ok(ScriptResult::Unlocked), 16)
+    );
+}
+
+#[test]
+fn signature_verification_costs() {
+    let signature = vec![0; 65];
+    let tag = vec![0b0000_0011];
+    let public = vec![0; 64];
+    let unlock = [Instruction::PushB(signature), Instruction::PushB(tag), Instruction::PushB(public)];
+    let (result, cost) = run(&unlock, &[], &[Instruction::ChkSig], VMConfig::default());
+    assert_eq!(result, Ok(ScriptResult::Fail));
+    assert_eq!(cost, 104);
+    assert_eq!(run(&unlock, &[], &[Instruction::ChkSig], config(103)), (Err(RuntimeError::OutOfCost), 104));
+}
+
+#[test]
+fn multisig_costs_for_each_public_key() {
+    let unlock = [Instruction::PushB(vec![0b0000_0011]), Instruction::PushB(vec![0; 65]), Instruction::Push(1)];
+    let lock = [
+        Instruction::PushB(vec![0; 64]),
+        Instruction::PushB(vec![0; 64]),
+        Instruction::PushB(vec![0; 64]),
+        Instruction::Push(3),
+        Instruction::ChkMultiSig,
+    ];
+    let (result, cost) = run(&unlock, &[], &lock, VMConfig::default());
+    assert_eq!(result, Ok(ScriptResult::Fail));
+    assert_eq!(cost, 8 + 300);
+}
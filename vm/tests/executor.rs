@@ -170,6 +170,7 @@ fn out_of_memory() {
     };
     let config = VMConfig {
         max_memory: 2,
+        ..VMConfig::default()
     };
     assert_eq!(
         execute(
@@ -0,0 +1,139 @@
+// Copyright 2020 Kodebox, Inc.
+// This file is part of CodeChain.
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+extern crate codechain_key as ckey;
+extern crate codechain_types as ctypes;
+extern crate codechain_vm as cvm;
+extern crate primitives;
+
+mod common;
+
+use ckey::hex::ToHex;
+use ckey::NetworkId;
+use common::TestClient;
+use ctypes::transaction::{AssetOutPoint, AssetTransferInput, AssetTransferOutput, ShardTransaction};
+use cvm::{assemble, decode, execute, RuntimeError, ScriptResult, VMConfig, EXTENDED_INSTRUCTIONS_ERA};
+use primitives::H160;
+
+fn output(lock_script_hash: H160, asset_type: H160, quantity: u64) -> AssetTransferOutput {
+    AssetTransferOutput {
+        lock_script_hash,
+        parameters: Vec::new(),
+        asset_type,
+        shard_id: 0,
+        quantity,
+    }
+}
+
+fn input() -> AssetTransferInput {
+    AssetTransferInput {
+        prev_out: AssetOutPoint {
+            tracker: Default::default(),
+            index: 0,
+            asset_type: H160::default(),
+            shard_id: 0,
+            quantity: 100,
+        },
+        timelock: None,
+        lock_script: Vec::new(),
+        unlock_script: Vec::new(),
+    }
+}
+
+fn run(outputs: Vec<AssetTransferOutput>, unlock: &str, lock: &str) -> Result<ScriptResult, RuntimeError> {
+    run_in_era(outputs, unlock, lock, EXTENDED_INSTRUCTIONS_ERA)
+}
+
+fn run_in_era(
+    outputs: Vec<AssetTransferOutput>,
+    unlock: &str,
+    lock: &str,
+    era: u64,
+) -> Result<ScriptResult, RuntimeError> {
+    let tx = ShardTransaction::TransferAsset {
+        network_id: NetworkId::default(),
+        burns: Vec::new(),
+        inputs: vec![input()],
+        outputs,
+    };
+    let unlock = decode(&assemble(unlock).unwrap()).unwrap();
+    let lock = decode(&assemble(lock).unwrap()).unwrap();
+    let config = VMConfig {
+        era,
+        ..VMConfig::default()
+    };
+    execute(&unlock, &[], &lock, &tx, config, &input(), false, &TestClient::default(), 0, 0)
+}
+
+#[test]
+fn count_outputs() {
+    let outputs = vec![output(H160::zero(), H160::zero(), 1), output(H160::zero(), H160::zero(), 2)];
+    assert_eq!(run(outputs.clone(), "PUSH 2", "OUTCOUNT EQ"), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(outputs, "PUSH 1", "OUTCOUNT EQ"), Ok(ScriptResult::Fail));
+    assert_eq!(run(Vec::new(), "PUSHB 0x", "OUTCOUNT EQ"), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn read_an_output() {
+    let lock_script_hash = H160::random();
+    let asset_type = H160::random();
+    let outputs = vec![output(H160::zero(), H160::zero(), 1), output(lock_script_hash, asset_type, 0x1234)];
+
+    let lock = format!("PUSH 1 OUTLOCKHASH PUSHB 0x{} EQ", lock_script_hash.to_hex());
+    assert_eq!(run(outputs.clone(), "", &lock), Ok(ScriptResult::Unlocked));
+    let lock = format!("PUSH 0 OUTLOCKHASH PUSHB 0x{} EQ", lock_script_hash.to_hex());
+    assert_eq!(run(outputs.clone(), "", &lock), Ok(ScriptResult::Fail));
+
+    let lock = format!("PUSH 1 OUTASSETTYPE PUSHB 0x{} EQ", asset_type.to_hex());
+    assert_eq!(run(outputs.clone(), "", &lock), Ok(ScriptResult::Unlocked));
+
+    assert_eq!(run(outputs, "", "PUSH 1 OUTQUANTITY PUSHB 0x1234 EQ"), Ok(ScriptResult::Unlocked));
+}
+
+#[test]
+fn inactive_before_the_era() {
+    let era = EXTENDED_INSTRUCTIONS_ERA;
+    let outputs = vec![output(H160::random(), H160::random(), 1)];
+    for lock in &["OUTCOUNT", "PUSH 0 OUTLOCKHASH", "PUSH 0 OUTASSETTYPE", "PUSH 0 OUTQUANTITY"] {
+        assert_eq!(run_in_era(outputs.clone(), "", lock, era - 1), Err(RuntimeError::InactiveInstruction));
+        assert_eq!(run_in_era(outputs.clone(), "", lock, era), Ok(ScriptResult::Unlocked));
+    }
+}
+
+#[test]
+fn output_index_out_of_bound() {
+    let outputs = vec![output(H160::zero(), H160::zero(), 1)];
+    assert_eq!(run(outputs.clone(), "", "PUSH 1 OUTQUANTITY"), Err(RuntimeError::IndexOutOfBound));
+    assert_eq!(run(outputs, "", "PUSHB 0x010000000000000000 OUTQUANTITY"), Err(RuntimeError::TypeMismatch));
+}
+
+#[test]
+fn vault_keeps_the_asset_in_the_lock() {
+    // The whole quantity must go to the output locked by the same script.
+    let vault = H160::random();
+    let lock = format!(
+        "OUTCOUNT PUSH 1 EQ JZ fail PUSH 0 OUTLOCKHASH PUSHB 0x{} EQ JZ fail PUSH 0 OUTQUANTITY PUSH 100 EQ JZ fail
+         SUCCESS fail: FAIL",
+        vault.to_hex()
+    );
+    assert_eq!(run(vec![output(vault, H160::zero(), 100)], "", &lock), Ok(ScriptResult::Unlocked));
+    assert_eq!(run(vec![output(H160::random(), H160::zero(), 100)], "", &lock), Ok(ScriptResult::Fail));
+    assert_eq!(run(vec![output(vault, H160::zero(), 60)], "", &lock), Ok(ScriptResult::Fail));
+    assert_eq!(
+        run(vec![output(vault, H160::zero(), 60), output(vault, H160::zero(), 40)], "", &lock),
+        Ok(ScriptResult::Fail)
+    );
+}